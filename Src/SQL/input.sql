@@ -24,8 +24,9 @@ CREATE TABLE input_discourse (
 DROP TABLE IF EXISTS input_discourse_title;
 
 CREATE TABLE input_discourse_title (
-    discourse_id INTEGER REFERENCES input_discourse (discourse_id),
-    word_id      INTEGER REFERENCES input_word (word_id) 
+    discourse_id INTEGER REFERENCES input_discourse (discourse_id) DEFERRABLE INITIALLY DEFERRED,
+    word_id      INTEGER REFERENCES input_word (word_id) DEFERRABLE INITIALLY DEFERRED,
+    PRIMARY KEY (discourse_id, word_id)
 );
 
 
@@ -33,7 +34,7 @@ CREATE TABLE input_discourse_title (
 DROP TABLE IF EXISTS input_exempt_feature;
 
 CREATE TABLE input_exempt_feature (
-    discourse_id     INTEGER REFERENCES input_discourse (discourse_id),
+    discourse_id     INTEGER REFERENCES input_discourse (discourse_id) DEFERRABLE INITIALLY DEFERRED,
     ud_feature       TEXT,
     ud_feature_value TEXT
 );
@@ -45,7 +46,7 @@ DROP TABLE IF EXISTS input_new_word_def;
 CREATE TABLE input_new_word_def (
     new_word_id  INTEGER PRIMARY KEY,
     is_a         INTEGER,
-    discourse_id INTEGER REFERENCES input_discourse (discourse_id),
+    discourse_id INTEGER REFERENCES input_discourse (discourse_id) DEFERRABLE INITIALLY DEFERRED,
     lexeme       TEXT,
     x            DOUBLE,
     y            DOUBLE
@@ -57,8 +58,8 @@ DROP TABLE IF EXISTS input_section;
 
 CREATE TABLE input_section (
     section_id   INTEGER PRIMARY KEY,
-    triplet_id   INTEGER REFERENCES input_triplet (triplet_id),
-    word_id      INTEGER REFERENCES input_word (word_id),
+    triplet_id   INTEGER REFERENCES input_triplet (triplet_id) DEFERRABLE INITIALLY DEFERRED,
+    word_id      INTEGER REFERENCES input_word (word_id) DEFERRABLE INITIALLY DEFERRED,
     section_type INTEGER
 );
 
@@ -68,7 +69,7 @@ DROP TABLE IF EXISTS input_sentence;
 
 CREATE TABLE input_sentence (
     sentence_id  INTEGER PRIMARY KEY,
-    discourse_id INTEGER REFERENCES input_discourse (discourse_id),
+    discourse_id INTEGER REFERENCES input_discourse (discourse_id) DEFERRABLE INITIALLY DEFERRED,
     is_question  BOOL
 );
 
@@ -78,7 +79,7 @@ DROP TABLE IF EXISTS input_triplet;
 
 CREATE TABLE input_triplet (
     triplet_id  INTEGER PRIMARY KEY,
-    sentence_id INTEGER REFERENCES input_sentence (sentence_id),
+    sentence_id INTEGER REFERENCES input_sentence (sentence_id) DEFERRABLE INITIALLY DEFERRED,
     tense       INTEGER,
     is_virtual  BOOLEAN,
     is_passive  BOOLEAN
@@ -90,18 +91,17 @@ DROP TABLE IF EXISTS input_word;
 
 CREATE TABLE input_word (
     word_id        INTEGER PRIMARY KEY,
-    sentence_id    INTEGER REFERENCES input_sentence (sentence_id),
+    sentence_id    INTEGER REFERENCES input_sentence (sentence_id) DEFERRABLE INITIALLY DEFERRED,
     synset_id      INTEGER DEFAULT (0),
     index_of_word  INTEGER,
     lexeme         TEXT,
     instance_name  TEXT,
-    instance_index TEXT  DEFAULT (0),
+    instance_index INTEGER DEFAULT (0),
     pos            TEXT,
     x              DOUBLE  DEFAULT (0),
     y              DOUBLE  DEFAULT (0),
     is_transition  BOOLEAN,
-    new_word_id    INTEGER DEFAULT ( -1) 
-                           REFERENCES input_new_word_def (new_word_id) 
+    new_word_id    INTEGER REFERENCES input_new_word_def (new_word_id) DEFERRABLE INITIALLY DEFERRED
 );
 
 
@@ -109,7 +109,7 @@ CREATE TABLE input_word (
 DROP TABLE IF EXISTS input_word_feature;
 
 CREATE TABLE input_word_feature (
-    word_id          INTEGER REFERENCES input_word (word_id),
+    word_id          INTEGER REFERENCES input_word (word_id) DEFERRABLE INITIALLY DEFERRED,
     ud_relation      TEXT,
     ud_feature       TEXT,
     ud_feature_value TEXT
@@ -120,11 +120,13 @@ CREATE TABLE input_word_feature (
 DROP TABLE IF EXISTS input_word_relation;
 
 CREATE TABLE input_word_relation (
-    word_id          INTEGER REFERENCES input_word (word_id),
-    word_id_modified INTEGER REFERENCES input_word (word_id),
+    word_id          INTEGER REFERENCES input_word (word_id) DEFERRABLE INITIALLY DEFERRED,
+    word_id_modified INTEGER REFERENCES input_word (word_id) DEFERRABLE INITIALLY DEFERRED,
     ud_relation      TEXT
 );
 
 
+PRAGMA user_version = 2;
+
 COMMIT TRANSACTION;
 PRAGMA foreign_keys = on;
@@ -6,11 +6,17 @@ CREATE TABLE antonyms
 (
     coarse_class integer,
     synset_id1 integer,
-    lemma1 string,
+    lemma1 text,
     synset_id2 integer,
-    lemma2 string
+    lemma2 text
 );
 
+-- Index: antonyms_synset_id1
+CREATE INDEX antonyms_synset_id1 ON antonyms (synset_id1);
+
+-- Index: antonyms_synset_id2
+CREATE INDEX antonyms_synset_id2 ON antonyms (synset_id2);
+
 -- Table: meaning_grid_item
 CREATE TABLE meaning_grid_item (
     synset_id integer,
@@ -18,5 +24,15 @@ CREATE TABLE meaning_grid_item (
     y         double
 );
 
+-- Index: meaning_grid_item_synset_id
+CREATE INDEX meaning_grid_item_synset_id ON meaning_grid_item (synset_id);
+
+-- Table: meaning_grid_version
+CREATE TABLE meaning_grid_version (
+    version text
+);
+
+PRAGMA user_version = 3;
+
 COMMIT TRANSACTION;
 PRAGMA foreign_keys = on;
@@ -14,6 +14,13 @@ CREATE TABLE entity (
     triplet_id     integer
 );
 
+-- Table: encoding_version
+CREATE TABLE encoding_version (
+    discourse_id    integer primary key,
+    library_version text,
+    grid_version    text
+);
+
 -- Table: hash_item
 CREATE TABLE hash_item (
     radius                     integer,
@@ -39,9 +46,10 @@ CREATE TABLE unit_tensor (
     when_entity_id      integer,
     predicate_entity_id integer,
     tense               integer,
-    mood                string,
+    mood                text,
     excited_x          double,
-    excited_y          double
+    excited_y          double,
+    is_negated          bool not null default 0
 );
 
 -- Table: unit_tensor_ethereal_def
@@ -51,5 +59,21 @@ id integer primary key,
     rejoin_id integer
     );
 
+-- Table: unit_tensor_location
+CREATE TABLE unit_tensor_location (
+    id             integer primary key,
+    unit_tensor_id integer,
+    entity_id      integer,
+    location_type  integer
+);
+
+-- Index: hash_item_discourse_id
+CREATE INDEX hash_item_discourse_id ON hash_item (discourse_id, is_virtual);
+
+-- Index: unit_tensor_location_unit_tensor_id
+CREATE INDEX unit_tensor_location_unit_tensor_id ON unit_tensor_location (unit_tensor_id);
+
+PRAGMA user_version = 5;
+
 COMMIT TRANSACTION;
 PRAGMA foreign_keys = on;
@@ -0,0 +1,439 @@
+/*
+DO NOT ALTER OR REMOVE COPYRIGHT NOTICES OR THIS HEADER.
+
+Copyright © 2019 Asame Imoni Obiomah. All rights reserved.
+
+Artificial intelligence ethics is of existential importance.
+The licensing model of OkeuvoLite enforces adherence to a strict ethical code.
+
+The contents of this file are subject to the terms of both the GNU General Public License Version 2 only (“GPL”)
+and Inverse license (collectively, the “License”). You may not use this file except in compliance with the License.
+You can obtain a copy of the License at LICENSE.txt. See the License for the specific language governing
+permissions and limitations under the License.
+
+When distributing the software, include this License Header Notice in each file and include the License file at LICENSE.txt.
+*/
+
+//! Reader for the antonym pointers of the Wordnet 3.0 database files
+//! (data.noun, data.verb, data.adj and data.adv, see wndb(5WN)),
+//! so that the table, antonyms of metadata.db can be filled from a local copy of Wordnet.
+
+use crate::data::AntonymPair;
+use crate::error::OkeuvoError;
+use crate::utils::pos_num_to_subtract;
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+use std::str::SplitWhitespace;
+
+/// Pointer symbol of an antonym.
+const ANTONYM_POINTER: &str = "!";
+
+/// Step between the synset_id's of two parts of speech in the Wordnet SQL format,
+/// so that synset_id / COARSE_CLASS_STEP is the coarse_class of a synset_id.
+const COARSE_CLASS_STEP: i32 = 100000000;
+
+/// An antonym pointer whose target word is looked up once the whole file is read.
+struct AntonymPointer {
+    /// Line of the file holding the pointer.
+    line: usize,
+    /// Synset_id of the synset holding the pointer.
+    synset_id: i32,
+    /// Lemma of the source word.
+    lemma: String,
+    /// Synset_id of the target synset.
+    target_synset_id: i32,
+    /// Number of the target word in its synset, from 1.
+    target_word: usize,
+}
+
+/// Reads the antonym pairs of a Wordnet 3.0 database file.
+pub(crate) fn read_wordnet_antonyms(path: &Path) -> Result<Vec<AntonymPair>, OkeuvoError> {
+    let file = File::open(path)?;
+
+    parse_wordnet_antonyms(BufReader::new(file))
+}
+
+/// Parses the antonym pairs of a Wordnet 3.0 database file:
+///
+/// 1. license information (lines starting with a space),
+/// 2. one synset per line: synset_offset lex_filenum ss_type w_cnt [word lex_id...]
+///    p_cnt [pointer_symbol synset_offset pos source/target...] and the rest of the
+///    line (verb frames and the gloss), which is ignored.
+///
+/// Every antonym pointer ("!") makes a pair of its source and target words.
+/// Offsets are converted to Wordnet SQL synset_id's, adjective satellites (s)
+/// counting as adjectives. Lemmas lose their adjective markers ("(a)", "(p)",
+/// "(ip)") and have their underscores replaced by spaces. Wordnet lists each pair
+/// from both of its words, so only the first listing is kept.
+///
+/// Every entry is validated, the first bad entry stops parsing with an error
+/// that names its line. Targets must be synsets of the same file.
+pub(crate) fn parse_wordnet_antonyms<R: BufRead>(
+    reader: R,
+) -> Result<Vec<AntonymPair>, OkeuvoError> {
+    // Format - HashMap<synset_id, lemmas of its words, in order>.
+    let mut synset_words: HashMap<i32, Vec<String>> = HashMap::new();
+    let mut pointers: Vec<AntonymPointer> = Vec::new();
+
+    for (i, line) in reader.lines().enumerate() {
+        let line = line?;
+        let line_number = i + 1;
+
+        // License header lines, and any empty line.
+        if line.starts_with(' ') || line.trim().is_empty() {
+            continue;
+        }
+
+        let mut fields: SplitWhitespace = line.split_whitespace();
+
+        let synset_offset: &str = next_field(&mut fields, "synset_offset", line_number)?;
+        next_field(&mut fields, "lex_filenum", line_number)?;
+        let ss_type: &str = next_field(&mut fields, "ss_type", line_number)?;
+        let synset_id: i32 = to_synset_id(synset_offset, ss_type, line_number)?;
+
+        let word_count: usize = parse_count(
+            next_field(&mut fields, "w_cnt", line_number)?,
+            16,
+            "w_cnt",
+            line_number,
+        )?;
+        let mut words: Vec<String> = Vec::with_capacity(word_count);
+        for _ in 0..word_count {
+            words.push(to_lemma(next_field(&mut fields, "word", line_number)?));
+            next_field(&mut fields, "lex_id", line_number)?;
+        }
+
+        let pointer_count: usize = parse_count(
+            next_field(&mut fields, "p_cnt", line_number)?,
+            10,
+            "p_cnt",
+            line_number,
+        )?;
+        for _ in 0..pointer_count {
+            let pointer_symbol: &str = next_field(&mut fields, "pointer_symbol", line_number)?;
+            let target_offset: &str = next_field(&mut fields, "synset_offset", line_number)?;
+            let target_pos: &str = next_field(&mut fields, "pos", line_number)?;
+            let source_target: &str = next_field(&mut fields, "source/target", line_number)?;
+
+            if pointer_symbol != ANTONYM_POINTER {
+                continue;
+            }
+
+            let (source_word, target_word) = parse_source_target(source_target, line_number)?;
+            let lemma: String = match words.get(source_word - 1) {
+                Some(lemma) => lemma.clone(),
+                None => {
+                    return Err(OkeuvoError::InvalidAntonyms {
+                        line: Some(line_number),
+                        message: format!(
+                            "source word {} of an antonym pointer is not in the synset",
+                            source_word
+                        ),
+                    })
+                }
+            };
+
+            pointers.push(AntonymPointer {
+                line: line_number,
+                synset_id,
+                lemma,
+                target_synset_id: to_synset_id(target_offset, target_pos, line_number)?,
+                target_word,
+            });
+        }
+
+        if synset_words.insert(synset_id, words).is_some() {
+            return Err(OkeuvoError::InvalidAntonyms {
+                line: Some(line_number),
+                message: format!("duplicate synset_offset {}", synset_offset),
+            });
+        }
+    }
+
+    let mut pairs: Vec<AntonymPair> = Vec::new();
+    let mut seen_pairs: HashSet<(i32, String, i32, String)> = HashSet::new();
+
+    for pointer in pointers {
+        let target_lemma: String = match synset_words
+            .get(&pointer.target_synset_id)
+            .and_then(|words| words.get(pointer.target_word - 1))
+        {
+            Some(lemma) => lemma.clone(),
+            None => {
+                return Err(OkeuvoError::InvalidAntonyms {
+                    line: Some(pointer.line),
+                    message: format!(
+                        "the antonym of '{}' (word {} of synset {}) is not in the file",
+                        pointer.lemma, pointer.target_word, pointer.target_synset_id
+                    ),
+                })
+            }
+        };
+
+        let reverse = (
+            pointer.target_synset_id,
+            target_lemma.clone(),
+            pointer.synset_id,
+            pointer.lemma.clone(),
+        );
+        if seen_pairs.contains(&reverse) {
+            continue;
+        }
+
+        let pair = (
+            pointer.synset_id,
+            pointer.lemma.clone(),
+            pointer.target_synset_id,
+            target_lemma.clone(),
+        );
+        if !seen_pairs.insert(pair) {
+            continue;
+        }
+
+        pairs.push(AntonymPair {
+            coarse_class: pointer.synset_id / COARSE_CLASS_STEP,
+            synset_id1: pointer.synset_id,
+            lemma1: pointer.lemma,
+            synset_id2: pointer.target_synset_id,
+            lemma2: target_lemma,
+        });
+    }
+
+    Ok(pairs)
+}
+
+/// Gets the next field of a synset line, failing if the line ends early.
+fn next_field<'a>(
+    fields: &mut SplitWhitespace<'a>,
+    name: &str,
+    line_number: usize,
+) -> Result<&'a str, OkeuvoError> {
+    fields.next().ok_or_else(|| OkeuvoError::InvalidAntonyms {
+        line: Some(line_number),
+        message: format!("the line ends before {}", name),
+    })
+}
+
+/// Converts a synset_offset and part of speech (n, v, a, s or r)
+/// to a Wordnet SQL format synset_id.
+fn to_synset_id(synset_offset: &str, pos: &str, line_number: usize) -> Result<i32, OkeuvoError> {
+    let offset: i32 = match synset_offset.parse::<i32>() {
+        Ok(offset) if (0..COARSE_CLASS_STEP).contains(&offset) => offset,
+        _ => {
+            return Err(OkeuvoError::InvalidAntonyms {
+                line: Some(line_number),
+                message: format!("synset_offset '{}' is not an 8 digit number", synset_offset),
+            })
+        }
+    };
+
+    match pos_num_to_subtract(pos) {
+        Some(pos_num) if pos_num > 0 => Ok(pos_num + offset),
+        _ => Err(OkeuvoError::InvalidAntonyms {
+            line: Some(line_number),
+            message: format!(
+                "'{}' is not a Wordnet part of speech (n, v, a, s or r)",
+                pos
+            ),
+        }),
+    }
+}
+
+/// Parses the count of words (hexadecimal) or pointers (decimal) of a synset.
+fn parse_count(
+    value: &str,
+    radix: u32,
+    name: &str,
+    line_number: usize,
+) -> Result<usize, OkeuvoError> {
+    usize::from_str_radix(value, radix).map_err(|_| OkeuvoError::InvalidAntonyms {
+        line: Some(line_number),
+        message: format!("{} '{}' is not a number", name, value),
+    })
+}
+
+/// Parses the source/target field of a pointer, four hexadecimal digits
+/// holding the numbers of the source and target words (from 1).
+///
+/// Return format: (source word, target word).
+fn parse_source_target(value: &str, line_number: usize) -> Result<(usize, usize), OkeuvoError> {
+    let words: Option<(usize, usize)> = match (value.get(..2), value.get(2..)) {
+        (Some(source), Some(target)) if value.len() == 4 => {
+            match (
+                usize::from_str_radix(source, 16),
+                usize::from_str_radix(target, 16),
+            ) {
+                (Ok(source), Ok(target)) if source > 0 && target > 0 => Some((source, target)),
+                _ => None,
+            }
+        }
+        _ => None,
+    };
+
+    words.ok_or_else(|| OkeuvoError::InvalidAntonyms {
+        line: Some(line_number),
+        message: format!(
+            "source/target '{}' of an antonym pointer does not name two words",
+            value
+        ),
+    })
+}
+
+/// Gets the lemma of a word of a synset line, without its adjective marker
+/// and with spaces for underscores.
+fn to_lemma(word: &str) -> String {
+    let word: &str = match word.find('(') {
+        Some(position) if word.ends_with(')') => &word[..position],
+        _ => word,
+    };
+
+    word.replace('_', " ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::Antonym;
+    use crate::test_support::grid;
+
+    /// Lines of data.adj: "able" and "unable" point at each other, and the
+    /// satellite "well_off(p)" at "poor(a)".
+    const DATA_ADJ: &str = "  1 This software and database is being provided to you, the LICENSEE, by
+  2 Princeton University under the following license.
+00001740 00 a 01 able 0 003 = 05207437 n 0000 ! 00002098 a 0101 + 05207437 n 0101 | (usually followed by `to') having the necessary means
+00002098 00 a 01 unable 0 002 = 05207437 n 0000 ! 00001740 a 0101 | (usually followed by `to') not having the necessary means
+02022167 00 s 02 well_off(p) 0 rich 0 001 ! 02023287 a 0101 | in fortunate circumstances
+02023287 00 a 01 poor(a) 0 000 | having little money
+";
+
+    fn pair(synset_id1: i32, lemma1: &str, synset_id2: i32, lemma2: &str) -> AntonymPair {
+        AntonymPair {
+            coarse_class: synset_id1 / COARSE_CLASS_STEP,
+            synset_id1,
+            lemma1: lemma1.to_string(),
+            synset_id2,
+            lemma2: lemma2.to_string(),
+        }
+    }
+
+    /// The line and message of an InvalidAntonyms error.
+    fn invalid(data: &str) -> (Option<usize>, String) {
+        match parse_wordnet_antonyms(data.as_bytes()) {
+            Err(OkeuvoError::InvalidAntonyms { line, message }) => (line, message),
+            other => panic!("expected InvalidAntonyms, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn parses_each_pair_once() {
+        assert_eq!(
+            parse_wordnet_antonyms(DATA_ADJ.as_bytes()).unwrap(),
+            vec![
+                pair(300001740, "able", 300002098, "unable"),
+                pair(302022167, "well off", 302023287, "poor"),
+            ]
+        );
+    }
+
+    #[test]
+    fn reads_the_pairs_of_a_file() {
+        let path = std::env::temp_dir().join(format!("okeuvo-data.adj-{}", std::process::id()));
+        std::fs::write(&path, DATA_ADJ).unwrap();
+
+        let pairs = read_wordnet_antonyms(&path);
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(pairs.unwrap().len(), 2);
+        assert!(matches!(
+            read_wordnet_antonyms(&path),
+            Err(OkeuvoError::Io(_))
+        ));
+    }
+
+    #[test]
+    fn names_the_line_of_a_bad_entry() {
+        assert_eq!(
+            invalid("00001740 00 a 01 able 0 001 ! 00002098 a 0101 | gloss\n"),
+            (
+                Some(1),
+                "the antonym of 'able' (word 1 of synset 300002098) is not in the file".to_string()
+            )
+        );
+        assert_eq!(
+            invalid("  1 license\n00001740 00 a 01 able 0 001 ! 00002098\n"),
+            (Some(2), "the line ends before pos".to_string())
+        );
+        assert_eq!(
+            invalid("00001740 00 x 01 able 0 000 | gloss\n"),
+            (
+                Some(1),
+                "'x' is not a Wordnet part of speech (n, v, a, s or r)".to_string()
+            )
+        );
+        assert_eq!(
+            invalid("00001740 00 a 01 able 0 001 ! 00002098 a 0201 | gloss\n"),
+            (
+                Some(1),
+                "source word 2 of an antonym pointer is not in the synset".to_string()
+            )
+        );
+        assert_eq!(
+            invalid("00001740 00 a 01 able 0 001 ! 00002098 a 00 | gloss\n"),
+            (
+                Some(1),
+                "source/target '00' of an antonym pointer does not name two words".to_string()
+            )
+        );
+        assert_eq!(
+            invalid("00001740 00 a 01 able 0 000 | a\n00001740 00 a 01 able 0 000 | b\n"),
+            (Some(2), "duplicate synset_offset 00001740".to_string())
+        );
+    }
+
+    #[test]
+    fn pairs_are_looked_up_from_either_word() {
+        let mut grid = grid(&[(300001740, 1.0, 2.0), (300002098, 3.0, 4.0)]);
+        let pairs = parse_wordnet_antonyms(DATA_ADJ.as_bytes()).unwrap();
+        grid.add_antonyms(&pairs);
+        // Pairs already on the grid are skipped.
+        grid.add_antonyms(&pairs);
+
+        assert_eq!(
+            grid.antonyms(&300002098),
+            vec![Antonym {
+                coarse_class: 3,
+                lemma: "unable".to_string(),
+                synset_id: 300001740,
+                antonym_lemma: "able".to_string(),
+                x: 1.0,
+                y: 2.0,
+            }]
+        );
+        assert_eq!(grid.antonyms(&300001740)[0].synset_id, 300002098);
+        assert_eq!(grid.antonym(&300001740).map(|item| item.y), Some(4.0));
+        // "poor" is not on the grid: listed at 0, but not an antonym to take.
+        assert_eq!(
+            grid.antonyms(&302022167)
+                .iter()
+                .map(|antonym| (antonym.synset_id, antonym.x, antonym.y))
+                .collect::<Vec<(i32, f64, f64)>>(),
+            vec![(302023287, 0.0, 0.0)]
+        );
+        assert!(grid.antonym(&302022167).is_none());
+        assert!(grid.antonyms(&100001740).is_empty());
+    }
+
+    #[test]
+    fn orients_a_pair_by_either_word() {
+        let able_unable = pair(300001740, "able", 300002098, "unable");
+
+        assert_eq!(able_unable.oriented(&300001740), Some(able_unable.clone()));
+        assert_eq!(
+            able_unable.oriented(&300002098),
+            Some(pair(300002098, "unable", 300001740, "able"))
+        );
+        assert_eq!(able_unable.oriented(&100001740), None);
+    }
+}
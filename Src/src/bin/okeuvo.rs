@@ -0,0 +1,569 @@
+/*
+DO NOT ALTER OR REMOVE COPYRIGHT NOTICES OR THIS HEADER.
+
+Copyright © 2019 Asame Imoni Obiomah. All rights reserved.
+
+Artificial intelligence ethics is of existential importance.
+The licensing model of OkeuvoLite enforces adherence to a strict ethical code.
+
+The contents of this file are subject to the terms of both the GNU General Public License Version 2 only (“GPL”)
+and Inverse license (collectively, the “License”). You may not use this file except in compliance with the License.
+You can obtain a copy of the License at LICENSE.txt. See the License for the specific language governing
+permissions and limitations under the License.
+
+When distributing the software, include this License Header Notice in each file and include the License file at LICENSE.txt.
+*/
+
+//! Command line tool for the encode/hash workflow of Okeuvo Lite.
+//!
+//! Run "okeuvo help" for usage. --agree-to-the-creed is passed on to the
+//! library as agrees_to_the_creed; without it every command that works on
+//! discourses is refused.
+
+#[cfg(feature = "signing")]
+use okeuvo_lite::HashEnvelope;
+use okeuvo_lite::{
+    ConlluToken, DatabaseKind, Discourse, Encoding, HashFormat, HashItemExplanation, HashSpec,
+    InputDiscourse, InputDiscourseTitle, MeaningGrid, MeaningGridIndex, Okeuvo, OkeuvoConfig,
+    OkeuvoError,
+};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::process;
+
+const USAGE: &str =
+    "Usage: okeuvo [--storage <directory>] [--agree-to-the-creed] <command> [arguments]
+
+Commands:
+    init                                  Create (or upgrade) input.db, output.db and metadata.db
+    import-grid <meaningGrid.csv>         Import the meaning grid into metadata.db
+    import-antonyms <data file>           Import the antonym pairs of a Wordnet 3.0 database file
+                                          (data.noun, data.verb, data.adj or data.adv)
+                                          into metadata.db
+    antonyms <synset_id>                  List the antonyms of a synset and their coordinates
+    import <file.conllu> --discourse-id <id> [--hypernym <synset_id>] [--title-sentence <n>]
+                                          Import a CoNLL-U file into input.db as one discourse.
+                                          A word's synset_id is read from SynsetId=<synset_id>
+                                          in its MISC field
+    import <file.json>                    Import a JSON discourse document into input.db
+    export <discourse_id>                 Write a discourse and its encoding as a JSON document
+    encode <discourse_id> [--no-grid]     Encode a discourse into output.db
+    hash <discourse_id> [--virtual] [--format <format>] [--records <count>] [--digits <count>]
+                                          Print the hash of an encoded discourse
+    define-senses <discourse_id>          Define senses for words that are not on the meaning grid.
+                                          Prints the health ratios of each new sense: the x and y
+                                          of its hypernym over its own (about 0.8 is expected)
+    explain <discourse_id> [--virtual] [--k <count>]
+                                          List the concepts nearest to each hash item
+    sign <discourse_id> --secret-key <file> [--virtual] [--format <format>]
+                                          Print the signed envelope of the hash of an encoded
+                                          discourse. The file holds the author's Ed25519 secret
+                                          key as 64 hexadecimal digits
+    verify <file>                         Verify a signed envelope
+    help                                  Show this message
+
+Options:
+    --storage <directory>   Folder holding the databases (default ./storage)
+    --agree-to-the-creed    Agree to The Creed, which every command but init requires
+    --discourse-id <id>     Discourse key for imported CoNLL-U files
+    --hypernym <synset_id>  Hypernym of an imported CoNLL-U discourse
+    --title-sentence <n>    Sentence (from 1) of an imported CoNLL-U file whose words are the
+                            discourse title. Only discourses with a title are given a hash
+    --virtual               Use the virtual (non indicative) events instead of the real ones
+    --no-grid               Encode with the coordinates in input.db as they are
+    --format <format>       Hash format: apex-angle (default), area-ratio or exact
+    --records <count>       Number of records in a hash (default 20)
+    --digits <count>        Digits of each number in a hash: 1 to 3 for apex-angle (default 2),
+                            0 to 16 for area-ratio (default 6), 16 for exact
+    --k <count>             Number of concepts listed per hash item (default 3)
+    --secret-key <file>     File holding an Ed25519 secret key";
+
+/// Default folder of the databases.
+const DEFAULT_STORAGE: &str = "./storage";
+
+/// Default number of concepts explain lists per hash item.
+const DEFAULT_K: usize = 3;
+
+/// A failure of the tool.
+enum CliError {
+    /// The command line is malformed. Usage is shown.
+    Usage(String),
+    /// The library failed.
+    Okeuvo(OkeuvoError),
+}
+
+impl fmt::Display for CliError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CliError::Usage(message) => write!(f, "{}", message),
+            CliError::Okeuvo(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl From<OkeuvoError> for CliError {
+    fn from(e: OkeuvoError) -> CliError {
+        CliError::Okeuvo(e)
+    }
+}
+
+/// The parsed command line.
+#[derive(Debug, Default)]
+struct Options {
+    storage: Option<PathBuf>,
+    agrees_to_the_creed: bool,
+    discourse_id: Option<i32>,
+    is_virtual: bool,
+    no_grid: bool,
+    format: HashFormat,
+    records: Option<usize>,
+    digits: Option<usize>,
+    secret_key: Option<PathBuf>,
+    k: Option<usize>,
+    hypernym: Option<i32>,
+    title_sentence: Option<usize>,
+    /// The command and its arguments.
+    arguments: Vec<String>,
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+
+    let result = parse_options(&args).and_then(|options| run(&options));
+
+    match result {
+        Ok(()) => {}
+        Err(CliError::Usage(message)) => {
+            eprintln!("{}\n\n{}", message, USAGE);
+            process::exit(2);
+        }
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            process::exit(1);
+        }
+    }
+}
+
+/// Splits the command line into options and arguments.
+/// Options may come before or after the command.
+fn parse_options(args: &[String]) -> Result<Options, CliError> {
+    let mut options = Options::default();
+    let mut args = args.iter();
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--agree-to-the-creed" => options.agrees_to_the_creed = true,
+            "--virtual" => options.is_virtual = true,
+            "--no-grid" => options.no_grid = true,
+            "--storage" => options.storage = Some(PathBuf::from(option_value(arg, args.next())?)),
+            "--discourse-id" => {
+                options.discourse_id = Some(parse_number(arg, option_value(arg, args.next())?)?)
+            }
+            "--hypernym" => {
+                options.hypernym = Some(parse_number(arg, option_value(arg, args.next())?)?)
+            }
+            "--title-sentence" => {
+                options.title_sentence = Some(parse_number(arg, option_value(arg, args.next())?)?)
+            }
+            "--format" => {
+                let name: &str = option_value(arg, args.next())?;
+                options.format = HashFormat::from_name(name)
+                    .ok_or_else(|| CliError::Usage(format!("Unknown hash format {}", name)))?
+            }
+            "--records" => {
+                options.records = Some(parse_number(arg, option_value(arg, args.next())?)?)
+            }
+            "--digits" => {
+                options.digits = Some(parse_number(arg, option_value(arg, args.next())?)?)
+            }
+            "--secret-key" => {
+                options.secret_key = Some(PathBuf::from(option_value(arg, args.next())?))
+            }
+            "--k" => options.k = Some(parse_number(arg, option_value(arg, args.next())?)?),
+            _ if arg.starts_with("--") => {
+                return Err(CliError::Usage(format!("Unknown option {}", arg)))
+            }
+            _ => options.arguments.push(arg.clone()),
+        }
+    }
+
+    Ok(options)
+}
+
+/// Gets the value following an option, failing if there is none.
+fn option_value<'a>(option: &str, value: Option<&'a String>) -> Result<&'a str, CliError> {
+    match value {
+        Some(value) if !value.starts_with("--") => Ok(value),
+        _ => Err(CliError::Usage(format!("{} needs a value", option))),
+    }
+}
+
+/// Parses a number given for an option or argument.
+fn parse_number<T: std::str::FromStr>(name: &str, value: &str) -> Result<T, CliError> {
+    value
+        .parse::<T>()
+        .map_err(|_| CliError::Usage(format!("{} must be a number, not '{}'", name, value)))
+}
+
+/// Gets the argument at index (after the command), failing if it is missing.
+fn argument<'a>(options: &'a Options, index: usize, name: &str) -> Result<&'a str, CliError> {
+    match options.arguments.get(index) {
+        Some(value) => Ok(value),
+        None => Err(CliError::Usage(format!(
+            "{} needs {}",
+            options.arguments[0], name
+        ))),
+    }
+}
+
+/// Gets the discourse_id argument of a command.
+fn discourse_id_argument(options: &Options) -> Result<i32, CliError> {
+    parse_number("discourse_id", argument(options, 1, "a discourse_id")?)
+}
+
+/// Opens the databases in the storage folder, creating the folder if need be.
+fn open(options: &Options) -> Result<Okeuvo, CliError> {
+    let storage: &Path = options
+        .storage
+        .as_deref()
+        .unwrap_or_else(|| Path::new(DEFAULT_STORAGE));
+    std::fs::create_dir_all(storage).map_err(OkeuvoError::from)?;
+
+    Ok(Okeuvo::new(OkeuvoConfig::from_directory(storage))?)
+}
+
+/// Runs a command.
+fn run(options: &Options) -> Result<(), CliError> {
+    let command: &str = match options.arguments.first() {
+        Some(command) => command,
+        None => return Err(CliError::Usage("No command given".to_string())),
+    };
+    let agrees_to_the_creed: &bool = &options.agrees_to_the_creed;
+
+    match command {
+        "help" => {
+            println!("{}", USAGE);
+            Ok(())
+        }
+        "init" => {
+            let okeuvo = open(options)?;
+            for kind in &[
+                DatabaseKind::Input,
+                DatabaseKind::Output,
+                DatabaseKind::Metadata,
+            ] {
+                println!(
+                    "{}: schema version {}",
+                    kind.name(),
+                    okeuvo.schema_version(*kind)?
+                );
+            }
+            Ok(okeuvo.close()?)
+        }
+        "import-grid" => {
+            let csv_path: &str = argument(options, 1, "the path of meaningGrid.csv")?;
+            let mut okeuvo = open(options)?;
+            let (version, item_count) =
+                okeuvo.import_meaning_grid(csv_path, agrees_to_the_creed)?;
+            println!(
+                "Imported {} meaning grid items (grid version {}).",
+                item_count, version
+            );
+            Ok(okeuvo.close()?)
+        }
+        "import-antonyms" => {
+            let wordnet_path: &str = argument(options, 1, "the path of a Wordnet data file")?;
+            let mut okeuvo = open(options)?;
+            let pair_count: usize = okeuvo.import_antonyms(wordnet_path, agrees_to_the_creed)?;
+            println!("Imported {} antonym pairs.", pair_count);
+            Ok(okeuvo.close()?)
+        }
+        "antonyms" => {
+            let synset_id: i32 = parse_number("synset_id", argument(options, 1, "a synset_id")?)?;
+            let okeuvo = open(options)?;
+            for antonym in okeuvo.antonyms(&synset_id, agrees_to_the_creed)? {
+                println!(
+                    "{}\t{}\t{}\t{}\t{}",
+                    antonym.lemma, antonym.synset_id, antonym.antonym_lemma, antonym.x, antonym.y
+                );
+            }
+            Ok(okeuvo.close()?)
+        }
+        "import" => import(options),
+        "export" => export(options),
+        "encode" => {
+            let discourse_id: i32 = discourse_id_argument(options)?;
+            let okeuvo = open(options)?;
+            let encoding: Encoding = if options.no_grid {
+                okeuvo.encode_discourse(&discourse_id, agrees_to_the_creed)?
+            } else {
+                let grid: MeaningGrid = okeuvo.load_meaning_grid(agrees_to_the_creed)?;
+                okeuvo.encode_discourse_with_grid(&discourse_id, &grid, agrees_to_the_creed)?
+            };
+            println!(
+                "Encoded discourse {}: {} entities, {} unit tensors, {} real and {} virtual hash items.",
+                discourse_id,
+                encoding.entities.len(),
+                encoding.unit_tensors.len(),
+                encoding.hash_items_real.len(),
+                encoding.hash_items_virtual.len()
+            );
+            Ok(okeuvo.close()?)
+        }
+        "hash" => {
+            let discourse_id: i32 = discourse_id_argument(options)?;
+            let okeuvo = open(options)?;
+            let hash: String = okeuvo.get_hash_with_spec(
+                &discourse_id,
+                agrees_to_the_creed,
+                &options.is_virtual,
+                &hash_spec(options),
+            )?;
+            println!("{}", hash);
+            Ok(okeuvo.close()?)
+        }
+        "define-senses" => {
+            let discourse_id: i32 = discourse_id_argument(options)?;
+            let okeuvo = open(options)?;
+            let senses = okeuvo.batch_define_new_word_sense(discourse_id, agrees_to_the_creed)?;
+            let mut senses: Vec<(i32, (f64, f64))> = senses.into_iter().collect();
+            senses.sort_by_key(|(new_word_id, _)| *new_word_id);
+            // The values are health ratios, not coordinates (see batch_define_new_word_sense).
+            println!("new_word_id\thealth_ratio_x\thealth_ratio_y");
+            for (new_word_id, (health_ratio_x, health_ratio_y)) in &senses {
+                println!("{}\t{}\t{}", new_word_id, health_ratio_x, health_ratio_y);
+            }
+            println!("Defined {} new word senses.", senses.len());
+            Ok(okeuvo.close()?)
+        }
+        "explain" => {
+            let discourse_id: i32 = discourse_id_argument(options)?;
+            let okeuvo = open(options)?;
+            let grid: MeaningGrid = okeuvo.load_meaning_grid(agrees_to_the_creed)?;
+            let index = MeaningGridIndex::new(&grid);
+            let explanations: Vec<HashItemExplanation> = okeuvo.explain_hash(
+                &discourse_id,
+                &options.is_virtual,
+                &index,
+                &options.k.unwrap_or(DEFAULT_K),
+                agrees_to_the_creed,
+            )?;
+            for explanation in &explanations {
+                print_explanation(explanation);
+            }
+            Ok(okeuvo.close()?)
+        }
+        "sign" => sign(options),
+        "verify" => verify(options),
+        _ => Err(CliError::Usage(format!("Unknown command {}", command))),
+    }
+}
+
+/// Gets the hash spec given by the options.
+fn hash_spec(options: &Options) -> HashSpec {
+    let mut spec = HashSpec::new(options.format);
+    if let Some(records) = options.records {
+        spec.records = records;
+    }
+    if let Some(digits) = options.digits {
+        spec.digits = digits;
+    }
+
+    spec
+}
+
+/// Imports a CoNLL-U file, or a JSON document, into input.db.
+fn import(options: &Options) -> Result<(), CliError> {
+    let path: &str = argument(options, 1, "the path of a CoNLL-U or JSON file")?;
+    let is_json: bool = path.to_lowercase().ends_with(".json");
+    let okeuvo = open(options)?;
+
+    let discourse = if is_json {
+        import_json(&okeuvo, path, options)?
+    } else {
+        let discourse_id: i32 = match options.discourse_id {
+            Some(discourse_id) => discourse_id,
+            None => {
+                return Err(CliError::Usage(
+                    "import of a CoNLL-U file needs --discourse-id".to_string(),
+                ))
+            }
+        };
+        let input_discourse = InputDiscourse {
+            discourse_id,
+            hypernym_synset_id: options.hypernym.unwrap_or_default(),
+            ..Default::default()
+        };
+        let mut discourse: Discourse = okeuvo_lite::discourse_from_conllu(
+            path,
+            input_discourse,
+            &mut |_, token| misc_synset_id(token),
+            &options.agrees_to_the_creed,
+        )?;
+
+        if let Some(title_sentence) = options.title_sentence {
+            let sentence_id: i32 = title_sentence as i32;
+            discourse.title = discourse
+                .words
+                .iter()
+                .filter(|word| word.sentence_id == sentence_id)
+                .map(|word| InputDiscourseTitle {
+                    discourse_id,
+                    word_id: word.word_id,
+                })
+                .collect();
+            if discourse.title.is_empty() {
+                return Err(CliError::Usage(format!(
+                    "--title-sentence {} is not a sentence of the file",
+                    title_sentence
+                )));
+            }
+        }
+
+        okeuvo.save_discourse(&discourse, &options.agrees_to_the_creed)?
+    };
+
+    println!(
+        "Imported discourse {}: {} sentences, {} words, {} triplets.",
+        discourse.discourse_id(),
+        discourse.sentences.len(),
+        discourse.words.len(),
+        discourse.triplets.len()
+    );
+
+    Ok(okeuvo.close()?)
+}
+
+/// Gets the Wordnet synset_id of a CoNLL-U word from a "SynsetId=<synset_id>"
+/// entry in its MISC field.
+fn misc_synset_id(token: &ConlluToken) -> Option<i32> {
+    token
+        .misc
+        .split('|')
+        .find_map(|entry| entry.strip_prefix("SynsetId="))
+        .and_then(|synset_id| synset_id.parse::<i32>().ok())
+}
+
+#[cfg(feature = "serde")]
+fn import_json(okeuvo: &Okeuvo, path: &str, options: &Options) -> Result<Discourse, CliError> {
+    let json: String = std::fs::read_to_string(path).map_err(OkeuvoError::from)?;
+
+    Ok(okeuvo.import_json(&json, &options.agrees_to_the_creed)?)
+}
+
+#[cfg(not(feature = "serde"))]
+fn import_json(_okeuvo: &Okeuvo, _path: &str, _options: &Options) -> Result<Discourse, CliError> {
+    Err(serde_missing())
+}
+
+/// Writes a discourse and its encoding as a JSON document to standard output.
+#[cfg(feature = "serde")]
+fn export(options: &Options) -> Result<(), CliError> {
+    let discourse_id: i32 = discourse_id_argument(options)?;
+    let okeuvo = open(options)?;
+    println!(
+        "{}",
+        okeuvo.export_json(&discourse_id, &options.agrees_to_the_creed)?
+    );
+
+    Ok(okeuvo.close()?)
+}
+
+#[cfg(not(feature = "serde"))]
+fn export(_options: &Options) -> Result<(), CliError> {
+    Err(serde_missing())
+}
+
+/// Prints the signed envelope of the hash of an encoded discourse.
+#[cfg(feature = "signing")]
+fn sign(options: &Options) -> Result<(), CliError> {
+    let discourse_id: i32 = discourse_id_argument(options)?;
+    let key_path: &Path = match &options.secret_key {
+        Some(key_path) => key_path,
+        None => return Err(CliError::Usage("sign needs --secret-key".to_string())),
+    };
+    let key: String = std::fs::read_to_string(key_path).map_err(OkeuvoError::from)?;
+    let secret_key: [u8; 32] = okeuvo_lite::from_hex(key.trim(), "the secret key")?;
+
+    let okeuvo = open(options)?;
+    let envelope: HashEnvelope = okeuvo.get_hash_envelope(
+        &discourse_id,
+        &options.agrees_to_the_creed,
+        &options.is_virtual,
+        &hash_spec(options),
+        &okeuvo_lite::public_key(&secret_key),
+    )?;
+    print!(
+        "{}",
+        okeuvo_lite::sign_hash_envelope(&envelope, &secret_key, &options.agrees_to_the_creed)?
+    );
+
+    Ok(okeuvo.close()?)
+}
+
+#[cfg(not(feature = "signing"))]
+fn sign(_options: &Options) -> Result<(), CliError> {
+    Err(signing_missing())
+}
+
+/// Verifies a signed envelope.
+#[cfg(feature = "signing")]
+fn verify(options: &Options) -> Result<(), CliError> {
+    let path: &str = argument(options, 1, "the path of a signed envelope")?;
+    let record: String = std::fs::read_to_string(path).map_err(OkeuvoError::from)?;
+    let signed_envelope = okeuvo_lite::verify_hash_envelope(&record, &options.agrees_to_the_creed)?;
+
+    println!(
+        "Verified the envelope of discourse {}, signed by {}.",
+        signed_envelope.envelope.discourse_id,
+        okeuvo_lite::to_hex(&signed_envelope.envelope.author_public_key)
+    );
+
+    Ok(())
+}
+
+#[cfg(not(feature = "signing"))]
+fn verify(_options: &Options) -> Result<(), CliError> {
+    Err(signing_missing())
+}
+
+#[cfg(not(feature = "signing"))]
+fn signing_missing() -> CliError {
+    CliError::Usage(
+        "Envelopes need okeuvo built with the signing feature (cargo build --features signing)"
+            .to_string(),
+    )
+}
+
+#[cfg(not(feature = "serde"))]
+fn serde_missing() -> CliError {
+    CliError::Usage(
+        "JSON documents need okeuvo built with the serde feature (cargo build --features serde)"
+            .to_string(),
+    )
+}
+
+/// Prints the concepts nearest to one hash item.
+fn print_explanation(explanation: &HashItemExplanation) {
+    let concepts = |nearest: &[(okeuvo_lite::MeaningGridItem, f64)]| -> String {
+        nearest
+            .iter()
+            .map(|(item, distance)| format!("{} ({:.4})", item.synset_id, distance))
+            .collect::<Vec<String>>()
+            .join(", ")
+    };
+
+    println!(
+        "{}\tground ({:.4}, {:.4}): {}",
+        explanation.position,
+        explanation.point.x,
+        explanation.point.y,
+        concepts(&explanation.nearest)
+    );
+    println!(
+        "\texcited ({:.4}, {:.4}): {}",
+        explanation.excited_point.x,
+        explanation.excited_point.y,
+        concepts(&explanation.excited_nearest)
+    );
+}
@@ -0,0 +1,545 @@
+/*
+DO NOT ALTER OR REMOVE COPYRIGHT NOTICES OR THIS HEADER.
+
+Copyright © 2019 Asame Imoni Obiomah. All rights reserved.
+
+Artificial intelligence ethics is of existential importance.
+The licensing model of OkeuvoLite enforces adherence to a strict ethical code.
+
+The contents of this file are subject to the terms of both the GNU General Public License Version 2 only (“GPL”)
+and Inverse license (collectively, the “License”). You may not use this file except in compliance with the License.
+You can obtain a copy of the License at LICENSE.txt. See the License for the specific language governing
+permissions and limitations under the License.
+
+When distributing the software, include this License Header Notice in each file and include the License file at LICENSE.txt.
+*/
+
+//! Reader for the CoNLL-U format of Universal Dependencies
+//! (https://universaldependencies.org/format.html), so that the output of
+//! any UD parser can be loaded as input.
+
+use crate::data::{InputDiscourse, InputSentence, InputWord, InputWordFeature, InputWordRelation};
+use crate::discourse::Discourse;
+use crate::error::OkeuvoError;
+use crate::triplets::extract_triplets;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+
+/// Value CoNLL-U uses for a field that is not given.
+const EMPTY_FIELD: &str = "_";
+
+/// A word (syntactic token) of a CoNLL-U sentence.
+#[derive(Debug, Clone)]
+pub struct ConlluToken {
+    /// Word index in the sentence, starting at 1.
+    pub id: i32,
+    /// Word form or punctuation symbol.
+    pub form: String,
+    /// Lemma or stem of the word form.
+    pub lemma: String,
+    /// Universal part of speech tag.
+    /// https://universaldependencies.org/u/pos/index.html
+    pub upos: String,
+    /// Language specific part of speech tag.
+    pub xpos: String,
+    /// Universal features, as (feature, value) pairs in file order.
+    /// https://universaldependencies.org/u/feat/index.html
+    pub feats: Vec<(String, String)>,
+    /// Id of the head of the word, 0 for the root of the sentence.
+    pub head: i32,
+    /// Universal dependency relation to the head.
+    /// https://universaldependencies.org/u/dep/
+    pub deprel: String,
+    /// Enhanced dependency graph, as written in the file.
+    pub deps: String,
+    /// Any other annotation, as written in the file.
+    pub misc: String,
+}
+
+/// A sentence of a CoNLL-U file.
+#[derive(Debug, Clone, Default)]
+pub struct ConlluSentence {
+    /// Comment lines before the sentence, without the leading "#".
+    pub comments: Vec<String>,
+    /// Words of the sentence, in order.
+    /// Multiword tokens and empty nodes are left out.
+    pub tokens: Vec<ConlluToken>,
+}
+
+impl ConlluSentence {
+    /// Gets the text of the sentence from its "# text = ..." comment.
+    pub fn text(&self) -> Option<&str> {
+        self.comments.iter().find_map(|comment| {
+            let comment = comment.trim();
+            comment
+                .strip_prefix("text")
+                .map(|rest| rest.trim_start())
+                .and_then(|rest| rest.strip_prefix('='))
+                .map(|text| text.trim())
+        })
+    }
+
+    /// Tests if the sentence is a question (ends with a question mark).
+    pub fn is_question(&self) -> bool {
+        match self.text() {
+            Some(text) => text.ends_with('?'),
+            None => self
+                .tokens
+                .last()
+                .map(|token| token.form == "?")
+                .unwrap_or(false),
+        }
+    }
+}
+
+/// Reads a CoNLL-U file.
+pub(crate) fn read_conllu(path: &Path) -> Result<Vec<ConlluSentence>, OkeuvoError> {
+    let file = File::open(path)?;
+
+    parse_conllu(BufReader::new(file))
+}
+
+/// Parses CoNLL-U:
+///
+/// 1. sentences are separated by empty lines,
+/// 2. comment lines start with "#",
+/// 3. word lines hold 10 tab separated fields (ID, FORM, LEMMA, UPOS,
+///    XPOS, FEATS, HEAD, DEPREL, DEPS and MISC).
+///
+/// Multiword token lines (ID "1-2") and empty nodes (ID "1.1") are skipped.
+/// The first malformed line stops parsing with an error that names it.
+pub(crate) fn parse_conllu<R: BufRead>(reader: R) -> Result<Vec<ConlluSentence>, OkeuvoError> {
+    let mut result: Vec<ConlluSentence> = Vec::new();
+    let mut sentence = ConlluSentence::default();
+
+    let mut line_count: usize = 0;
+
+    for (i, line) in reader.lines().enumerate() {
+        let line = line?;
+        let line_number = i + 1;
+        line_count = line_number;
+        let trimmed = line.trim_end_matches(['\r', '\n']);
+
+        if trimmed.trim().is_empty() {
+            if !sentence.tokens.is_empty() {
+                check_heads(&sentence, line_number)?;
+                result.push(sentence);
+            }
+            sentence = ConlluSentence::default();
+            continue;
+        }
+
+        if let Some(comment) = trimmed.strip_prefix('#') {
+            sentence.comments.push(comment.trim().to_string());
+            continue;
+        }
+
+        let columns: Vec<&str> = trimmed.split('\t').collect();
+        if columns.len() != 10 {
+            return Err(OkeuvoError::InvalidConllu {
+                line: Some(line_number),
+                message: format!("expected 10 tab separated fields, found {}", columns.len()),
+            });
+        }
+
+        // Multiword tokens and empty nodes.
+        if columns[0].contains('-') || columns[0].contains('.') {
+            continue;
+        }
+
+        let id: i32 = parse_index(columns[0], "ID", line_number)?;
+        let head: i32 = parse_index(columns[6], "HEAD", line_number)?;
+
+        if id != sentence.tokens.len() as i32 + 1 {
+            return Err(OkeuvoError::InvalidConllu {
+                line: Some(line_number),
+                message: format!(
+                    "ID {} is out of sequence, expected {}",
+                    id,
+                    sentence.tokens.len() + 1
+                ),
+            });
+        }
+
+        let mut feats: Vec<(String, String)> = Vec::new();
+        if columns[5] != EMPTY_FIELD {
+            for feat in columns[5].split('|') {
+                let mut parts = feat.splitn(2, '=');
+                match (parts.next(), parts.next()) {
+                    (Some(feature), Some(value)) if !feature.is_empty() && !value.is_empty() => {
+                        feats.push((feature.to_string(), value.to_string()))
+                    }
+                    _ => {
+                        return Err(OkeuvoError::InvalidConllu {
+                            line: Some(line_number),
+                            message: format!("feature '{}' is not in the form Feature=Value", feat),
+                        })
+                    }
+                }
+            }
+        }
+
+        sentence.tokens.push(ConlluToken {
+            id,
+            form: columns[1].to_string(),
+            lemma: columns[2].to_string(),
+            upos: columns[3].to_string(),
+            xpos: columns[4].to_string(),
+            feats,
+            head,
+            deprel: columns[7].to_string(),
+            deps: columns[8].to_string(),
+            misc: columns[9].to_string(),
+        });
+    }
+
+    if !sentence.tokens.is_empty() {
+        check_heads(&sentence, line_count + 1)?;
+        result.push(sentence);
+    }
+
+    Ok(result)
+}
+
+/// Checks that the head of every word of a sentence is a word of the sentence (or 0).
+/// end_line is the line that ends the sentence.
+fn check_heads(sentence: &ConlluSentence, end_line: usize) -> Result<(), OkeuvoError> {
+    for token in &sentence.tokens {
+        if token.head > sentence.tokens.len() as i32 {
+            return Err(OkeuvoError::InvalidConllu {
+                line: Some(end_line),
+                message: format!(
+                    "HEAD {} of word {} is not a word of the sentence",
+                    token.head, token.id
+                ),
+            });
+        }
+    }
+
+    Ok(())
+}
+
+/// Parses a word index (ID or HEAD), rejecting values that are not whole numbers.
+fn parse_index(value: &str, field: &str, line_number: usize) -> Result<i32, OkeuvoError> {
+    match value.trim().parse::<i32>() {
+        Ok(index) if index >= 0 => Ok(index),
+        _ => Err(OkeuvoError::InvalidConllu {
+            line: Some(line_number),
+            message: format!("{} '{}' is not a word index", field, value),
+        }),
+    }
+}
+
+/// Builds a discourse from CoNLL-U sentences.
+/// Each token becomes an InputWord (lemma as lexeme, ID as index_of_word and
+/// UPOS as pos), each FEATS entry an InputWordFeature, and HEAD/DEPREL an
+/// InputWordRelation from the word to its head. The root of a sentence is
+/// related to word_id -1.
+///
+/// Triplets and sections are derived from the relations (see extract_triplets).
+///
+/// Sentences and words are numbered from 1 within the discourse.
+/// synset_id is called for every token to supply its Wordnet synset_id
+/// (0 when it returns None).
+pub(crate) fn conllu_to_discourse(
+    discourse: InputDiscourse,
+    sentences: &[ConlluSentence],
+    synset_id: &mut dyn FnMut(&ConlluSentence, &ConlluToken) -> Option<i32>,
+) -> Discourse {
+    let discourse_id: i32 = discourse.discourse_id;
+    let mut result = Discourse::new(discourse);
+
+    for (i, sentence) in sentences.iter().enumerate() {
+        let sentence_id: i32 = i as i32 + 1;
+        // word_id of the word before the first word of this sentence.
+        let word_id_offset: i32 = result.words.len() as i32;
+
+        result.sentences.push(InputSentence {
+            sentence_id,
+            discourse_id,
+            is_question: sentence.is_question(),
+        });
+
+        for token in &sentence.tokens {
+            let word_id: i32 = word_id_offset + token.id;
+
+            result.words.push(InputWord {
+                word_id,
+                sentence_id,
+                synset_id: synset_id(sentence, token).unwrap_or(0),
+                index_of_word: token.id,
+                lexeme: match token.lemma.as_str() {
+                    EMPTY_FIELD => token.form.clone(),
+                    lemma => lemma.to_string(),
+                },
+                instance_name: match token.upos.as_str() {
+                    "PROPN" => token.form.clone(),
+                    _ => String::new(),
+                },
+                instance_index: 0,
+                pos: token.upos.clone(),
+                x: 0.0,
+                y: 0.0,
+                is_transition: false,
+                new_word_id: -1,
+            });
+
+            for (feature, value) in &token.feats {
+                result.features.push(InputWordFeature {
+                    word_id,
+                    ud_relation: token.deprel.clone(),
+                    ud_feature: feature.clone(),
+                    ud_feature_value: value.clone(),
+                });
+            }
+
+            result.relations.push(InputWordRelation {
+                word_id,
+                word_id_modified: match token.head {
+                    0 => -1,
+                    head => word_id_offset + head,
+                },
+                ud_relation: token.deprel.clone(),
+            });
+        }
+    }
+
+    let (triplets, sections) = extract_triplets(&result);
+    result.triplets = triplets;
+    result.sections = sections;
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Joins the fields of a word line with tabs.
+    fn line(fields: &[&str]) -> String {
+        fields.join("\t")
+    }
+
+    fn parse(text: &str) -> Result<Vec<ConlluSentence>, OkeuvoError> {
+        parse_conllu(text.as_bytes())
+    }
+
+    fn error_line(result: Result<Vec<ConlluSentence>, OkeuvoError>) -> Option<usize> {
+        match result {
+            Err(OkeuvoError::InvalidConllu { line, .. }) => line,
+            other => panic!("expected InvalidConllu, got {:?}", other),
+        }
+    }
+
+    /// "She reads books."
+    fn she_reads_books() -> String {
+        [
+            "# text = She reads books.".to_string(),
+            line(&[
+                "1",
+                "She",
+                "she",
+                "PRON",
+                "PRP",
+                "Case=Nom|Number=Sing",
+                "2",
+                "nsubj",
+                "_",
+                "_",
+            ]),
+            line(&[
+                "2",
+                "reads",
+                "read",
+                "VERB",
+                "VBZ",
+                "Tense=Pres",
+                "0",
+                "root",
+                "_",
+                "_",
+            ]),
+            line(&[
+                "3",
+                "books",
+                "book",
+                "NOUN",
+                "NNS",
+                "Number=Plur",
+                "2",
+                "obj",
+                "_",
+                "_",
+            ]),
+            line(&["4", ".", ".", "PUNCT", ".", "_", "2", "punct", "_", "_"]),
+            String::new(),
+        ]
+        .join("\n")
+    }
+
+    #[test]
+    fn parses_words_features_and_comments() {
+        let sentences = parse(&she_reads_books()).unwrap();
+
+        assert_eq!(sentences.len(), 1);
+        assert_eq!(sentences[0].text(), Some("She reads books."));
+        assert!(!sentences[0].is_question());
+
+        let tokens = &sentences[0].tokens;
+        assert_eq!(tokens.len(), 4);
+        assert_eq!(tokens[0].form, "She");
+        assert_eq!(
+            tokens[0].feats,
+            vec![
+                ("Case".to_string(), "Nom".to_string()),
+                ("Number".to_string(), "Sing".to_string())
+            ]
+        );
+        assert_eq!(tokens[1].head, 0);
+        assert_eq!(tokens[2].deprel, "obj");
+        assert!(tokens[3].feats.is_empty());
+    }
+
+    #[test]
+    fn skips_multiword_tokens_and_empty_nodes() {
+        let text = [
+            line(&["1-2", "don't", "_", "_", "_", "_", "_", "_", "_", "_"]),
+            line(&["1", "do", "do", "AUX", "VBP", "_", "3", "aux", "_", "_"]),
+            line(&[
+                "2",
+                "n't",
+                "not",
+                "PART",
+                "RB",
+                "Polarity=Neg",
+                "3",
+                "advmod",
+                "_",
+                "_",
+            ]),
+            line(&["3", "go", "go", "VERB", "VB", "_", "0", "root", "_", "_"]),
+            line(&[
+                "3.1", "go", "go", "VERB", "VB", "_", "_", "_", "3:conj", "_",
+            ]),
+            line(&["4", "!", "!", "PUNCT", ".", "_", "3", "punct", "_", "_"]),
+        ]
+        .join("\n");
+
+        let sentences = parse(&text).unwrap();
+
+        assert_eq!(sentences.len(), 1);
+        let ids: Vec<i32> = sentences[0].tokens.iter().map(|token| token.id).collect();
+        assert_eq!(ids, vec![1, 2, 3, 4]);
+        assert_eq!(sentences[0].tokens[1].lemma, "not");
+    }
+
+    #[test]
+    fn splits_sentences_on_empty_lines() {
+        let text = format!("{}\n{}", she_reads_books(), she_reads_books());
+
+        let sentences = parse(&text).unwrap();
+
+        assert_eq!(sentences.len(), 2);
+        assert_eq!(sentences[1].tokens.len(), 4);
+    }
+
+    #[test]
+    fn rejects_a_word_line_with_too_few_fields() {
+        let text = [
+            "# text = Hi".to_string(),
+            line(&["1", "Hi", "hi", "INTJ", "UH", "_", "0", "root", "_"]),
+        ]
+        .join("\n");
+
+        assert_eq!(error_line(parse(&text)), Some(2));
+    }
+
+    #[test]
+    fn rejects_a_word_line_with_too_many_fields() {
+        let text = line(&[
+            "1", "Hi", "hi", "INTJ", "UH", "_", "0", "root", "_", "_", "_",
+        ]);
+
+        assert_eq!(error_line(parse(&text)), Some(1));
+    }
+
+    #[test]
+    fn rejects_malformed_ids_heads_and_features() {
+        let bad_id = line(&["x", "Hi", "hi", "INTJ", "UH", "_", "0", "root", "_", "_"]);
+        assert_eq!(error_line(parse(&bad_id)), Some(1));
+
+        let out_of_sequence = [
+            line(&["1", "Hi", "hi", "INTJ", "UH", "_", "0", "root", "_", "_"]),
+            line(&[
+                "3", "there", "there", "ADV", "RB", "_", "1", "advmod", "_", "_",
+            ]),
+        ]
+        .join("\n");
+        assert_eq!(error_line(parse(&out_of_sequence)), Some(2));
+
+        let bad_feature = line(&[
+            "1", "Hi", "hi", "INTJ", "UH", "Polite", "0", "root", "_", "_",
+        ]);
+        assert_eq!(error_line(parse(&bad_feature)), Some(1));
+
+        // A head outside the sentence is reported at the line that ends it.
+        let bad_head = [
+            line(&["1", "Hi", "hi", "INTJ", "UH", "_", "5", "root", "_", "_"]),
+            String::new(),
+        ]
+        .join("\n");
+        assert_eq!(error_line(parse(&bad_head)), Some(2));
+    }
+
+    #[test]
+    fn builds_a_discourse_from_a_sentence() {
+        let sentences = parse(&she_reads_books()).unwrap();
+        let input = InputDiscourse {
+            discourse_id: 7,
+            ..Default::default()
+        };
+
+        let discourse = conllu_to_discourse(input, &sentences, &mut |_, token| match token
+            .lemma
+            .as_str()
+        {
+            "read" => Some(100),
+            _ => None,
+        });
+
+        assert_eq!(discourse.sentences.len(), 1);
+        assert_eq!(discourse.sentences[0].discourse_id, 7);
+
+        let lexemes: Vec<&str> = discourse
+            .words
+            .iter()
+            .map(|word| word.lexeme.as_str())
+            .collect();
+        assert_eq!(lexemes, vec!["she", "read", "book", "."]);
+        assert_eq!(discourse.words[1].synset_id, 100);
+        assert_eq!(discourse.words[0].synset_id, 0);
+        assert_eq!(discourse.features.len(), 4);
+
+        let root = discourse
+            .relations
+            .iter()
+            .find(|relation| relation.word_id == 2)
+            .unwrap();
+        assert_eq!(root.word_id_modified, -1);
+        let object = discourse
+            .relations
+            .iter()
+            .find(|relation| relation.word_id == 3)
+            .unwrap();
+        assert_eq!(object.word_id_modified, 2);
+
+        assert_eq!(discourse.triplets.len(), 1);
+        let sections: Vec<(i32, i32)> = discourse
+            .sections
+            .iter()
+            .map(|section| (section.word_id, section.section_type))
+            .collect();
+        assert_eq!(sections, vec![(1, 1), (2, 2), (3, 3)]);
+    }
+}
@@ -14,10 +14,12 @@ permissions and limitations under the License.
 When distributing the software, include this License Header Notice in each file and include the License file at LICENSE.txt.
 */
 
+use crate::discourse::{Discourse, Encoding};
+use crate::error::OkeuvoError;
 use crate::math::Point2D;
-use crate::METAPATH;
+use crate::version::Versions;
 use rusqlite::types::ToSql;
-use rusqlite::{Connection, Result, NO_PARAMS};
+use rusqlite::{Connection, Result, Row, NO_PARAMS};
 use std::collections::HashMap;
 
 /// Structure to hold the summed vectors of a triplet.
@@ -41,13 +43,13 @@ pub struct ExcitationData {
 
 /// Structure to hold a meaning grid item.
 /// Corresponds to meaning_grid_item in database, metadata.db.
-#[derive(Debug)]
-pub struct MeaningGridItem {    
+#[derive(Debug, Clone)]
+pub struct MeaningGridItem {
     /// The first coordinate of this words
     /// position on the meaning grid.
     /// A value of 0 indicates a word that
     /// does not exist on the meaning grid.
-    pub x: f64,    
+    pub x: f64,
     /// The second coordinate of this words
     /// position on the meaning grid.
     /// A value of 0 indicates a word that
@@ -59,9 +61,86 @@ pub struct MeaningGridItem {
     pub synset_id: i32,
 }
 
+impl MeaningGridItem {
+    /// Complexity, the estimated measure of this meanings
+    /// aggregation of diverse functions; c = (x * y) / 2.
+    pub fn complexity(&self) -> f64 {
+        self.x * self.y * 0.5
+    }
+
+    /// Length of the position vector of this meaning.
+    pub fn vector_length(&self) -> f64 {
+        self.x.hypot(self.y)
+    }
+}
+
+/// A pair of words of opposite meaning (Wordnet antonyms).
+/// Corresponds to antonyms in database, metadata.db.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AntonymPair {
+    /// Part of speech of the pair, the leading digit of its synset_id's:
+    /// 1 = noun, 2 = verb, 3 = adjective and 4 = adverb.
+    pub coarse_class: i32,
+    /// Wordnet synset_id of the first word, in SQL format.
+    pub synset_id1: i32,
+    /// Lemma of the first word.
+    pub lemma1: String,
+    /// Wordnet synset_id of the second word, in SQL format.
+    pub synset_id2: i32,
+    /// Lemma of the second word.
+    pub lemma2: String,
+}
+
+impl AntonymPair {
+    /// Gets the pair with the word of synset_id first,
+    /// or None if neither word belongs to synset_id.
+    pub fn oriented(&self, synset_id: &i32) -> Option<AntonymPair> {
+        if self.synset_id1 == *synset_id {
+            return Some(self.clone());
+        }
+
+        if self.synset_id2 == *synset_id {
+            return Some(AntonymPair {
+                coarse_class: self.coarse_class,
+                synset_id1: self.synset_id2,
+                lemma1: self.lemma2.clone(),
+                synset_id2: self.synset_id1,
+                lemma2: self.lemma1.clone(),
+            });
+        }
+
+        None
+    }
+}
+
+/// An antonym of a synset, with its place on the meaning grid.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Antonym {
+    /// Part of speech of the antonym: 1 = noun, 2 = verb,
+    /// 3 = adjective and 4 = adverb.
+    pub coarse_class: i32,
+    /// Lemma of the word of the synset looked up that the antonym opposes.
+    pub lemma: String,
+    /// Wordnet synset_id of the antonym, in SQL format.
+    pub synset_id: i32,
+    /// Lemma of the antonym.
+    pub antonym_lemma: String,
+    /// The first coordinate of the antonyms
+    /// position on the meaning grid.
+    /// A value of 0 indicates an antonym that
+    /// does not exist on the meaning grid.
+    pub x: f64,
+    /// The second coordinate of the antonyms
+    /// position on the meaning grid.
+    /// A value of 0 indicates an antonym that
+    /// does not exist on the meaning grid.
+    pub y: f64,
+}
+
 /// Structure to hold an entity.
 /// Corresponds to entity in database, output.db.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Entity {
     /// Primary key, autonumber.
     pub entity_id: i32,
@@ -105,7 +184,8 @@ pub struct Entity {
 /// A virtual object is one obtained from a triplet whose mood is not indicative,
 /// these can be conditionals and future tense events for example.
 /// Each virtual is a new dimension and as many as is necessary should be spawned.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct UnitTensorEtherealDef {
     /// Virtual items branch off index from unit tensor series.
     pub branch_id: i64,
@@ -113,12 +193,36 @@ pub struct UnitTensorEtherealDef {
     pub rejoin_id: i64,
 }
 
+/// Links a unit tensor to an entity that tells where or when its event happens.
+/// A unit tensor can have several of each (see function, get_locations in location.rs).
+/// Corresponds to unit_tensor_location in database, output.db.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct UnitTensorLocation {
+    /// The unit tensor whose event is located.
+    pub unit_tensor_id: i64,
+    /// The entity_id of the "where" or the "when".
+    pub entity_id: i64,
+    /// 2 = geographic location (where),
+    /// 3 = temporal location (when),
+    /// 4 = temporal location, terminative (when, until).
+    pub location_type: i32,
+}
+
+impl UnitTensorLocation {
+    /// Tests if the location is a "when" rather than a "where".
+    pub fn is_temporal(&self) -> bool {
+        self.location_type == 3 || self.location_type == 4
+    }
+}
+
 /// Structure to hold a unit tensor.
 /// It is a node in a graph whose edges are time.
 /// This graph is the backbone on which all other graphs
 /// (directed and undirected) that make up the discourse hang.
 /// Corresponds to unit_tensor in database, output.db.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct UnitTensor {
     /// Primary key, autonumber.
     pub unit_tensor_id: i32,
@@ -132,12 +236,13 @@ pub struct UnitTensor {
     pub object_entity_id: i64,
     /// The entity_id of the subject.
     pub subject_entity_id: i64,
-    /// The entity_id of the "where".
+    /// The entity_id of the first "where", -1 if there is none.
+    /// All of them are in unit_tensor_location (see Encoding::locations).
     pub where_entity_id: i64,
-    /// The entity_id of the "when".
+    /// The entity_id of the first "when", -1 if there is none.
     /// This field can only have a value
     /// when an explicit time is given.
-    /// In this case, sister field, object_entity_id must be empty.
+    /// All of them are in unit_tensor_location (see Encoding::locations).
     pub when_entity_id: i64,
     /// The entity_id of the predicate, most likely a verb.
     pub predicate_entity_id: i64,
@@ -149,6 +254,10 @@ pub struct UnitTensor {
     pub excited_x: f64,
     /// Holds excited value of the y coordinate (see function, get_excitation in lib.rs).
     pub excited_y: f64,
+    /// Indicates that the predicate or the object of the triplet is negated
+    /// (see negation.rs). Their entities hold the negated coordinates.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub is_negated: bool,
 }
 
 /// Redundant.
@@ -160,7 +269,8 @@ pub struct UniversalDependencyTags {
 
 /// Structure to describe a discourse, for use in GUI form.
 /// Corresponds to input_discourse in database, input.db.
-#[derive(Debug)]
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct InputDiscourse {
     /// Unique discourse key, supplied by the network - autonumber.
     pub discourse_id: i32,
@@ -199,7 +309,8 @@ pub struct InputDiscourse {
 
 /// Word ids of the words that make up the InputDiscourse title.
 /// Corresponds to input_discourse_title in database, input.db.
-#[derive(Debug)]
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct InputDiscourseTitle {
     /// Unique discourse key, supplied by the network - autonumber.
     pub discourse_id: i32,
@@ -211,7 +322,8 @@ pub struct InputDiscourseTitle {
 
 /// Structure that acts a node for all words in a sentence.
 /// Corresponds to input_sentence in database, input.db.
-#[derive(Debug)]
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct InputSentence {
     /// Primary key, autonumber.
     pub sentence_id: i32,
@@ -223,7 +335,8 @@ pub struct InputSentence {
 
 /// Holds data for a word.
 /// Corresponds to input_word in database, input.db.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct InputWord {
     /// Index of word in array of words in sentence.
     /// Primary key and autonumber column in table
@@ -269,12 +382,14 @@ pub struct InputWord {
     /// When this value is greater than -1
     /// it Identifies a new word - one not
     /// found on the meaning grid.
+    /// Stored as null in input.db when it is -1.
     pub new_word_id: i32,
 }
 
 /// Data for a words Universal Features.
 /// Corresponds to input_word_feature in database, input.db.
-#[derive(Debug)]
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct InputWordFeature {
     /// Index of word in array of words in sentence.
     /// Primary key and autonumber column in table
@@ -293,6 +408,8 @@ pub struct InputWordFeature {
 
 /// Represents a word that doesn't exist on the meaning grid.
 /// Corresponds to input_new_word_def in database, input.db.
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct InputNewWordDef {
     /// Index of word in array of words in sentence.
     /// Primary key and autonumber column in table
@@ -317,7 +434,8 @@ pub struct InputNewWordDef {
 }
 
 /// Holds Universal Features that are exempt from virtuality checks.
-#[derive(Debug)]
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct InputExemptFeature {
     /// Unique discourse key, supplied by the network - autonumber.
     pub discourse_id: i32,
@@ -331,11 +449,13 @@ pub struct InputExemptFeature {
 
 /// Edge data for a words Universal Dependency tags.
 /// Corresponds to input_word_relation in database, input.db.
-#[derive(Debug)]
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct InputWordRelation {
     /// The word id of the first component of the ordered pair of this edge.
     pub word_id: i32,
     /// The word id of the second component of the ordered pair of this edge.
+    /// -1 for the root, which has no head (stored as null in input.db).
     pub word_id_modified: i32,
     /// The Universal Dependency tag that denotes the edge type.
     pub ud_relation: String,
@@ -343,7 +463,8 @@ pub struct InputWordRelation {
 
 /// Represents one of three sections of a triplet.
 /// Corresponds to input_section in database, input.db.
-#[derive(Debug)]
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct InputSection {
     /// Primary key and autonumber column.
     pub section_id: i32,
@@ -366,7 +487,8 @@ pub struct InputSection {
 
 /// Data for a triplet.
 /// Corresponds to input_triplet in database, input.db.
-#[derive(Debug)]
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct InputTriplet {
     /// Primary key and autonumber column.
     pub triplet_id: i32,
@@ -383,6 +505,7 @@ pub struct InputTriplet {
 /// Data for normal and excited states of a unit tensor.
 /// Corresponds to hash_item in database, input.db.
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct HashItem {
     /// Polar coordinate radius obtained from converting
     /// the unit tensors ground state x and y coordinates.
@@ -407,7 +530,7 @@ pub struct HashItem {
 
 /// Holds the hash items in their final format before
 /// the hash is generated.
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct HashItemFormatted {
     /// The lean direction of the angle, upper_angle.
     /// 
@@ -449,12 +572,12 @@ pub(crate) fn update_input_new_word_def(
     Ok(())
 }
 
-pub(crate) fn insert_unit_tensor(conn: &Connection, unit_tensor: &UnitTensor) -> Result<(i64)> {
+pub(crate) fn insert_unit_tensor(conn: &Connection, unit_tensor: &UnitTensor) -> Result<i64> {
     conn.execute(
         "insert into unit_tensor
         (sentence_id, discourse_id, object_entity_id, subject_entity_id, where_entity_id, when_entity_id,
-        predicate_entity_id, tense, mood,excited_x, excited_y)
-        values (?1,?2,?3,?4,?5,?6.?7,?8,?9,?10)",
+        predicate_entity_id, tense, mood, excited_x, excited_y, is_negated)
+        values (?1,?2,?3,?4,?5,?6,?7,?8,?9,?10,?11,?12)",
         &[
             &unit_tensor.sentence_id as &dyn ToSql,
             &unit_tensor.discourse_id as &dyn ToSql,
@@ -466,7 +589,8 @@ pub(crate) fn insert_unit_tensor(conn: &Connection, unit_tensor: &UnitTensor) ->
             &unit_tensor.tense as &dyn ToSql,
             &unit_tensor.mood as &dyn ToSql,
             &unit_tensor.excited_x as &dyn ToSql,
-            &unit_tensor.excited_y as &dyn ToSql
+            &unit_tensor.excited_y as &dyn ToSql,
+            &unit_tensor.is_negated as &dyn ToSql,
         ],
     )?;
 
@@ -482,9 +606,9 @@ pub(crate) fn insert_hash(
     is_virtual: &bool,
 ) -> Result<()> {
     conn.execute(
-        "insert into hashes (radius,radius_original,angle,angle_original,hash_type,discourse_id,
+        "insert into hash_item (radius,radius_original,angle,angle_original,hash_type,discourse_id,
         excited_radius,excited_angle,order_by,is_virtual)
-        values (?1,?2,?3,?4,?5,?6,?7,?8)",
+        values (?1,?2,?3,?4,?5,?6,?7,?8,?9,?10)",
         &[
             &hash_item.radius.round() as &dyn ToSql,
             &hash_item.radius as &dyn ToSql,
@@ -502,216 +626,480 @@ pub(crate) fn insert_hash(
     Ok(())
 }
 
-pub(crate) fn insert_unit_tensor_ethereal(conn: &Connection, branch_id: &i64) -> Result<i64> {
+pub(crate) fn insert_unit_tensor_ethereal(
+    conn: &Connection,
+    branch_id: &i64,
+    rejoin_id: &i64,
+) -> Result<i64> {
     conn.execute(
-        "insert into unit_tensor_ethereal_def (branch_id)
-        values (?1)",
-        &[&branch_id as &dyn ToSql],
+        "insert into unit_tensor_ethereal_def (branch_id, rejoin_id)
+        values (?1,?2)",
+        [&branch_id as &dyn ToSql, &rejoin_id as &dyn ToSql],
     )?;
 
     Ok(conn.last_insert_rowid())
 }
 
-pub(crate) fn update_unit_tensor_ethereal(
+pub(crate) fn insert_unit_tensor_location(
     conn: &Connection,
-    last_row_id: &i64,
-    rejoin_id: &i64,
-) -> Result<()> {
+    location: &UnitTensorLocation,
+) -> Result<i64> {
     conn.execute(
-        "update unit_tensor_ethereal_def set rejoin_id = ?1 where id = ?2)",
-        &[&rejoin_id as &dyn ToSql, &last_row_id as &dyn ToSql],
+        "insert into unit_tensor_location (unit_tensor_id, entity_id, location_type)
+        values (?1,?2,?3)",
+        [
+            &location.unit_tensor_id as &dyn ToSql,
+            &location.entity_id as &dyn ToSql,
+            &location.location_type as &dyn ToSql,
+        ],
     )?;
 
-    Ok(())
+    Ok(conn.last_insert_rowid())
 }
 
-/// Inserts an entity if it does not occur in the database or updates it if it does.
-pub(crate) fn insert_or_update_entity(conn: &Connection, entity: &Entity) -> Result<(i64)> {
-    let mut current_entity_id: i64 = -1;
-    // 0 is the default value, so it doesn't count in the check for existing
-    let has_duplicate_instance_index: i64 = conn.query_row(
-        "select exists (select 1 from entity where instance_index > 0 and instance_index = ?1)",
-        &[&entity.instance_index],
-        |row| row.get(0),
+pub(crate) fn insert_entity(conn: &Connection, entity: &Entity) -> Result<i64> {
+    conn.execute(
+        "insert into entity (instance_index, discourse_id, synset_id, word_id, rank, x, y, triplet_id)
+        values (?1,?2,?3,?4,?5,?6,?7,?8)",
+        [
+            &entity.instance_index as &dyn ToSql,
+            &entity.discourse_id as &dyn ToSql,
+            &entity.synset_id as &dyn ToSql,
+            &entity.word_id as &dyn ToSql,
+            &entity.rank as &dyn ToSql,
+            &entity.x as &dyn ToSql,
+            &entity.y as &dyn ToSql,
+            &entity.triplet_id as &dyn ToSql,
+        ],
     )?;
 
-    // No existing record for this instance, so insert.
-    if has_duplicate_instance_index == 0 {
-        conn.execute(
-            "insert into entity (instance_index, synset_id, word_id, rank, x, y, triplet_id)
-        values (?1,?2,?3,?4,?5,?6,?7)",
-            &[
-                &entity.instance_index as &dyn ToSql,
-                &entity.synset_id as &dyn ToSql,
-                &entity.word_id as &dyn ToSql,
-                &entity.rank as &dyn ToSql,
-                &entity.x as &dyn ToSql,
-                &entity.y as &dyn ToSql,
-                &entity.triplet_id as &dyn ToSql,
-            ],
+    Ok(conn.last_insert_rowid())
+}
+
+/// Writes an encoding to output.db, replacing any earlier encoding of the same discourse.
+/// Entity and unit tensor ids of the encoding are mapped to the keys the rows are given.
+/// Everything happens in one transaction, so a failed write leaves the earlier encoding in place.
+pub(crate) fn insert_encoding(conn: &Connection, encoding: &Encoding) -> Result<()> {
+    let tx = conn.unchecked_transaction()?;
+
+    tx.execute(
+        "delete from unit_tensor_ethereal_def where branch_id in
+        (select unit_tensor_id from unit_tensor where discourse_id = ?1)",
+        [&encoding.discourse_id],
+    )?;
+    tx.execute(
+        "delete from unit_tensor_location where unit_tensor_id in
+        (select unit_tensor_id from unit_tensor where discourse_id = ?1)",
+        [&encoding.discourse_id],
+    )?;
+    tx.execute(
+        "delete from unit_tensor where discourse_id = ?1",
+        [&encoding.discourse_id],
+    )?;
+    tx.execute(
+        "delete from entity where discourse_id = ?1",
+        [&encoding.discourse_id],
+    )?;
+    tx.execute(
+        "delete from hash_item where discourse_id = ?1",
+        [&encoding.discourse_id],
+    )?;
+    tx.execute(
+        "delete from encoding_version where discourse_id = ?1",
+        [&encoding.discourse_id],
+    )?;
+
+    // Format - HashMap<entity_id in encoding, entity_id in output.db>.
+    let mut entity_ids: HashMap<i64, i64> = HashMap::new();
+    for entity in &encoding.entities {
+        entity_ids.insert(i64::from(entity.entity_id), insert_entity(&tx, entity)?);
+    }
+
+    // Entity ids of -1 (no entity) are kept as they are.
+    let entity_id = |id: &i64| -> i64 { *entity_ids.get(id).unwrap_or(id) };
+
+    // Format - HashMap<unit_tensor_id in encoding, unit_tensor_id in output.db>.
+    let mut unit_tensor_ids: HashMap<i64, i64> = HashMap::new();
+    for unit_tensor in &encoding.unit_tensors {
+        let stored = UnitTensor {
+            object_entity_id: entity_id(&unit_tensor.object_entity_id),
+            subject_entity_id: entity_id(&unit_tensor.subject_entity_id),
+            where_entity_id: entity_id(&unit_tensor.where_entity_id),
+            when_entity_id: entity_id(&unit_tensor.when_entity_id),
+            predicate_entity_id: entity_id(&unit_tensor.predicate_entity_id),
+            ..unit_tensor.clone()
+        };
+
+        unit_tensor_ids.insert(
+            i64::from(unit_tensor.unit_tensor_id),
+            insert_unit_tensor(&tx, &stored)?,
+        );
+    }
+
+    for def in &encoding.ethereal_defs {
+        insert_unit_tensor_ethereal(
+            &tx,
+            unit_tensor_ids
+                .get(&def.branch_id)
+                .unwrap_or(&def.branch_id),
+            unit_tensor_ids
+                .get(&def.rejoin_id)
+                .unwrap_or(&def.rejoin_id),
         )?;
+    }
+
+    for location in &encoding.locations {
+        let stored = UnitTensorLocation {
+            unit_tensor_id: *unit_tensor_ids
+                .get(&location.unit_tensor_id)
+                .unwrap_or(&location.unit_tensor_id),
+            entity_id: entity_id(&location.entity_id),
+            ..location.clone()
+        };
 
-        current_entity_id = conn.last_insert_rowid();
+        insert_unit_tensor_location(&tx, &stored)?;
     }
-    // There is an existing record, so update.
-    else {
-        conn.execute(
-        "update set instance_index = ?1, synset_id = ?2, word_id = ?3, rank = ?4, x = ?5, y = ?6, triplet_id = ?7",
-        &[
-            &entity.instance_index as &dyn ToSql,
-                &entity.synset_id as &dyn ToSql,
-                &entity.word_id as &dyn ToSql,
-                &entity.rank as &dyn ToSql,
-                &entity.x as &dyn ToSql,
-                &entity.y as &dyn ToSql,
-                &entity.triplet_id as &dyn ToSql,
+
+    for (is_virtual, hash_items) in &[
+        (false, &encoding.hash_items_real),
+        (true, &encoding.hash_items_virtual),
+    ] {
+        for (i, hash_item) in hash_items.iter().enumerate() {
+            insert_hash(&tx, hash_item, &(i as i32), is_virtual)?;
+        }
+    }
+
+    tx.execute(
+        "insert into encoding_version (discourse_id, library_version, grid_version)
+        values (?1, ?2, ?3)",
+        [
+            &encoding.discourse_id as &dyn ToSql,
+            &encoding.versions.library,
+            &encoding.versions.grid,
+        ],
+    )?;
+
+    tx.commit()
+}
+
+/// Writes a discourse (see Discourse) to input.db, replacing any earlier rows of
+/// the same discourse_id. The discourse_id is kept; sentence_id, word_id, triplet_id,
+/// section_id and new_word_id are numbered within the discourse and are replaced by
+/// the database keys, which is why the stored discourse is returned.
+///
+/// Return format: the discourse as stored.
+pub(crate) fn insert_input_discourse(
+    conn: &Connection,
+    discourse: &Discourse,
+) -> Result<Discourse> {
+    let discourse_id: i32 = discourse.discourse_id();
+    let tx = conn.unchecked_transaction()?;
+
+    tx.execute(
+        "delete from input_word_feature where word_id in
+        (select w.word_id from input_word w
+        inner join input_sentence s on s.sentence_id = w.sentence_id where s.discourse_id = ?1)",
+        [discourse_id],
+    )?;
+    tx.execute(
+        "delete from input_word_relation where word_id in
+        (select w.word_id from input_word w
+        inner join input_sentence s on s.sentence_id = w.sentence_id where s.discourse_id = ?1)",
+        [discourse_id],
+    )?;
+    delete_triplet_rows(&tx, &discourse_id)?;
+    tx.execute(
+        "delete from input_word where sentence_id in
+        (select sentence_id from input_sentence where discourse_id = ?1)",
+        [discourse_id],
+    )?;
+    for table in &[
+        "input_sentence",
+        "input_discourse_title",
+        "input_exempt_feature",
+        "input_new_word_def",
+        "input_discourse",
+    ] {
+        tx.execute(
+            &format!("delete from {} where discourse_id = ?1", table),
+            [discourse_id],
+        )?;
+    }
+
+    let input_discourse = &discourse.discourse;
+    tx.execute(
+        "insert into input_discourse (discourse_id, hypernym_synset_id, document_hash, author_public_hash,
+        author_title, author_first_name, author_middle_name, author_surname, author_zone, date_unix_epoch, x, y)
+        values (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
+        [
+            &discourse_id as &dyn ToSql,
+            &input_discourse.hypernym_synset_id,
+            &input_discourse.document_hash,
+            &input_discourse.author_public_hash,
+            &input_discourse.author_title,
+            &input_discourse.author_first_name,
+            &input_discourse.author_middle_name,
+            &input_discourse.author_surname,
+            &input_discourse.author_zone,
+            &input_discourse.date_unix_epoch,
+            &input_discourse.x,
+            &input_discourse.y,
         ],
     )?;
 
-        current_entity_id = conn.query_row(
-            "select entity_id from entity where instance_index = ?1",
-            &[&entity.instance_index],
-            |row| row.get(0),
+    let mut result = Discourse::new(input_discourse.clone());
+
+    // Format - HashMap<new_word_id in discourse, new_word_id in input.db>.
+    let mut new_word_ids: HashMap<i32, i32> = HashMap::new();
+    for def in &discourse.new_word_defs {
+        tx.execute(
+            "insert into input_new_word_def (is_a, discourse_id, lexeme, x, y) values (?1, ?2, ?3, ?4, ?5)",
+            [
+                &def.hypernym_synset_id as &dyn ToSql,
+                &discourse_id,
+                &def.lexeme,
+                &def.x,
+                &def.y,
+            ],
         )?;
+        let new_word_id = tx.last_insert_rowid() as i32;
+        new_word_ids.insert(def.new_word_id, new_word_id);
+        result.new_word_defs.push(InputNewWordDef {
+            new_word_id,
+            discourse_id,
+            ..def.clone()
+        });
     }
 
-    // Return the last entity_id.
-    Ok(current_entity_id)
-}
+    // Format - HashMap<sentence_id in discourse, sentence_id in input.db>.
+    let mut sentence_ids: HashMap<i32, i32> = HashMap::new();
+    for sentence in &discourse.sentences {
+        tx.execute(
+            "insert into input_sentence (discourse_id, is_question) values (?1, ?2)",
+            [&discourse_id as &dyn ToSql, &sentence.is_question],
+        )?;
+        let sentence_id = tx.last_insert_rowid() as i32;
+        sentence_ids.insert(sentence.sentence_id, sentence_id);
+        result.sentences.push(InputSentence {
+            sentence_id,
+            discourse_id,
+            is_question: sentence.is_question,
+        });
+    }
 
-pub(crate) fn insert_discourse(conn: &Connection) -> Result<()> {
-    conn.execute("insert into discourse default values", NO_PARAMS)?;
-    Ok(())
+    // Format - HashMap<word_id in discourse, word_id in input.db>.
+    let mut word_ids: HashMap<i32, i32> = HashMap::new();
+    for word in &discourse.words {
+        let stored = InputWord {
+            sentence_id: stored_id(&sentence_ids, &word.sentence_id),
+            new_word_id: stored_id(&new_word_ids, &word.new_word_id),
+            ..word.clone()
+        };
+        // Words that are not new have no new_word_id (null), rather than -1.
+        let new_word_id: Option<i32> = Some(stored.new_word_id).filter(|id| *id != -1);
+        tx.execute(
+            "insert into input_word (sentence_id, synset_id, index_of_word, lexeme, instance_name,
+            instance_index, pos, x, y, is_transition, new_word_id)
+            values (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+            [
+                &stored.sentence_id as &dyn ToSql,
+                &stored.synset_id,
+                &stored.index_of_word,
+                &stored.lexeme,
+                &stored.instance_name,
+                &stored.instance_index,
+                &stored.pos,
+                &stored.x,
+                &stored.y,
+                &stored.is_transition,
+                &new_word_id,
+            ],
+        )?;
+        let word_id = tx.last_insert_rowid() as i32;
+        word_ids.insert(word.word_id, word_id);
+        result.words.push(InputWord { word_id, ..stored });
+    }
+
+    for title in &discourse.title {
+        let word_id = stored_id(&word_ids, &title.word_id);
+        tx.execute(
+            "insert into input_discourse_title (discourse_id, word_id) values (?1, ?2)",
+            [discourse_id, word_id],
+        )?;
+        result.title.push(InputDiscourseTitle {
+            discourse_id,
+            word_id,
+        });
+    }
+
+    for feature in &discourse.features {
+        let stored = InputWordFeature {
+            word_id: stored_id(&word_ids, &feature.word_id),
+            ..feature.clone()
+        };
+        tx.execute(
+            "insert into input_word_feature (word_id, ud_relation, ud_feature, ud_feature_value)
+            values (?1, ?2, ?3, ?4)",
+            [
+                &stored.word_id as &dyn ToSql,
+                &stored.ud_relation,
+                &stored.ud_feature,
+                &stored.ud_feature_value,
+            ],
+        )?;
+        result.features.push(stored);
+    }
+
+    for relation in &discourse.relations {
+        let stored = InputWordRelation {
+            word_id: stored_id(&word_ids, &relation.word_id),
+            word_id_modified: stored_id(&word_ids, &relation.word_id_modified),
+            ud_relation: relation.ud_relation.clone(),
+        };
+        // The root has no head (null), rather than -1.
+        let word_id_modified: Option<i32> = Some(stored.word_id_modified).filter(|id| *id != -1);
+        tx.execute(
+            "insert into input_word_relation (word_id, word_id_modified, ud_relation) values (?1, ?2, ?3)",
+            [
+                &stored.word_id as &dyn ToSql,
+                &word_id_modified,
+                &stored.ud_relation,
+            ],
+        )?;
+        result.relations.push(stored);
+    }
+
+    let (triplets, sections) = insert_triplet_rows(
+        &tx,
+        &discourse.triplets,
+        &discourse.sections,
+        &sentence_ids,
+        &word_ids,
+    )?;
+    result.triplets = triplets;
+    result.sections = sections;
+
+    for exempt in &discourse.exempt_features {
+        tx.execute(
+            "insert into input_exempt_feature (discourse_id, ud_feature, ud_feature_value) values (?1, ?2, ?3)",
+            [
+                &discourse_id as &dyn ToSql,
+                &exempt.ud_feature,
+                &exempt.ud_feature_value,
+            ],
+        )?;
+        result.exempt_features.push(InputExemptFeature {
+            discourse_id,
+            ..exempt.clone()
+        });
+    }
+
+    tx.commit()?;
+
+    Ok(result)
 }
 
-/// Gets unit tensors filtered by real or virtual.
-pub(crate) fn select_unit_tensor(
+/// Replaces the triplets and sections of a discourse in input.db.
+/// Sentence and word ids are used as given; triplet_id and section_id are
+/// replaced by the database keys.
+///
+/// Return format: (triplets as stored, sections as stored).
+pub(crate) fn insert_input_triplets(
     conn: &Connection,
     discourse_id: &i32,
-    is_virtual: &bool,
-) -> Result<Vec<UnitTensor>> {
-    let sql =
-        "select unit_tensor_id, sentence_id, discourse_id, object_entity_id, subject_entity_id,
-where_entity_id, when_entity_id, predicate_entity_id, tense, mood, excited_x, excited_y
-  from unit_tensor u1 ";
-
-    // Filter for real events.
-    let sql_real: String = format!(
-        "{}{}",
-        sql,
-        "left join unit_tensor_ethereal_def u2 on u1.unit_tensor_id
-    not between u2.branch_id and u2.rejoin_id
-where mood = 'ind' or mood = 'imp"
-    );
+    triplets: &[InputTriplet],
+    sections: &[InputSection],
+) -> Result<(Vec<InputTriplet>, Vec<InputSection>)> {
+    let tx = conn.unchecked_transaction()?;
 
-    // Filter for virtual events.
-    let sql_virtual: String = format!(
-        "{}{}",
-        sql,
-        "left join unit_tensor_ethereal_def u2 on u1.unit_tensor_id
-    between u2.branch_id and u2.rejoin_id
-where mood = 'ind' or mood = 'imp"
-    );
+    delete_triplet_rows(&tx, discourse_id)?;
+    let result = insert_triplet_rows(&tx, triplets, sections, &HashMap::new(), &HashMap::new())?;
 
-    let mut stmt = conn.prepare(match is_virtual {
-        true => sql_virtual.as_str(),
-        false => sql_real.as_str(),
-    })?;
-    let unit_tensor_iter = stmt.query_map(&[&discourse_id], |row| {
-        Ok(UnitTensor {
-            unit_tensor_id: row.get(0)?,
-            sentence_id: row.get(1)?,
-            discourse_id: row.get(2)?,
-            object_entity_id: row.get(3)?,
-            subject_entity_id: row.get(4)?,
-            where_entity_id: row.get(5)?,
-            when_entity_id: row.get(6)?,
-            predicate_entity_id: row.get(7)?,
-            tense: row.get(8)?,
-            mood: row.get(9)?,
-            excited_x: row.get(10)?,
-            excited_y: row.get(11)?,
-        })
-    })?;
+    tx.commit()?;
 
-    unit_tensor_iter.collect::<Result<Vec<UnitTensor>>>()
+    Ok(result)
+}
+
+/// Gets the key an id was stored under. Ids that were not stored,
+/// such as -1 (none), are kept as they are.
+fn stored_id(ids: &HashMap<i32, i32>, id: &i32) -> i32 {
+    *ids.get(id).unwrap_or(id)
+}
+
+/// Deletes the triplets and sections of a discourse.
+fn delete_triplet_rows(conn: &Connection, discourse_id: &i32) -> Result<()> {
+    conn.execute(
+        "delete from input_section where triplet_id in
+        (select t.triplet_id from input_triplet t
+        inner join input_sentence s on s.sentence_id = t.sentence_id where s.discourse_id = ?1)",
+        [discourse_id],
+    )?;
+    conn.execute(
+        "delete from input_triplet where sentence_id in
+        (select sentence_id from input_sentence where discourse_id = ?1)",
+        [discourse_id],
+    )?;
+
+    Ok(())
 }
 
-/// Get degree centrality of unit tensors filtered by real or virtual.
-/// Only coreferenced items are considered.
-pub(crate) fn select_unit_tensor_centrality(
+/// Inserts triplets and their sections, mapping sentence and word ids
+/// through sentence_ids and word_ids.
+///
+/// Return format: (triplets as stored, sections as stored).
+fn insert_triplet_rows(
     conn: &Connection,
-    discourse_id: &i32,
-    is_virtual: &bool,
-) -> Result<HashMap<i64, i32>> {
-    // Filter for real events.
-    let sql_real = "select entity_id, count(entity_id) from
-(
-    select object_entity_id entity_id
-    from unit_tensor u1
-    left join unit_tensor_ethereal_def u2 on u1.unit_tensor_id not between u2.branch_id and u2.rejoin_id
-    where discourse_id = ?1 and instance_index > 0
-    union
-    select subject_entity_id
-    from unit_tensor u1
-    left join unit_tensor_ethereal_def u2 on u1.unit_tensor_id not between u2.branch_id and u2.rejoin_id
-    where discourse_id = ?1 and instance_index > 0
-    union
-    select where_entity_id
-    from unit_tensor u1
-    left join unit_tensor_ethereal_def u2 on u1.unit_tensor_id not between u2.branch_id and u2.rejoin_id
-    where discourse_id = ?1 and instance_index > 0
-    union
-    select when_entity_id
-    from unit_tensor u1
-    left join unit_tensor_ethereal_def u2 on u1.unit_tensor_id not between u2.branch_id and u2.rejoin_id
-    where discourse_id = ?1 and instance_index > 0
-    union
-    select predicate_entity_id
-    from unit_tensor u1
-    left join unit_tensor_ethereal_def u2 on u1.unit_tensor_id not between u2.branch_id and u2.rejoin_id
-    where discourse_id = ?1 and instance_index > 0
-)
-group by entity_id";
-
-    // Filter for virtual events.
-    let sql_virtual = "select entity_id, count(entity_id) from
-(
-    select object_entity_id entity_id
-    from unit_tensor u1
-    left join unit_tensor_ethereal_def u2 on u1.unit_tensor_id between u2.branch_id and u2.rejoin_id
-    where discourse_id = ?1 and instance_index > 0
-    union
-    select subject_entity_id
-    from unit_tensor u1
-    left join unit_tensor_ethereal_def u2 on u1.unit_tensor_id between u2.branch_id and u2.rejoin_id
-    where discourse_id = ?1 and instance_index > 0
-    union
-    select where_entity_id
-    from unit_tensor u1
-    left join unit_tensor_ethereal_def u2 on u1.unit_tensor_id between u2.branch_id and u2.rejoin_id
-    where discourse_id = ?1 and instance_index > 0
-    union
-    select when_entity_id
-    from unit_tensor u1
-    left join unit_tensor_ethereal_def u2 on u1.unit_tensor_id between u2.branch_id and u2.rejoin_id
-    where discourse_id = ?1 and instance_index > 0
-    union
-    select predicate_entity_id
-    from unit_tensor u1
-    left join unit_tensor_ethereal_def u2 on u1.unit_tensor_id between u2.branch_id and u2.rejoin_id
-    where discourse_id = ?1 and instance_index > 0
-)
-group by entity_id";
-
-    let mut stmt = conn.prepare(match is_virtual {
-        true => sql_virtual,
-        false => sql_real,
-    })?;
-    let unit_tensor_iter =
-        stmt.query_map(&[&discourse_id], |row| Ok((row.get(0)?, row.get(1)?)))?;
+    triplets: &[InputTriplet],
+    sections: &[InputSection],
+    sentence_ids: &HashMap<i32, i32>,
+    word_ids: &HashMap<i32, i32>,
+) -> Result<(Vec<InputTriplet>, Vec<InputSection>)> {
+    let mut stored_triplets: Vec<InputTriplet> = Vec::new();
+    let mut stored_sections: Vec<InputSection> = Vec::new();
+
+    // Format - HashMap<triplet_id given, triplet_id in input.db>.
+    let mut triplet_ids: HashMap<i32, i32> = HashMap::new();
+    for triplet in triplets {
+        let sentence_id = stored_id(sentence_ids, &triplet.sentence_id);
+        conn.execute(
+            "insert into input_triplet (sentence_id, tense, is_virtual, is_passive) values (?1, ?2, ?3, ?4)",
+            [
+                &sentence_id as &dyn ToSql,
+                &triplet.tense,
+                &triplet.is_virtual,
+                &triplet.is_passive,
+            ],
+        )?;
+        let triplet_id = conn.last_insert_rowid() as i32;
+        triplet_ids.insert(triplet.triplet_id, triplet_id);
+        stored_triplets.push(InputTriplet {
+            triplet_id,
+            sentence_id,
+            ..triplet.clone()
+        });
+    }
+
+    for section in sections {
+        let triplet_id = stored_id(&triplet_ids, &section.triplet_id);
+        let word_id = stored_id(word_ids, &section.word_id);
+        conn.execute(
+            "insert into input_section (triplet_id, word_id, section_type) values (?1, ?2, ?3)",
+            [triplet_id, word_id, section.section_type],
+        )?;
+        stored_sections.push(InputSection {
+            section_id: conn.last_insert_rowid() as i32,
+            triplet_id,
+            word_id,
+            section_type: section.section_type,
+        });
+    }
+
+    Ok((stored_triplets, stored_sections))
+}
 
-    unit_tensor_iter.collect::<Result<HashMap<i64, i32>>>()
+pub(crate) fn insert_discourse(conn: &Connection) -> Result<()> {
+    conn.execute("insert into discourse default values", NO_PARAMS)?;
+    Ok(())
 }
 
 pub(crate) fn select_entity(conn: &Connection, discourse_id: &i32) -> Result<HashMap<i64, Entity>> {
@@ -743,14 +1131,119 @@ from entity where discourse_id = ?1",
     Ok(entity_map)
 }
 
+/// Loads the encoding of a discourse in output.db into memory.
+/// entity_id and unit_tensor_id are the database keys.
+pub(crate) fn select_encoding(conn: &Connection, discourse_id: &i32) -> Result<Encoding> {
+    let mut entities: Vec<Entity> = select_entity(conn, discourse_id)?.into_values().collect();
+    entities.sort_by_key(|entity| entity.entity_id);
+
+    Ok(Encoding {
+        discourse_id: *discourse_id,
+        entities,
+        unit_tensors: select_unit_tensors(conn, discourse_id)?,
+        ethereal_defs: select_unit_tensor_ethereal_defs(conn, discourse_id)?,
+        locations: select_unit_tensor_locations(conn, discourse_id)?,
+        hash_items_real: select_hash_item(conn, discourse_id, &0)?,
+        hash_items_virtual: select_hash_item(conn, discourse_id, &1)?,
+        versions: select_encoding_versions(conn, discourse_id)?.unwrap_or_default(),
+    })
+}
+
+/// Gets the library and meaning grid versions a discourse was encoded with.
+/// Returns None for discourses that have not been encoded, or were encoded
+/// before versions were recorded.
+pub(crate) fn select_encoding_versions(
+    conn: &Connection,
+    discourse_id: &i32,
+) -> Result<Option<Versions>> {
+    let mut stmt = conn.prepare(
+        "select library_version, grid_version from encoding_version where discourse_id = ?1",
+    )?;
+    let mut rows = stmt.query([discourse_id])?;
+
+    match rows.next()? {
+        Some(row) => Ok(Some(Versions {
+            library: row.get(0)?,
+            grid: row.get(1)?,
+        })),
+        None => Ok(None),
+    }
+}
+
+pub(crate) fn select_unit_tensors(conn: &Connection, discourse_id: &i32) -> Result<Vec<UnitTensor>> {
+    let mut stmt = conn.prepare(
+        "select unit_tensor_id, sentence_id, discourse_id, object_entity_id, subject_entity_id,
+where_entity_id, when_entity_id, predicate_entity_id, tense, mood, excited_x, excited_y, is_negated
+from unit_tensor where discourse_id = ?1 order by unit_tensor_id",
+    )?;
+    let unit_tensor_iter = stmt.query_map([discourse_id], |row| {
+        Ok(UnitTensor {
+            unit_tensor_id: row.get(0)?,
+            sentence_id: row.get(1)?,
+            discourse_id: row.get(2)?,
+            object_entity_id: row.get(3)?,
+            subject_entity_id: row.get(4)?,
+            where_entity_id: row.get(5)?,
+            when_entity_id: row.get(6)?,
+            predicate_entity_id: row.get(7)?,
+            tense: row.get::<_, Option<i32>>(8)?.unwrap_or_default(),
+            mood: get_text(row, 9)?,
+            excited_x: row.get::<_, Option<f64>>(10)?.unwrap_or_default(),
+            excited_y: row.get::<_, Option<f64>>(11)?.unwrap_or_default(),
+            is_negated: row.get::<_, Option<bool>>(12)?.unwrap_or_default(),
+        })
+    })?;
+
+    unit_tensor_iter.collect::<Result<Vec<UnitTensor>>>()
+}
+
+pub(crate) fn select_unit_tensor_ethereal_defs(
+    conn: &Connection,
+    discourse_id: &i32,
+) -> Result<Vec<UnitTensorEtherealDef>> {
+    let mut stmt = conn.prepare(
+        "select branch_id, rejoin_id from unit_tensor_ethereal_def where branch_id in
+(select unit_tensor_id from unit_tensor where discourse_id = ?1)
+order by branch_id",
+    )?;
+    let def_iter = stmt.query_map([discourse_id], |row| {
+        Ok(UnitTensorEtherealDef {
+            branch_id: row.get(0)?,
+            rejoin_id: row.get(1)?,
+        })
+    })?;
+
+    def_iter.collect::<Result<Vec<UnitTensorEtherealDef>>>()
+}
+
+pub(crate) fn select_unit_tensor_locations(
+    conn: &Connection,
+    discourse_id: &i32,
+) -> Result<Vec<UnitTensorLocation>> {
+    let mut stmt = conn.prepare(
+        "select unit_tensor_id, entity_id, location_type from unit_tensor_location
+where unit_tensor_id in (select unit_tensor_id from unit_tensor where discourse_id = ?1)
+order by unit_tensor_id, id",
+    )?;
+    let location_iter = stmt.query_map([discourse_id], |row| {
+        Ok(UnitTensorLocation {
+            unit_tensor_id: row.get(0)?,
+            entity_id: row.get(1)?,
+            location_type: row.get(2)?,
+        })
+    })?;
+
+    location_iter.collect::<Result<Vec<UnitTensorLocation>>>()
+}
+
 pub(crate) fn select_hash_item(
     conn: &Connection,
     discourse_id: &i32,
     is_virtual: &i32,
 ) -> Result<Vec<HashItem>> {
     let mut stmt = conn.prepare(
-        "select radius,angle,hash_type,discourse_id,excited_radius,excited_angle       
-from hash_item order by order_by asc where discourse_id = ?1 and is_virtual = ?2",
+        "select radius_original,angle_original,hash_type,discourse_id,excited_radius,excited_angle
+from hash_item where discourse_id = ?1 and is_virtual = ?2 order by order_by asc",
     )?;
     let input_hash_item_iter = stmt.query_map(&[&discourse_id, &is_virtual], |row| {
         Ok(HashItem {
@@ -771,172 +1264,259 @@ pub(crate) fn select_input_sentences_all(
     discourse_id: &i32,
 ) -> Result<Vec<InputSentence>> {
     let mut stmt = conn.prepare(
-        "select sentence_id,discourse_id,is_question from input_sentence where discourse_id = ?1",
+        "select sentence_id,discourse_id,is_question from input_sentence where discourse_id = ?1
+order by sentence_id",
     )?;
     let input_sentence_iter = stmt.query_map(&[&discourse_id], |row| {
         Ok(InputSentence {
             sentence_id: row.get(0)?,
             discourse_id: row.get(1)?,
-            is_question: row.get(2)?,
+            is_question: row.get::<_, Option<bool>>(2)?.unwrap_or_default(),
         })
     })?;
 
     input_sentence_iter.collect::<Result<Vec<InputSentence>>>()
 }
 
-pub(crate) fn select_input_triplets_by_sentence(
-    conn: &Connection,
-    sentence_id: &i32,
-) -> Result<HashMap<i32, InputTriplet>> {
-    let mut stmt = conn.prepare(
-        "select triplet_id, sentence_id, tense, is_virtual, is_passive
-from input_triplet where sentence_id = ?1",
-    )?;
-    let mut rows = stmt.query(&[&sentence_id])?;
-
-    let mut triplet_map = HashMap::<i32, InputTriplet>::new();
-
-    while let Ok(Some(result_row)) = rows.next() {
-        let row = result_row;
-        let input_triplet = InputTriplet {
-            triplet_id: row.get(0)?,
-            sentence_id: row.get(1)?,
-            tense: row.get(2)?,
-            is_virtual: row.get(3)?,
-            is_passive: row.get(4)?,
-        };
-
-        triplet_map.insert(input_triplet.triplet_id, input_triplet);
-    }
+/// Gets a text column, reading NULL as an empty string.
+fn get_text(row: &Row, index: usize) -> Result<String> {
+    Ok(row.get::<_, Option<String>>(index)?.unwrap_or_default())
+}
 
-    Ok(triplet_map)
+/// Reads an input_word row selected as word_id, sentence_id, synset_id, index_of_word,
+/// lexeme, instance_name, instance_index, pos, x, y, is_transition, new_word_id.
+fn input_word_from_row(row: &Row) -> Result<InputWord> {
+    Ok(InputWord {
+        word_id: row.get(0)?,
+        sentence_id: row.get(1)?,
+        synset_id: row.get::<_, Option<i32>>(2)?.unwrap_or_default(),
+        index_of_word: row.get::<_, Option<i32>>(3)?.unwrap_or_default(),
+        lexeme: get_text(row, 4)?,
+        instance_name: get_text(row, 5)?,
+        instance_index: row.get::<_, Option<i32>>(6)?.unwrap_or_default(),
+        pos: get_text(row, 7)?,
+        x: row.get::<_, Option<f64>>(8)?.unwrap_or_default(),
+        y: row.get::<_, Option<f64>>(9)?.unwrap_or_default(),
+        is_transition: row.get::<_, Option<bool>>(10)?.unwrap_or_default(),
+        new_word_id: row.get::<_, Option<i32>>(11)?.unwrap_or(-1),
+    })
 }
 
-pub(crate) fn select_new_def_isa(is_a: &i32) -> Result<Point2D> {
-    let conn_meta = Connection::open(METAPATH)?;
+/// Loads a discourse and all of its rows in input.db into memory,
+/// failing with OkeuvoError::DiscourseNotFound if there is none with discourse_id.
+pub(crate) fn select_discourse(
+    conn: &Connection,
+    discourse_id: &i32,
+) -> std::result::Result<Discourse, OkeuvoError> {
+    let in_discourse = |e: rusqlite::Error| OkeuvoError::from(e).in_discourse(discourse_id);
+
+    Ok(Discourse {
+        discourse: select_input_discourse(conn, discourse_id)?,
+        title: select_input_discourse_title(conn, discourse_id).map_err(in_discourse)?,
+        sentences: select_input_sentences_all(conn, discourse_id).map_err(in_discourse)?,
+        words: select_input_words_by_discourse(conn, discourse_id).map_err(in_discourse)?,
+        features: select_input_features_by_discourse(conn, discourse_id).map_err(in_discourse)?,
+        relations: select_input_word_relations_by_discourse(conn, discourse_id)
+            .map_err(in_discourse)?,
+        triplets: select_input_triplets_by_discourse(conn, discourse_id).map_err(in_discourse)?,
+        sections: select_input_sections_by_discourse(conn, discourse_id).map_err(in_discourse)?,
+        exempt_features: select_input_exempt_features_by_discourse_id(conn, discourse_id)
+            .map_err(in_discourse)?,
+        new_word_defs: select_input_new_word_defs(conn, *discourse_id).map_err(in_discourse)?,
+    })
+}
 
-    let isa = conn_meta.query_row(
-        "select x, y, synset_id  from meaning_grid_item where synset_id = ?1",
-        &[&is_a],
-        |row| {
-            Ok(Point2D {
-                x: row.get(0)?,
-                y: row.get(1)?,
-            })
-        },
+pub(crate) fn select_input_discourse_title(
+    conn: &Connection,
+    discourse_id: &i32,
+) -> Result<Vec<InputDiscourseTitle>> {
+    let mut stmt = conn.prepare(
+        "select discourse_id, word_id from input_discourse_title where discourse_id = ?1
+order by word_id",
     )?;
+    let title_iter = stmt.query_map([discourse_id], |row| {
+        Ok(InputDiscourseTitle {
+            discourse_id: row.get(0)?,
+            word_id: row.get(1)?,
+        })
+    })?;
 
-    if let Err(e) = conn_meta.close() {
-        return Err(e.1);
-    }
-
-    Ok(isa)
+    title_iter.collect::<Result<Vec<InputDiscourseTitle>>>()
 }
 
-pub(crate) fn select_input_discourse(
+pub(crate) fn select_input_words_by_discourse(
     conn: &Connection,
     discourse_id: &i32,
-) -> Result<InputDiscourse> {
-    let input_discourse: InputDiscourse = conn.query_row(
-        "select discourse_id,hypernym_synset_id,document_hash,author_public_hash,author_title,author_first_name,
-        author_middle_name,author_surname,author_zone,date_unix_epoch from input_discourse
-        where discourse_id = ?1",
-        &[&discourse_id],
-        |row| Ok(InputDiscourse {
-            discourse_id: row.get(0)?,
-            hypernym_synset_id: row.get(1)?,
-            document_hash: row.get(2)?,
-            author_public_hash: row.get(3)?,
-            author_title: row.get(4)?,
-            author_first_name: row.get(5)?,
-            author_middle_name: row.get(6)?,
-            author_surname: row.get(7)?,
-            author_zone: row.get(8)?,
-            date_unix_epoch: row.get(9)?,
-            x: row.get(10)?,
-            y: row.get(11)?,
-        }),
+) -> Result<Vec<InputWord>> {
+    let mut stmt = conn.prepare(
+        "select w.word_id, w.sentence_id, w.synset_id, w.index_of_word,
+w.lexeme, w.instance_name, w.instance_index, w.pos, w.x, w.y, w.is_transition, w.new_word_id
+from input_word w
+inner join input_sentence s on s.sentence_id = w.sentence_id
+where s.discourse_id = ?1
+order by w.word_id",
     )?;
+    let input_word_iter = stmt.query_map([discourse_id], input_word_from_row)?;
 
-    Ok(input_discourse)
+    input_word_iter.collect::<Result<Vec<InputWord>>>()
 }
 
-pub(crate) fn select_input_discourse_title(
+pub(crate) fn select_input_features_by_discourse(
     conn: &Connection,
     discourse_id: &i32,
-) -> Result<Vec<InputWord>> {
+) -> Result<Vec<InputWordFeature>> {
     let mut stmt = conn.prepare(
-        "select w.word_id, w.sentence_id, w.synset_id, w.index_of_word,  
-w.lexeme, w.instance_name, w.instance_index, w.pos, w.x, w.y, w.is_transition, w.new_word_id
-from input_word w
-inner join input_discourse_title d on d.word_id = w.word_id
-	where w.discourse_id = ?1",
+        "select f.word_id, f.ud_relation, f.ud_feature, f.ud_feature_value
+from input_word_feature f
+inner join input_word w on w.word_id = f.word_id
+inner join input_sentence s on s.sentence_id = w.sentence_id
+where s.discourse_id = ?1
+order by f.word_id, f.rowid",
     )?;
-    let mut rows = stmt.query(&[&discourse_id])?;
+    let feature_iter = stmt.query_map([discourse_id], |row| {
+        Ok(InputWordFeature {
+            word_id: row.get(0)?,
+            ud_relation: get_text(row, 1)?,
+            ud_feature: get_text(row, 2)?,
+            ud_feature_value: get_text(row, 3)?,
+        })
+    })?;
 
-    let mut word_vec = Vec::<InputWord>::new();
+    feature_iter.collect::<Result<Vec<InputWordFeature>>>()
+}
 
-    while let Ok(Some(result_row)) = rows.next() {
-        let row = result_row;
-        let input_word = InputWord {
+pub(crate) fn select_input_word_relations_by_discourse(
+    conn: &Connection,
+    discourse_id: &i32,
+) -> Result<Vec<InputWordRelation>> {
+    let mut stmt = conn.prepare(
+        "select r.word_id, r.word_id_modified, r.ud_relation
+from input_word_relation r
+inner join input_word w on w.word_id = r.word_id
+inner join input_sentence s on s.sentence_id = w.sentence_id
+where s.discourse_id = ?1
+order by r.word_id, r.rowid",
+    )?;
+    let relation_iter = stmt.query_map([discourse_id], |row| {
+        Ok(InputWordRelation {
             word_id: row.get(0)?,
-            sentence_id: row.get(1)?,
-            synset_id: row.get(2)?,
-            index_of_word: row.get(3)?,
-            lexeme: row.get(4)?,
-            instance_name: row.get(5)?,
-            instance_index: row.get(6)?,
-            pos: row.get(7)?,
-            x: row.get(8)?,
-            y: row.get(9)?,
-            is_transition: row.get(10)?,
-            new_word_id: row.get(11)?,
-        };
+            word_id_modified: row.get::<_, Option<i32>>(1)?.unwrap_or(-1),
+            ud_relation: get_text(row, 2)?,
+        })
+    })?;
 
-        word_vec.push(input_word);
-    }
+    relation_iter.collect::<Result<Vec<InputWordRelation>>>()
+}
 
-    Ok(word_vec)
+pub(crate) fn select_input_triplets_by_discourse(
+    conn: &Connection,
+    discourse_id: &i32,
+) -> Result<Vec<InputTriplet>> {
+    let mut stmt = conn.prepare(
+        "select t.triplet_id, t.sentence_id, t.tense, t.is_virtual, t.is_passive
+from input_triplet t
+inner join input_sentence s on s.sentence_id = t.sentence_id
+where s.discourse_id = ?1
+order by t.triplet_id",
+    )?;
+    let triplet_iter = stmt.query_map([discourse_id], |row| {
+        Ok(InputTriplet {
+            triplet_id: row.get(0)?,
+            sentence_id: row.get(1)?,
+            tense: row.get::<_, Option<i32>>(2)?.unwrap_or_default(),
+            is_virtual: row.get::<_, Option<bool>>(3)?.unwrap_or_default(),
+            is_passive: row.get::<_, Option<bool>>(4)?.unwrap_or_default(),
+        })
+    })?;
+
+    triplet_iter.collect::<Result<Vec<InputTriplet>>>()
 }
 
-pub(crate) fn select_input_words_by_sentence(
+pub(crate) fn select_input_sections_by_discourse(
     conn: &Connection,
-    sentence_id: &i32,
-) -> Result<HashMap<i32, InputWord>> {
+    discourse_id: &i32,
+) -> Result<Vec<InputSection>> {
     let mut stmt = conn.prepare(
-        "select w.word_id, w.sentence_id, w.synset_id, w.index_of_word,  
-w.lexeme, w.instance_name, w.instance_index, w.pos, w.x, w.y, w.is_transition, w.new_word_id
-from input_sentence s1
-inner join input_triplet t on t.sentence_id = s1.sentence_id
-inner join input_section s2 on s2.triplet_id = t.triplet_id
-inner join input_word w on w.word_id = s3.word_id
-	where s1.sentence_id = ?1 and w.new_word_id > 0",
+        "select x.section_id, x.triplet_id, x.word_id, x.section_type
+from input_section x
+inner join input_triplet t on t.triplet_id = x.triplet_id
+inner join input_sentence s on s.sentence_id = t.sentence_id
+where s.discourse_id = ?1
+order by x.section_id",
     )?;
-    let mut rows = stmt.query(&[&sentence_id])?;
+    let section_iter = stmt.query_map([discourse_id], |row| {
+        Ok(InputSection {
+            section_id: row.get(0)?,
+            triplet_id: row.get(1)?,
+            word_id: row.get(2)?,
+            section_type: row.get(3)?,
+        })
+    })?;
 
-    let mut word_map = HashMap::<i32, InputWord>::new();
+    section_iter.collect::<Result<Vec<InputSection>>>()
+}
 
-    while let Ok(Some(result_row)) = rows.next() {
-        let row = result_row;
-        let input_word = InputWord {
-            word_id: row.get(0)?,
-            sentence_id: row.get(1)?,
-            synset_id: row.get(2)?,
-            index_of_word: row.get(3)?,
-            lexeme: row.get(4)?,
-            instance_name: row.get(5)?,
-            instance_index: row.get(6)?,
-            pos: row.get(7)?,
-            x: row.get(8)?,
-            y: row.get(9)?,
-            is_transition: row.get(10)?,
-            new_word_id: row.get(11)?,
-        };
+/// Gets the meaning grid coordinates of the hypernym (is_a) of a new word sense,
+/// failing with OkeuvoError::MissingSynset if it is not on the grid.
+pub(crate) fn select_new_def_isa(
+    conn_meta: &Connection,
+    is_a: &i32,
+) -> std::result::Result<Point2D, OkeuvoError> {
+    let isa = conn_meta.query_row(
+        "select x, y, synset_id  from meaning_grid_item where synset_id = ?1",
+        &[&is_a],
+        |row| {
+            Ok(Point2D {
+                x: row.get(0)?,
+                y: row.get(1)?,
+            })
+        },
+    );
 
-        word_map.insert(input_word.word_id, input_word);
+    match isa {
+        Ok(isa) => Ok(isa),
+        Err(rusqlite::Error::QueryReturnedNoRows) => {
+            Err(OkeuvoError::MissingSynset { synset_id: *is_a })
+        }
+        Err(e) => Err(e.into()),
     }
+}
 
-    Ok(word_map)
+/// Gets a discourse, failing with OkeuvoError::DiscourseNotFound if there is none with discourse_id.
+pub(crate) fn select_input_discourse(
+    conn: &Connection,
+    discourse_id: &i32,
+) -> std::result::Result<InputDiscourse, OkeuvoError> {
+    let input_discourse = conn.query_row(
+        "select discourse_id,hypernym_synset_id,document_hash,author_public_hash,author_title,author_first_name,
+        author_middle_name,author_surname,author_zone,date_unix_epoch,x,y from input_discourse
+        where discourse_id = ?1",
+        &[&discourse_id],
+        |row| {
+            Ok(InputDiscourse {
+                discourse_id: row.get(0)?,
+                hypernym_synset_id: row.get::<_, Option<i32>>(1)?.unwrap_or_default(),
+                document_hash: get_text(row, 2)?,
+                author_public_hash: get_text(row, 3)?,
+                author_title: get_text(row, 4)?,
+                author_first_name: get_text(row, 5)?,
+                author_middle_name: get_text(row, 6)?,
+                author_surname: get_text(row, 7)?,
+                author_zone: get_text(row, 8)?,
+                date_unix_epoch: row.get::<_, Option<i32>>(9)?.unwrap_or_default(),
+                x: row.get::<_, Option<f64>>(10)?.unwrap_or_default(),
+                y: row.get::<_, Option<f64>>(11)?.unwrap_or_default(),
+            })
+        },
+    );
+
+    match input_discourse {
+        Ok(input_discourse) => Ok(input_discourse),
+        Err(rusqlite::Error::QueryReturnedNoRows) => Err(OkeuvoError::DiscourseNotFound {
+            discourse_id: *discourse_id,
+        }),
+        Err(e) => Err(OkeuvoError::from(e).in_discourse(discourse_id)),
+    }
 }
 
 pub(crate) fn select_input_words_by_triplet(
@@ -987,6 +1567,7 @@ pub(crate) fn select_input_words_new_def(
         "select w.word_id, w.sentence_id, w.synset_id, w.index_of_word,  
 w.lexeme, w.instance_name, w.instance_index, w.pos, w.x, w.y, w.is_transition, w.new_word_id from input_word w
 inner join input_new_word_def n on n.new_word_id = w.new_word_id
+inner join input_sentence s1 on s1.sentence_id = w.sentence_id
 where s1.discourse_id = ?1 and w.new_word_id = ?2",
     )?;
     let mut rows = stmt.query(&[&discourse_id, &new_word_id])?;
@@ -1032,7 +1613,7 @@ from input_word_relation where word_id = ?1",
         let row = result_row;
         let input_word_relation = InputWordRelation {
             word_id: row.get(0)?,
-            word_id_modified: row.get(1)?,
+            word_id_modified: row.get::<_, Option<i32>>(1)?.unwrap_or(-1),
             ud_relation: row.get(2)?,
         };
 
@@ -1042,34 +1623,6 @@ from input_word_relation where word_id = ?1",
     Ok(relation_map)
 }
 
-pub(crate) fn select_input_word_relation_by_sentence(
-    conn: &Connection,
-    p1: &i32,
-) -> Result<Vec<InputWordRelation>> {
-    let mut stmt = conn.prepare(
-        "select r.word_id, r.word_id_modified, r.ud_relation
-from input_word w
-inner join input_word_relation r on r.word_id = w.word_id
-where w.sentence_id = ?1 and w.new_word_id > 0",
-    )?;
-    let mut rows = stmt.query(&[&p1])?;
-
-    let mut relation_vec = Vec::new();
-
-    while let Ok(Some(result_row)) = rows.next() {
-        let row = result_row;
-        let input_word_relation = InputWordRelation {
-            word_id: row.get(0)?,
-            word_id_modified: row.get(1)?,
-            ud_relation: row.get(2)?,
-        };
-
-        relation_vec.push(input_word_relation);
-    }
-
-    Ok(relation_vec)
-}
-
 pub(crate) fn select_input_new_word_defs(
     conn: &Connection,
     discourse_id: i32,
@@ -1087,9 +1640,9 @@ pub(crate) fn select_input_new_word_defs(
             new_word_id: row.get(0)?,
             hypernym_synset_id: row.get(1)?,
             discourse_id: row.get(2)?,
-            lexeme: row.get(3)?,
-            x: row.get(4)?,
-            y: row.get(5)?,
+            lexeme: get_text(row, 3)?,
+            x: row.get::<_, Option<f64>>(4)?.unwrap_or_default(),
+            y: row.get::<_, Option<f64>>(5)?.unwrap_or_default(),
         };
 
         new_words.push(input_word_feature);
@@ -1149,266 +1702,192 @@ pub(crate) fn select_input_features_by_word_id(
     Ok(feature_vec)
 }
 
-pub(crate) fn select_input_features_by_sentence_and_section(
-    conn: &Connection,
-    section_id: &i32,
-) -> Result<Vec<InputWordFeature>> {
-    let mut stmt = conn.prepare(
-        "select distinct f.word_id, f.ud_relation, f.ud_feature, f.ud_feature_value
-from input_triplet t
-inner join input_section s on s.triplet_id = t.triplet_id
-inner join input_word w on w.word_id = s.word_id
-inner join input_word_feature f on f.word_id = w.word_id
-section_id = ?1 and w.new_word_id > 0",
-    )?;
-    let mut rows = stmt.query(&[&section_id])?;
-
-    let mut feature_vec: Vec<InputWordFeature> = Vec::new();
-
-    while let Ok(Some(result_row)) = rows.next() {
-        let row = result_row;
-        let input_word_feature = InputWordFeature {
-            word_id: row.get(0)?,
-            ud_relation: row.get(1)?,
-            ud_feature: row.get(2)?,
-            ud_feature_value: row.get(3)?,
-        };
-
-        feature_vec.push(input_word_feature);
-    }
+/// Gets all meaning grid items in the order they were imported.
+pub(crate) fn select_meaning_grid_all(conn: &Connection) -> Result<Vec<MeaningGridItem>> {
+    let mut stmt = conn.prepare("select x,y,synset_id from meaning_grid_item order by rowid")?;
+    let meaning_item_iter = stmt.query_map([], |row| {
+        Ok(MeaningGridItem {
+            x: row.get(0)?,
+            y: row.get(1)?,
+            synset_id: row.get(2)?,
+        })
+    })?;
 
-    Ok(feature_vec)
+    meaning_item_iter.collect::<Result<Vec<MeaningGridItem>>>()
 }
 
-pub(crate) fn select_input_features_by_section(
-    conn: &Connection,
-    word_id: &i32,
-) -> Result<Vec<InputWordFeature>> {
+/// Gets the meaning grid items of the synsets of the table, antonyms,
+/// in the order they were imported.
+pub(crate) fn select_antonym_grid_items(conn: &Connection) -> Result<Vec<MeaningGridItem>> {
     let mut stmt = conn.prepare(
-        "select f2.word_id, f2.ud_relation, f2.ud_feature, f2.ud_feature_value
-from input_word_feature f1
-inner join input_section s on s.word_id = f1.word_id
-inner join input_word_feature f2 on f2.word_id = s.word_id
-where f1.word_id = ?1;",
+        "select x,y,synset_id from meaning_grid_item
+where synset_id in (select synset_id1 from antonyms union select synset_id2 from antonyms)
+order by rowid",
     )?;
-    let mut rows = stmt.query(&[&word_id])?;
-
-    let mut feature_vec: Vec<InputWordFeature> = Vec::new();
-
-    while let Ok(Some(result_row)) = rows.next() {
-        let row = result_row;
-        let input_word_feature = InputWordFeature {
-            word_id: row.get(0)?,
-            ud_relation: row.get(1)?,
-            ud_feature: row.get(2)?,
-            ud_feature_value: row.get(3)?,
-        };
-
-        feature_vec.push(input_word_feature);
-    }
+    let meaning_item_iter = stmt.query_map([], |row| {
+        Ok(MeaningGridItem {
+            x: row.get(0)?,
+            y: row.get(1)?,
+            synset_id: row.get(2)?,
+        })
+    })?;
 
-    Ok(feature_vec)
+    meaning_item_iter.collect::<Result<Vec<MeaningGridItem>>>()
 }
 
-pub(crate) fn select_input_features_by_triplet(
-    conn: &Connection,
-    triplet_id: &i32,
-) -> Result<Vec<InputWordFeature>> {
+/// Gets the antonym pairs of the table, antonyms in the order they were imported.
+pub(crate) fn select_antonym_pairs(conn: &Connection) -> Result<Vec<AntonymPair>> {
     let mut stmt = conn.prepare(
-        "select distinct f.word_id, f.ud_relation, f.ud_feature,  f.ud_feature_value
-from input_triplet t
-inner join input_section s on s.triplet_id = t.triplet_id
-inner join input_word w on w.word_id = s.word_id
-inner join input_word_feature f on f.word_id = w.word_id
-t.triplet_id = ?1 and w.new_word_id > 0",
+        "select coarse_class, synset_id1, lemma1, synset_id2, lemma2 from antonyms order by rowid",
     )?;
-    let mut rows = stmt.query(&[&triplet_id])?;
-
-    let mut feature_vec: Vec<InputWordFeature> = Vec::new();
-
-    while let Ok(Some(result_row)) = rows.next() {
-        let row = result_row;
-        let input_word_feature = InputWordFeature {
-            word_id: row.get(0)?,
-            ud_relation: row.get(1)?,
-            ud_feature: row.get(2)?,
-            ud_feature_value: row.get(3)?,
-        };
-
-        feature_vec.push(input_word_feature);
-    }
+    let antonym_iter = stmt.query_map([], |row| {
+        Ok(AntonymPair {
+            coarse_class: row.get::<_, Option<i32>>(0)?.unwrap_or_default(),
+            synset_id1: row.get(1)?,
+            lemma1: get_text(row, 2)?,
+            synset_id2: row.get(3)?,
+            lemma2: get_text(row, 4)?,
+        })
+    })?;
 
-    Ok(feature_vec)
+    antonym_iter.collect::<Result<Vec<AntonymPair>>>()
 }
 
-pub(crate) fn select_input_features_by_sentence_and_section_map(
-    conn: &Connection,
-    section_id: &i32,
-) -> Result<HashMap<i32, Vec<InputWordFeature>>> {
+/// Gets the antonyms of a synset_id in the order they were imported,
+/// with their coordinates on the meaning grid (0 for antonyms not on the grid).
+pub(crate) fn select_antonyms(conn: &Connection, synset_id: &i32) -> Result<Vec<Antonym>> {
     let mut stmt = conn.prepare(
-        "select distinct f.word_id, f.ud_relation, f.ud_feature, f.ud_feature_value
-from input_triplet t
-inner join input_section s on s.triplet_id = t.triplet_id
-inner join input_word w on w.word_id = s.word_id
-inner join input_word_feature f on f.word_id = w.word_id
-section_id = ?1 and w.new_word_id > 0",
+        "select a.rowid, a.coarse_class, a.lemma1, a.synset_id2, a.lemma2, m.x, m.y
+from antonyms a left join meaning_grid_item m on m.synset_id = a.synset_id2
+where a.synset_id1 = ?1
+union all
+select a.rowid, a.coarse_class, a.lemma2, a.synset_id1, a.lemma1, m.x, m.y
+from antonyms a left join meaning_grid_item m on m.synset_id = a.synset_id1
+where a.synset_id2 = ?1
+order by 1",
     )?;
-    let mut rows = stmt.query(&[&section_id])?;
+    let antonym_iter = stmt.query_map([synset_id], |row| {
+        Ok(Antonym {
+            coarse_class: row.get::<_, Option<i32>>(1)?.unwrap_or_default(),
+            lemma: get_text(row, 2)?,
+            synset_id: row.get(3)?,
+            antonym_lemma: get_text(row, 4)?,
+            x: row.get::<_, Option<f64>>(5)?.unwrap_or_default(),
+            y: row.get::<_, Option<f64>>(6)?.unwrap_or_default(),
+        })
+    })?;
 
-    let mut feature_map: HashMap<i32, Vec<InputWordFeature>> = HashMap::new();
+    antonym_iter.collect::<Result<Vec<Antonym>>>()
+}
 
-    while let Ok(Some(result_row)) = rows.next() {
-        let row = result_row;
-        let input_word_feature = InputWordFeature {
-            word_id: row.get(0)?,
-            ud_relation: row.get(1)?,
-            ud_feature: row.get(2)?,
-            ud_feature_value: row.get(3)?,
-        };
+/// Adds antonym pairs to the table, antonyms, replacing those already held
+/// for the parts of speech (coarse_class) of the new pairs.
+/// Everything happens in one transaction, so a failed import leaves the previous pairs in place.
+pub(crate) fn insert_antonym_pairs(conn: &mut Connection, pairs: &[AntonymPair]) -> Result<usize> {
+    let tx = conn.transaction()?;
+
+    let mut coarse_classes: Vec<i32> = pairs.iter().map(|pair| pair.coarse_class).collect();
+    coarse_classes.sort_unstable();
+    coarse_classes.dedup();
+    for coarse_class in &coarse_classes {
+        tx.execute(
+            "delete from antonyms where coarse_class = ?1",
+            [coarse_class],
+        )?;
+    }
 
-        if !feature_map.contains_key(&input_word_feature.word_id) {
-            feature_map.insert(input_word_feature.word_id, Vec::<InputWordFeature>::new());
-        }
+    {
+        let mut stmt = tx.prepare(
+            "insert into antonyms (coarse_class, synset_id1, lemma1, synset_id2, lemma2)
+            values (?1,?2,?3,?4,?5)",
+        )?;
 
-        if let Some(val) = feature_map.get_mut(&input_word_feature.word_id) {
-            val.push(input_word_feature);
+        for pair in pairs {
+            stmt.execute([
+                &pair.coarse_class as &dyn ToSql,
+                &pair.synset_id1 as &dyn ToSql,
+                &pair.lemma1 as &dyn ToSql,
+                &pair.synset_id2 as &dyn ToSql,
+                &pair.lemma2 as &dyn ToSql,
+            ])?;
         }
     }
 
-    Ok(feature_map)
-}
-
-pub(crate) fn select_input_features_by_sentence_and_section_and_word(
-    conn: &Connection,
-    section_id: &i32,
-    temporary_word_id: &i32,
-) -> Result<Vec<InputWordFeature>> {
-    let mut stmt = conn.prepare(
-        "select distinct f.word_id, f.ud_relation, f.ud_feature, f.ud_feature_value
-from input_triplet t
-inner join input_section s on s.triplet_id = t.triplet_id
-inner join input_word w on w.word_id = s.word_id
-inner join input_word_feature f on f.word_id = w.word_id
-section_id = ?1 and w.word_id != ?2 and w.new_word_id > 0",
-    )?;
-    let mut rows = stmt.query(&[&section_id, &temporary_word_id])?;
-
-    let mut feature_vec: Vec<InputWordFeature> = Vec::new();
-
-    while let Ok(Some(result_row)) = rows.next() {
-        let row = result_row;
-        let input_word_feature = InputWordFeature {
-            word_id: row.get(0)?,
-            ud_relation: row.get(1)?,
-            ud_feature: row.get(2)?,
-            ud_feature_value: row.get(3)?,
-        };
-
-        feature_vec.push(input_word_feature);
-    }
+    tx.commit()?;
 
-    Ok(feature_vec)
+    Ok(pairs.len())
 }
 
-pub(crate) fn select_input_section_all_ranked(
-    conn: &Connection,
-    triplet_id: &i32,
-) -> Result<Vec<InputSection>> {
-    let mut stmt = conn.prepare(
-        "select t.section_id,
-       t.triplet_id,
-       t.word_id,
-       t.section_type,
-       tbl.grade
-  from input_section t
-  -- apply rules
-  inner join input_word w on w.word_id = t.word_id and w.new_word_id > 0
-  inner join input_word_relation r on r.word_id = t.word_id 
- left join
- (
-    select ud_relation, grade from
-    ( 
-        select 'nsubj' ud_relation, 40 grade
-        union
-        select 'obj', 40
-        union
-        select 'iobj', 40
-        union
-        select 'csubj', 39
-        union
-        select 'ccomp', 39
-        union
-        select 'xcomp', 39
-        union
-        select 'nmod', 38
-        union
-        select 'appos', 38
-        union
-        select 'nummod', 38
-        union
-        select 'amod', 38
-        union
-        select 'acl', 38
-    )
-) tbl on instr(r.ud_relation, tbl.ud_relation) > 0 where t.triplet_id = ?1
-order by t.section_type, tbl.grade desc",
+/// Gets the version of the imported meaning grid.
+/// Returns None for databases that predate meaning_grid_version
+/// or whose grid was not loaded through import_meaning_grid.
+pub(crate) fn select_meaning_grid_version(conn: &Connection) -> Result<Option<String>> {
+    let has_version_table: i64 = conn.query_row(
+        "select exists (select 1 from sqlite_master where type = 'table' and name = 'meaning_grid_version')",
+        [],
+        |row| row.get(0),
     )?;
-    let input_section_iter = stmt.query_map(&[&triplet_id], |row| {
-        Ok(InputSection {
-            section_id: row.get(0)?,
-            triplet_id: row.get(1)?,
-            word_id: row.get(2)?,
-            section_type: row.get(3)?,
-        })
-    })?;
 
-    input_section_iter.collect::<Result<Vec<InputSection>>>()
-}
-
-/// Used to pre-load meaning grid items as lazy_static.
-pub(crate) fn get_meaning_grid() -> Result<HashMap<i32, MeaningGridItem>> {
-    let conn_meta = Connection::open(METAPATH)?;
-    let meaning_grid_items: HashMap<i32, MeaningGridItem> = select_meaning_grid_all(&conn_meta)?;
-
-    if let Err(e) = conn_meta.close() {
-        return Err(e.1);
+    if has_version_table == 0 {
+        return Ok(None);
     }
 
-    Ok(meaning_grid_items)
-}
-
-pub(crate) fn select_meaning_grid_all(conn: &Connection) -> Result<HashMap<i32, MeaningGridItem>> {
-    let mut stmt = conn.prepare("select x,y,synset_id from meaning_grid_item")?;
-    let mut rows = stmt.query(NO_PARAMS)?;
+    let mut stmt = conn.prepare("select version from meaning_grid_version limit 1")?;
+    let mut rows = stmt.query([])?;
 
-    let mut meaning_map = HashMap::new();
-
-    while let Ok(Some(result_row)) = rows.next() {
-        let row = result_row;
-        let meaning_item = MeaningGridItem {
-            x: row.get(0)?,
-            y: row.get(1)?,
-            synset_id: row.get(2)?,
-        };
-
-        meaning_map.insert(meaning_item.synset_id, meaning_item);
+    match rows.next()? {
+        Some(row) => Ok(row.get(0)?),
+        None => Ok(None),
     }
-
-    Ok(meaning_map)
 }
 
-pub(crate) fn select_meaning_grid_max_xy() -> Result<(f64, f64)> {
-    let conn_meta = Connection::open(METAPATH)?;
-
-    let max_xy = conn_meta.query_row(
+/// Gets the (max x, max y) of the meaning grid,
+/// failing with OkeuvoError::EmptyMeaningGrid if the grid has no items.
+pub(crate) fn select_meaning_grid_max_xy(
+    conn_meta: &Connection,
+) -> std::result::Result<(f64, f64), OkeuvoError> {
+    let max_xy: (Option<f64>, Option<f64>) = conn_meta.query_row(
         "select max(x), max(y) from meaning_grid_item",
         NO_PARAMS,
         |row| Ok((row.get(0)?, row.get(1)?)),
     )?;
 
-    if let Err(e) = conn_meta.close() {
-        return Err(e.1);
+    match max_xy {
+        (Some(max_x), Some(max_y)) => Ok((max_x, max_y)),
+        _ => Err(OkeuvoError::EmptyMeaningGrid),
+    }
+}
+
+/// Replaces the contents of meaning_grid_item with items and records the grid version.
+/// Everything happens in one transaction, so a failed import leaves the previous grid in place.
+pub(crate) fn insert_meaning_grid_items(
+    conn: &mut Connection,
+    version: &str,
+    items: &[MeaningGridItem],
+) -> Result<usize> {
+    let tx = conn.transaction()?;
+
+    tx.execute("delete from meaning_grid_version", [])?;
+    tx.execute(
+        "insert into meaning_grid_version (version) values (?1)",
+        [&version as &dyn ToSql],
+    )?;
+
+    tx.execute("delete from meaning_grid_item", [])?;
+    {
+        let mut stmt =
+            tx.prepare("insert into meaning_grid_item (synset_id, x, y) values (?1,?2,?3)")?;
+
+        for item in items {
+            stmt.execute([
+                &item.synset_id as &dyn ToSql,
+                &item.x as &dyn ToSql,
+                &item.y as &dyn ToSql,
+            ])?;
+        }
     }
 
-    Ok(max_xy)
+    tx.commit()?;
+
+    Ok(items.len())
 }
@@ -0,0 +1,351 @@
+/*
+DO NOT ALTER OR REMOVE COPYRIGHT NOTICES OR THIS HEADER.
+
+Copyright © 2019 Asame Imoni Obiomah. All rights reserved.
+
+Artificial intelligence ethics is of existential importance.
+The licensing model of OkeuvoLite enforces adherence to a strict ethical code.
+
+The contents of this file are subject to the terms of both the GNU General Public License Version 2 only (“GPL”)
+and Inverse license (collectively, the “License”). You may not use this file except in compliance with the License.
+You can obtain a copy of the License at LICENSE.txt. See the License for the specific language governing
+permissions and limitations under the License.
+
+When distributing the software, include this License Header Notice in each file and include the License file at LICENSE.txt.
+*/
+
+use crate::data::{
+    Entity, HashItem, InputDiscourse, InputDiscourseTitle, InputExemptFeature, InputNewWordDef,
+    InputSection, InputSentence, InputTriplet, InputWord, InputWordFeature, InputWordRelation,
+    UnitTensor, UnitTensorEtherealDef, UnitTensorLocation,
+};
+use crate::version::Versions;
+
+/// A discourse (or communication) held in memory, ready to be encoded.
+/// Each field mirrors a table of input.db, holding the rows that belong to
+/// one discourse, so a Discourse can be built in code or loaded from input.db
+/// (see Okeuvo::load_discourse).
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct Discourse {
+    /// Properties of the discourse (input_discourse).
+    pub discourse: InputDiscourse,
+    /// Words that make up the discourse title (input_discourse_title).
+    /// Only discourses with a title are given a hash.
+    pub title: Vec<InputDiscourseTitle>,
+    /// Sentences, in order (input_sentence).
+    pub sentences: Vec<InputSentence>,
+    /// Words of all sentences (input_word).
+    pub words: Vec<InputWord>,
+    /// Universal features of the words (input_word_feature).
+    pub features: Vec<InputWordFeature>,
+    /// Universal dependency relations between the words (input_word_relation).
+    /// word_id is the dependent and word_id_modified its head.
+    pub relations: Vec<InputWordRelation>,
+    /// Triplets of all sentences (input_triplet).
+    pub triplets: Vec<InputTriplet>,
+    /// Sections of all triplets (input_section).
+    pub sections: Vec<InputSection>,
+    /// Universal features that are exempt from virtuality checks (input_exempt_feature).
+    pub exempt_features: Vec<InputExemptFeature>,
+    /// Definitions of words that are not on the meaning grid (input_new_word_def).
+    pub new_word_defs: Vec<InputNewWordDef>,
+}
+
+impl Discourse {
+    /// Creates a discourse with no sentences.
+    pub fn new(discourse: InputDiscourse) -> Discourse {
+        Discourse {
+            discourse,
+            ..Default::default()
+        }
+    }
+
+    /// Unique discourse key, supplied by the network - autonumber.
+    pub fn discourse_id(&self) -> i32 {
+        self.discourse.discourse_id
+    }
+
+    /// Gets the triplets of a sentence, sorted by triplet_id so that
+    /// chronological order is maintained.
+    pub(crate) fn triplets_by_sentence(&self, sentence_id: &i32) -> Vec<&InputTriplet> {
+        let mut result: Vec<&InputTriplet> = self
+            .triplets
+            .iter()
+            .filter(|triplet| triplet.sentence_id == *sentence_id)
+            .collect();
+        result.sort_by_key(|triplet| triplet.triplet_id);
+
+        result
+    }
+
+    /// Gets the sections of a triplet, sorted by section_id.
+    pub(crate) fn sections_by_triplet(&self, triplet_id: &i32) -> Vec<&InputSection> {
+        let mut result: Vec<&InputSection> = self
+            .sections
+            .iter()
+            .filter(|section| section.triplet_id == *triplet_id)
+            .collect();
+        result.sort_by_key(|section| section.section_id);
+
+        result
+    }
+
+    /// Gets the words of a sentence that belong to a section of one of its triplets.
+    pub(crate) fn section_words_by_sentence(&self, sentence_id: &i32) -> Vec<&InputWord> {
+        let triplet_ids: Vec<i32> = self
+            .triplets_by_sentence(sentence_id)
+            .iter()
+            .map(|triplet| triplet.triplet_id)
+            .collect();
+
+        self.words
+            .iter()
+            .filter(|word| {
+                word.sentence_id == *sentence_id
+                    && self.sections.iter().any(|section| {
+                        section.word_id == word.word_id && triplet_ids.contains(&section.triplet_id)
+                    })
+            })
+            .collect()
+    }
+
+    /// Gets the universal features of a word.
+    pub(crate) fn features_by_word(&self, word_id: &i32) -> Vec<&InputWordFeature> {
+        self.features
+            .iter()
+            .filter(|feature| feature.word_id == *word_id)
+            .collect()
+    }
+
+    /// Gets the relations in which a word is the dependent.
+    pub(crate) fn relations_by_word(&self, word_id: &i32) -> Vec<&InputWordRelation> {
+        self.relations
+            .iter()
+            .filter(|relation| relation.word_id == *word_id)
+            .collect()
+    }
+
+    /// Gets the relations in which a word is the head, sorted by the word_id of the dependent.
+    pub(crate) fn relations_by_head(&self, word_id: &i32) -> Vec<&InputWordRelation> {
+        let mut result: Vec<&InputWordRelation> = self
+            .relations
+            .iter()
+            .filter(|relation| relation.word_id_modified == *word_id)
+            .collect();
+        result.sort_by_key(|relation| relation.word_id);
+
+        result
+    }
+
+    /// Gets the relations a word takes part in, as dependent or as head.
+    pub(crate) fn relations_touching_word(&self, word_id: &i32) -> Vec<&InputWordRelation> {
+        self.relations
+            .iter()
+            .filter(|relation| {
+                relation.word_id == *word_id || relation.word_id_modified == *word_id
+            })
+            .collect()
+    }
+}
+
+/// The encoding of a discourse, as produced by encode.
+/// These are the rows encode_discourse writes to output.db; here entity_id
+/// and unit_tensor_id are numbered from 1 within the encoding, and are
+/// replaced by the database keys when the encoding is saved.
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct Encoding {
+    /// Unique discourse key, supplied by the network - autonumber.
+    pub discourse_id: i32,
+    /// Entities for the focii of each triplet section, one per coreference instance.
+    pub entities: Vec<Entity>,
+    /// The graph of the discourse, one UnitTensor per triplet, in chronological order.
+    pub unit_tensors: Vec<UnitTensor>,
+    /// Blocks of virtual unit tensors.
+    pub ethereal_defs: Vec<UnitTensorEtherealDef>,
+    /// The "where"s and "when"s of the unit tensors, in unit tensor order.
+    pub locations: Vec<UnitTensorLocation>,
+    /// Hash items of the real events, the discourse hypernym first.
+    /// Empty if the discourse has no title.
+    pub hash_items_real: Vec<HashItem>,
+    /// Hash items of the virtual events, the discourse hypernym first.
+    /// Empty if the discourse has no title.
+    pub hash_items_virtual: Vec<HashItem>,
+    /// Versions of the library and meaning grid the discourse was encoded with.
+    pub versions: Versions,
+}
+
+impl Encoding {
+    /// Gets the hash items of the real or virtual events.
+    pub fn hash_items(&self, is_virtual: &bool) -> &[HashItem] {
+        match is_virtual {
+            true => &self.hash_items_virtual,
+            false => &self.hash_items_real,
+        }
+    }
+
+    /// Gets an entity by its entity_id.
+    pub fn entity(&self, entity_id: &i64) -> Option<&Entity> {
+        self.entities
+            .iter()
+            .find(|entity| i64::from(entity.entity_id) == *entity_id)
+    }
+
+    /// Gets the "where"s and "when"s of a unit tensor.
+    pub fn locations(&self, unit_tensor_id: &i32) -> Vec<&UnitTensorLocation> {
+        let id = i64::from(*unit_tensor_id);
+
+        self.locations
+            .iter()
+            .filter(|location| location.unit_tensor_id == id)
+            .collect()
+    }
+
+    /// Tests if a unit tensor falls in a block of virtual unit tensors.
+    pub fn is_virtual_unit_tensor(&self, unit_tensor_id: &i32) -> bool {
+        let id = i64::from(*unit_tensor_id);
+
+        self.ethereal_defs
+            .iter()
+            .any(|def| id >= def.branch_id && id <= def.rejoin_id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::OkeuvoError;
+    use crate::okeuvo::Okeuvo;
+    use crate::test_support::discourse;
+
+    /// "they asked him to read the book", whose triplets are "they asked him"
+    /// and "him read book".
+    fn asked_to_read() -> Discourse {
+        let mut discourse = discourse(&[
+            "they they PRON _ 2 nsubj",
+            "asked ask VERB Tense=Past 0 root",
+            "him he PRON _ 2 obj",
+            "to to PART _ 5 mark",
+            "read read VERB VerbForm=Inf 2 xcomp",
+            "the the DET _ 7 det",
+            "book book NOUN _ 5 obj",
+        ]);
+        discourse.discourse.discourse_id = 7;
+
+        discourse
+    }
+
+    fn lexemes(words: &[&InputWord]) -> Vec<String> {
+        words.iter().map(|word| word.lexeme.clone()).collect()
+    }
+
+    #[test]
+    fn builds_an_empty_discourse() {
+        let discourse = Discourse::new(InputDiscourse {
+            discourse_id: 7,
+            ..Default::default()
+        });
+
+        assert_eq!(discourse.discourse_id(), 7);
+        assert!(discourse.sentences.is_empty() && discourse.words.is_empty());
+    }
+
+    #[test]
+    fn looks_up_the_rows_of_a_sentence() {
+        let discourse = asked_to_read();
+
+        let triplet_ids: Vec<i32> = discourse
+            .triplets_by_sentence(&1)
+            .iter()
+            .map(|triplet| triplet.triplet_id)
+            .collect();
+        assert_eq!(triplet_ids, vec![1, 2]);
+        assert_eq!(discourse.sections_by_triplet(&2).len(), 3);
+        // "to" and "the" belong to no section.
+        assert_eq!(
+            lexemes(&discourse.section_words_by_sentence(&1)),
+            vec!["they", "ask", "he", "read", "book"]
+        );
+        assert_eq!(discourse.features_by_word(&5).len(), 1);
+
+        let dependents: Vec<i32> = discourse
+            .relations_by_head(&2)
+            .iter()
+            .map(|relation| relation.word_id)
+            .collect();
+        assert_eq!(dependents, vec![1, 3, 5]);
+        assert_eq!(discourse.relations_by_word(&5)[0].ud_relation, "xcomp");
+        // The relation of "read" to its head and those of its dependents.
+        assert_eq!(discourse.relations_touching_word(&5).len(), 3);
+    }
+
+    #[test]
+    fn loads_a_saved_discourse() {
+        let okeuvo = Okeuvo::open_in_memory().unwrap();
+        let discourse = asked_to_read();
+
+        let stored = okeuvo.save_discourse(&discourse, &true).unwrap();
+        let loaded = okeuvo.load_discourse(&7, &true).unwrap();
+
+        assert_eq!(loaded.discourse_id(), 7);
+        assert_eq!(loaded.sentences.len(), 1);
+        let words: Vec<&InputWord> = loaded.words.iter().collect();
+        assert_eq!(
+            lexemes(&words),
+            vec!["they", "ask", "he", "to", "read", "the", "book"]
+        );
+        let relations = |discourse: &Discourse| -> Vec<(i32, i32, String)> {
+            discourse
+                .relations
+                .iter()
+                .map(|relation| {
+                    (
+                        relation.word_id,
+                        relation.word_id_modified,
+                        relation.ud_relation.clone(),
+                    )
+                })
+                .collect()
+        };
+        assert_eq!(relations(&loaded), relations(&stored));
+        assert!(loaded
+            .relations
+            .iter()
+            .any(|relation| relation.ud_relation == "root" && relation.word_id_modified == -1));
+        assert_eq!(loaded.triplets.len(), 2);
+        assert_eq!(loaded.sections.len(), stored.sections.len());
+        assert_eq!(loaded.features.len(), discourse.features.len());
+    }
+
+    #[test]
+    fn loading_an_unknown_discourse_fails() {
+        let okeuvo = Okeuvo::open_in_memory().unwrap();
+
+        assert!(matches!(
+            okeuvo.load_discourse(&7, &true),
+            Err(OkeuvoError::DiscourseNotFound { discourse_id: 7 })
+        ));
+    }
+
+    #[test]
+    fn looks_up_the_rows_of_an_encoding() {
+        let encoding = crate::encode(&asked_to_read(), &true).unwrap();
+
+        assert_eq!(encoding.unit_tensors.len(), 2);
+        let subject_entity_id: i64 = encoding.unit_tensors[1].subject_entity_id;
+        assert_eq!(
+            encoding
+                .entity(&subject_entity_id)
+                .map(|entity| entity.word_id),
+            Some(3)
+        );
+        assert!(encoding.entity(&99).is_none());
+        assert!(encoding.locations(&1).is_empty());
+        // Untitled discourses have no hash.
+        assert!(encoding.hash_items(&false).is_empty() && encoding.hash_items(&true).is_empty());
+        assert!(!encoding.is_virtual_unit_tensor(&1));
+    }
+}
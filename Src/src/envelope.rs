@@ -0,0 +1,500 @@
+/*
+DO NOT ALTER OR REMOVE COPYRIGHT NOTICES OR THIS HEADER.
+
+Copyright © 2019 Asame Imoni Obiomah. All rights reserved.
+
+Artificial intelligence ethics is of existential importance.
+The licensing model of OkeuvoLite enforces adherence to a strict ethical code.
+
+The contents of this file are subject to the terms of both the GNU General Public License Version 2 only (“GPL”)
+and Inverse license (collectively, the “License”). You may not use this file except in compliance with the License.
+You can obtain a copy of the License at LICENSE.txt. See the License for the specific language governing
+permissions and limitations under the License.
+
+When distributing the software, include this License Header Notice in each file and include the License file at LICENSE.txt.
+*/
+
+//! Tamper-evident envelopes that bind a discourse hash to its document and author
+//! (cargo feature "signing"), for submission to the LushCoin ledger.
+//!
+//! An envelope is written as one "name value" line per field, in this order:
+//!
+//! ```text
+//! format okeuvo-lite-envelope
+//! version 1
+//! discourse_id 7
+//! semantic_hash v26.10.18/19.07.23:aa1r20d2#-!:2!29!2!06-...
+//! document_hash 9f86d081...
+//! author_public_hash 2c26b46b...
+//! author_public_key 3d4017c3...
+//! date_unix_epoch 1563840000
+//! library_version 26.10.18
+//! grid_version 19.07.23
+//! ```
+//!
+//! The digest is the SHA-256 hash of those lines. A signed envelope adds the
+//! lines "digest" and "signature": the Ed25519 signature of the digest by the
+//! author, whose public key is in the envelope. Keys, digests and signatures
+//! are written as lowercase hexadecimal.
+
+use crate::data::InputDiscourse;
+use crate::error::OkeuvoError;
+use crate::hash::ParsedHash;
+use crate::version::Versions;
+use ed25519_dalek::{Signature, Signer, SigningKey, VerifyingKey};
+use sha2::{Digest, Sha256};
+use std::fmt;
+use std::str::FromStr;
+
+/// Value of the "format" line of every envelope.
+pub const ENVELOPE_FORMAT: &str = "okeuvo-lite-envelope";
+
+/// Version of the envelope layout written by this library.
+pub const ENVELOPE_FORMAT_VERSION: u32 = 1;
+
+/// Fields of an envelope, in the order they are written.
+const ENVELOPE_FIELDS: [&str; 10] = [
+    "format",
+    "version",
+    "discourse_id",
+    "semantic_hash",
+    "document_hash",
+    "author_public_hash",
+    "author_public_key",
+    "date_unix_epoch",
+    "library_version",
+    "grid_version",
+];
+
+/// Fields a signed envelope adds to its envelope.
+const SIGNATURE_FIELDS: [&str; 2] = ["digest", "signature"];
+
+/// Binds the semantic hash of a discourse (see get_hash) to the hash of
+/// its document, its author and its date, and to the library and meaning grid
+/// versions the semantic hash was made with.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HashEnvelope {
+    /// Unique discourse key, supplied by the network - autonumber.
+    pub discourse_id: i32,
+    /// The semantic hash of the discourse.
+    pub semantic_hash: String,
+    /// Hash of the document (see InputDiscourse::document_hash).
+    pub document_hash: String,
+    /// Public hash of the author (see InputDiscourse::author_public_hash).
+    pub author_public_hash: String,
+    /// Ed25519 public key of the author, who signs the envelope.
+    pub author_public_key: [u8; 32],
+    /// Duration in seconds since UNIX_EPOCH.
+    pub date_unix_epoch: i32,
+    /// Versions the semantic hash was made with.
+    pub versions: Versions,
+}
+
+impl HashEnvelope {
+    /// Creates the envelope of the semantic hash of a discourse.
+    /// The versions are those the semantic hash is tagged with.
+    pub fn new(
+        discourse: &InputDiscourse,
+        semantic_hash: &str,
+        author_public_key: &[u8; 32],
+    ) -> Result<HashEnvelope, OkeuvoError> {
+        let envelope = HashEnvelope {
+            discourse_id: discourse.discourse_id,
+            semantic_hash: semantic_hash.to_string(),
+            document_hash: discourse.document_hash.clone(),
+            author_public_hash: discourse.author_public_hash.clone(),
+            author_public_key: *author_public_key,
+            date_unix_epoch: discourse.date_unix_epoch,
+            versions: ParsedHash::parse(semantic_hash)?.versions,
+        };
+        envelope.check()?;
+
+        Ok(envelope)
+    }
+
+    /// Gets the SHA-256 digest of the envelope, that of its lines (see envelope.rs).
+    pub fn digest(&self) -> [u8; 32] {
+        Sha256::digest(self.to_string().as_bytes()).into()
+    }
+
+    /// Signs the envelope with the Ed25519 secret key of its author.
+    /// The secret key must be that of author_public_key.
+    pub fn sign(&self, secret_key: &[u8; 32]) -> Result<SignedEnvelope, OkeuvoError> {
+        self.check()?;
+
+        let signing_key = SigningKey::from_bytes(secret_key);
+        if signing_key.verifying_key().to_bytes() != self.author_public_key {
+            return Err(invalid_envelope(
+                "the secret key is not that of the author public key".to_string(),
+            ));
+        }
+
+        let digest: [u8; 32] = self.digest();
+
+        Ok(SignedEnvelope {
+            envelope: self.clone(),
+            digest,
+            signature: signing_key.sign(&digest).to_bytes(),
+        })
+    }
+
+    /// Checks that the envelope can be written and read back: no field holds
+    /// a line break, and the semantic hash is tagged with the versions.
+    fn check(&self) -> Result<(), OkeuvoError> {
+        for (name, value) in &[
+            ("semantic_hash", &self.semantic_hash),
+            ("document_hash", &self.document_hash),
+            ("author_public_hash", &self.author_public_hash),
+            ("library_version", &self.versions.library),
+            ("grid_version", &self.versions.grid),
+        ] {
+            if value.contains(['\n', '\r']) {
+                return Err(invalid_envelope(format!("{} holds a line break", name)));
+            }
+        }
+
+        if ParsedHash::parse(&self.semantic_hash)?.versions != self.versions {
+            return Err(invalid_envelope(
+                "the versions are not those the semantic hash is tagged with".to_string(),
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Reads the fields of an envelope from its lines.
+    fn from_fields(fields: &[(&str, &str)]) -> Result<HashEnvelope, OkeuvoError> {
+        let value = |name: &str| -> &str {
+            fields
+                .iter()
+                .find(|(field, _)| *field == name)
+                .map_or("", |(_, value)| value)
+        };
+
+        if value("format") != ENVELOPE_FORMAT {
+            return Err(invalid_envelope(format!(
+                "format is '{}', expected '{}'",
+                value("format"),
+                ENVELOPE_FORMAT
+            )));
+        }
+
+        match value("version").parse::<u32>() {
+            Ok(version) if version >= 1 && version <= ENVELOPE_FORMAT_VERSION => {}
+            _ => {
+                return Err(invalid_envelope(format!(
+                    "version {} is not supported, this version of Okeuvo Lite reads up to {}",
+                    value("version"),
+                    ENVELOPE_FORMAT_VERSION
+                )))
+            }
+        }
+
+        let envelope = HashEnvelope {
+            discourse_id: value("discourse_id")
+                .parse()
+                .map_err(|_| invalid_envelope("discourse_id is not a number".to_string()))?,
+            semantic_hash: value("semantic_hash").to_string(),
+            document_hash: value("document_hash").to_string(),
+            author_public_hash: value("author_public_hash").to_string(),
+            author_public_key: from_hex(value("author_public_key"), "author_public_key")?,
+            date_unix_epoch: value("date_unix_epoch")
+                .parse()
+                .map_err(|_| invalid_envelope("date_unix_epoch is not a number".to_string()))?,
+            versions: Versions {
+                library: value("library_version").to_string(),
+                grid: value("grid_version").to_string(),
+            },
+        };
+        envelope.check()?;
+
+        Ok(envelope)
+    }
+}
+
+impl fmt::Display for HashEnvelope {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let values: [String; 10] = [
+            ENVELOPE_FORMAT.to_string(),
+            ENVELOPE_FORMAT_VERSION.to_string(),
+            self.discourse_id.to_string(),
+            self.semantic_hash.clone(),
+            self.document_hash.clone(),
+            self.author_public_hash.clone(),
+            to_hex(&self.author_public_key),
+            self.date_unix_epoch.to_string(),
+            self.versions.library.clone(),
+            self.versions.grid.clone(),
+        ];
+
+        for (name, value) in ENVELOPE_FIELDS.iter().zip(values.iter()) {
+            writeln!(f, "{} {}", name, value)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// An envelope with its digest, signed by its author.
+/// Displaying a SignedEnvelope gives the record submitted to the ledger,
+/// which SignedEnvelope::parse reads back.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SignedEnvelope {
+    /// The envelope.
+    pub envelope: HashEnvelope,
+    /// SHA-256 digest of the envelope (see HashEnvelope::digest).
+    pub digest: [u8; 32],
+    /// Ed25519 signature of the digest by the author.
+    pub signature: [u8; 64],
+}
+
+impl SignedEnvelope {
+    /// Checks that the envelope has not been tampered with: its digest is that
+    /// of the envelope and the signature is that of the digest by the author.
+    pub fn verify(&self) -> Result<(), OkeuvoError> {
+        self.envelope.check()?;
+
+        if self.envelope.digest() != self.digest {
+            return Err(invalid_envelope(
+                "the digest is not that of the envelope".to_string(),
+            ));
+        }
+
+        let verifying_key =
+            VerifyingKey::from_bytes(&self.envelope.author_public_key).map_err(|_| {
+                invalid_envelope("the author public key is not an Ed25519 key".to_string())
+            })?;
+
+        verifying_key
+            .verify_strict(&self.digest, &Signature::from_bytes(&self.signature))
+            .map_err(|_| invalid_envelope("the signature is not that of the author".to_string()))
+    }
+
+    /// Parses a signed envelope (see envelope.rs). The envelope is not
+    /// verified; call verify for that.
+    pub fn parse(text: &str) -> Result<SignedEnvelope, OkeuvoError> {
+        let lines: Vec<&str> = text.lines().collect();
+        let names = ENVELOPE_FIELDS.iter().chain(SIGNATURE_FIELDS.iter());
+
+        if lines.len() != ENVELOPE_FIELDS.len() + SIGNATURE_FIELDS.len() {
+            return Err(invalid_envelope(format!(
+                "expected {} lines, found {}",
+                ENVELOPE_FIELDS.len() + SIGNATURE_FIELDS.len(),
+                lines.len()
+            )));
+        }
+
+        let mut fields: Vec<(&str, &str)> = Vec::new();
+
+        for (i, (line, name)) in lines.iter().zip(names).enumerate() {
+            match line.split_once(' ') {
+                Some((field, value)) if field == *name => fields.push((field, value)),
+                _ => {
+                    return Err(invalid_envelope(format!(
+                        "line {}: expected the field {}",
+                        i + 1,
+                        name
+                    )))
+                }
+            }
+        }
+
+        let (envelope_fields, signature_fields) = fields.split_at(ENVELOPE_FIELDS.len());
+
+        Ok(SignedEnvelope {
+            envelope: HashEnvelope::from_fields(envelope_fields)?,
+            digest: from_hex(signature_fields[0].1, "digest")?,
+            signature: from_hex(signature_fields[1].1, "signature")?,
+        })
+    }
+}
+
+impl FromStr for SignedEnvelope {
+    type Err = OkeuvoError;
+
+    fn from_str(text: &str) -> Result<SignedEnvelope, OkeuvoError> {
+        SignedEnvelope::parse(text)
+    }
+}
+
+impl fmt::Display for SignedEnvelope {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.envelope)?;
+        writeln!(f, "{} {}", SIGNATURE_FIELDS[0], to_hex(&self.digest))?;
+        writeln!(f, "{} {}", SIGNATURE_FIELDS[1], to_hex(&self.signature))
+    }
+}
+
+/// Gets the Ed25519 public key of a secret key.
+pub fn public_key(secret_key: &[u8; 32]) -> [u8; 32] {
+    SigningKey::from_bytes(secret_key)
+        .verifying_key()
+        .to_bytes()
+}
+
+/// Writes bytes as lowercase hexadecimal.
+pub fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// Reads a fixed number of bytes written as lowercase hexadecimal.
+/// The parameter, name is that of the field, for messages.
+pub fn from_hex<const N: usize>(text: &str, name: &str) -> Result<[u8; N], OkeuvoError> {
+    let malformed = || {
+        invalid_envelope(format!(
+            "{} is not {} lowercase hexadecimal digits",
+            name,
+            N * 2
+        ))
+    };
+
+    if text.len() != N * 2
+        || !text
+            .bytes()
+            .all(|byte| byte.is_ascii_digit() || (b'a'..=b'f').contains(&byte))
+    {
+        return Err(malformed());
+    }
+
+    let mut bytes = [0u8; N];
+    for (i, byte) in bytes.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&text[i * 2..i * 2 + 2], 16).map_err(|_| malformed())?;
+    }
+
+    Ok(bytes)
+}
+
+/// Makes the error for a malformed or tampered envelope.
+fn invalid_envelope(message: String) -> OkeuvoError {
+    OkeuvoError::InvalidEnvelope { message }
+}
+
+#[cfg(all(test, feature = "signing"))]
+mod tests {
+    use super::*;
+
+    const SECRET_KEY: [u8; 32] = [7; 32];
+    const SEMANTIC_HASH: &str = "v26.10.18/19.07.23:aa1r2d2#-!:2!29!2!06-1!90!0!45";
+
+    fn envelope() -> HashEnvelope {
+        let discourse = InputDiscourse {
+            discourse_id: 7,
+            document_hash: "9f86d081884c7d65".to_string(),
+            author_public_hash: "2c26b46b68ffc68f".to_string(),
+            date_unix_epoch: 1563840000,
+            ..Default::default()
+        };
+
+        HashEnvelope::new(&discourse, SEMANTIC_HASH, &public_key(&SECRET_KEY)).unwrap()
+    }
+
+    /// Changes the last character of a value to another of the same kind.
+    fn edit(value: &str) -> String {
+        let mut chars: Vec<char> = value.chars().collect();
+        let last: char = chars.pop().unwrap();
+        chars.push(match last {
+            '9' => '0',
+            'f' => 'a',
+            'z' => 'a',
+            c => (c as u8 + 1) as char,
+        });
+
+        chars.into_iter().collect()
+    }
+
+    #[test]
+    fn takes_the_versions_from_the_semantic_hash() {
+        let versions: &Versions = &envelope().versions;
+
+        assert_eq!(versions.library, "26.10.18");
+        assert_eq!(versions.grid, "19.07.23");
+    }
+
+    #[test]
+    fn signed_envelopes_round_trip_and_verify() {
+        let signed: SignedEnvelope = envelope().sign(&SECRET_KEY).unwrap();
+        signed.verify().unwrap();
+
+        let record: String = signed.to_string();
+        assert_eq!(
+            record
+                .lines()
+                .map(|line| line.split(' ').next().unwrap())
+                .collect::<Vec<&str>>(),
+            ENVELOPE_FIELDS
+                .iter()
+                .chain(SIGNATURE_FIELDS.iter())
+                .copied()
+                .collect::<Vec<&str>>()
+        );
+
+        let parsed: SignedEnvelope = SignedEnvelope::parse(&record).unwrap();
+        assert_eq!(parsed, signed);
+        assert_eq!(parsed.to_string(), record);
+        parsed.verify().unwrap();
+    }
+
+    #[test]
+    fn refuses_to_sign_with_another_key() {
+        assert!(matches!(
+            envelope().sign(&[8; 32]),
+            Err(OkeuvoError::InvalidEnvelope { .. })
+        ));
+    }
+
+    #[test]
+    fn editing_any_line_of_the_record_fails() {
+        let record: String = envelope().sign(&SECRET_KEY).unwrap().to_string();
+        let lines: Vec<&str> = record.lines().collect();
+
+        for i in 0..lines.len() {
+            let (name, value) = lines[i].split_once(' ').unwrap();
+            let mut edited: Vec<String> = lines.iter().map(|line| line.to_string()).collect();
+            edited[i] = format!("{} {}", name, edit(value));
+
+            let result =
+                SignedEnvelope::parse(&edited.join("\n")).and_then(|signed| signed.verify());
+            assert!(result.is_err(), "editing {} was not noticed", name);
+        }
+    }
+
+    #[test]
+    fn editing_any_field_fails_verification() {
+        let signed: SignedEnvelope = envelope().sign(&SECRET_KEY).unwrap();
+        let edits: Vec<fn(&mut SignedEnvelope)> = vec![
+            |signed| signed.envelope.discourse_id += 1,
+            |signed| {
+                signed.envelope.semantic_hash =
+                    "v26.10.18/19.07.23:aa1r2d2#-!:2!29!2!07-1!90!0!45".to_string()
+            },
+            |signed| signed.envelope.document_hash.push('0'),
+            |signed| signed.envelope.author_public_hash.push('0'),
+            |signed| signed.envelope.author_public_key = public_key(&[8; 32]),
+            |signed| signed.envelope.date_unix_epoch += 1,
+            |signed| signed.envelope.versions.library = "26.10.19".to_string(),
+            |signed| signed.envelope.versions.grid = "19.07.24".to_string(),
+            |signed| signed.digest[0] ^= 1,
+            |signed| signed.signature[0] ^= 1,
+            |signed| signed.signature[63] ^= 1,
+        ];
+
+        for (i, edit) in edits.iter().enumerate() {
+            let mut edited: SignedEnvelope = signed.clone();
+            edit(&mut edited);
+
+            assert!(edited.verify().is_err(), "edit {} was not noticed", i + 1);
+        }
+    }
+
+    #[test]
+    fn a_digest_that_matches_an_edited_envelope_still_needs_the_signature() {
+        let mut signed: SignedEnvelope = envelope().sign(&SECRET_KEY).unwrap();
+        signed.envelope.date_unix_epoch += 1;
+        signed.digest = signed.envelope.digest();
+
+        assert!(matches!(
+            signed.verify(),
+            Err(OkeuvoError::InvalidEnvelope { message }) if message.contains("signature")
+        ));
+    }
+}
@@ -0,0 +1,408 @@
+/*
+DO NOT ALTER OR REMOVE COPYRIGHT NOTICES OR THIS HEADER.
+
+Copyright © 2019 Asame Imoni Obiomah. All rights reserved.
+
+Artificial intelligence ethics is of existential importance.
+The licensing model of OkeuvoLite enforces adherence to a strict ethical code.
+
+The contents of this file are subject to the terms of both the GNU General Public License Version 2 only (“GPL”)
+and Inverse license (collectively, the “License”). You may not use this file except in compliance with the License.
+You can obtain a copy of the License at LICENSE.txt. See the License for the specific language governing
+permissions and limitations under the License.
+
+When distributing the software, include this License Header Notice in each file and include the License file at LICENSE.txt.
+*/
+
+use crate::grid::UnresolvedWord;
+use crate::schema::DatabaseKind;
+use std::fmt;
+
+/// Errors raised by Okeuvo Lite.
+#[derive(Debug)]
+pub enum OkeuvoError {
+    /// The user has not agreed to The Creed.
+    CreedNotAccepted,
+    /// Encoding with coordinate resolution met words that are neither on
+    /// the meaning grid nor defined as new words.
+    /// Nothing is written to output.db when this error is returned.
+    MissingCoordinates {
+        /// Unique discourse key, supplied by the network - autonumber.
+        discourse_id: i32,
+        /// Every word that could not be resolved, in sentence order.
+        words: Vec<UnresolvedWord>,
+    },
+    /// A triplet has no word for one of its sections.
+    MissingTripletSection {
+        /// Id of the sentence the triplet belongs in.
+        sentence_id: i32,
+        /// Id of the triplet.
+        triplet_id: i32,
+        /// 1 = subject, 2 = predicate, 3 = object.
+        section_type: i32,
+    },
+    /// A triplet section points at a word that is not in the triplet's sentence.
+    MissingWord {
+        /// Id of the sentence the triplet belongs in.
+        sentence_id: i32,
+        /// Id of the triplet.
+        triplet_id: i32,
+        /// Primary key of the missing word in table input_word.
+        word_id: i32,
+    },
+    /// There is no discourse with this id.
+    DiscourseNotFound {
+        /// Unique discourse key, supplied by the network - autonumber.
+        discourse_id: i32,
+    },
+    /// The discourse has no sentences, so there is nothing to encode.
+    EmptyDiscourse {
+        /// Unique discourse key, supplied by the network - autonumber.
+        discourse_id: i32,
+    },
+    /// A synset is not on the meaning grid.
+    MissingSynset {
+        /// Wordnet synset_id in SQL format.
+        synset_id: i32,
+    },
+    /// The meaning grid has no items (has meaningGrid.csv been imported?).
+    EmptyMeaningGrid,
+    /// A meaning grid file failed validation.
+    InvalidMeaningGrid {
+        /// Line of the file holding the bad entry, if the problem is with one entry.
+        line: Option<usize>,
+        /// What is wrong.
+        message: String,
+    },
+    /// A CoNLL-U file failed validation.
+    InvalidConllu {
+        /// Line of the file holding the bad entry, if the problem is with one entry.
+        line: Option<usize>,
+        /// What is wrong.
+        message: String,
+    },
+    /// A Wordnet database file of antonyms failed validation.
+    InvalidAntonyms {
+        /// Line of the file holding the bad entry, if the problem is with one entry.
+        line: Option<usize>,
+        /// What is wrong.
+        message: String,
+    },
+    /// A JSON document (see DiscourseDocument) could not be read.
+    InvalidJson {
+        /// What is wrong.
+        message: String,
+    },
+    /// A hash is malformed.
+    InvalidHash {
+        /// The hash, or the part of it that is malformed.
+        hash: String,
+        /// What is wrong.
+        message: String,
+    },
+    /// Hashes can't be made with a HashSpec.
+    InvalidHashSpec {
+        /// What is wrong.
+        message: String,
+    },
+    /// The library or meaning grid version of an encoding or hash
+    /// is missing, malformed or not the one expected.
+    InvalidVersion {
+        /// What is wrong.
+        message: String,
+    },
+    /// A hash envelope is malformed, or has been tampered with.
+    InvalidEnvelope {
+        /// What is wrong.
+        message: String,
+    },
+    /// Two hashes can't be compared, as they were made differently.
+    IncomparableHashes {
+        /// What differs.
+        message: String,
+    },
+    /// A database has a schema version newer than this library knows.
+    UnsupportedSchemaVersion {
+        /// The database.
+        database: DatabaseKind,
+        /// Schema version stamped in the database.
+        version: u32,
+        /// Latest schema version this library knows.
+        supported: u32,
+    },
+    /// A database or file failure met while working on a discourse.
+    /// The failure (OkeuvoError::Database or OkeuvoError::Io) is kept as source.
+    InDiscourse {
+        /// Unique discourse key, supplied by the network - autonumber.
+        discourse_id: i32,
+        /// Id of the triplet being worked on, if the failure concerns one.
+        triplet_id: Option<i32>,
+        /// Primary key of the word being worked on in table input_word,
+        /// if the failure concerns one.
+        word_id: Option<i32>,
+        /// The failure.
+        source: Box<OkeuvoError>,
+    },
+    /// A file could not be read or written.
+    Io(std::io::Error),
+    /// An SQLite failure.
+    Database(rusqlite::Error),
+}
+
+impl fmt::Display for OkeuvoError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            OkeuvoError::CreedNotAccepted => write!(f, "You must agree to The Creed to continue"),
+            OkeuvoError::MissingCoordinates {
+                discourse_id,
+                words,
+            } => {
+                write!(
+                    f,
+                    "Discourse {}: {} word(s) have no meaning grid coordinates:",
+                    discourse_id,
+                    words.len()
+                )?;
+
+                for word in words {
+                    if word.word_id == 0 {
+                        write!(f, " the discourse hypernym (synset_id {});", word.synset_id)?;
+                        continue;
+                    }
+
+                    write!(
+                        f,
+                        " '{}' (word_id {}, synset_id {}, new_word_id {});",
+                        word.lexeme, word.word_id, word.synset_id, word.new_word_id
+                    )?;
+                }
+
+                Ok(())
+            }
+            OkeuvoError::MissingTripletSection {
+                sentence_id,
+                triplet_id,
+                section_type,
+            } => write!(
+                f,
+                "Triplet {} (sentence {}) has no {} section",
+                triplet_id,
+                sentence_id,
+                match section_type {
+                    1 => "subject",
+                    2 => "predicate",
+                    _ => "object",
+                }
+            ),
+            OkeuvoError::MissingWord {
+                sentence_id,
+                triplet_id,
+                word_id,
+            } => write!(
+                f,
+                "Triplet {} refers to word {}, which is not in sentence {}",
+                triplet_id, word_id, sentence_id
+            ),
+            OkeuvoError::DiscourseNotFound { discourse_id } => {
+                write!(f, "Discourse {} does not exist", discourse_id)
+            }
+            OkeuvoError::EmptyDiscourse { discourse_id } => {
+                write!(f, "Discourse {} has no sentences", discourse_id)
+            }
+            OkeuvoError::MissingSynset { synset_id } => {
+                write!(f, "Synset {} is not on the meaning grid", synset_id)
+            }
+            OkeuvoError::EmptyMeaningGrid => write!(f, "The meaning grid has no items"),
+            OkeuvoError::InvalidMeaningGrid { line, message } => match line {
+                Some(line) => write!(f, "Meaning grid line {}: {}", line, message),
+                None => write!(f, "Meaning grid: {}", message),
+            },
+            OkeuvoError::InvalidConllu { line, message } => match line {
+                Some(line) => write!(f, "CoNLL-U line {}: {}", line, message),
+                None => write!(f, "CoNLL-U: {}", message),
+            },
+            OkeuvoError::InvalidAntonyms { line, message } => match line {
+                Some(line) => write!(f, "Antonyms line {}: {}", line, message),
+                None => write!(f, "Antonyms: {}", message),
+            },
+            OkeuvoError::InvalidJson { message } => write!(f, "Invalid JSON document: {}", message),
+            OkeuvoError::InvalidHash { hash, message } => {
+                write!(f, "Invalid hash '{}': {}", hash, message)
+            }
+            OkeuvoError::InvalidHashSpec { message } => write!(f, "Invalid hash spec: {}", message),
+            OkeuvoError::InvalidVersion { message } => write!(f, "Invalid version: {}", message),
+            OkeuvoError::InvalidEnvelope { message } => {
+                write!(f, "Invalid hash envelope: {}", message)
+            }
+            OkeuvoError::IncomparableHashes { message } => {
+                write!(f, "Hashes can't be compared: {}", message)
+            }
+            OkeuvoError::UnsupportedSchemaVersion {
+                database,
+                version,
+                supported,
+            } => write!(
+                f,
+                "{} has schema version {}, but this version of Okeuvo Lite only knows up to {}",
+                database.name(),
+                version,
+                supported
+            ),
+            OkeuvoError::InDiscourse {
+                discourse_id,
+                triplet_id,
+                word_id,
+                source,
+            } => {
+                write!(f, "Discourse {}", discourse_id)?;
+                if let Some(triplet_id) = triplet_id {
+                    write!(f, ", triplet {}", triplet_id)?;
+                }
+                if let Some(word_id) = word_id {
+                    write!(f, ", word {}", word_id)?;
+                }
+                write!(f, ": {}", source)
+            }
+            OkeuvoError::Io(e) => write!(f, "{}", e),
+            OkeuvoError::Database(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for OkeuvoError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            OkeuvoError::InDiscourse { source, .. } => Some(source.as_ref()),
+            OkeuvoError::Io(e) => Some(e),
+            OkeuvoError::Database(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl OkeuvoError {
+    /// Attaches the discourse being worked on to a database or file failure
+    /// (see OkeuvoError::InDiscourse). Other errors are returned as they are,
+    /// as they identify what failed already.
+    pub(crate) fn in_discourse(self, discourse_id: &i32) -> OkeuvoError {
+        self.in_discourse_at(discourse_id, None, None)
+    }
+
+    /// Attaches the discourse, and the triplet or word, being worked on
+    /// to a database or file failure (see in_discourse).
+    pub(crate) fn in_discourse_at(
+        self,
+        discourse_id: &i32,
+        triplet_id: Option<i32>,
+        word_id: Option<i32>,
+    ) -> OkeuvoError {
+        match self {
+            OkeuvoError::Database(_) | OkeuvoError::Io(_) => OkeuvoError::InDiscourse {
+                discourse_id: *discourse_id,
+                triplet_id,
+                word_id,
+                source: Box::new(self),
+            },
+            e => e,
+        }
+    }
+
+    /// Gets the failure behind any discourse context (see OkeuvoError::InDiscourse).
+    pub fn root(&self) -> &OkeuvoError {
+        match self {
+            OkeuvoError::InDiscourse { source, .. } => source.root(),
+            e => e,
+        }
+    }
+}
+
+impl From<rusqlite::Error> for OkeuvoError {
+    fn from(e: rusqlite::Error) -> OkeuvoError {
+        OkeuvoError::Database(e)
+    }
+}
+
+impl From<std::io::Error> for OkeuvoError {
+    fn from(e: std::io::Error) -> OkeuvoError {
+        OkeuvoError::Io(e)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn attaches_the_discourse_to_database_failures() {
+        let error =
+            OkeuvoError::from(rusqlite::Error::InvalidQuery).in_discourse_at(&7, Some(3), Some(12));
+
+        assert!(matches!(
+            error,
+            OkeuvoError::InDiscourse {
+                discourse_id: 7,
+                triplet_id: Some(3),
+                word_id: Some(12),
+                ..
+            }
+        ));
+        assert!(matches!(error.root(), OkeuvoError::Database(_)));
+        assert!(std::error::Error::source(&error).is_some());
+        assert!(error
+            .to_string()
+            .starts_with("Discourse 7, triplet 3, word 12: "));
+    }
+
+    #[test]
+    fn leaves_errors_that_identify_what_failed() {
+        let error = OkeuvoError::DiscourseNotFound { discourse_id: 7 }.in_discourse(&7);
+
+        assert!(matches!(
+            error,
+            OkeuvoError::DiscourseNotFound { discourse_id: 7 }
+        ));
+    }
+
+    #[test]
+    fn names_the_meaning_grid_in_validation_failures() {
+        let error = OkeuvoError::InvalidMeaningGrid {
+            line: Some(4),
+            message: "duplicate synsetId 100001740".to_string(),
+        };
+
+        assert_eq!(
+            error.to_string(),
+            "Meaning grid line 4: duplicate synsetId 100001740"
+        );
+    }
+
+    #[test]
+    fn lists_the_words_and_hypernym_missing_coordinates() {
+        let error = OkeuvoError::MissingCoordinates {
+            discourse_id: 7,
+            words: vec![
+                UnresolvedWord {
+                    word_id: 0,
+                    sentence_id: 0,
+                    synset_id: 100002137,
+                    new_word_id: -1,
+                    lexeme: String::new(),
+                },
+                UnresolvedWord {
+                    word_id: 3,
+                    sentence_id: 1,
+                    synset_id: 100001740,
+                    new_word_id: -1,
+                    lexeme: "cake".to_string(),
+                },
+            ],
+        };
+
+        assert_eq!(
+            error.to_string(),
+            "Discourse 7: 2 word(s) have no meaning grid coordinates: \
+             the discourse hypernym (synset_id 100002137); \
+             'cake' (word_id 3, synset_id 100001740, new_word_id -1);"
+        );
+    }
+}
@@ -0,0 +1,172 @@
+/*
+DO NOT ALTER OR REMOVE COPYRIGHT NOTICES OR THIS HEADER.
+
+Copyright © 2019 Asame Imoni Obiomah. All rights reserved.
+
+Artificial intelligence ethics is of existential importance.
+The licensing model of OkeuvoLite enforces adherence to a strict ethical code.
+
+The contents of this file are subject to the terms of both the GNU General Public License Version 2 only (“GPL”)
+and Inverse license (collectively, the “License”). You may not use this file except in compliance with the License.
+You can obtain a copy of the License at LICENSE.txt. See the License for the specific language governing
+permissions and limitations under the License.
+
+When distributing the software, include this License Header Notice in each file and include the License file at LICENSE.txt.
+*/
+
+use crate::data::{HashItem, MeaningGridItem};
+use crate::grid_index::MeaningGridIndex;
+use crate::math::{polar_to_cartesian, Point2D};
+
+/// A human readable account of one hash item: the meaning grid concepts
+/// that lie nearest to its ground state and excited state coordinates.
+#[derive(Debug, Clone)]
+pub struct HashItemExplanation {
+    /// Position of the item in the hash. The discourse hypernym is at 0.
+    pub position: usize,
+    /// The hash item being explained.
+    pub hash_item: HashItem,
+    /// Ground state coordinates of the item (from radius and angle).
+    pub point: Point2D,
+    /// Concepts nearest to point.
+    ///
+    /// Format: Vec<(meaning grid item, distance)>, nearest first.
+    pub nearest: Vec<(MeaningGridItem, f64)>,
+    /// Excited state coordinates of the item (from excited_radius and excited_angle).
+    pub excited_point: Point2D,
+    /// Concepts nearest to excited_point.
+    ///
+    /// Format: Vec<(meaning grid item, distance)>, nearest first.
+    pub excited_nearest: Vec<(MeaningGridItem, f64)>,
+}
+
+/// Explains each of a set of hash items by the k meaning grid concepts nearest
+/// to its ground state and excited state coordinates.
+pub(crate) fn explain_hash_items(
+    hash_items: &[HashItem],
+    index: &MeaningGridIndex,
+    k: usize,
+) -> Vec<HashItemExplanation> {
+    let mut result: Vec<HashItemExplanation> = Vec::new();
+
+    for (position, hash_item) in hash_items.iter().enumerate() {
+        let point: Point2D = polar_to_cartesian(&hash_item.radius, &hash_item.angle);
+        let excited_point: Point2D =
+            polar_to_cartesian(&hash_item.excited_radius, &hash_item.excited_angle);
+
+        let nearest: Vec<(MeaningGridItem, f64)> = index
+            .nearest(&point, k)
+            .into_iter()
+            .map(|(item, distance)| (item.clone(), distance))
+            .collect();
+        let excited_nearest: Vec<(MeaningGridItem, f64)> = index
+            .nearest(&excited_point, k)
+            .into_iter()
+            .map(|(item, distance)| (item.clone(), distance))
+            .collect();
+
+        result.push(HashItemExplanation {
+            position,
+            hash_item: hash_item.clone(),
+            point,
+            nearest,
+            excited_point,
+            excited_nearest,
+        });
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grid::MeaningGrid;
+    use crate::test_support::{self, discourse, hash_item};
+
+    const HE: i32 = 100001740;
+    const EAT: i32 = 201168468;
+    const CAKE: i32 = 107628870;
+    const FOOD: i32 = 100021265;
+
+    fn grid() -> MeaningGrid {
+        test_support::grid(&[
+            (HE, 1.0, 1.0),
+            (EAT, 6.0, 2.0),
+            (CAKE, 4.0, 5.0),
+            (FOOD, 3.0, 4.0),
+        ])
+    }
+
+    fn synset_ids(nearest: &[(MeaningGridItem, f64)]) -> Vec<i32> {
+        nearest.iter().map(|(item, _)| item.synset_id).collect()
+    }
+
+    #[test]
+    fn names_the_concepts_nearest_each_state() {
+        let index = grid().spatial_index();
+
+        let explanations = explain_hash_items(
+            &[
+                hash_item((3.0, 4.0), (6.0, 3.0)),
+                hash_item((1.0, 1.5), (1.0, 1.5)),
+            ],
+            &index,
+            2,
+        );
+
+        assert_eq!(explanations.len(), 2);
+        let hypernym = &explanations[0];
+        assert_eq!(hypernym.position, 0);
+        assert!((hypernym.point.x - 3.0).abs() < 1e-9 && (hypernym.point.y - 4.0).abs() < 1e-9);
+        assert_eq!(synset_ids(&hypernym.nearest), vec![FOOD, CAKE]);
+        assert!(hypernym.nearest[0].1.abs() < 1e-9);
+        assert!((hypernym.nearest[1].1 - 2f64.sqrt()).abs() < 1e-9);
+        assert_eq!(synset_ids(&hypernym.excited_nearest), vec![EAT, CAKE]);
+        assert!((hypernym.excited_nearest[0].1 - 1.0).abs() < 1e-9);
+
+        let subject = &explanations[1];
+        assert_eq!(subject.position, 1);
+        assert_eq!(synset_ids(&subject.nearest), vec![HE, FOOD]);
+        assert!((subject.nearest[0].1 - 0.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn explains_an_encoding_by_its_own_words() {
+        let grid = grid();
+        let mut discourse = discourse(&[
+            "he he PRON _ 2 nsubj",
+            "ate eat VERB Tense=Past 0 root",
+            "cake cake NOUN _ 2 obj",
+        ]);
+        for (word, synset_id) in discourse.words.iter_mut().zip(vec![HE, EAT, CAKE]) {
+            word.synset_id = synset_id;
+            // Only instances are given a place in the hash.
+            word.instance_index = word.word_id;
+        }
+        discourse.discourse.hypernym_synset_id = FOOD;
+        discourse.title = vec![crate::data::InputDiscourseTitle {
+            discourse_id: 0,
+            word_id: 1,
+        }];
+        let encoding = crate::encode_with_grid(&discourse, &grid, &true).unwrap();
+
+        let explanations =
+            explain_hash_items(encoding.hash_items(&false), &grid.spatial_index(), 1);
+
+        // The hypernym, then the only subject, "he".
+        let nearest: Vec<(i32, i32)> = explanations
+            .iter()
+            .map(|explanation| {
+                (
+                    explanation.nearest[0].0.synset_id,
+                    explanation.position as i32,
+                )
+            })
+            .collect();
+        assert_eq!(nearest, vec![(FOOD, 0), (HE, 1)]);
+        assert!(explanations
+            .iter()
+            .all(|explanation| explanation.nearest[0].1 < 1e-9));
+    }
+}
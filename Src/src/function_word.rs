@@ -0,0 +1,247 @@
+/*
+DO NOT ALTER OR REMOVE COPYRIGHT NOTICES OR THIS HEADER.
+
+Copyright © 2019 Asame Imoni Obiomah. All rights reserved.
+
+Artificial intelligence ethics is of existential importance.
+The licensing model of OkeuvoLite enforces adherence to a strict ethical code.
+
+The contents of this file are subject to the terms of both the GNU General Public License Version 2 only (“GPL”)
+and Inverse license (collectively, the “License”). You may not use this file except in compliance with the License.
+You can obtain a copy of the License at LICENSE.txt. See the License for the specific language governing
+permissions and limitations under the License.
+
+When distributing the software, include this License Header Notice in each file and include the License file at LICENSE.txt.
+*/
+
+//! The function-word lexicon: how determiners, auxiliaries, adpositions,
+//! conjunctions, particles and pronouns take part in excitation and
+//! new-sense definition, where they would otherwise count as content words.
+
+use crate::data::InputWord;
+use std::collections::{BTreeMap, HashMap};
+
+/// Universal part of speech tags of function words.
+/// https://universaldependencies.org/u/pos/index.html
+pub const FUNCTION_WORD_UPOS: &[&str] = &["DET", "AUX", "ADP", "CCONJ", "SCONJ", "PART", "PRON"];
+
+/// How a function word takes part in excitation and new-sense definition.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FunctionWordTreatment {
+    /// Left out. The word adds no vector and needs no coordinates.
+    Excluded,
+    /// The word adds no vector of its own, but scales the vector of the word
+    /// it attaches to (its head in input_word_relation) by the factor.
+    /// It needs no coordinates.
+    Operator(f64),
+    /// The word counts as a content word, at its coordinates on the meaning grid.
+    Grid,
+}
+
+/// The function-word lexicon.
+/// Function words are known by their universal part of speech tag (InputWord::pos).
+/// Each tag has a treatment, which single lemmas of the tag can override.
+///
+/// By default, pronouns (PRON) stand for the entities they refer to and take
+/// grid coordinates, while the other tags of FUNCTION_WORD_UPOS are excluded.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FunctionWords {
+    /// Treatment of each part of speech, by universal part of speech tag.
+    classes: BTreeMap<String, FunctionWordTreatment>,
+    /// Treatments of single lemmas, by (universal part of speech tag, lowercase lemma).
+    lemmas: BTreeMap<(String, String), FunctionWordTreatment>,
+}
+
+impl FunctionWords {
+    /// Creates a lexicon that gives every tag of FUNCTION_WORD_UPOS the same treatment.
+    pub fn new(treatment: FunctionWordTreatment) -> FunctionWords {
+        FunctionWords {
+            classes: FUNCTION_WORD_UPOS
+                .iter()
+                .map(|upos| (upos.to_string(), treatment))
+                .collect(),
+            lemmas: BTreeMap::new(),
+        }
+    }
+
+    /// Sets the treatment of a part of speech, which becomes a function word
+    /// class if it wasn't one.
+    pub fn set_class(&mut self, upos: &str, treatment: FunctionWordTreatment) {
+        self.classes.insert(upos.to_uppercase(), treatment);
+    }
+
+    /// Makes a part of speech a content word class again.
+    pub fn remove_class(&mut self, upos: &str) {
+        self.classes.remove(&upos.to_uppercase());
+    }
+
+    /// Sets the treatment of one lemma of a part of speech, overriding that of its class.
+    pub fn set_lemma(&mut self, upos: &str, lemma: &str, treatment: FunctionWordTreatment) {
+        self.lemmas
+            .insert((upos.to_uppercase(), lemma.to_lowercase()), treatment);
+    }
+
+    /// Gets the treatment of a word, or None for a content word.
+    pub fn treatment(&self, word: &InputWord) -> Option<FunctionWordTreatment> {
+        let upos: String = word.pos.to_uppercase();
+
+        self.lemmas
+            .get(&(upos.clone(), word.lexeme.to_lowercase()))
+            .or_else(|| self.classes.get(&upos))
+            .copied()
+    }
+
+    /// Tests if a word is a function word.
+    pub fn is_function_word(&self, word: &InputWord) -> bool {
+        self.treatment(word).is_some()
+    }
+
+    /// Tests if a word needs coordinates: content words and
+    /// function words treated as FunctionWordTreatment::Grid.
+    pub fn needs_coordinates(&self, word: &InputWord) -> bool {
+        matches!(
+            self.treatment(word),
+            None | Some(FunctionWordTreatment::Grid)
+        )
+    }
+
+    /// Gets the weight of the vector of each word in a resultant.
+    /// Words that need coordinates weigh 1 and the others 0; each operator
+    /// then scales the weight of its head, when the head is among words.
+    ///
+    /// heads holds the head of each word (format - HashMap<word_id, word_id_modified>).
+    ///
+    /// Return format: HashMap<word_id, weight>.
+    pub(crate) fn weights(
+        &self,
+        words: &[&InputWord],
+        heads: &HashMap<i32, i32>,
+    ) -> HashMap<i32, f64> {
+        let mut result: HashMap<i32, f64> = words
+            .iter()
+            .map(|word| {
+                let weight: f64 = if self.needs_coordinates(word) {
+                    1.0
+                } else {
+                    0.0
+                };
+                (word.word_id, weight)
+            })
+            .collect();
+
+        for word in words {
+            if let Some(FunctionWordTreatment::Operator(factor)) = self.treatment(word) {
+                if let Some(weight) = heads
+                    .get(&word.word_id)
+                    .and_then(|head_id| result.get_mut(head_id))
+                {
+                    *weight *= factor;
+                }
+            }
+        }
+
+        result
+    }
+}
+
+impl Default for FunctionWords {
+    fn default() -> FunctionWords {
+        let mut function_words = FunctionWords::new(FunctionWordTreatment::Excluded);
+        function_words.set_class("PRON", FunctionWordTreatment::Grid);
+
+        function_words
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support;
+
+    fn word(word_id: i32, lexeme: &str, pos: &str) -> InputWord {
+        InputWord {
+            lexeme: lexeme.to_string(),
+            pos: pos.to_string(),
+            ..test_support::word(word_id, 100001740, 1.0, 1.0)
+        }
+    }
+
+    #[test]
+    fn pronouns_take_grid_coordinates_and_other_function_words_are_left_out() {
+        let function_words = FunctionWords::default();
+
+        for upos in FUNCTION_WORD_UPOS {
+            let expected = match *upos {
+                "PRON" => FunctionWordTreatment::Grid,
+                _ => FunctionWordTreatment::Excluded,
+            };
+            assert_eq!(
+                function_words.treatment(&word(1, "x", upos)),
+                Some(expected)
+            );
+        }
+        assert_eq!(function_words.treatment(&word(1, "cake", "NOUN")), None);
+        assert!(!function_words.is_function_word(&word(1, "eat", "VERB")));
+        assert!(function_words.is_function_word(&word(1, "the", "det")));
+    }
+
+    #[test]
+    fn lemmas_override_their_class() {
+        let mut function_words = FunctionWords::new(FunctionWordTreatment::Excluded);
+        function_words.set_lemma("Part", "NOT", FunctionWordTreatment::Operator(-1.0));
+        function_words.set_class("adv", FunctionWordTreatment::Operator(0.5));
+        function_words.remove_class("pron");
+
+        assert_eq!(
+            function_words.treatment(&word(1, "Not", "PART")),
+            Some(FunctionWordTreatment::Operator(-1.0))
+        );
+        assert_eq!(
+            function_words.treatment(&word(1, "to", "PART")),
+            Some(FunctionWordTreatment::Excluded)
+        );
+        assert_eq!(
+            function_words.treatment(&word(1, "very", "ADV")),
+            Some(FunctionWordTreatment::Operator(0.5))
+        );
+        assert_eq!(function_words.treatment(&word(1, "he", "PRON")), None);
+    }
+
+    #[test]
+    fn content_words_and_grid_function_words_need_coordinates() {
+        let mut function_words = FunctionWords::default();
+        function_words.set_class("ADV", FunctionWordTreatment::Operator(2.0));
+
+        assert!(function_words.needs_coordinates(&word(1, "cake", "NOUN")));
+        assert!(function_words.needs_coordinates(&word(1, "he", "PRON")));
+        assert!(!function_words.needs_coordinates(&word(1, "the", "DET")));
+        assert!(!function_words.needs_coordinates(&word(1, "very", "ADV")));
+    }
+
+    #[test]
+    fn operators_scale_the_weight_of_their_head() {
+        // "the very big cake": "very" doubles "big", "the" is left out.
+        let mut function_words = FunctionWords::default();
+        function_words.set_class("ADV", FunctionWordTreatment::Operator(2.0));
+        let words = [
+            word(1, "the", "DET"),
+            word(2, "very", "ADV"),
+            word(3, "big", "ADJ"),
+            word(4, "cake", "NOUN"),
+            word(5, "so", "ADV"),
+        ];
+        let heads: HashMap<i32, i32> = vec![(1, 4), (2, 3), (3, 4), (4, -1), (5, 9)]
+            .into_iter()
+            .collect();
+
+        let weights = function_words.weights(&words.iter().collect::<Vec<&InputWord>>(), &heads);
+
+        assert_eq!(weights[&1], 0.0);
+        assert_eq!(weights[&2], 0.0);
+        assert_eq!(weights[&3], 2.0);
+        assert_eq!(weights[&4], 1.0);
+        // The head of "so" is not among the words.
+        assert_eq!(weights[&5], 0.0);
+        assert_eq!(weights.len(), 5);
+    }
+}
@@ -0,0 +1,634 @@
+/*
+DO NOT ALTER OR REMOVE COPYRIGHT NOTICES OR THIS HEADER.
+
+Copyright © 2019 Asame Imoni Obiomah. All rights reserved.
+
+Artificial intelligence ethics is of existential importance.
+The licensing model of OkeuvoLite enforces adherence to a strict ethical code.
+
+The contents of this file are subject to the terms of both the GNU General Public License Version 2 only (“GPL”)
+and Inverse license (collectively, the “License”). You may not use this file except in compliance with the License.
+You can obtain a copy of the License at LICENSE.txt. See the License for the specific language governing
+permissions and limitations under the License.
+
+When distributing the software, include this License Header Notice in each file and include the License file at LICENSE.txt.
+*/
+
+use crate::data::{
+    select_antonym_grid_items, select_antonym_pairs, select_meaning_grid_all,
+    select_meaning_grid_version, Antonym, AntonymPair, InputNewWordDef, InputWord, MeaningGridItem,
+};
+use crate::error::OkeuvoError;
+use crate::function_word::FunctionWords;
+use crate::grid_index::MeaningGridIndex;
+use crate::utils::is_wordnet_sql_synset_id;
+use rusqlite::{Connection, OpenFlags};
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+
+/// Marker at the start of the line in the header of meaningGrid.csv
+/// that holds the version of the meaning grid.
+const GRID_VERSION_MARKER: &str = "#Alpha version";
+
+/// Contents of a meaning grid CSV file (see meaningGrid.csv).
+#[derive(Debug)]
+pub struct MeaningGridCsv {
+    /// Version of the meaning grid, taken from the
+    /// "#Alpha version ..." line of the header.
+    pub version: String,
+    /// Synset/coordinate entries, in file order.
+    pub items: Vec<MeaningGridItem>,
+}
+
+/// The meaning grid held in memory.
+/// Load it once (from metadata.db or straight from meaningGrid.csv)
+/// and share it, rather than querying metadata.db for every lookup.
+#[derive(Debug, Clone)]
+pub struct MeaningGrid {
+    /// Version of the meaning grid. Empty when the source did not record one.
+    version: String,
+    /// Meaning grid items, in the order they were loaded.
+    items: Vec<MeaningGridItem>,
+    /// Index into items by synset_id.
+    index: HashMap<i32, usize>,
+    /// Maximum x coordinate on the grid.
+    max_x: f64,
+    /// Maximum y coordinate on the grid.
+    max_y: f64,
+    /// Antonym pairs of each synset_id, its word first, in the order they were added.
+    /// Empty unless the grid was loaded from metadata.db (table antonyms)
+    /// or given pairs with add_antonyms.
+    antonyms: HashMap<i32, Vec<AntonymPair>>,
+}
+
+impl MeaningGrid {
+    /// Builds a meaning grid from a version and a set of items.
+    /// Where a synset_id occurs more than once, the last occurrence wins.
+    pub fn new(version: &str, items: Vec<MeaningGridItem>) -> MeaningGrid {
+        let mut grid = MeaningGrid {
+            version: version.to_string(),
+            items: Vec::with_capacity(items.len()),
+            index: HashMap::with_capacity(items.len()),
+            max_x: 0.0,
+            max_y: 0.0,
+            antonyms: HashMap::new(),
+        };
+
+        for item in items {
+            if item.x > grid.max_x {
+                grid.max_x = item.x;
+            }
+            if item.y > grid.max_y {
+                grid.max_y = item.y;
+            }
+
+            match grid.index.get(&item.synset_id) {
+                Some(position) => grid.items[*position] = item,
+                None => {
+                    grid.index.insert(item.synset_id, grid.items.len());
+                    grid.items.push(item);
+                }
+            }
+        }
+
+        grid
+    }
+
+    /// Loads the meaning grid from the table, meaning_grid_item of an open metadata.db connection,
+    /// together with the antonym pairs of the table, antonyms.
+    pub fn from_metadata(conn: &Connection) -> Result<MeaningGrid, OkeuvoError> {
+        let version: String = select_meaning_grid_version(conn)?.unwrap_or_default();
+        let items: Vec<MeaningGridItem> = select_meaning_grid_all(conn)?;
+
+        let mut grid = MeaningGrid::new(&version, items);
+        grid.add_antonyms(&select_antonym_pairs(conn)?);
+
+        Ok(grid)
+    }
+
+    /// Loads the antonym pairs of the table, antonyms of an open metadata.db
+    /// connection, with only the meaning grid items of their synsets: enough
+    /// for negation to take antonyms (see negate_word) where the rest of the
+    /// grid isn't needed.
+    pub(crate) fn antonyms_from_metadata(conn: &Connection) -> Result<MeaningGrid, OkeuvoError> {
+        let version: String = select_meaning_grid_version(conn)?.unwrap_or_default();
+        let items: Vec<MeaningGridItem> = select_antonym_grid_items(conn)?;
+
+        let mut grid = MeaningGrid::new(&version, items);
+        grid.add_antonyms(&select_antonym_pairs(conn)?);
+
+        Ok(grid)
+    }
+
+    /// Loads the meaning grid from the metadata database at path.
+    /// The database is opened read only and closed once the grid is loaded.
+    pub fn from_metadata_path(path: &Path) -> Result<MeaningGrid, OkeuvoError> {
+        let conn_meta = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+        let grid = MeaningGrid::from_metadata(&conn_meta)?;
+
+        if let Err(e) = conn_meta.close() {
+            Err(e.1)?
+        }
+
+        Ok(grid)
+    }
+
+    /// Loads the meaning grid directly from a CSV file in the format of meaningGrid.csv.
+    pub fn from_csv(path: &Path) -> Result<MeaningGrid, OkeuvoError> {
+        let csv: MeaningGridCsv = read_meaning_grid_csv(path)?;
+
+        Ok(MeaningGrid::new(&csv.version, csv.items))
+    }
+
+    /// Version of the meaning grid (e.g. "19.07.23 (codename, Ama)").
+    pub fn version(&self) -> &str {
+        &self.version
+    }
+
+    /// Gets the meaning grid item for a synset_id.
+    pub fn get(&self, synset_id: &i32) -> Option<&MeaningGridItem> {
+        self.index
+            .get(synset_id)
+            .map(|position| &self.items[*position])
+    }
+
+    /// Checks if a synset_id is on the meaning grid.
+    pub fn contains(&self, synset_id: &i32) -> bool {
+        self.index.contains_key(synset_id)
+    }
+
+    /// Gets the (x, y) coordinates of a synset_id.
+    pub fn coordinates(&self, synset_id: &i32) -> Option<(f64, f64)> {
+        self.get(synset_id).map(|item| (item.x, item.y))
+    }
+
+    /// Gets the complexity of a synset_id; c = (x * y) / 2.
+    pub fn complexity(&self, synset_id: &i32) -> Option<f64> {
+        self.get(synset_id).map(|item| item.complexity())
+    }
+
+    /// Gets the length of the position vector of a synset_id.
+    pub fn vector_length(&self, synset_id: &i32) -> Option<f64> {
+        self.get(synset_id).map(|item| item.vector_length())
+    }
+
+    /// Maximum x coordinate on the grid.
+    pub fn max_x(&self) -> f64 {
+        self.max_x
+    }
+
+    /// Maximum y coordinate on the grid.
+    pub fn max_y(&self) -> f64 {
+        self.max_y
+    }
+
+    /// Maximum x and y coordinates on the grid.
+    ///
+    /// Return format: (max x, max y).
+    pub fn max_xy(&self) -> (f64, f64) {
+        (self.max_x, self.max_y)
+    }
+
+    /// Number of items on the grid.
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    /// Checks if the grid has no items.
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    /// Iterates the items of the grid, in the order they were loaded.
+    pub fn iter(&self) -> std::slice::Iter<'_, MeaningGridItem> {
+        self.items.iter()
+    }
+
+    /// Adds antonym pairs to the grid (see read_wordnet_antonyms),
+    /// so that they can be looked up with antonyms.
+    /// Pairs already on the grid are skipped.
+    pub fn add_antonyms(&mut self, pairs: &[AntonymPair]) {
+        for pair in pairs {
+            for synset_id in [pair.synset_id1, pair.synset_id2] {
+                if let Some(oriented) = pair.oriented(&synset_id) {
+                    let synset_pairs = self.antonyms.entry(synset_id).or_default();
+                    if !synset_pairs.contains(&oriented) {
+                        synset_pairs.push(oriented);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Gets the antonyms of a synset_id, in the order they were added, with their
+    /// coordinates on the grid (0 for antonyms not on the grid).
+    pub fn antonyms(&self, synset_id: &i32) -> Vec<Antonym> {
+        self.antonyms
+            .get(synset_id)
+            .map(|pairs| {
+                pairs
+                    .iter()
+                    .map(|pair| {
+                        let (x, y) = self.coordinates(&pair.synset_id2).unwrap_or_default();
+
+                        Antonym {
+                            coarse_class: pair.coarse_class,
+                            lemma: pair.lemma1.clone(),
+                            synset_id: pair.synset_id2,
+                            antonym_lemma: pair.lemma2.clone(),
+                            x,
+                            y,
+                        }
+                    })
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Gets the first antonym of a synset_id that is on the meaning grid.
+    pub(crate) fn antonym(&self, synset_id: &i32) -> Option<&MeaningGridItem> {
+        self.antonyms
+            .get(synset_id)?
+            .iter()
+            .find_map(|pair| self.get(&pair.synset_id2))
+    }
+
+    /// Builds a spatial index over the grid for nearest concept queries.
+    pub fn spatial_index(&self) -> MeaningGridIndex {
+        MeaningGridIndex::new(self)
+    }
+}
+
+impl<'a> IntoIterator for &'a MeaningGrid {
+    type Item = &'a MeaningGridItem;
+    type IntoIter = std::slice::Iter<'a, MeaningGridItem>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.items.iter()
+    }
+}
+
+/// An input word whose coordinates could not be resolved.
+/// The discourse hypernym is reported as a word with word_id and
+/// sentence_id 0, an empty lexeme and its hypernym_synset_id.
+#[derive(Debug, Clone, PartialEq)]
+pub struct UnresolvedWord {
+    /// Primary key of the word in table input_word.
+    pub word_id: i32,
+    /// Id of the sentence this word belongs in.
+    pub sentence_id: i32,
+    /// Wordnet synset_id in SQL format (0 when the word has none).
+    pub synset_id: i32,
+    /// Identifies the words definition in input_new_word_def (-1 when it has none).
+    pub new_word_id: i32,
+    /// Lemma of the word.
+    pub lexeme: String,
+}
+
+/// Fills in missing coordinates of input words.
+/// A word is missing coordinates when both its x and y are 0.
+/// Coordinates are looked up on the meaning grid by synset_id first;
+/// words not on the grid that have a new_word_id (!= -1) take the
+/// coordinates of their definition in new_word_defs (see
+/// batch_define_new_word_sense). Words given coordinates by the caller,
+/// and function words that need none (see FunctionWords::needs_coordinates),
+/// are left untouched.
+///
+/// Returns the words that could not be resolved. Their coordinates are left at 0.
+pub(crate) fn resolve_input_word_coordinates(
+    grid: &MeaningGrid,
+    new_word_defs: &HashMap<i32, InputNewWordDef>,
+    function_words: &FunctionWords,
+    input_words: &mut HashMap<i32, InputWord>,
+) -> Vec<UnresolvedWord> {
+    let mut unresolved: Vec<UnresolvedWord> = Vec::new();
+
+    for word in input_words.values_mut() {
+        if word.x != 0.0 || word.y != 0.0 || !function_words.needs_coordinates(word) {
+            continue;
+        }
+
+        if let Some(item) = grid.get(&word.synset_id) {
+            word.x = item.x;
+            word.y = item.y;
+            continue;
+        }
+
+        if word.new_word_id != -1 {
+            if let Some(new_word_def) = new_word_defs.get(&word.new_word_id) {
+                if new_word_def.x != 0.0 || new_word_def.y != 0.0 {
+                    word.x = new_word_def.x;
+                    word.y = new_word_def.y;
+                    continue;
+                }
+            }
+        }
+
+        unresolved.push(UnresolvedWord {
+            word_id: word.word_id,
+            sentence_id: word.sentence_id,
+            synset_id: word.synset_id,
+            new_word_id: word.new_word_id,
+            lexeme: word.lexeme.clone(),
+        });
+    }
+
+    unresolved.sort_by_key(|word| (word.sentence_id, word.word_id));
+
+    unresolved
+}
+
+/// Reads and validates a meaning grid CSV file.
+pub(crate) fn read_meaning_grid_csv(path: &Path) -> Result<MeaningGridCsv, OkeuvoError> {
+    let file = File::open(path)?;
+
+    parse_meaning_grid_csv(BufReader::new(file))
+}
+
+/// Parses a meaning grid in the format of meaningGrid.csv:
+///
+/// 1. licensing information (lines starting with "#"),
+/// 2. a line holding the grid version ("#Alpha version ..."),
+/// 3. tab separated synsetId, x and y columns.
+///
+/// Empty lines are skipped. Every entry is validated, the first
+/// bad entry stops parsing with an error that names its line.
+pub(crate) fn parse_meaning_grid_csv<R: BufRead>(reader: R) -> Result<MeaningGridCsv, OkeuvoError> {
+    let mut version: Option<String> = None;
+    let mut items: Vec<MeaningGridItem> = Vec::new();
+    let mut seen_synset_ids: HashSet<i32> = HashSet::new();
+
+    for (i, line) in reader.lines().enumerate() {
+        let line = line?;
+        let line_number = i + 1;
+        let trimmed = line.trim();
+
+        if trimmed.is_empty() {
+            continue;
+        }
+
+        // Header lines. Only the version line is of interest.
+        if trimmed.starts_with('#') {
+            if let Some(rest) = trimmed.strip_prefix(GRID_VERSION_MARKER) {
+                version = Some(rest.trim().to_string());
+            }
+            continue;
+        }
+
+        let columns: Vec<&str> = trimmed.split('\t').collect();
+        if columns.len() != 3 {
+            return Err(OkeuvoError::InvalidMeaningGrid {
+                line: Some(line_number),
+                message: format!(
+                    "expected 3 tab separated columns (synsetId, x, y), found {}",
+                    columns.len()
+                ),
+            });
+        }
+
+        let synset_id: i32 =
+            columns[0]
+                .trim()
+                .parse()
+                .map_err(|_| OkeuvoError::InvalidMeaningGrid {
+                    line: Some(line_number),
+                    message: format!("synsetId '{}' is not a number", columns[0]),
+                })?;
+
+        if !is_wordnet_sql_synset_id(&synset_id) {
+            return Err(OkeuvoError::InvalidMeaningGrid {
+                line: Some(line_number),
+                message: format!(
+                    "synsetId {} is outside the Wordnet SQL synset_id ranges",
+                    synset_id
+                ),
+            });
+        }
+
+        let x = parse_coordinate(columns[1], "x", line_number)?;
+        let y = parse_coordinate(columns[2], "y", line_number)?;
+
+        if !seen_synset_ids.insert(synset_id) {
+            return Err(OkeuvoError::InvalidMeaningGrid {
+                line: Some(line_number),
+                message: format!("duplicate synsetId {}", synset_id),
+            });
+        }
+
+        items.push(MeaningGridItem { x, y, synset_id });
+    }
+
+    let version = match version {
+        Some(version) if !version.is_empty() => version,
+        _ => {
+            return Err(OkeuvoError::InvalidMeaningGrid {
+                line: None,
+                message: format!("no \"{}\" line found in the header", GRID_VERSION_MARKER),
+            })
+        }
+    };
+
+    Ok(MeaningGridCsv { version, items })
+}
+
+/// Parses a single meaning grid coordinate, rejecting values that are not finite numbers.
+fn parse_coordinate(value: &str, axis: &str, line_number: usize) -> Result<f64, OkeuvoError> {
+    match value.trim().parse::<f64>() {
+        Ok(coordinate) if coordinate.is_finite() => Ok(coordinate),
+        _ => Err(OkeuvoError::InvalidMeaningGrid {
+            line: Some(line_number),
+            message: format!("{} coordinate '{}' is not a number", axis, value),
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{self, item};
+
+    const HEADER: &str = "#Meaning grid\n#Alpha version 19.07.23 (codename, Ama)\n";
+
+    fn parse(entries: &str) -> Result<MeaningGridCsv, OkeuvoError> {
+        parse_meaning_grid_csv(format!("{}{}", HEADER, entries).as_bytes())
+    }
+
+    /// The message of an InvalidMeaningGrid error and the line it names.
+    fn invalid(entries: &str) -> (Option<usize>, String) {
+        match parse(entries) {
+            Err(OkeuvoError::InvalidMeaningGrid { line, message }) => (line, message),
+            other => panic!("expected InvalidMeaningGrid, got {:?}", other),
+        }
+    }
+
+    /// A word of sentence 1 left without coordinates.
+    fn word(word_id: i32, synset_id: i32, new_word_id: i32, pos: &str) -> InputWord {
+        InputWord {
+            new_word_id,
+            pos: pos.to_string(),
+            lexeme: format!("word{}", word_id),
+            ..test_support::word(word_id, synset_id, 0.0, 0.0)
+        }
+    }
+
+    #[test]
+    fn parses_the_version_and_entries_in_file_order() {
+        let csv = parse("100001740\t3\t4.5\n\n201168468 \t 1.25\t2\n").unwrap();
+
+        assert_eq!(csv.version, "19.07.23 (codename, Ama)");
+        let items: Vec<(i32, f64, f64)> = csv
+            .items
+            .iter()
+            .map(|item| (item.synset_id, item.x, item.y))
+            .collect();
+        assert_eq!(items, vec![(100001740, 3.0, 4.5), (201168468, 1.25, 2.0)]);
+    }
+
+    #[test]
+    fn names_the_line_of_a_bad_entry() {
+        // The header takes lines 1 and 2.
+        assert_eq!(
+            invalid("100001740\t3\n"),
+            (
+                Some(3),
+                "expected 3 tab separated columns (synsetId, x, y), found 2".to_string()
+            )
+        );
+        assert_eq!(
+            invalid("100001740\t3\t4\nentity\t3\t4\n"),
+            (Some(4), "synsetId 'entity' is not a number".to_string())
+        );
+        assert_eq!(
+            invalid("100001740\tthree\t4\n"),
+            (Some(3), "x coordinate 'three' is not a number".to_string())
+        );
+        assert_eq!(
+            invalid("100001740\t3\tNaN\n"),
+            (Some(3), "y coordinate 'NaN' is not a number".to_string())
+        );
+    }
+
+    #[test]
+    fn rejects_duplicate_and_out_of_range_synsets() {
+        assert_eq!(
+            invalid("100001740\t3\t4\n100001740\t5\t6\n"),
+            (Some(4), "duplicate synsetId 100001740".to_string())
+        );
+        assert_eq!(
+            invalid("1740\t3\t4\n"),
+            (
+                Some(3),
+                "synsetId 1740 is outside the Wordnet SQL synset_id ranges".to_string()
+            )
+        );
+        assert_eq!(
+            invalid("500001740\t3\t4\n"),
+            (
+                Some(3),
+                "synsetId 500001740 is outside the Wordnet SQL synset_id ranges".to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn requires_a_version_line() {
+        let error = parse_meaning_grid_csv("#Meaning grid\n100001740\t3\t4\n".as_bytes());
+
+        assert!(matches!(
+            error,
+            Err(OkeuvoError::InvalidMeaningGrid { line: None, .. })
+        ));
+    }
+
+    #[test]
+    fn looks_up_items_by_synset_id() {
+        let grid = MeaningGrid::new(
+            "19.07.23",
+            vec![item(100001740, 3.0, 4.0), item(201168468, 6.0, 1.0)],
+        );
+
+        assert_eq!(grid.version(), "19.07.23");
+        assert_eq!(grid.len(), 2);
+        assert!(grid.contains(&201168468));
+        assert!(!grid.contains(&100002137));
+        assert_eq!(grid.coordinates(&100001740), Some((3.0, 4.0)));
+        assert_eq!(grid.coordinates(&100002137), None);
+        assert_eq!(grid.complexity(&100001740), Some(6.0));
+        assert_eq!(grid.vector_length(&100001740), Some(5.0));
+        assert_eq!(grid.max_xy(), (6.0, 4.0));
+    }
+
+    #[test]
+    fn the_last_occurrence_of_a_synset_wins() {
+        let grid = MeaningGrid::new(
+            "19.07.23",
+            vec![
+                item(100001740, 3.0, 4.0),
+                item(201168468, 6.0, 1.0),
+                item(100001740, 2.0, 2.0),
+            ],
+        );
+
+        assert_eq!(grid.len(), 2);
+        assert_eq!(grid.coordinates(&100001740), Some((2.0, 2.0)));
+        let synset_ids: Vec<i32> = grid.iter().map(|item| item.synset_id).collect();
+        assert_eq!(synset_ids, vec![100001740, 201168468]);
+    }
+
+    #[test]
+    fn resolves_coordinates_from_the_grid_then_new_word_definitions() {
+        let grid = MeaningGrid::new("19.07.23", vec![item(100001740, 3.0, 4.0)]);
+        let new_word_defs: HashMap<i32, InputNewWordDef> = vec![(
+            7,
+            InputNewWordDef {
+                new_word_id: 7,
+                x: 2.0,
+                y: 5.0,
+                ..Default::default()
+            },
+        )]
+        .into_iter()
+        .collect();
+        let mut input_words: HashMap<i32, InputWord> = vec![
+            word(1, 100001740, -1, "NOUN"),
+            word(2, 0, 7, "NOUN"),
+            word(3, 100002137, -1, "NOUN"),
+            word(4, 0, -1, "DET"),
+            InputWord {
+                x: 9.0,
+                y: 9.0,
+                ..word(5, 100001740, -1, "NOUN")
+            },
+        ]
+        .into_iter()
+        .map(|word| (word.word_id, word))
+        .collect();
+
+        let unresolved = resolve_input_word_coordinates(
+            &grid,
+            &new_word_defs,
+            &FunctionWords::default(),
+            &mut input_words,
+        );
+
+        let coordinates = |word_id: i32| (input_words[&word_id].x, input_words[&word_id].y);
+        assert_eq!(coordinates(1), (3.0, 4.0));
+        assert_eq!(coordinates(2), (2.0, 5.0));
+        assert_eq!(coordinates(3), (0.0, 0.0));
+        // Function words need no coordinates, and those given are kept.
+        assert_eq!(coordinates(4), (0.0, 0.0));
+        assert_eq!(coordinates(5), (9.0, 9.0));
+        assert_eq!(
+            unresolved,
+            vec![UnresolvedWord {
+                word_id: 3,
+                sentence_id: 1,
+                synset_id: 100002137,
+                new_word_id: -1,
+                lexeme: "word3".to_string(),
+            }]
+        );
+    }
+}
@@ -0,0 +1,486 @@
+/*
+DO NOT ALTER OR REMOVE COPYRIGHT NOTICES OR THIS HEADER.
+
+Copyright © 2019 Asame Imoni Obiomah. All rights reserved.
+
+Artificial intelligence ethics is of existential importance.
+The licensing model of OkeuvoLite enforces adherence to a strict ethical code.
+
+The contents of this file are subject to the terms of both the GNU General Public License Version 2 only (“GPL”)
+and Inverse license (collectively, the “License”). You may not use this file except in compliance with the License.
+You can obtain a copy of the License at LICENSE.txt. See the License for the specific language governing
+permissions and limitations under the License.
+
+When distributing the software, include this License Header Notice in each file and include the License file at LICENSE.txt.
+*/
+
+use crate::data::MeaningGridItem;
+use crate::grid::MeaningGrid;
+use crate::math::Point2D;
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+
+/// A 2D k-d tree over meaning grid items, for finding the concepts that lie
+/// nearest to a point on the meaning grid (such as the excitation coordinates,
+/// excited_x and excited_y of a unit tensor).
+///
+/// The tree is implicit: items are stored in a single vector arranged so that
+/// the median of every range splits it, alternating between the x axis (even
+/// depths) and the y axis (odd depths).
+///
+/// Distances are Euclidean. Results are ordered by distance, with ties broken
+/// by synset_id so that queries are deterministic.
+#[derive(Debug, Clone)]
+pub struct MeaningGridIndex {
+    items: Vec<MeaningGridItem>,
+}
+
+/// A candidate for the k nearest neighbours.
+/// Ordered by squared distance, then by synset_id.
+struct Candidate {
+    distance_squared: f64,
+    synset_id: i32,
+    position: usize,
+}
+
+impl PartialEq for Candidate {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Candidate {}
+
+impl PartialOrd for Candidate {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Candidate {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.distance_squared
+            .partial_cmp(&other.distance_squared)
+            .unwrap_or(Ordering::Equal)
+            .then(self.synset_id.cmp(&other.synset_id))
+    }
+}
+
+impl MeaningGridIndex {
+    /// Builds an index over all the items of a meaning grid.
+    pub fn new(grid: &MeaningGrid) -> MeaningGridIndex {
+        MeaningGridIndex::from_items(grid.iter().cloned().collect())
+    }
+
+    /// Builds an index over a set of meaning grid items.
+    pub fn from_items(mut items: Vec<MeaningGridItem>) -> MeaningGridIndex {
+        build(&mut items, 0);
+
+        MeaningGridIndex { items }
+    }
+
+    /// Number of items in the index.
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    /// Checks if the index has no items.
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    /// Gets the k items nearest to point.
+    ///
+    /// Return format: Vec<(item, distance)>, nearest first.
+    pub fn nearest(&self, point: &Point2D, k: usize) -> Vec<(&MeaningGridItem, f64)> {
+        if k == 0 {
+            return Vec::new();
+        }
+
+        let mut heap: BinaryHeap<Candidate> = BinaryHeap::with_capacity(k + 1);
+        self.nearest_in_range(0, self.items.len(), 0, point, k, &mut heap);
+
+        let mut candidates: Vec<Candidate> = heap.into_vec();
+        candidates.sort();
+
+        candidates
+            .iter()
+            .map(|candidate| {
+                (
+                    &self.items[candidate.position],
+                    candidate.distance_squared.sqrt(),
+                )
+            })
+            .collect()
+    }
+
+    /// Gets every item whose distance from point is at most radius.
+    ///
+    /// Return format: Vec<(item, distance)>, nearest first.
+    pub fn within_radius(&self, point: &Point2D, radius: f64) -> Vec<(&MeaningGridItem, f64)> {
+        let mut result: Vec<(&MeaningGridItem, f64)> = Vec::new();
+
+        if radius < 0.0 {
+            return result;
+        }
+
+        self.radius_in_range(0, self.items.len(), 0, point, radius, &mut result);
+
+        result.sort_by(|a, b| {
+            a.1.partial_cmp(&b.1)
+                .unwrap_or(Ordering::Equal)
+                .then(a.0.synset_id.cmp(&b.0.synset_id))
+        });
+
+        result
+    }
+
+    /// Gets every item inside the axis aligned rectangle with corners, min and max
+    /// (edges included).
+    ///
+    /// Return format: Vec<item>, in ascending order of synset_id.
+    pub fn within_rectangle(&self, min: &Point2D, max: &Point2D) -> Vec<&MeaningGridItem> {
+        let mut result: Vec<&MeaningGridItem> = Vec::new();
+
+        self.rectangle_in_range(0, self.items.len(), 0, min, max, &mut result);
+
+        result.sort_by_key(|item| item.synset_id);
+
+        result
+    }
+
+    fn nearest_in_range(
+        &self,
+        start: usize,
+        end: usize,
+        depth: usize,
+        point: &Point2D,
+        k: usize,
+        heap: &mut BinaryHeap<Candidate>,
+    ) {
+        if start >= end {
+            return;
+        }
+
+        let middle = start + (end - start) / 2;
+        let item = &self.items[middle];
+
+        let candidate = Candidate {
+            distance_squared: distance_squared(item, point),
+            synset_id: item.synset_id,
+            position: middle,
+        };
+        if heap.len() < k {
+            heap.push(candidate);
+        } else if let Some(worst) = heap.peek() {
+            if candidate < *worst {
+                heap.pop();
+                heap.push(candidate);
+            }
+        }
+
+        let difference = axis_value_of_point(point, depth) - axis_value(item, depth);
+        let (near, far) = if difference < 0.0 {
+            ((start, middle), (middle + 1, end))
+        } else {
+            ((middle + 1, end), (start, middle))
+        };
+
+        self.nearest_in_range(near.0, near.1, depth + 1, point, k, heap);
+
+        // Only cross the splitting line if something on the far side could be nearer.
+        let cross = match heap.peek() {
+            Some(worst) => heap.len() < k || difference * difference <= worst.distance_squared,
+            None => true,
+        };
+        if cross {
+            self.nearest_in_range(far.0, far.1, depth + 1, point, k, heap);
+        }
+    }
+
+    fn radius_in_range<'a>(
+        &'a self,
+        start: usize,
+        end: usize,
+        depth: usize,
+        point: &Point2D,
+        radius: f64,
+        result: &mut Vec<(&'a MeaningGridItem, f64)>,
+    ) {
+        if start >= end {
+            return;
+        }
+
+        let middle = start + (end - start) / 2;
+        let item = &self.items[middle];
+
+        let distance = distance_squared(item, point).sqrt();
+        if distance <= radius {
+            result.push((item, distance));
+        }
+
+        let difference = axis_value_of_point(point, depth) - axis_value(item, depth);
+        if difference - radius <= 0.0 {
+            self.radius_in_range(start, middle, depth + 1, point, radius, result);
+        }
+        if difference + radius >= 0.0 {
+            self.radius_in_range(middle + 1, end, depth + 1, point, radius, result);
+        }
+    }
+
+    fn rectangle_in_range<'a>(
+        &'a self,
+        start: usize,
+        end: usize,
+        depth: usize,
+        min: &Point2D,
+        max: &Point2D,
+        result: &mut Vec<&'a MeaningGridItem>,
+    ) {
+        if start >= end {
+            return;
+        }
+
+        let middle = start + (end - start) / 2;
+        let item = &self.items[middle];
+
+        if item.x >= min.x && item.x <= max.x && item.y >= min.y && item.y <= max.y {
+            result.push(item);
+        }
+
+        let split = axis_value(item, depth);
+        if axis_value_of_point(min, depth) <= split {
+            self.rectangle_in_range(start, middle, depth + 1, min, max, result);
+        }
+        if axis_value_of_point(max, depth) >= split {
+            self.rectangle_in_range(middle + 1, end, depth + 1, min, max, result);
+        }
+    }
+}
+
+/// Arranges items into an implicit k-d tree.
+/// The median of each range (on the axis for its depth) is moved to the middle
+/// of the range, with smaller values before it and larger values after it.
+fn build(items: &mut [MeaningGridItem], depth: usize) {
+    if items.len() <= 1 {
+        return;
+    }
+
+    let middle = items.len() / 2;
+    items.select_nth_unstable_by(middle, |a, b| {
+        axis_value(a, depth)
+            .partial_cmp(&axis_value(b, depth))
+            .unwrap_or(Ordering::Equal)
+    });
+
+    let (before, after) = items.split_at_mut(middle);
+    build(before, depth + 1);
+    build(&mut after[1..], depth + 1);
+}
+
+/// The coordinate of an item on the splitting axis for depth (x for even depths, y for odd).
+fn axis_value(item: &MeaningGridItem, depth: usize) -> f64 {
+    match depth % 2 {
+        0 => item.x,
+        _ => item.y,
+    }
+}
+
+/// The coordinate of a point on the splitting axis for depth (x for even depths, y for odd).
+fn axis_value_of_point(point: &Point2D, depth: usize) -> f64 {
+    match depth % 2 {
+        0 => point.x,
+        _ => point.y,
+    }
+}
+
+fn distance_squared(item: &MeaningGridItem, point: &Point2D) -> f64 {
+    let dx = item.x - point.x;
+    let dy = item.y - point.y;
+
+    dx * dx + dy * dy
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{self, item};
+
+    /// Items on whole coordinates of a 12 x 12 grid, many of them at the same
+    /// distance from any point, with a few sharing coordinates.
+    fn items() -> Vec<MeaningGridItem> {
+        // A small linear congruential generator keeps the layout fixed.
+        let mut state: u32 = 7;
+        let mut next = || {
+            state = state.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+            f64::from((state >> 16) % 13)
+        };
+
+        (0..150)
+            .map(|i| MeaningGridItem {
+                x: next(),
+                y: next(),
+                synset_id: 100_000_000 + i,
+            })
+            .collect()
+    }
+
+    fn points() -> Vec<Point2D> {
+        let mut points: Vec<Point2D> = Vec::new();
+        for x in &[-3.0, 0.0, 2.5, 6.0, 11.75, 15.0] {
+            for y in &[-1.0, 0.0, 4.0, 6.5, 12.0, 20.0] {
+                points.push(Point2D { x: *x, y: *y });
+            }
+        }
+
+        points
+    }
+
+    /// Every item with its distance from point, ordered as the index orders them.
+    fn brute_force(items: &[MeaningGridItem], point: &Point2D) -> Vec<(i32, f64)> {
+        let mut result: Vec<(i32, f64)> = items
+            .iter()
+            .map(|item| (item.synset_id, distance_squared(item, point)))
+            .collect();
+        result.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap().then(a.0.cmp(&b.0)));
+
+        result
+            .into_iter()
+            .map(|(synset_id, distance_squared)| (synset_id, distance_squared.sqrt()))
+            .collect()
+    }
+
+    fn synset_ids_and_distances(found: Vec<(&MeaningGridItem, f64)>) -> Vec<(i32, f64)> {
+        found
+            .into_iter()
+            .map(|(item, distance)| (item.synset_id, distance))
+            .collect()
+    }
+
+    #[test]
+    fn nearest_matches_a_brute_force_scan() {
+        let items = items();
+        let index = MeaningGridIndex::from_items(items.clone());
+
+        for point in points() {
+            let expected = brute_force(&items, &point);
+            for k in &[1, 2, 5, 17, 150, 200] {
+                let found = synset_ids_and_distances(index.nearest(&point, *k));
+
+                assert_eq!(
+                    found,
+                    expected[..(*k).min(items.len())],
+                    "{:?}, k {}",
+                    point,
+                    k
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn within_radius_matches_a_brute_force_scan() {
+        let items = items();
+        let index = MeaningGridIndex::from_items(items.clone());
+
+        for point in points() {
+            let expected = brute_force(&items, &point);
+            // Whole radii put items exactly on the circle, which are included.
+            for radius in &[0.0, 1.0, 2.0, 3.5, 5.0, 40.0] {
+                let found = synset_ids_and_distances(index.within_radius(&point, *radius));
+                let within: Vec<(i32, f64)> = expected
+                    .iter()
+                    .filter(|(_, distance)| distance <= radius)
+                    .copied()
+                    .collect();
+
+                assert_eq!(found, within, "{:?}, radius {}", point, radius);
+            }
+        }
+    }
+
+    #[test]
+    fn within_rectangle_matches_a_brute_force_scan() {
+        let items = items();
+        let index = MeaningGridIndex::from_items(items.clone());
+
+        let rectangles = vec![
+            ((0.0, 0.0), (12.0, 12.0)),
+            ((3.0, 4.0), (3.0, 4.0)),
+            ((2.5, 1.0), (7.0, 9.5)),
+            ((-5.0, 10.0), (4.0, 30.0)),
+            ((8.0, 8.0), (2.0, 2.0)),
+        ];
+        for (min, max) in rectangles {
+            let (min, max) = (
+                Point2D { x: min.0, y: min.1 },
+                Point2D { x: max.0, y: max.1 },
+            );
+            let found: Vec<i32> = index
+                .within_rectangle(&min, &max)
+                .iter()
+                .map(|item| item.synset_id)
+                .collect();
+            let expected: Vec<i32> = items
+                .iter()
+                .filter(|item| {
+                    item.x >= min.x && item.x <= max.x && item.y >= min.y && item.y <= max.y
+                })
+                .map(|item| item.synset_id)
+                .collect();
+
+            assert_eq!(found, expected, "{:?} to {:?}", min, max);
+        }
+    }
+
+    #[test]
+    fn ties_are_broken_by_synset_id() {
+        let index = MeaningGridIndex::from_items(vec![
+            item(100000004, 1.0, 0.0),
+            item(100000002, 0.0, 1.0),
+            item(100000003, -1.0, 0.0),
+            item(100000001, 0.0, -1.0),
+            item(100000005, 1.0, 0.0),
+        ]);
+        let origin = Point2D { x: 0.0, y: 0.0 };
+
+        let found: Vec<i32> = index
+            .nearest(&origin, 3)
+            .iter()
+            .map(|(item, _)| item.synset_id)
+            .collect();
+        assert_eq!(found, vec![100000001, 100000002, 100000003]);
+
+        let found: Vec<i32> = index
+            .within_radius(&origin, 1.0)
+            .iter()
+            .map(|(item, _)| item.synset_id)
+            .collect();
+        assert_eq!(
+            found,
+            vec![100000001, 100000002, 100000003, 100000004, 100000005]
+        );
+    }
+
+    #[test]
+    fn an_empty_index_finds_nothing() {
+        let index = test_support::grid(&[]).spatial_index();
+        let point = Point2D { x: 1.0, y: 1.0 };
+
+        assert!(index.is_empty());
+        assert!(index.nearest(&point, 3).is_empty());
+        assert!(index.within_radius(&point, 10.0).is_empty());
+        assert!(index
+            .within_rectangle(&Point2D { x: 0.0, y: 0.0 }, &point)
+            .is_empty());
+    }
+
+    #[test]
+    fn no_neighbours_and_negative_radii_find_nothing() {
+        let index = MeaningGridIndex::from_items(items());
+        let point = Point2D { x: 1.0, y: 1.0 };
+
+        assert!(index.nearest(&point, 0).is_empty());
+        assert!(index.within_radius(&point, -1.0).is_empty());
+    }
+}
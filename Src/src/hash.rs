@@ -0,0 +1,1237 @@
+/*
+DO NOT ALTER OR REMOVE COPYRIGHT NOTICES OR THIS HEADER.
+
+Copyright © 2019 Asame Imoni Obiomah. All rights reserved.
+
+Artificial intelligence ethics is of existential importance.
+The licensing model of OkeuvoLite enforces adherence to a strict ethical code.
+
+The contents of this file are subject to the terms of both the GNU General Public License Version 2 only (“GPL”)
+and Inverse license (collectively, the “License”). You may not use this file except in compliance with the License.
+You can obtain a copy of the License at LICENSE.txt. See the License for the specific language governing
+permissions and limitations under the License.
+
+When distributing the software, include this License Header Notice in each file and include the License file at LICENSE.txt.
+*/
+
+//! The hash string formats (see get_hash) and their parser.
+//!
+//! A hash starts with the tag of the library and meaning grid versions it was
+//! made with (see Versions::tag) and the prefix of its spec (see HashSpec),
+//! followed by its records, the discourse hypernym first.
+//!
+//! Hashes in the apex angle format (the default) separate their records with "-"
+//! and their elements with "!". Each record holds the lean direction, apex angle,
+//! excited lean direction and excited apex angle, e.g. "1!09!0!36".
+//!
+//! Hashes in the area ratio format separate their records with ";" and their
+//! elements with ",". Each record holds the area ratios of x, y, excited x and
+//! excited y (see area_ratio), e.g. "2.500000e-1,1.111111e-1,2.500000e-1,4.000000e-2".
+//!
+//! Hashes in the exact format are laid out as apex angle hashes, but keep the
+//! apex angles (in radians) as they were computed: each is written as the
+//! 16 hexadecimal digits of its IEEE 754 bits,
+//! e.g. "2!3fe022d6fab7635c!2!3fbccdb93a777c60".
+//!
+//! Records that are not needed are padded with "#" for each digit group,
+//! as "#!##!#!##", "#,#,#,#" or "#!################!#!################".
+
+use crate::data::{HashItem, HashItemFormatted};
+use crate::error::OkeuvoError;
+use crate::math::{area_ratio, area_ratio_coordinate, polar_to_cartesian, Point2D};
+use crate::similarity::{compare_records, hash_comparable_records, HashSimilarity};
+use crate::version::Versions;
+use std::fmt;
+use std::str::FromStr;
+
+/// Version of the layout of hash spec prefixes (see HashSpec::prefix).
+pub const HASH_SPEC_VERSION: u32 = 1;
+/// Default number of records in a hash.
+pub const HASH_RECORDS: usize = 20;
+/// Default separator of the records of apex angle and exact hashes.
+pub const RECORD_SEPARATOR: char = '-';
+/// Default separator of the elements of a record of apex angle and exact hashes.
+pub const ELEMENT_SEPARATOR: char = '!';
+/// Default separator of the records of area ratio hashes.
+pub const AREA_RATIO_RECORD_SEPARATOR: char = ';';
+/// Default separator of the elements of a record of area ratio hashes.
+pub const AREA_RATIO_ELEMENT_SEPARATOR: char = ',';
+/// Default character that replaces every digit group of a padding record.
+pub const PADDING: char = '#';
+/// Tag of the apex angle format in hash spec prefixes.
+pub const APEX_ANGLE_TAG: &str = "aa";
+/// Tag of the area ratio format in hash spec prefixes.
+pub const AREA_RATIO_TAG: &str = "ar";
+/// Tag of the exact format in hash spec prefixes.
+pub const EXACT_TAG: &str = "ex";
+/// Ends the versions tag and the spec prefix of a hash.
+pub const SPEC_SEPARATOR: char = ':';
+/// Largest error, as a fraction of the (max x, max y) of the meaning grid, of the
+/// coordinates decoded from an area ratio hash with the default digits (see
+/// ParsedHash::coordinates), for coordinates up to four times the max either
+/// side of the origin (excited coordinates can lie beyond the max).
+/// Each digit fewer makes it ten times larger.
+pub const AREA_RATIO_TOLERANCE: f64 = 1.0e-6;
+
+/// Default fewest digits an apex angle is written with.
+const ANGLE_DIGITS: usize = 2;
+/// Most digits an apex angle can be written with.
+const MAX_ANGLE_DIGITS: usize = 3;
+/// Largest apex angle, in degrees.
+const MAX_ANGLE: u16 = 180;
+/// Default digits after the decimal point of the mantissa of an area ratio.
+const AREA_RATIO_DIGITS: usize = 6;
+/// Most digits after the decimal point of the mantissa of an area ratio
+/// that an f64 holds.
+const MAX_AREA_RATIO_DIGITS: usize = 16;
+/// Hexadecimal digits of an apex angle of an exact hash (the 64 bits of an f64).
+const EXACT_DIGITS: usize = 16;
+
+/// The formats a hash is made in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum HashFormat {
+    /// Each coordinate is the apex angle of the triangle it makes with
+    /// the x axis, and its lean direction. Angles are rounded to whole degrees.
+    #[default]
+    ApexAngle,
+    /// Each coordinate is the pair of area ratios of the similar triangles
+    /// it makes with lines parallel to the axes (see area_ratio).
+    /// The coordinates can be decoded from the hash.
+    AreaRatio,
+    /// As ApexAngle, but with apex angles in radians at full precision,
+    /// so the formatted hash items can be decoded from the hash exactly.
+    Exact,
+}
+
+impl HashFormat {
+    /// Gets the name of the format: "apex-angle", "area-ratio" or "exact".
+    pub fn name(&self) -> &'static str {
+        match self {
+            HashFormat::ApexAngle => "apex-angle",
+            HashFormat::AreaRatio => "area-ratio",
+            HashFormat::Exact => "exact",
+        }
+    }
+
+    /// Gets the format of a name (see name).
+    pub fn from_name(name: &str) -> Option<HashFormat> {
+        match name {
+            "apex-angle" => Some(HashFormat::ApexAngle),
+            "area-ratio" => Some(HashFormat::AreaRatio),
+            "exact" => Some(HashFormat::Exact),
+            _ => None,
+        }
+    }
+
+    /// Gets the tag of the format in hash spec prefixes.
+    pub fn tag(&self) -> &'static str {
+        match self {
+            HashFormat::ApexAngle => APEX_ANGLE_TAG,
+            HashFormat::AreaRatio => AREA_RATIO_TAG,
+            HashFormat::Exact => EXACT_TAG,
+        }
+    }
+
+    /// Gets the format of a tag (see tag).
+    pub fn from_tag(tag: &str) -> Option<HashFormat> {
+        match tag {
+            APEX_ANGLE_TAG => Some(HashFormat::ApexAngle),
+            AREA_RATIO_TAG => Some(HashFormat::AreaRatio),
+            EXACT_TAG => Some(HashFormat::Exact),
+            _ => None,
+        }
+    }
+}
+
+/// The geometry of a hash: its format, how many records it has and how
+/// they are written. Every hash starts with the prefix of its spec,
+/// so hashes made with different specs are never confused.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct HashSpec {
+    /// The format of the hash.
+    pub format: HashFormat,
+    /// Number of records. Hashes with more hash items are truncated,
+    /// those with fewer are padded.
+    pub records: usize,
+    /// Digits of each number:
+    ///
+    /// 1. apex angle: fewest digits an apex angle is written with (1 to 3),
+    /// 2. area ratio: digits after the decimal point of the mantissa (0 to 16),
+    /// 3. exact: 16, the hexadecimal digits of an f64.
+    pub digits: usize,
+    /// Character that replaces every digit group of a padding record.
+    pub padding: char,
+    /// Separates the records of the hash.
+    pub record_separator: char,
+    /// Separates the elements of a record.
+    pub element_separator: char,
+}
+
+impl HashSpec {
+    /// Gets the default spec of a format.
+    pub fn new(format: HashFormat) -> HashSpec {
+        let (digits, record_separator, element_separator) = match format {
+            HashFormat::ApexAngle => (ANGLE_DIGITS, RECORD_SEPARATOR, ELEMENT_SEPARATOR),
+            HashFormat::AreaRatio => (
+                AREA_RATIO_DIGITS,
+                AREA_RATIO_RECORD_SEPARATOR,
+                AREA_RATIO_ELEMENT_SEPARATOR,
+            ),
+            HashFormat::Exact => (EXACT_DIGITS, RECORD_SEPARATOR, ELEMENT_SEPARATOR),
+        };
+
+        HashSpec {
+            format,
+            records: HASH_RECORDS,
+            digits,
+            padding: PADDING,
+            record_separator,
+            element_separator,
+        }
+    }
+
+    /// Checks that hashes made with the spec can be parsed:
+    ///
+    /// 1. there is at least one record,
+    /// 2. digits is in the range of the format (see digits),
+    /// 3. the padding and separators differ from each other, and are not letters,
+    ///    digits, white space or SPEC_SEPARATOR. Area ratio hashes can't use
+    ///    "-", "+" or "." either, as they are in the numbers.
+    pub fn validate(&self) -> Result<(), OkeuvoError> {
+        if self.records == 0 {
+            return Err(invalid_hash_spec(
+                "a hash needs at least one record".to_string(),
+            ));
+        }
+
+        let (min_digits, max_digits) = match self.format {
+            HashFormat::ApexAngle => (1, MAX_ANGLE_DIGITS),
+            HashFormat::AreaRatio => (0, MAX_AREA_RATIO_DIGITS),
+            HashFormat::Exact => (EXACT_DIGITS, EXACT_DIGITS),
+        };
+        if self.digits < min_digits || self.digits > max_digits {
+            let range: String = if min_digits == max_digits {
+                min_digits.to_string()
+            } else {
+                format!("{} to {}", min_digits, max_digits)
+            };
+
+            return Err(invalid_hash_spec(format!(
+                "{} hashes need {} digits, not {}",
+                self.format.name(),
+                range,
+                self.digits
+            )));
+        }
+
+        let marks: [char; 3] = [self.padding, self.record_separator, self.element_separator];
+        if marks[0] == marks[1] || marks[0] == marks[2] || marks[1] == marks[2] {
+            return Err(invalid_hash_spec(
+                "the padding and separators must all differ".to_string(),
+            ));
+        }
+
+        for mark in &marks {
+            let is_in_numbers: bool =
+                self.format == HashFormat::AreaRatio && matches!(mark, '-' | '+' | '.');
+
+            if mark.is_alphanumeric()
+                || mark.is_whitespace()
+                || *mark == SPEC_SEPARATOR
+                || is_in_numbers
+            {
+                return Err(invalid_hash_spec(format!(
+                    "'{}' can't be the padding or a separator of {} hashes",
+                    mark,
+                    self.format.name()
+                )));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Gets the prefix of hashes made with the spec:
+    /// format tag, HASH_SPEC_VERSION, "r" and the number of records, "d" and
+    /// the digits, then the padding, record separator and element separator,
+    /// ending with SPEC_SEPARATOR. The default apex angle spec is "aa1r20d2#-!:".
+    pub fn prefix(&self) -> String {
+        format!(
+            "{}{}r{}d{}{}{}{}{}",
+            self.format.tag(),
+            HASH_SPEC_VERSION,
+            self.records,
+            self.digits,
+            self.padding,
+            self.record_separator,
+            self.element_separator,
+            SPEC_SEPARATOR
+        )
+    }
+
+    /// Parses the prefix of a hash (see prefix), without SPEC_SEPARATOR.
+    fn parse_prefix(prefix: &str) -> Result<HashSpec, String> {
+        let malformed = || format!("spec prefix '{}' is malformed", prefix);
+
+        let format: HashFormat = prefix
+            .get(..2)
+            .and_then(HashFormat::from_tag)
+            .ok_or_else(|| format!("spec prefix '{}' has no known format tag", prefix))?;
+
+        let rest: &str = &prefix[2..];
+        let (version, rest) = split_number(rest).ok_or_else(malformed)?;
+        if version != HASH_SPEC_VERSION as usize {
+            return Err(format!(
+                "hash spec version {} is not supported (latest {})",
+                version, HASH_SPEC_VERSION
+            ));
+        }
+
+        let (records, rest) = rest
+            .strip_prefix('r')
+            .and_then(split_number)
+            .ok_or_else(malformed)?;
+        let (digits, rest) = rest
+            .strip_prefix('d')
+            .and_then(split_number)
+            .ok_or_else(malformed)?;
+
+        let marks: Vec<char> = rest.chars().collect();
+        if marks.len() != 3 {
+            return Err(malformed());
+        }
+
+        let spec = HashSpec {
+            format,
+            records,
+            digits,
+            padding: marks[0],
+            record_separator: marks[1],
+            element_separator: marks[2],
+        };
+
+        spec.validate().map_err(|e| match e {
+            OkeuvoError::InvalidHashSpec { message } => message,
+            e => e.to_string(),
+        })?;
+
+        // Numbers with leading zeros.
+        if format!("{}{}", prefix, SPEC_SEPARATOR) != spec.prefix() {
+            return Err(malformed());
+        }
+
+        Ok(spec)
+    }
+
+    /// Gets the padding record.
+    fn padding_record(&self) -> String {
+        let width: usize = match self.format {
+            HashFormat::AreaRatio => 1,
+            HashFormat::ApexAngle | HashFormat::Exact => self.digits,
+        };
+        let group: String = self.padding.to_string().repeat(width);
+
+        [
+            self.padding.to_string(),
+            group.clone(),
+            self.padding.to_string(),
+            group,
+        ]
+        .join(&self.element_separator.to_string())
+    }
+}
+
+impl Default for HashSpec {
+    fn default() -> HashSpec {
+        HashSpec::new(HashFormat::ApexAngle)
+    }
+}
+
+/// The direction in which an apex angle falls
+/// relative to the full span of the x axis.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Lean {
+    /// Left leaning (toward origin), written as 0.
+    Left,
+    /// No lean, written as 1.
+    Upright,
+    /// Right leaning (away from origin), written as 2.
+    Right,
+}
+
+impl Lean {
+    /// Gets the lean for its digit in a hash.
+    pub fn from_digit(digit: &i32) -> Option<Lean> {
+        match digit {
+            0 => Some(Lean::Left),
+            1 => Some(Lean::Upright),
+            2 => Some(Lean::Right),
+            _ => None,
+        }
+    }
+
+    /// Gets the digit of the lean in a hash.
+    pub fn digit(&self) -> i32 {
+        match self {
+            Lean::Left => 0,
+            Lean::Upright => 1,
+            Lean::Right => 2,
+        }
+    }
+}
+
+/// A record of a hash.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum HashRecord {
+    /// A hash item of an apex angle hash.
+    ApexAngle {
+        /// Lean direction of apex_angle.
+        lean: Lean,
+        /// Apex angle of the unmodified coordinate, in whole degrees.
+        apex_angle: u16,
+        /// Lean direction of excited_apex_angle.
+        excited_lean: Lean,
+        /// Apex angle of the excited coordinate, in whole degrees.
+        excited_apex_angle: u16,
+    },
+    /// A hash item of an area ratio hash.
+    AreaRatio {
+        /// Area ratio of the x of the unmodified coordinate.
+        x_ratio: f64,
+        /// Area ratio of the y of the unmodified coordinate.
+        y_ratio: f64,
+        /// Area ratio of the x of the excited coordinate.
+        excited_x_ratio: f64,
+        /// Area ratio of the y of the excited coordinate.
+        excited_y_ratio: f64,
+    },
+    /// A hash item of an exact hash.
+    Exact {
+        /// Lean direction of apex_angle.
+        lean: Lean,
+        /// Apex angle of the unmodified coordinate, in radians.
+        apex_angle: f64,
+        /// Lean direction of excited_apex_angle.
+        excited_lean: Lean,
+        /// Apex angle of the excited coordinate, in radians.
+        excited_apex_angle: f64,
+    },
+    /// A padding record standing for no hash item.
+    Padding,
+}
+
+impl HashRecord {
+    /// Tests if the record is padding.
+    pub fn is_padding(&self) -> bool {
+        matches!(self, HashRecord::Padding)
+    }
+}
+
+/// A hash parsed into its versions, spec and records.
+/// Displaying a ParsedHash gives back the hash it was parsed from.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParsedHash {
+    /// The library and meaning grid versions the hash was made with.
+    pub versions: Versions,
+    /// The spec the hash was made with.
+    pub spec: HashSpec,
+    /// The records of the hash, the discourse hypernym first,
+    /// then any padding.
+    pub records: Vec<HashRecord>,
+}
+
+impl ParsedHash {
+    /// Parses a hash (see get_hash), in any format.
+    ///
+    /// The versions and spec are read from the start of the hash. The hash must have as many
+    /// records as the spec gives, each of which is either a hash item or padding,
+    /// with padding only after the last hash item.
+    ///
+    /// In the apex angle format, lean directions are single digits from 0 to 2
+    /// and apex angles are whole degrees from 0 to 180, written with at least
+    /// the digits of the spec and no other leading zeros.
+    /// In the area ratio format, area ratios are written in scientific notation
+    /// with the digits of the spec after the decimal point.
+    /// In the exact format, apex angles are 16 lowercase hexadecimal digits.
+    pub fn parse(hash: &str) -> Result<ParsedHash, OkeuvoError> {
+        let (tag, rest) = hash
+            .split_once(SPEC_SEPARATOR)
+            .ok_or_else(|| invalid_hash(hash, "the hash has no versions tag".to_string()))?;
+        let versions: Versions = Versions::from_tag(tag).map_err(|e| match e {
+            OkeuvoError::InvalidVersion { message } => invalid_hash(hash, message),
+            e => e,
+        })?;
+
+        let (prefix, body) = rest
+            .split_once(SPEC_SEPARATOR)
+            .ok_or_else(|| invalid_hash(hash, "the hash has no spec prefix".to_string()))?;
+        let spec: HashSpec =
+            HashSpec::parse_prefix(prefix).map_err(|message| invalid_hash(hash, message))?;
+
+        let parts: Vec<&str> = body.split(spec.record_separator).collect();
+        if parts.len() != spec.records {
+            return Err(invalid_hash(
+                hash,
+                format!("expected {} records, found {}", spec.records, parts.len()),
+            ));
+        }
+
+        let mut records: Vec<HashRecord> = Vec::new();
+
+        for (i, part) in parts.iter().enumerate() {
+            let record: HashRecord = parse_record(part, &spec)
+                .map_err(|message| invalid_hash(part, format!("record {}: {}", i + 1, message)))?;
+
+            if !record.is_padding() && records.last().is_some_and(HashRecord::is_padding) {
+                return Err(invalid_hash(
+                    part,
+                    format!("record {}: a hash item follows padding", i + 1),
+                ));
+            }
+
+            records.push(record);
+        }
+
+        Ok(ParsedHash {
+            versions,
+            spec,
+            records,
+        })
+    }
+
+    /// Gets the record of the discourse hypernym, if the hash has any hash items.
+    pub fn hypernym(&self) -> Option<&HashRecord> {
+        self.records.first().filter(|record| !record.is_padding())
+    }
+
+    /// Gets the records that are hash items, the discourse hypernym first.
+    pub fn items(&self) -> Vec<&HashRecord> {
+        self.records
+            .iter()
+            .filter(|record| !record.is_padding())
+            .collect()
+    }
+
+    /// Decodes the coordinates of the hash items of an area ratio hash,
+    /// the discourse hypernym first.
+    /// The coordinates are within AREA_RATIO_TOLERANCE of those of the hash items.
+    /// The parameter, max_xy is the (max x, max y) of the meaning grid the
+    /// hash was made with (see MeaningGrid::max_xy).
+    ///
+    /// Return format: Vec<(coordinate, excited coordinate)>.
+    pub fn coordinates(&self, max_xy: &(f64, f64)) -> Result<Vec<(Point2D, Point2D)>, OkeuvoError> {
+        let mut result: Vec<(Point2D, Point2D)> = Vec::new();
+
+        for record in &self.records {
+            match record {
+                HashRecord::AreaRatio {
+                    x_ratio,
+                    y_ratio,
+                    excited_x_ratio,
+                    excited_y_ratio,
+                } => result.push((
+                    Point2D {
+                        x: area_ratio_coordinate(x_ratio, &max_xy.0),
+                        y: area_ratio_coordinate(y_ratio, &max_xy.1),
+                    },
+                    Point2D {
+                        x: area_ratio_coordinate(excited_x_ratio, &max_xy.0),
+                        y: area_ratio_coordinate(excited_y_ratio, &max_xy.1),
+                    },
+                )),
+                HashRecord::ApexAngle { .. } | HashRecord::Exact { .. } => {
+                    return Err(invalid_hash(
+                        &self.to_string(),
+                        "only area ratio hashes can be decoded to coordinates".to_string(),
+                    ))
+                }
+                HashRecord::Padding => {}
+            }
+        }
+
+        Ok(result)
+    }
+
+    /// Makes the exact hash of formatted hash items, the discourse hypernym
+    /// first, with the default exact spec and the versions they were made with.
+    pub fn exact(formatted_hash_items: &[HashItemFormatted], versions: &Versions) -> ParsedHash {
+        exact_records(
+            formatted_hash_items,
+            &HashSpec::new(HashFormat::Exact),
+            versions,
+        )
+    }
+
+    /// Decodes the formatted hash items of an exact hash, the discourse
+    /// hypernym first. They are the same, bit for bit, as those it was made from.
+    pub fn formatted_items(&self) -> Result<Vec<HashItemFormatted>, OkeuvoError> {
+        let mut result: Vec<HashItemFormatted> = Vec::new();
+
+        for record in &self.records {
+            match record {
+                HashRecord::Exact {
+                    lean,
+                    apex_angle,
+                    excited_lean,
+                    excited_apex_angle,
+                } => result.push(HashItemFormatted {
+                    right_leaning: lean.digit(),
+                    upper_angle: *apex_angle,
+                    right_leaning_excited: excited_lean.digit(),
+                    upper_angle_excited: *excited_apex_angle,
+                }),
+                HashRecord::Padding => {}
+                _ => {
+                    return Err(invalid_hash(
+                        &self.to_string(),
+                        "only exact hashes can be decoded to formatted hash items".to_string(),
+                    ))
+                }
+            }
+        }
+
+        Ok(result)
+    }
+
+    /// Measures how similar this hash is to another (see compare_hashes).
+    /// Hashes of different versions or formats can't be compared.
+    pub fn similarity(&self, other: &ParsedHash) -> Result<HashSimilarity, OkeuvoError> {
+        self.versions.check_comparable(&other.versions)?;
+
+        if self.spec.format != other.spec.format {
+            return Err(OkeuvoError::IncomparableHashes {
+                message: format!(
+                    "one is in the {} format, the other in the {} format",
+                    self.spec.format.name(),
+                    other.spec.format.name()
+                ),
+            });
+        }
+
+        Ok(compare_records(
+            &hash_comparable_records(self),
+            &hash_comparable_records(other),
+        ))
+    }
+}
+
+impl FromStr for ParsedHash {
+    type Err = OkeuvoError;
+
+    fn from_str(hash: &str) -> Result<ParsedHash, OkeuvoError> {
+        ParsedHash::parse(hash)
+    }
+}
+
+impl fmt::Display for ParsedHash {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let spec: &HashSpec = &self.spec;
+
+        write!(
+            f,
+            "{}{}{}",
+            self.versions.tag(),
+            SPEC_SEPARATOR,
+            spec.prefix()
+        )?;
+
+        for (i, record) in self.records.iter().enumerate() {
+            if i > 0 {
+                write!(f, "{}", spec.record_separator)?;
+            }
+
+            match record {
+                HashRecord::ApexAngle {
+                    lean,
+                    apex_angle,
+                    excited_lean,
+                    excited_apex_angle,
+                } => write!(
+                    f,
+                    "{}{sep}{:0width$}{sep}{}{sep}{:0width$}",
+                    lean.digit(),
+                    apex_angle,
+                    excited_lean.digit(),
+                    excited_apex_angle,
+                    sep = spec.element_separator,
+                    width = spec.digits
+                )?,
+                HashRecord::AreaRatio {
+                    x_ratio,
+                    y_ratio,
+                    excited_x_ratio,
+                    excited_y_ratio,
+                } => write!(
+                    f,
+                    "{:.digits$e}{sep}{:.digits$e}{sep}{:.digits$e}{sep}{:.digits$e}",
+                    x_ratio,
+                    y_ratio,
+                    excited_x_ratio,
+                    excited_y_ratio,
+                    sep = spec.element_separator,
+                    digits = spec.digits
+                )?,
+                HashRecord::Exact {
+                    lean,
+                    apex_angle,
+                    excited_lean,
+                    excited_apex_angle,
+                } => write!(
+                    f,
+                    "{}{sep}{:0width$x}{sep}{}{sep}{:0width$x}",
+                    lean.digit(),
+                    apex_angle.to_bits(),
+                    excited_lean.digit(),
+                    excited_apex_angle.to_bits(),
+                    sep = spec.element_separator,
+                    width = EXACT_DIGITS
+                )?,
+                HashRecord::Padding => write!(f, "{}", spec.padding_record())?,
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Does the final formatting of apex angle hashes.
+/// Rounds apex angles (in radians) to whole degrees.
+/// Truncates the hash to the records of the spec, padding it
+/// where there are fewer hash items.
+pub(crate) fn round_pad_records(
+    formatted_hash_items: &[HashItemFormatted],
+    spec: &HashSpec,
+    versions: &Versions,
+) -> ParsedHash {
+    let mut records: Vec<HashRecord> = formatted_hash_items
+        .iter()
+        .take(spec.records)
+        .map(|item| HashRecord::ApexAngle {
+            lean: Lean::from_digit(&item.right_leaning).unwrap_or(Lean::Upright),
+            apex_angle: round_angle(&item.upper_angle),
+            excited_lean: Lean::from_digit(&item.right_leaning_excited).unwrap_or(Lean::Upright),
+            excited_apex_angle: round_angle(&item.upper_angle_excited),
+        })
+        .collect();
+
+    records.resize(spec.records, HashRecord::Padding);
+
+    ParsedHash {
+        versions: versions.clone(),
+        spec: spec.clone(),
+        records,
+    }
+}
+
+/// Formats hash items, the discourse hypernym first, as an area ratio hash.
+/// Area ratios are rounded to the digits they are written with.
+/// Truncates the hash to the records of the spec, padding it
+/// where there are fewer hash items.
+/// The parameter, max_xy is the (max x, max y) of the meaning grid.
+pub(crate) fn area_ratio_records(
+    hash_item_vec: &[HashItem],
+    max_xy: &(f64, f64),
+    spec: &HashSpec,
+    versions: &Versions,
+) -> ParsedHash {
+    let mut records: Vec<HashRecord> = hash_item_vec
+        .iter()
+        .take(spec.records)
+        .map(|item| {
+            let point: Point2D = polar_to_cartesian(&item.radius, &item.angle);
+            let excited_point: Point2D =
+                polar_to_cartesian(&item.excited_radius, &item.excited_angle);
+
+            HashRecord::AreaRatio {
+                x_ratio: round_ratio(area_ratio(&point.x, &max_xy.0), &spec.digits),
+                y_ratio: round_ratio(area_ratio(&point.y, &max_xy.1), &spec.digits),
+                excited_x_ratio: round_ratio(area_ratio(&excited_point.x, &max_xy.0), &spec.digits),
+                excited_y_ratio: round_ratio(area_ratio(&excited_point.y, &max_xy.1), &spec.digits),
+            }
+        })
+        .collect();
+
+    records.resize(spec.records, HashRecord::Padding);
+
+    ParsedHash {
+        versions: versions.clone(),
+        spec: spec.clone(),
+        records,
+    }
+}
+
+/// Formats hash items, the discourse hypernym first, as an exact hash.
+/// Truncates the hash to the records of the spec, padding it
+/// where there are fewer hash items.
+pub(crate) fn exact_records(
+    formatted_hash_items: &[HashItemFormatted],
+    spec: &HashSpec,
+    versions: &Versions,
+) -> ParsedHash {
+    let mut records: Vec<HashRecord> = formatted_hash_items
+        .iter()
+        .take(spec.records)
+        .map(|item| HashRecord::Exact {
+            lean: Lean::from_digit(&item.right_leaning).unwrap_or(Lean::Upright),
+            apex_angle: item.upper_angle,
+            excited_lean: Lean::from_digit(&item.right_leaning_excited).unwrap_or(Lean::Upright),
+            excited_apex_angle: item.upper_angle_excited,
+        })
+        .collect();
+
+    records.resize(spec.records, HashRecord::Padding);
+
+    ParsedHash {
+        versions: versions.clone(),
+        spec: spec.clone(),
+        records,
+    }
+}
+
+/// Rounds an angle in radians to whole degrees from 0 to 180.
+/// An angle that can't be computed (NaN) is 0.
+fn round_angle(angle: &f64) -> u16 {
+    if angle.is_nan() {
+        return 0;
+    }
+
+    angle.to_degrees().round().clamp(0.0, MAX_ANGLE as f64) as u16
+}
+
+/// Rounds an area ratio to the digits it is written with in a hash.
+fn round_ratio(ratio: f64, digits: &usize) -> f64 {
+    format!("{:.digits$e}", ratio, digits = digits)
+        .parse()
+        .unwrap_or(ratio)
+}
+
+/// Splits the whole number at the start of text from the rest.
+fn split_number(text: &str) -> Option<(usize, &str)> {
+    let end: usize = text
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(text.len());
+    let number: usize = text[..end].parse().ok()?;
+
+    Some((number, &text[end..]))
+}
+
+/// Parses a record of a hash.
+fn parse_record(record: &str, spec: &HashSpec) -> Result<HashRecord, String> {
+    if record == spec.padding_record() {
+        return Ok(HashRecord::Padding);
+    }
+
+    let elements: Vec<&str> = record.split(spec.element_separator).collect();
+    if elements.len() != 4 {
+        return Err(format!("expected 4 elements, found {}", elements.len()));
+    }
+
+    match spec.format {
+        HashFormat::ApexAngle => Ok(HashRecord::ApexAngle {
+            lean: parse_lean(elements[0])?,
+            apex_angle: parse_angle(elements[1], &spec.digits)?,
+            excited_lean: parse_lean(elements[2])?,
+            excited_apex_angle: parse_angle(elements[3], &spec.digits)?,
+        }),
+        HashFormat::AreaRatio => Ok(HashRecord::AreaRatio {
+            x_ratio: parse_ratio(elements[0], &spec.digits)?,
+            y_ratio: parse_ratio(elements[1], &spec.digits)?,
+            excited_x_ratio: parse_ratio(elements[2], &spec.digits)?,
+            excited_y_ratio: parse_ratio(elements[3], &spec.digits)?,
+        }),
+        HashFormat::Exact => Ok(HashRecord::Exact {
+            lean: parse_lean(elements[0])?,
+            apex_angle: parse_exact_angle(elements[1])?,
+            excited_lean: parse_lean(elements[2])?,
+            excited_apex_angle: parse_exact_angle(elements[3])?,
+        }),
+    }
+}
+
+/// Parses the lean direction element of a record.
+fn parse_lean(element: &str) -> Result<Lean, String> {
+    let digit: Option<i32> = match element.as_bytes() {
+        [digit] if digit.is_ascii_digit() => Some((digit - b'0') as i32),
+        _ => None,
+    };
+
+    digit
+        .and_then(|digit| Lean::from_digit(&digit))
+        .ok_or_else(|| format!("lean direction '{}' is not 0, 1 or 2", element))
+}
+
+/// Parses the apex angle element of a record.
+fn parse_angle(element: &str, digits: &usize) -> Result<u16, String> {
+    if element.len() < *digits || !element.bytes().all(|byte| byte.is_ascii_digit()) {
+        return Err(format!(
+            "apex angle '{}' is not a number of at least {} digits",
+            element, digits
+        ));
+    }
+
+    match element.parse::<u16>() {
+        Ok(angle)
+            if angle <= MAX_ANGLE
+                && format!("{:0width$}", angle, width = digits) == element =>
+        {
+            Ok(angle)
+        }
+        _ => Err(format!(
+            "apex angle '{}' is not a whole number of degrees from 0 to {}, written with {} digits or more and no other leading zeros",
+            element, MAX_ANGLE, digits
+        )),
+    }
+}
+
+/// Parses the area ratio element of a record.
+fn parse_ratio(element: &str, digits: &usize) -> Result<f64, String> {
+    match element.parse::<f64>() {
+        Ok(ratio)
+            if !ratio.is_nan() && format!("{:.digits$e}", ratio, digits = digits) == element =>
+        {
+            Ok(ratio)
+        }
+        _ => Err(format!(
+            "area ratio '{}' is not a number in scientific notation with {} digits after the decimal point",
+            element, digits
+        )),
+    }
+}
+
+/// Parses the apex angle element of a record of an exact hash.
+fn parse_exact_angle(element: &str) -> Result<f64, String> {
+    let is_hexadecimal: bool = element
+        .bytes()
+        .all(|byte| byte.is_ascii_digit() || (b'a'..=b'f').contains(&byte));
+
+    match u64::from_str_radix(element, 16) {
+        Ok(bits) if element.len() == EXACT_DIGITS && is_hexadecimal => Ok(f64::from_bits(bits)),
+        _ => Err(format!(
+            "apex angle '{}' is not {} lowercase hexadecimal digits",
+            element, EXACT_DIGITS
+        )),
+    }
+}
+
+/// Makes the error for a malformed hash.
+fn invalid_hash(hash: &str, message: String) -> OkeuvoError {
+    OkeuvoError::InvalidHash {
+        hash: hash.to_string(),
+        message,
+    }
+}
+
+/// Makes the error for a spec that hashes can't be made with.
+fn invalid_hash_spec(message: String) -> OkeuvoError {
+    OkeuvoError::InvalidHashSpec { message }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{hash_item, versions};
+
+    /// Parses a hash and checks that displaying it gives back the same string.
+    fn round_trip(hash: &str) -> ParsedHash {
+        let parsed: ParsedHash = ParsedHash::parse(hash).unwrap();
+        assert_eq!(parsed.to_string(), hash);
+
+        parsed
+    }
+
+    /// Gets the message of the error for a hash that fails to parse.
+    fn parse_error(hash: &str) -> String {
+        match ParsedHash::parse(hash) {
+            Err(OkeuvoError::InvalidHash { message, .. }) => message,
+            other => panic!("expected InvalidHash for '{}', got {:?}", hash, other),
+        }
+    }
+
+    #[test]
+    fn apex_angle_hashes_round_trip_with_padding() {
+        let parsed =
+            round_trip("v19.07.23/19.07.23:aa1r4d2#-!:1!09!0!36-2!180!1!00-#!##!#!##-#!##!#!##");
+
+        assert_eq!(parsed.versions, versions());
+        assert_eq!(
+            parsed.spec,
+            HashSpec {
+                records: 4,
+                ..HashSpec::default()
+            }
+        );
+        assert_eq!(parsed.items().len(), 2);
+        assert_eq!(
+            parsed.hypernym(),
+            Some(&HashRecord::ApexAngle {
+                lean: Lean::Upright,
+                apex_angle: 9,
+                excited_lean: Lean::Left,
+                excited_apex_angle: 36,
+            })
+        );
+        assert!(parsed.records[2].is_padding() && parsed.records[3].is_padding());
+    }
+
+    #[test]
+    fn hashes_of_other_specs_round_trip() {
+        round_trip("v19.07.23/19.07.23:aa1r2d3#-!:1!009!0!180-#!###!#!###");
+        round_trip("v19.07.23/19.07.23:aa1r2d1*/~:0~5~2~123/*~*~*~*");
+        round_trip("v19.07.23/19.07.23:aa1r1d2#-!:#!##!#!##");
+        round_trip("v19.07.23/19.07.23:ar1r2d3#;,:2.500e-1,-1.111e0,0.000e0,1.000e0;#,#,#,#");
+        round_trip(
+            "v19.07.23/19.07.23:ex1r2d16#-!:2!3fe022d6fab7635c!2!3fbccdb93a777c60-#!################!#!################",
+        );
+    }
+
+    #[test]
+    fn refuses_the_wrong_number_of_records() {
+        assert_eq!(
+            parse_error("v19.07.23/19.07.23:aa1r3d2#-!:1!09!0!36-#!##!#!##"),
+            "expected 3 records, found 2"
+        );
+        assert_eq!(
+            parse_error("v19.07.23/19.07.23:aa1r1d2#-!:1!09!0!36-#!##!#!##"),
+            "expected 1 records, found 2"
+        );
+    }
+
+    #[test]
+    fn refuses_elements_of_the_wrong_width() {
+        for record in &[
+            "1!9!0!36",    // fewer digits than the spec
+            "1!009!0!36",  // a leading zero beyond the digits of the spec
+            "1!181!0!36",  // more than 180 degrees
+            "1!09!0",      // too few elements
+            "1!09!0!36!1", // too many elements
+            "3!09!0!36",   // lean direction out of range
+            "10!09!0!36",  // lean direction of two digits
+            "1!+9!0!36",   // a sign
+            "#!#!#!#",     // padding of the wrong width
+            "#!##!#!###",  // padding of the wrong width
+        ] {
+            let hash: String = format!("v19.07.23/19.07.23:aa1r2d2#-!:{}-#!##!#!##", record);
+            assert!(
+                parse_error(&hash).starts_with("record 1: "),
+                "{} was not refused",
+                record
+            );
+        }
+
+        assert!(parse_error(
+            "v19.07.23/19.07.23:ex1r1d16#-!:2!3FE022D6FAB7635C!2!3fbccdb93a777c60"
+        )
+        .starts_with("record 1: "));
+        assert!(
+            parse_error("v19.07.23/19.07.23:ar1r1d3#;,:2.5e-1,0.000e0,0.000e0,1.000e0")
+                .starts_with("record 1: ")
+        );
+    }
+
+    #[test]
+    fn refuses_hash_items_after_padding() {
+        assert_eq!(
+            parse_error("v19.07.23/19.07.23:aa1r2d2#-!:#!##!#!##-1!09!0!36"),
+            "record 2: a hash item follows padding"
+        );
+    }
+
+    #[test]
+    fn refuses_malformed_tags_and_prefixes() {
+        // Hashes made before they were tagged with versions.
+        assert_eq!(
+            parse_error("1!09!0!36-#!##!#!##"),
+            "the hash has no versions tag"
+        );
+        assert_eq!(
+            parse_error("v19.07.23/19.07.23:1!09!0!36"),
+            "the hash has no spec prefix"
+        );
+        assert!(parse_error("v19.07.23/19.07.23:aa2r1d2#-!:1!09!0!36")
+            .starts_with("hash spec version 2"));
+        assert!(parse_error("v19.07.23/19.07.23:aa1r01d2#-!:1!09!0!36").ends_with("is malformed"));
+        assert!(parse_error("v19.07.23/19.07.23:aa1r1d2##!:1!09!0!36").contains("must all differ"));
+        assert!(parse_error("19.07.23/19.07.23:aa1r1d2#-!:1!09!0!36").contains("versions tag"));
+    }
+
+    #[test]
+    fn rounds_pads_and_truncates_formatted_hash_items() {
+        let item = |angle: f64, excited_angle: f64| HashItemFormatted {
+            right_leaning: 2,
+            upper_angle: angle,
+            right_leaning_excited: 0,
+            upper_angle_excited: excited_angle,
+        };
+        let spec: HashSpec = HashSpec {
+            records: 3,
+            ..HashSpec::default()
+        };
+
+        let parsed: ParsedHash = round_pad_records(
+            &[item(std::f64::consts::FRAC_PI_2, 0.1), item(f64::NAN, 4.0)],
+            &spec,
+            &versions(),
+        );
+        assert_eq!(
+            parsed.to_string(),
+            "v19.07.23/19.07.23:aa1r3d2#-!:2!90!0!06-2!00!0!180-#!##!#!##"
+        );
+
+        let items: Vec<HashItemFormatted> = (0..5).map(|_| item(0.5, 0.5)).collect();
+        let parsed: ParsedHash = round_pad_records(&items, &spec, &versions());
+        assert_eq!(parsed.records.len(), 3);
+        assert!(parsed.records.iter().all(|record| !record.is_padding()));
+        round_trip(&parsed.to_string());
+    }
+
+    /// Gets the largest error, as a fraction of max_xy, of the coordinates decoded
+    /// from an area ratio hash of points along the diagonal from -span to +span
+    /// times max_xy.
+    fn largest_area_ratio_error(digits: usize, span: f64) -> f64 {
+        let max_xy: (f64, f64) = (7.5, 5.0);
+        let spec = HashSpec {
+            records: 401,
+            digits,
+            ..HashSpec::new(HashFormat::AreaRatio)
+        };
+        let points: Vec<(f64, f64)> = (-200..=200)
+            .map(|i| {
+                let fraction: f64 = i as f64 * span / 200.0;
+                (fraction * max_xy.0, -fraction * max_xy.1)
+            })
+            .collect();
+        let hash_items: Vec<HashItem> = points
+            .iter()
+            .map(|point| hash_item(*point, (-point.0, -point.1)))
+            .collect();
+
+        let hash: String = area_ratio_records(&hash_items, &max_xy, &spec, &versions()).to_string();
+        let coordinates = round_trip(&hash).coordinates(&max_xy).unwrap();
+        assert_eq!(coordinates.len(), points.len());
+
+        let mut result: f64 = 0.0;
+        for (point, (decoded, excited_decoded)) in points.iter().zip(&coordinates) {
+            for error in &[
+                (decoded.x - point.0) / max_xy.0,
+                (decoded.y - point.1) / max_xy.1,
+                (excited_decoded.x + point.0) / max_xy.0,
+                (excited_decoded.y + point.1) / max_xy.1,
+            ] {
+                result = result.max(error.abs());
+            }
+        }
+
+        result
+    }
+
+    #[test]
+    fn area_ratio_hashes_decode_within_the_tolerance() {
+        assert!(largest_area_ratio_error(AREA_RATIO_DIGITS, 1.0) <= AREA_RATIO_TOLERANCE);
+        assert!(largest_area_ratio_error(AREA_RATIO_DIGITS, 4.0) <= AREA_RATIO_TOLERANCE);
+    }
+
+    #[test]
+    fn each_digit_fewer_makes_the_tolerance_ten_times_larger() {
+        for digits in 0..AREA_RATIO_DIGITS {
+            let tolerance: f64 =
+                AREA_RATIO_TOLERANCE * 10f64.powi((AREA_RATIO_DIGITS - digits) as i32);
+
+            assert!(
+                largest_area_ratio_error(digits, 4.0) <= tolerance,
+                "{} digits",
+                digits
+            );
+        }
+        // The bound is tight: the error of the default digits is not far below it.
+        assert!(largest_area_ratio_error(AREA_RATIO_DIGITS, 4.0) > AREA_RATIO_TOLERANCE / 10.0);
+    }
+
+    #[test]
+    fn coordinates_at_the_max_decode_exactly() {
+        let max_xy: (f64, f64) = (7.5, 5.0);
+        let spec = HashSpec {
+            records: 1,
+            ..HashSpec::new(HashFormat::AreaRatio)
+        };
+
+        // On the x axis, at +max and -max.
+        let hash: String = area_ratio_records(
+            &[hash_item((7.5, 0.0), (-7.5, 0.0))],
+            &max_xy,
+            &spec,
+            &versions(),
+        )
+        .to_string();
+        assert!(hash.ends_with(":0.000000e0,1.000000e0,inf,1.000000e0"));
+
+        let parsed: ParsedHash =
+            round_trip("v19.07.23/19.07.23:ar1r2d6#;,:0.000000e0,inf,inf,0.000000e0;#,#,#,#");
+        let coordinates = parsed.coordinates(&max_xy).unwrap();
+
+        assert_eq!(coordinates.len(), 1);
+        let (point, excited_point) = &coordinates[0];
+        assert_eq!((point.x, point.y), (7.5, -5.0));
+        assert_eq!((excited_point.x, excited_point.y), (-7.5, 5.0));
+    }
+
+    #[test]
+    fn area_ratios_beyond_the_max_are_negative() {
+        let max_xy: (f64, f64) = (7.5, 5.0);
+        let hash_items: Vec<HashItem> = vec![hash_item((15.0, -10.0), (22.5, 15.0))];
+        let spec = HashSpec {
+            records: 1,
+            ..HashSpec::new(HashFormat::AreaRatio)
+        };
+
+        let hash: String = area_ratio_records(&hash_items, &max_xy, &spec, &versions()).to_string();
+
+        assert!(hash.ends_with(":-1.111111e-1,-9.000000e0,-2.500000e-1,-2.500000e-1"));
+    }
+
+    #[test]
+    fn exact_hashes_keep_every_bit_of_the_apex_angles() {
+        let angles: [f64; 8] = [
+            0.1 + 0.2,
+            std::f64::consts::PI / 3.0,
+            std::f64::consts::PI - f64::EPSILON * 2.0,
+            1.0 / 7.0,
+            f64::MIN_POSITIVE,
+            5.0e-324,
+            -0.0,
+            f64::NAN,
+        ];
+        let items: Vec<HashItemFormatted> = angles
+            .iter()
+            .zip(angles.iter().rev())
+            .enumerate()
+            .map(|(i, (angle, excited_angle))| HashItemFormatted {
+                right_leaning: i as i32 % 3,
+                upper_angle: *angle,
+                right_leaning_excited: (i as i32 + 1) % 3,
+                upper_angle_excited: *excited_angle,
+            })
+            .collect();
+
+        let hash: String = ParsedHash::exact(&items, &versions()).to_string();
+        let parsed: ParsedHash = round_trip(&hash);
+        let decoded: Vec<HashItemFormatted> = parsed.formatted_items().unwrap();
+
+        let bits = |items: &[HashItemFormatted]| -> Vec<(i32, u64, i32, u64)> {
+            items
+                .iter()
+                .map(|item| {
+                    (
+                        item.right_leaning,
+                        item.upper_angle.to_bits(),
+                        item.right_leaning_excited,
+                        item.upper_angle_excited.to_bits(),
+                    )
+                })
+                .collect()
+        };
+        assert_eq!(bits(&decoded), bits(&items));
+        assert_eq!(ParsedHash::exact(&decoded, &versions()).to_string(), hash);
+        assert_eq!(parsed.records.len(), HASH_RECORDS);
+        assert!(hash.contains("!3fd3333333333334!"));
+    }
+}
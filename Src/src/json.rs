@@ -0,0 +1,245 @@
+/*
+DO NOT ALTER OR REMOVE COPYRIGHT NOTICES OR THIS HEADER.
+
+Copyright © 2019 Asame Imoni Obiomah. All rights reserved.
+
+Artificial intelligence ethics is of existential importance.
+The licensing model of OkeuvoLite enforces adherence to a strict ethical code.
+
+The contents of this file are subject to the terms of both the GNU General Public License Version 2 only (“GPL”)
+and Inverse license (collectively, the “License”). You may not use this file except in compliance with the License.
+You can obtain a copy of the License at LICENSE.txt. See the License for the specific language governing
+permissions and limitations under the License.
+
+When distributing the software, include this License Header Notice in each file and include the License file at LICENSE.txt.
+*/
+
+//! JSON interchange format for a discourse and its encoding (cargo feature "serde"),
+//! so that a discourse can be posted, diffed and archived as a single document:
+//!
+//! ```text
+//! {
+//!   "format": "okeuvo-lite",
+//!   "version": 1,
+//!   "discourse": { "discourse": {...}, "title": [...], "sentences": [...], "words": [...], ... },
+//!   "encoding": { "discourse_id": 1, "entities": [...], "unit_tensors": [...], ... }
+//! }
+//! ```
+//!
+//! "discourse" holds the fields of Discourse (the rows of input.db) and "encoding",
+//! which may be null or left out, those of Encoding (the rows of output.db).
+//! Field names are those of the structs in data.rs. Tables left out of
+//! "discourse" or "encoding" are empty.
+//!
+//! version is raised whenever the layout changes. Documents of a later version
+//! than JSON_FORMAT_VERSION are refused.
+
+use crate::discourse::{Discourse, Encoding};
+use crate::error::OkeuvoError;
+use serde::{Deserialize, Serialize};
+
+/// Value of the "format" field of every document.
+pub const JSON_FORMAT: &str = "okeuvo-lite";
+
+/// Version of the document layout written by this library.
+pub const JSON_FORMAT_VERSION: u32 = 1;
+
+/// A discourse and, optionally, its encoding, as exchanged in JSON.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DiscourseDocument {
+    /// Always JSON_FORMAT.
+    pub format: String,
+    /// Version of the document layout.
+    pub version: u32,
+    /// The discourse (input.db).
+    pub discourse: Discourse,
+    /// The encoding of the discourse (output.db), if it has been encoded.
+    #[serde(default)]
+    pub encoding: Option<Encoding>,
+}
+
+impl DiscourseDocument {
+    /// Creates a document of the current version.
+    pub fn new(discourse: Discourse, encoding: Option<Encoding>) -> DiscourseDocument {
+        DiscourseDocument {
+            format: JSON_FORMAT.to_string(),
+            version: JSON_FORMAT_VERSION,
+            discourse,
+            encoding,
+        }
+    }
+
+    /// Writes the document as pretty printed JSON.
+    pub fn to_json(&self) -> Result<String, OkeuvoError> {
+        serde_json::to_string_pretty(self).map_err(invalid_json)
+    }
+
+    /// Reads a document, checking its format and version first so that
+    /// documents from another format or a later version are refused
+    /// with a message that says so.
+    pub fn from_json(json: &str) -> Result<DiscourseDocument, OkeuvoError> {
+        let value: serde_json::Value = serde_json::from_str(json).map_err(invalid_json)?;
+
+        match value.get("format").and_then(|format| format.as_str()) {
+            Some(JSON_FORMAT) => {}
+            Some(format) => {
+                return Err(OkeuvoError::InvalidJson {
+                    message: format!("format is '{}', expected '{}'", format, JSON_FORMAT),
+                })
+            }
+            None => {
+                return Err(OkeuvoError::InvalidJson {
+                    message: "the document has no format".to_string(),
+                })
+            }
+        }
+
+        match value.get("version").and_then(|version| version.as_u64()) {
+            Some(version) if version >= 1 && version <= u64::from(JSON_FORMAT_VERSION) => {}
+            Some(version) => {
+                return Err(OkeuvoError::InvalidJson {
+                    message: format!(
+                        "version {} is not supported, this version of Okeuvo Lite reads up to {}",
+                        version, JSON_FORMAT_VERSION
+                    ),
+                })
+            }
+            None => {
+                return Err(OkeuvoError::InvalidJson {
+                    message: "the document has no version".to_string(),
+                })
+            }
+        }
+
+        let document: DiscourseDocument = serde_json::from_value(value).map_err(invalid_json)?;
+
+        if let Some(encoding) = &document.encoding {
+            if encoding.discourse_id != document.discourse.discourse_id() {
+                return Err(OkeuvoError::InvalidJson {
+                    message: format!(
+                        "the encoding is of discourse {}, not of discourse {}",
+                        encoding.discourse_id,
+                        document.discourse.discourse_id()
+                    ),
+                });
+            }
+        }
+
+        Ok(document)
+    }
+}
+
+/// Converts a serde_json failure.
+fn invalid_json(e: serde_json::Error) -> OkeuvoError {
+    OkeuvoError::InvalidJson {
+        message: e.to_string(),
+    }
+}
+
+#[cfg(all(test, feature = "serde"))]
+mod tests {
+    use super::*;
+    use crate::test_support::discourse;
+
+    fn document() -> DiscourseDocument {
+        let mut discourse = discourse(&[
+            "he he PRON _ 2 nsubj",
+            "ate eat VERB Tense=Past 0 root",
+            "cake cake NOUN _ 2 obj",
+        ]);
+        discourse.discourse.discourse_id = 7;
+        for word in &mut discourse.words {
+            word.x = word.word_id as f64;
+            word.y = 0.5;
+        }
+        let encoding = crate::encode(&discourse, &true).unwrap();
+
+        DiscourseDocument::new(discourse, Some(encoding))
+    }
+
+    /// The message of an InvalidJson error.
+    fn invalid(json: &str) -> String {
+        match DiscourseDocument::from_json(json) {
+            Err(OkeuvoError::InvalidJson { message }) => message,
+            other => panic!("expected InvalidJson, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn documents_round_trip() {
+        let json: String = document().to_json().unwrap();
+
+        let read = DiscourseDocument::from_json(&json).unwrap();
+
+        assert_eq!((read.format.as_str(), read.version), (JSON_FORMAT, 1));
+        assert_eq!(read.discourse.discourse_id(), 7);
+        assert_eq!(read.discourse.words.len(), 3);
+        assert_eq!(read.discourse.relations.len(), 3);
+        assert_eq!(read.discourse.triplets.len(), 1);
+        let encoding: &Encoding = read.encoding.as_ref().unwrap();
+        assert_eq!(encoding.unit_tensors.len(), 1);
+        assert_eq!(
+            encoding.entities.len(),
+            document().encoding.unwrap().entities.len()
+        );
+        // Written again, the document is the same.
+        assert_eq!(read.to_json().unwrap(), json);
+    }
+
+    #[test]
+    fn tables_and_the_encoding_can_be_left_out() {
+        let mut json: serde_json::Value =
+            serde_json::from_str(&document().to_json().unwrap()).unwrap();
+        let document = json.as_object_mut().unwrap();
+        document.remove("encoding");
+        let discourse = document["discourse"].as_object_mut().unwrap();
+        discourse.retain(|table, _| table == "discourse");
+
+        let read = DiscourseDocument::from_json(&json.to_string()).unwrap();
+
+        assert_eq!(read.discourse.discourse_id(), 7);
+        assert!(read.discourse.words.is_empty());
+        assert!(read.encoding.is_none());
+    }
+
+    #[test]
+    fn refuses_other_formats_and_later_versions() {
+        assert_eq!(
+            invalid(r#"{"format": "other", "version": 1, "discourse": {}}"#),
+            "format is 'other', expected 'okeuvo-lite'"
+        );
+        assert_eq!(
+            invalid(r#"{"version": 1, "discourse": {}}"#),
+            "the document has no format"
+        );
+        assert_eq!(
+            invalid(r#"{"format": "okeuvo-lite", "version": 2, "discourse": {}}"#),
+            "version 2 is not supported, this version of Okeuvo Lite reads up to 1"
+        );
+        assert_eq!(
+            invalid(r#"{"format": "okeuvo-lite", "discourse": {}}"#),
+            "the document has no version"
+        );
+    }
+
+    #[test]
+    fn refuses_malformed_documents() {
+        // Not JSON, a missing discourse and a field of the wrong type.
+        invalid("{\"format\": ");
+        invalid(r#"{"format": "okeuvo-lite", "version": 1}"#);
+        invalid(
+            r#"{"format": "okeuvo-lite", "version": 1, "discourse": {"words": [{"x": "far"}]}}"#,
+        );
+    }
+
+    #[test]
+    fn refuses_an_encoding_of_another_discourse() {
+        let mut document = document();
+        document.encoding.as_mut().unwrap().discourse_id = 8;
+
+        assert_eq!(
+            invalid(&document.to_json().unwrap()),
+            "the encoding is of discourse 8, not of discourse 7"
+        );
+    }
+}
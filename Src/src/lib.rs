@@ -15,35 +15,97 @@ When distributing the software, include this License Header Notice in each file
 */
 
 #![allow(dead_code)]
+mod antonym;
+mod conllu;
 mod data;
+mod discourse;
+#[cfg(feature = "signing")]
+mod envelope;
+mod error;
+mod explain;
+mod function_word;
+mod grid;
+mod grid_index;
+mod hash;
+#[cfg(feature = "serde")]
+mod json;
+mod location;
 mod math;
+mod negation;
+mod okeuvo;
+mod schema;
+mod similarity;
+#[cfg(test)]
+mod test_support;
+mod triplets;
 mod utils;
+mod version;
+use crate::antonym::read_wordnet_antonyms as read_wordnet_antonyms_inner;
+use crate::conllu::{conllu_to_discourse, read_conllu};
 use crate::data::{
-    insert_hash, insert_or_update_entity, insert_unit_tensor, insert_unit_tensor_ethereal,
-    select_entity, select_hash_item, select_input_discourse, select_input_discourse_title,
-    select_input_exempt_features_by_discourse_id, select_input_features_by_section,
-    select_input_features_by_sentence_and_section_and_word, select_input_features_by_triplet,
-    select_input_features_by_word_id, select_input_new_word_defs, select_input_section_all_ranked,
-    select_input_sentences_all, select_input_triplets_by_sentence,
-    select_input_word_relation_by_sentence, select_input_words_by_sentence,
-    select_input_words_new_def, select_meaning_grid_max_xy, select_new_def_isa, select_unit_tensor,
-    select_unit_tensor_centrality, update_input_new_word_def, update_unit_tensor_ethereal, Entity,
-    ExcitationData, HashItem, HashItemFormatted, InputDiscourse, InputExemptFeature,
-    InputNewWordDef, InputSection, InputSentence, InputTriplet, InputWord, InputWordFeature,
-    InputWordRelation, UnitTensor,
+    insert_antonym_pairs, insert_meaning_grid_items, select_encoding_versions, select_hash_item,
+    select_input_new_word_defs, select_input_word_relations_by_discourse,
+    select_input_words_new_def, select_meaning_grid_max_xy, select_meaning_grid_version,
+    select_new_def_isa, update_input_new_word_def, ExcitationData, InputNewWordDef,
 };
-use crate::math::{cartesian_to_polar, vector_addition_2d, vector_magnitude_2d, Point2D, Vector2D};
+use crate::explain::explain_hash_items as explain_hash_items_inner;
+use crate::grid::{read_meaning_grid_csv, resolve_input_word_coordinates, MeaningGridCsv};
+use crate::hash::{area_ratio_records, exact_records, round_pad_records};
+use crate::location::get_locations;
+use crate::math::{cartesian_to_polar, vector_addition_2d, vector_magnitude_2d};
+use crate::negation::{get_negations, negate_triplet_words};
+use crate::similarity::{compare_records, formatted_comparable_records};
+use crate::triplets::{extract_triplets as extract_triplets_inner, get_modifiers};
 use crate::utils::{
     calculate_convex_hull, distance, point_vec_to_position_vec_2d, position_vec_to_point_vec_2d,
 };
 use rusqlite::Connection;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 
-/// Path to the input database which contains raw triplets and triplet-word relations.
+pub use crate::conllu::{ConlluSentence, ConlluToken};
+pub use crate::data::{
+    Antonym, AntonymPair, Entity, HashItem, HashItemFormatted, InputDiscourse, InputDiscourseTitle,
+    InputExemptFeature, InputSection, InputSentence, InputTriplet, InputWord, InputWordFeature,
+    InputWordRelation, MeaningGridItem, UnitTensor, UnitTensorEtherealDef, UnitTensorLocation,
+};
+pub use crate::discourse::{Discourse, Encoding};
+#[cfg(feature = "signing")]
+pub use crate::envelope::{
+    from_hex, public_key, to_hex, HashEnvelope, SignedEnvelope, ENVELOPE_FORMAT,
+    ENVELOPE_FORMAT_VERSION,
+};
+pub use crate::error::OkeuvoError;
+pub use crate::explain::HashItemExplanation;
+pub use crate::function_word::{FunctionWordTreatment, FunctionWords, FUNCTION_WORD_UPOS};
+pub use crate::grid::{MeaningGrid, UnresolvedWord};
+pub use crate::grid_index::MeaningGridIndex;
+pub use crate::hash::{
+    HashFormat, HashRecord, HashSpec, Lean, ParsedHash, APEX_ANGLE_TAG,
+    AREA_RATIO_ELEMENT_SEPARATOR, AREA_RATIO_RECORD_SEPARATOR, AREA_RATIO_TAG,
+    AREA_RATIO_TOLERANCE, ELEMENT_SEPARATOR, EXACT_TAG, HASH_RECORDS, HASH_SPEC_VERSION, PADDING,
+    RECORD_SEPARATOR, SPEC_SEPARATOR,
+};
+#[cfg(feature = "serde")]
+pub use crate::json::{DiscourseDocument, JSON_FORMAT, JSON_FORMAT_VERSION};
+pub use crate::math::{Point2D, Vector2D};
+pub use crate::okeuvo::{DatabaseLocation, Okeuvo, OkeuvoConfig};
+pub use crate::schema::DatabaseKind;
+pub use crate::similarity::{HashSimilarity, RecordSimilarity};
+pub use crate::version::{
+    version_number, Versions, LIBRARY_CODENAME, LIBRARY_VERSION, VERSIONS_SEPARATOR, VERSIONS_TAG,
+};
+
+// The functions of this module that take no engine or connection (encode_discourse,
+// get_hash and the like) open the default databases with OkeuvoConfig::default,
+// so, as Okeuvo::new does, they create the databases that don't exist and upgrade
+// the schemas of those that do in place, as a side effect of the call.
+
+/// Default path to the input database which contains raw triplets and triplet-word relations.
+/// See OkeuvoConfig to use databases elsewhere.
 const INPUTPATH: &str = "./storage/input.db";
-/// Path to the output database that contains an encoding of the input as "hash items".
+/// Default path to the output database that contains an encoding of the input as "hash items".
 const OUTPUTPATH: &str = "./storage/output.db";
-/// Path to the metadata database whose contents include the meaning grid.
+/// Default path to the metadata database whose contents include the meaning grid.
 const METAPATH: &str = "./storage/metadata.db";
 const TOLERANCE: f64 = 1.0e-6;
 
@@ -82,111 +144,427 @@ fn path_root() -> String {
 /// It is the prelimnary step to hashing.
 /// The resulting encoding is dumped in output.db as a set of "HashItem's".
 /// Hashing methods work off this encoding.
+///
+/// The x and y coordinates of input words are used as written into
+/// input_word. See encode_discourse_with_grid to have them filled in
+/// from the meaning grid. Negated words still take their antonyms from
+/// the antonyms table of metadata.db (see negate_word).
+///
+/// Works on the databases in ./storage. See Okeuvo::encode_discourse
+/// to work on databases elsewhere.
 pub extern "C" fn encode_discourse(
     discourse_id: &i32,
     agrees_to_the_creed: &bool,
-) -> Result<(), Box<dyn std::error::Error>> {
+) -> Result<(), OkeuvoError> {
+    // Check that the user has agreed to The Creed.
+    // Stop processing with an error if they haven't.
+    // The user interface must present a choice whose value we use here.
+    if !agrees_to_the_creed {
+        return Err(OkeuvoError::CreedNotAccepted);
+    }
+
+    let okeuvo = Okeuvo::new(OkeuvoConfig::default())?;
+    okeuvo.encode_discourse(discourse_id, agrees_to_the_creed)?;
+
+    okeuvo.close()
+}
+
+/// Encodes a discourse (or communication), as encode_discourse does, but first
+/// resolves the coordinates of input words (and of the discourse hypernym) that
+/// were left at 0. Coordinates are taken from the meaning grid by synset_id, or,
+/// for words not on the grid that have a new_word_id, from input_new_word_def.
+///
+/// If any word (or the hypernym of a discourse with a title) can't be resolved,
+/// OkeuvoError::MissingCoordinates listing every such word is returned and nothing
+/// is written to output.db.
+pub fn encode_discourse_with_grid(
+    discourse_id: &i32,
+    grid: &MeaningGrid,
+    agrees_to_the_creed: &bool,
+) -> Result<(), OkeuvoError> {
+    // Check that the user has agreed to The Creed.
+    // Stop processing with an error if they haven't.
+    // The user interface must present a choice whose value we use here.
+    if !agrees_to_the_creed {
+        return Err(OkeuvoError::CreedNotAccepted);
+    }
+
+    let okeuvo = Okeuvo::new(OkeuvoConfig::default())?;
+    okeuvo.encode_discourse_with_grid(discourse_id, grid, agrees_to_the_creed)?;
+
+    okeuvo.close()
+}
+
+/// Encodes a discourse held in memory, as encode_discourse does for one in input.db.
+/// No database is touched; the entities, unit tensors and hash items are returned
+/// as an Encoding, which can be hashed with get_encoding_hash or written to
+/// output.db with Okeuvo::save_encoding.
+///
+/// The x and y coordinates of words are used as given. See encode_with_grid
+/// to have them filled in from the meaning grid.
+pub fn encode(discourse: &Discourse, agrees_to_the_creed: &bool) -> Result<Encoding, OkeuvoError> {
+    // Check that the user has agreed to The Creed.
+    // Stop processing with an error if they haven't.
+    // The user interface must present a choice whose value we use here.
+    if !agrees_to_the_creed {
+        return Err(OkeuvoError::CreedNotAccepted);
+    }
+
+    encode_inner(discourse, None, None, &FunctionWords::default())
+}
+
+/// Encodes a discourse held in memory, as encode does, but first resolves the
+/// coordinates of words (and of the discourse hypernym) that were left at 0
+/// (see encode_discourse_with_grid).
+pub fn encode_with_grid(
+    discourse: &Discourse,
+    grid: &MeaningGrid,
+    agrees_to_the_creed: &bool,
+) -> Result<Encoding, OkeuvoError> {
+    // Check that the user has agreed to The Creed.
+    // Stop processing with an error if they haven't.
+    // The user interface must present a choice whose value we use here.
+    if !agrees_to_the_creed {
+        return Err(OkeuvoError::CreedNotAccepted);
+    }
+
+    encode_inner(discourse, Some(grid), Some(grid), &FunctionWords::default())
+}
+
+/// Encodes a discourse held in memory, as encode (grid None) or encode_with_grid
+/// do, with function words treated as function_words sets out instead of by
+/// the default lexicon (see FunctionWords).
+pub fn encode_with_function_words(
+    discourse: &Discourse,
+    grid: Option<&MeaningGrid>,
+    function_words: &FunctionWords,
+    agrees_to_the_creed: &bool,
+) -> Result<Encoding, OkeuvoError> {
+    // Check that the user has agreed to The Creed.
+    // Stop processing with an error if they haven't.
+    // The user interface must present a choice whose value we use here.
+    if !agrees_to_the_creed {
+        return Err(OkeuvoError::CreedNotAccepted);
+    }
+
+    encode_inner(discourse, grid, grid, function_words)
+}
+
+/// Reads a CoNLL-U file (the output format of Universal Dependencies parsers)
+/// into memory as one discourse, with the properties given in discourse.
+///
+/// Every word becomes an InputWord, with the lemma as lexeme, its ID as
+/// index_of_word and its UPOS as pos. Its FEATS become InputWordFeature's and
+/// its HEAD and DEPREL an InputWordRelation to the head word.
+/// Sentences and words are numbered from 1 within the discourse.
+///
+/// synset_id is called for each word, with the sentence it is in, and supplies
+/// the Wordnet synset_id of the word; None leaves it at 0 (not in Wordnet).
+/// Triplets and sections are derived from the relations (see extract_triplets).
+pub fn discourse_from_conllu(
+    conllu_path: &str,
+    discourse: InputDiscourse,
+    synset_id: &mut dyn FnMut(&ConlluSentence, &ConlluToken) -> Option<i32>,
+    agrees_to_the_creed: &bool,
+) -> Result<Discourse, OkeuvoError> {
+    // Check that the user has agreed to The Creed.
+    // Stop processing with an error if they haven't.
+    // The user interface must present a choice whose value we use here.
+    if !agrees_to_the_creed {
+        return Err(OkeuvoError::CreedNotAccepted);
+    }
+
+    let sentences: Vec<ConlluSentence> = read_conllu(std::path::Path::new(conllu_path))?;
+
+    Ok(conllu_to_discourse(discourse, &sentences, synset_id))
+}
+
+/// Imports a CoNLL-U file into input.db as one discourse (see discourse_from_conllu),
+/// replacing any earlier rows of the same discourse_id.
+/// The file is read in full before input.db is touched, so a malformed file
+/// (OkeuvoError::InvalidConllu) leaves the database as it was.
+///
+/// Works on ./storage/input.db. See Okeuvo::import_conllu
+/// to import into an input database elsewhere.
+///
+/// Return format: the discourse as stored, with the input.db keys.
+pub fn import_conllu(
+    conllu_path: &str,
+    discourse: InputDiscourse,
+    synset_id: &mut dyn FnMut(&ConlluSentence, &ConlluToken) -> Option<i32>,
+    agrees_to_the_creed: &bool,
+) -> Result<Discourse, OkeuvoError> {
     // Check that the user has agreed to The Creed.
     // Stop processing with an error if they haven't.
     // The user interface must present a choice whose value we use here.
     if !agrees_to_the_creed {
-        Err("You must agree to The Creed to continue".to_string())?
+        return Err(OkeuvoError::CreedNotAccepted);
     }
 
+    let okeuvo = Okeuvo::new(OkeuvoConfig::default())?;
+    let result: Discourse =
+        okeuvo.import_conllu(conllu_path, discourse, synset_id, agrees_to_the_creed)?;
+    okeuvo.close()?;
+
+    Ok(result)
+}
+
+/// Imports the discourse of a JSON document (see DiscourseDocument) into input.db,
+/// replacing any earlier rows of the same discourse_id. Sentence, word, triplet,
+/// section and new word ids are replaced by the input.db keys.
+/// The encoding in the document, if any, is not imported: encode_discourse
+/// derives it again from the discourse.
+///
+/// Works on ./storage/input.db. See Okeuvo::import_json
+/// to import into an input database elsewhere.
+///
+/// Return format: the discourse as stored.
+#[cfg(feature = "serde")]
+pub fn import_json(json: &str, agrees_to_the_creed: &bool) -> Result<Discourse, OkeuvoError> {
+    // Check that the user has agreed to The Creed.
+    // Stop processing with an error if they haven't.
+    // The user interface must present a choice whose value we use here.
+    if !agrees_to_the_creed {
+        return Err(OkeuvoError::CreedNotAccepted);
+    }
+
+    let okeuvo = Okeuvo::new(OkeuvoConfig::default())?;
+    let result: Discourse = okeuvo.import_json(json, agrees_to_the_creed)?;
+    okeuvo.close()?;
+
+    Ok(result)
+}
+
+/// Exports a discourse in input.db as a JSON document (see DiscourseDocument),
+/// with its encoding in output.db when it has been encoded.
+///
+/// Works on the databases in ./storage. See Okeuvo::export_json
+/// to work on databases elsewhere.
+#[cfg(feature = "serde")]
+pub fn export_json(discourse_id: &i32, agrees_to_the_creed: &bool) -> Result<String, OkeuvoError> {
+    // Check that the user has agreed to The Creed.
+    // Stop processing with an error if they haven't.
+    // The user interface must present a choice whose value we use here.
+    if !agrees_to_the_creed {
+        return Err(OkeuvoError::CreedNotAccepted);
+    }
+
+    let okeuvo = Okeuvo::new(OkeuvoConfig::default())?;
+    let result: String = okeuvo.export_json(discourse_id, agrees_to_the_creed)?;
+    okeuvo.close()?;
+
+    Ok(result)
+}
+
+/// Derives the triplets and sections of a discourse from the Universal Dependencies
+/// relations and features of its words, replacing any it has, so that a plain
+/// UD parse is enough input.
+///
+/// Each verb (or other word with a subject or object) with both a subject
+/// (nsubj, nsubj:pass, csubj) and an object (obj, iobj, ccomp, xcomp, or an obl
+/// that is not a place or a time) makes a triplet. Predicates joined by xcomp or
+/// conj share subjects, and conj'ed subjects and objects join their sections.
+/// Voice=Pass (or a :pass relation) sets is_passive and the Tense of the
+/// auxiliaries or predicate sets tense.
+///
+/// Return format: number of triplets.
+pub fn extract_triplets(
+    discourse: &mut Discourse,
+    agrees_to_the_creed: &bool,
+) -> Result<usize, OkeuvoError> {
+    // Check that the user has agreed to The Creed.
+    // Stop processing with an error if they haven't.
+    // The user interface must present a choice whose value we use here.
+    if !agrees_to_the_creed {
+        return Err(OkeuvoError::CreedNotAccepted);
+    }
+
+    let (triplets, sections) = extract_triplets_inner(discourse);
+    discourse.triplets = triplets;
+    discourse.sections = sections;
+
+    Ok(discourse.triplets.len())
+}
+
+/// Does the work of encode and encode_with_grid (and so of encode_discourse).
+/// Coordinates are resolved only when grid is supplied. Negated words take
+/// their antonyms on antonym_grid, which is grid where coordinates are resolved
+/// (see negate_word).
+fn encode_inner(
+    discourse: &Discourse,
+    grid: Option<&MeaningGrid>,
+    antonym_grid: Option<&MeaningGrid>,
+    function_words: &FunctionWords,
+) -> Result<Encoding, OkeuvoError> {
     // (Step 1)--------------------------------------- Process Input ---------------------------------------\\
 
-    let conn_input = Connection::open(INPUTPATH)?;
-    let conn_output = Connection::open(OUTPUTPATH)?;
+    let discourse_id: i32 = discourse.discourse_id();
+    let mut input_discourse: InputDiscourse = discourse.discourse.clone();
+
+    if discourse.sentences.is_empty() {
+        return Err(OkeuvoError::EmptyDiscourse { discourse_id });
+    }
+
+    // Definitions of new words, keyed by new_word_id.
+    // Only needed when coordinates are being resolved.
+    let mut new_word_defs: HashMap<i32, InputNewWordDef> = HashMap::new();
+    if grid.is_some() {
+        for new_word_def in &discourse.new_word_defs {
+            new_word_defs.insert(new_word_def.new_word_id, new_word_def.clone());
+        }
+    }
+
+    // Grab input words for every sentence before building any output,
+    // so that words without coordinates are all reported up front.
+    // Format - HashMap<sentence_id, HashMap<word_id, InputWord>>.
+    let mut sentences_and_words: HashMap<i32, HashMap<i32, InputWord>> = HashMap::new();
+    let mut unresolved_words: Vec<UnresolvedWord> = Vec::new();
+
+    for sentence in &discourse.sentences {
+        let mut input_words: HashMap<i32, InputWord> = discourse
+            .section_words_by_sentence(&sentence.sentence_id)
+            .into_iter()
+            .map(|word| (word.word_id, word.clone()))
+            .collect();
+
+        // Modifiers of the section words add to the excitation (see get_excitation).
+        // They are not required to have coordinates, those without any add nothing.
+        let modifier_ids: BTreeSet<i32> = input_words
+            .keys()
+            .flat_map(|word_id| get_modifiers(discourse, word_id))
+            .filter(|word_id| !input_words.contains_key(word_id))
+            .collect();
+        let mut modifier_words: HashMap<i32, InputWord> = discourse
+            .words
+            .iter()
+            .filter(|word| modifier_ids.contains(&word.word_id))
+            .map(|word| (word.word_id, word.clone()))
+            .collect();
+
+        if let Some(grid) = grid {
+            unresolved_words.extend(resolve_input_word_coordinates(
+                grid,
+                &new_word_defs,
+                function_words,
+                &mut input_words,
+            ));
+            resolve_input_word_coordinates(
+                grid,
+                &new_word_defs,
+                function_words,
+                &mut modifier_words,
+            );
+        }
 
-    let mut sentences_and_words: HashMap<i32, Vec<InputWord>> = HashMap::new();
+        input_words.extend(modifier_words);
+        sentences_and_words.insert(sentence.sentence_id, input_words);
+    }
 
-    // Fetch sentences for the current discourse.
-    let sentences_vec: Vec<InputSentence> = select_input_sentences_all(&conn_input, discourse_id)?;
+    // Place the discourse hypernym on the meaning grid if it was left at 0.
+    // It is only needed for the hash, so only for discourses with a title.
+    if let Some(grid) = grid {
+        if !discourse.title.is_empty() && input_discourse.x == 0.0 && input_discourse.y == 0.0 {
+            match grid.get(&input_discourse.hypernym_synset_id) {
+                Some(item) => {
+                    input_discourse.x = item.x;
+                    input_discourse.y = item.y;
+                }
+                None => unresolved_words.insert(
+                    0,
+                    UnresolvedWord {
+                        word_id: 0,
+                        sentence_id: 0,
+                        synset_id: input_discourse.hypernym_synset_id,
+                        new_word_id: -1,
+                        lexeme: String::new(),
+                    },
+                ),
+            }
+        }
+    }
 
-    // Get universal features that are exempt from virtuality checks.
-    let exempt_features: Vec<InputExemptFeature> =
-        select_input_exempt_features_by_discourse_id(&conn_input, discourse_id)?;
+    if !unresolved_words.is_empty() {
+        return Err(OkeuvoError::MissingCoordinates {
+            discourse_id,
+            words: unresolved_words,
+        });
+    }
+
+    let mut encoding = Encoding {
+        discourse_id,
+        versions: Versions::new(grid.map_or("", |grid| grid.version())),
+        ..Default::default()
+    };
+
+    // Tracks changes to variable, is_virtual_triplet.
+    // Virtual blocks can run across sentences, so these live for the whole discourse.
+    let mut is_virtual_triplet_tracker = false;
 
     // Iterate all sentences in the current discourse.
-    for sentence in &sentences_vec {
+    for sentence in &discourse.sentences {
         let sentence_id: i32 = sentence.sentence_id;
 
-        // Grab input words for sentence.
+        // Input words for sentence.
         // For use in building output.
-        let input_words: HashMap<i32, InputWord> =
-            select_input_words_by_sentence(&conn_input, &sentence_id)?;
-        // Get values as Vec<InputWord> for storage in sentences_and_words.
-        let input_word_vec: Vec<InputWord> = input_words.values().cloned().collect();
-        sentences_and_words.insert(sentence_id, input_word_vec);
-
-        // Get triplets for current sentence.
-        let triplets_by_sentence: HashMap<i32, InputTriplet> =
-            select_input_triplets_by_sentence(&conn_input, &sentence_id)?;
-
-        let mut triplets_pruned: Vec<InputTriplet> = Vec::new();
-        let mut sections_pruned: HashMap<i32, (i32, i32, i32)> = HashMap::new();
-
-        // Eliminate duplicate sections.
-        for triplet in &triplets_by_sentence {
-            // Fetch subject, predicate, object for this triplet as a hash map in the format:
-            // HashMap<triplet_id, (word_id, word_id, word_id)>.
-            let mut subject_predicate_object: HashMap<i32, (i32, i32, i32)> =
-                get_subject_predicate_object(&conn_input, &triplet.0)?;
+        let input_words: &HashMap<i32, InputWord> = &sentences_and_words[&sentence_id];
 
-            // Swap subject and object word_id's if the triplet is in passive voice.
-            match (triplet.1).is_passive {
-                true =>
-                // Swap subject and object word_ids.
-                {
-                    swap_passive_subject_object(
-                        &mut subject_predicate_object,
-                        &(triplet.1).triplet_id,
-                    )?
-                }
-                false => {}
-            };
+        // Get triplets for current sentence, sorted by triplet_id (ascending)
+        // so we can maintain chronological order.
+        let triplets_pruned: Vec<&InputTriplet> = discourse.triplets_by_sentence(&sentence_id);
+        let mut sections_pruned: BTreeMap<i32, (i32, i32, i32)> = BTreeMap::new();
 
-            let mut contains: bool = false;
+        for triplet in &triplets_pruned {
+            // Fetch subject, predicate, object for this triplet as a tuple of word_id's.
+            let mut subject_predicate_object: (i32, i32, i32) =
+                get_subject_predicate_object(discourse, &triplet.triplet_id);
 
-            for item in &triplets_pruned {
-                if item.triplet_id == (triplet.1).triplet_id {
-                    contains = true;
-                    break;
-                }
+            // Swap subject and object word_id's if the triplet is in passive voice.
+            if triplet.is_passive {
+                subject_predicate_object = swap_passive_subject_object(&subject_predicate_object);
             }
 
-            if !contains {
-                let triplet_current = InputTriplet { ..*triplet.1 };
-                triplets_pruned.push(triplet_current);
-                sections_pruned.insert(*triplet.0, subject_predicate_object[triplet.0]);
-            }
+            sections_pruned.insert(triplet.triplet_id, subject_predicate_object);
         }
 
-        // Sort triplets_pruned by triplet_id (ascending) so we can maintain chronological order.
-        // For use in building output.
-        triplets_pruned.sort_by(|a, b| a.triplet_id.cmp(&b.triplet_id));
-
         // (Step 2)--------------------------------------- Begin Ouput ---------------------------------------\\
 
-        // Get excitation value.
-        // Returned as Vec<(subject word_id, excitation value)>.
-        let excitation_data: (i32, ExcitationData) = get_excitation(&input_words, &sections_pruned);
-
-        // Tracks changes to variable, is_virtual_triplet.
-        let mut is_virtual_triplet_tracker = false;
-        let mut last_unit_tensor_index = -1;
-        let mut last_ethereal_branch_index = -1;
-
         for triplet in &triplets_pruned {
             let tense: i32 = triplet.tense;
             let triplet_id: i32 = triplet.triplet_id;
             let sections = sections_pruned[&triplet_id];
 
-            let subject_focus: &InputWord = &input_words[&sections.0];
-            let predicate_focus: &InputWord = &input_words[&sections.1];
-            let object_focus: &InputWord = &input_words[&sections.2];
+            // Check for negations.
+            // A negated predicate or object takes the coordinates of its antonym
+            // (or is reflected, see negate_word) in this triplet alone, before
+            // its output is built, so that its excitation and entities carry the
+            // negated meaning while the other triplets keep the word as it is.
+            let negations: (bool, bool) = get_negations(discourse, &sections);
+            let is_negated: bool = negations.0 || negations.1;
+            let negated_words: HashMap<i32, InputWord>;
+            let triplet_words: &HashMap<i32, InputWord> = if is_negated {
+                negated_words =
+                    negate_triplet_words(input_words, &sections, &negations, antonym_grid);
+                &negated_words
+            } else {
+                input_words
+            };
 
-            let excitation: f64 = (excitation_data.1).magnitude;
+            let subject_focus: &InputWord =
+                get_section_focus(triplet_words, &sentence_id, &triplet_id, &1, &sections.0)?;
+            let predicate_focus: &InputWord =
+                get_section_focus(triplet_words, &sentence_id, &triplet_id, &2, &sections.1)?;
+            let object_focus: &InputWord =
+                get_section_focus(triplet_words, &sentence_id, &triplet_id, &3, &sections.2)?;
+
+            // Get the excitation of the subject by the words of this triplet.
+            let excitation_data: ExcitationData = get_excitation(
+                discourse,
+                triplet_words,
+                &triplet_id,
+                &sections.0,
+                function_words,
+            );
+            let excitation: f64 = excitation_data.magnitude;
 
             // Create Entities for the focii of each triplet section.
             // An entity can be an instance (in which case it has an
@@ -194,62 +572,68 @@ pub extern "C" fn encode_discourse(
             // can also be anonymous (marked by lacking both
             // instance_index, and instance_name).
             let subject_entity: Entity =
-                create_entity(&subject_focus, &excitation, &triplet_id, &discourse_id);
+                create_entity(subject_focus, &excitation, &triplet_id, &discourse_id);
             let predicate_entity: Entity = create_entity(
-                &predicate_focus,
+                predicate_focus,
                 &(predicate_focus.x * predicate_focus.y),
                 &triplet_id,
-                discourse_id,
+                &discourse_id,
             );
             let object_entity: Entity = create_entity(
-                &object_focus,
+                object_focus,
                 &(object_focus.x * object_focus.y),
                 &triplet_id,
-                discourse_id,
+                &discourse_id,
             );
 
-            // Insert or update new Entities for the focii of each triplet sections.
-            // There is a unique constraint on the database field, instance_index to ensure
-            // that attempts to insert duplicates of this column are ignored.
-            let subject_entity_id: i64 = insert_or_update_entity(&conn_output, &subject_entity)?;
-            let predicate_entity_id: i64 =
-                insert_or_update_entity(&conn_output, &predicate_entity)?;
-            let object_entity_id: i64 = insert_or_update_entity(&conn_output, &object_entity)?;
-
-            // Every object is the location of an event. Locations are of varying types
-            // so, test the current object - 3rd (rightmost) section of triplet - to
-            // determine its location type.
-            // 1 = virtual location (the default),
-            // 2 = geographic location,
-            // 3 = temporal location.
-            // 4 = temporal location (terminative).
-            let location_type: i32 = get_location_type(&conn_input, &triplet_id, &sentence_id)?;
-
-            // Set where and when entities based on location_type.
-            let mut where_entity_id = -1;
-            let mut when_entity_id = -1;
-            match location_type {
-                2 => where_entity_id = object_entity_id,
-                3 | 4 => when_entity_id = object_entity_id,
-                _ => {}
+            // Add or update Entities for the focii of each triplet sections.
+            // Entities with the same instance_index are one entity, so
+            // later mentions of an instance update the existing entity.
+            let subject_entity_id: i64 = add_or_update_entity(&mut encoding, subject_entity);
+            let predicate_entity_id: i64 = add_or_update_entity(&mut encoding, predicate_entity);
+            let object_entity_id: i64 = add_or_update_entity(&mut encoding, object_entity);
+
+            // Where and when the event happens: the obl and nmod modifiers
+            // of the sections that are marked as places or times (see get_locations).
+            // Each is an entity of its own.
+            let mut locations: Vec<UnitTensorLocation> = Vec::new();
+            for (word_id, location_type) in get_locations(discourse, &sections) {
+                let word: &InputWord = match triplet_words.get(&word_id) {
+                    Some(word) => word,
+                    None => continue,
+                };
+                let location_entity: Entity =
+                    create_entity(word, &(word.x * word.y), &triplet_id, &discourse_id);
+                let entity_id: i64 = add_or_update_entity(&mut encoding, location_entity);
+
+                locations.push(UnitTensorLocation {
+                    unit_tensor_id: -1,
+                    entity_id,
+                    location_type,
+                });
             }
 
+            // The unit tensor itself holds the first of each.
+            let where_entity_id: i64 = locations
+                .iter()
+                .find(|location| !location.is_temporal())
+                .map_or(-1, |location| location.entity_id);
+            let when_entity_id: i64 = locations
+                .iter()
+                .find(|location| location.is_temporal())
+                .map_or(-1, |location| location.entity_id);
+
             // Determine if triplet is virtual or real.
             // A virtual triplet is one whose mood is not indicative,
             // such as conditionals and future tense events for example.
             // Each virtual is a new dimension and as many as is
             // necessary should be spawned.
-            let is_virtual_and_mood: (bool, String) = match exempt_features.len() {
-                0 => is_virtual(&conn_input, triplet, &vec![])?,
-                _ => is_virtual(&conn_input, triplet, &exempt_features)?,
-            };
+            let is_virtual_and_mood: (bool, String) =
+                is_virtual(discourse, triplet, &discourse.exempt_features);
 
             let is_virtual_triplet: bool = is_virtual_and_mood.0;
             let mood: String = is_virtual_and_mood.1;
 
-            // Important consideration -
-            // Check for negations.
-
             // Create UnitTensor for current interaction.
             // Each UnitTensor is a node in the graph of
             // the current discourse. It has a set of
@@ -259,9 +643,9 @@ pub extern "C" fn encode_discourse(
             // of index, entity_id), instance entity,
             // anonymous entity and branch and rejoin
             // indices of virtual UnitTensor's.
-            let unit_tensor: UnitTensor = create_unit_tensor(
+            let mut unit_tensor: UnitTensor = create_unit_tensor(
                 &sentence_id,
-                discourse_id,
+                &discourse_id,
                 &object_entity_id,
                 &subject_entity_id,
                 &where_entity_id,
@@ -269,13 +653,17 @@ pub extern "C" fn encode_discourse(
                 &predicate_entity_id,
                 &tense,
                 &mood,
-                &(excitation_data.1).x,
-                &(excitation_data.1).y,
+                &excitation_data.x,
+                &excitation_data.y,
+                &is_negated,
             );
-            // Insert the tensor.
-            // It's ok if it goes in the database as a duplicate.
-            // We can select distinct values.
-            let unit_tensor_id: i64 = insert_unit_tensor(&conn_output, &unit_tensor)?;
+            unit_tensor.unit_tensor_id = encoding.unit_tensors.len() as i32 + 1;
+            let unit_tensor_id: i64 = i64::from(unit_tensor.unit_tensor_id);
+            encoding.unit_tensors.push(unit_tensor);
+            for mut location in locations {
+                location.unit_tensor_id = unit_tensor_id;
+                encoding.locations.push(location);
+            }
 
             // If the triplet is virtual and not part of an existing block, create a UnitTensorEtherealDef to record
             // the current index at which it branches off the main UnitTensor column.
@@ -283,146 +671,467 @@ pub extern "C" fn encode_discourse(
             if is_virtual_triplet && is_virtual_triplet != is_virtual_triplet_tracker
             // && !is_triplet_transitional(&input_words)
             {
-                last_ethereal_branch_index =
-                    insert_unit_tensor_ethereal(&conn_output, &unit_tensor_id)?;
+                encoding.ethereal_defs.push(UnitTensorEtherealDef {
+                    branch_id: unit_tensor_id,
+                    rejoin_id: unit_tensor_id,
+                });
             }
 
-            // If the triplet is virtual and not part of the current block, update its UnitTensorEtherealDef to record
-            // the previous index as the index at which it rejoins the main UnitTensor column.
-            // The test to determine if the triplet is transitional has been commented out for now.
-            if (!is_virtual_triplet
-                && is_virtual_triplet != is_virtual_triplet_tracker
-                && last_ethereal_branch_index > -1)
-            // && !is_triplet_transitional(&input_words)
-            {
-                update_unit_tensor_ethereal(
-                    &conn_output,
-                    &last_unit_tensor_index,
-                    &unit_tensor_id,
-                )?;
+            // If the triplet is virtual and part of the current block, extend its UnitTensorEtherealDef
+            // so that it rejoins the main UnitTensor column after the current index.
+            if is_virtual_triplet && is_virtual_triplet == is_virtual_triplet_tracker {
+                if let Some(def) = encoding.ethereal_defs.last_mut() {
+                    def.rejoin_id = unit_tensor_id;
+                }
             }
 
             // Set is_virtual_triplet_tracker to current is_virtual_triplet value.
-            match is_virtual_triplet {
-                true => is_virtual_triplet_tracker = true,
-                false => is_virtual_triplet_tracker = false,
-            }
-
-            last_unit_tensor_index = unit_tensor_id;
+            is_virtual_triplet_tracker = is_virtual_triplet;
         }
     }
 
     // (Step 3)--------------------------------------- Vector Representation ---------------------------------------\\
 
-    // Fetch the discourse title.
-    let discourse_title: Vec<InputWord> = select_input_discourse_title(&conn_input, &discourse_id)?;
-
-    if discourse_title.len() > 0 {
-        // Retrieve discourse properties.
-        let discourse: InputDiscourse = select_input_discourse(&conn_input, &discourse_id)?;
-
+    if !discourse.title.is_empty() {
         let degree_centralities_real: HashMap<i64, i32> =
-            select_unit_tensor_centrality(&conn_input, &discourse_id, &false)?;
+            get_unit_tensor_centrality(&encoding, &false);
 
         let degree_centralities_virtual: HashMap<i64, i32> =
-            select_unit_tensor_centrality(&conn_input, &discourse_id, &true)?;
+            get_unit_tensor_centrality(&encoding, &true);
 
         // Get unit tensors for real events.
-        let unit_tensors_real: Vec<UnitTensor> =
-            select_unit_tensor(&conn_output, discourse_id, &false)?;
+        let unit_tensors_real: Vec<UnitTensor> = get_unit_tensors(&encoding, &false);
 
         // Get unit tensors for virtual events.
-        let unit_tensors_virtual: Vec<UnitTensor> =
-            select_unit_tensor(&conn_output, discourse_id, &false)?;
+        let unit_tensors_virtual: Vec<UnitTensor> = get_unit_tensors(&encoding, &true);
 
-        let discourse_entities: HashMap<i64, Entity> = select_entity(&conn_output, discourse_id)?;
+        let discourse_entities: HashMap<i64, Entity> = encoding
+            .entities
+            .iter()
+            .map(|entity| (i64::from(entity.entity_id), entity.clone()))
+            .collect();
 
         let hash_real: (HashItem, Vec<HashItem>) = get_hash_raw(
-            &discourse,
+            &input_discourse,
             &degree_centralities_real,
             &unit_tensors_real,
-            &sentences_vec,
+            &discourse.sentences,
             &discourse_entities,
             &1,
         )?;
 
-        insert_hash(&conn_output, &hash_real.0, &0, &false)?;
-        let mut i: i32 = 0;
-        for hash_item in &hash_real.1 {
-            insert_hash(&conn_output, &hash_item, &i, &false)?;
-            i = i + 1;
-        }
+        // The discource hypernym portion of the hash goes first.
+        encoding.hash_items_real.push(hash_real.0);
+        encoding.hash_items_real.extend(hash_real.1);
 
         let hash_virtual: (HashItem, Vec<HashItem>) = get_hash_raw(
-            &discourse,
+            &input_discourse,
             &degree_centralities_virtual,
             &unit_tensors_virtual,
-            &sentences_vec,
+            &discourse.sentences,
             &discourse_entities,
             &2,
         )?;
 
-        // Insert the discource hypernym portion of the hash.
-        insert_hash(&conn_output, &hash_virtual.0, &0, &true)?;
-        // Insert the rest of the hash.
-        i = 0;
-        for hash_item in &hash_virtual.1 {
-            insert_hash(&conn_output, &hash_item, &i, &true)?;
-            i = i + 1;
+        encoding.hash_items_virtual.push(hash_virtual.0);
+        encoding.hash_items_virtual.extend(hash_virtual.1);
+    }
+
+    // All done!
+    Ok(encoding)
+}
+
+/// Creates the discourse hash.
+/// Invoke after calling encode_discourse.
+///
+/// Works on the databases in ./storage. See Okeuvo::get_hash
+/// to work on databases elsewhere.
+pub extern "C" fn get_hash(
+    discourse_id: &i32,
+    agrees_to_the_creed: &bool,
+    is_virtual: &bool,
+) -> Result<String, OkeuvoError> {
+    // Check that the user has agreed to The Creed.
+    // Stop processing with an error if they haven't.
+    // The user interface must present a choice whose value we use here.
+    if !agrees_to_the_creed {
+        return Err(OkeuvoError::CreedNotAccepted);
+    }
+
+    let okeuvo = Okeuvo::new(OkeuvoConfig::default())?;
+    let result: String = okeuvo.get_hash(discourse_id, agrees_to_the_creed, is_virtual)?;
+    okeuvo.close()?;
+
+    Ok(result)
+}
+
+/// Creates the discourse hash with a given spec (see HashSpec).
+/// Invoke after calling encode_discourse.
+///
+/// Works on the databases in ./storage. See Okeuvo::get_hash_with_spec
+/// to work on databases elsewhere.
+pub fn get_hash_with_spec(
+    discourse_id: &i32,
+    agrees_to_the_creed: &bool,
+    is_virtual: &bool,
+    spec: &HashSpec,
+) -> Result<String, OkeuvoError> {
+    // Check that the user has agreed to The Creed.
+    // Stop processing with an error if they haven't.
+    // The user interface must present a choice whose value we use here.
+    if !agrees_to_the_creed {
+        return Err(OkeuvoError::CreedNotAccepted);
+    }
+
+    let okeuvo = Okeuvo::new(OkeuvoConfig::default())?;
+    let result: String =
+        okeuvo.get_hash_with_spec(discourse_id, agrees_to_the_creed, is_virtual, spec)?;
+    okeuvo.close()?;
+
+    Ok(result)
+}
+
+/// Does the work of get_hash.
+fn get_hash_inner(
+    conn_output: &Connection,
+    conn_meta: &Connection,
+    discourse_id: &i32,
+    is_virtual: &bool,
+    spec: &HashSpec,
+) -> Result<String, OkeuvoError> {
+    let in_discourse = |e: rusqlite::Error| OkeuvoError::from(e).in_discourse(discourse_id);
+
+    let hash_item_vec: Vec<HashItem> =
+        select_hash_item(conn_output, discourse_id, &(*is_virtual as i32)).map_err(in_discourse)?;
+
+    let versions: Versions = select_encoding_versions(conn_output, discourse_id)
+        .map_err(in_discourse)?
+        .ok_or_else(|| OkeuvoError::InvalidVersion {
+            message: format!(
+                "discourse {} was encoded before versions were recorded; encode it again",
+                discourse_id
+            ),
+        })?;
+    let grid_version: String = select_meaning_grid_version(conn_meta)
+        .map_err(in_discourse)?
+        .unwrap_or_default();
+    let versions: Versions = hash_versions(&versions, &grid_version)?;
+
+    let max_xy: (f64, f64) =
+        select_meaning_grid_max_xy(conn_meta).map_err(|e| e.in_discourse(discourse_id))?;
+
+    format_hash(&hash_item_vec, &max_xy, spec, &versions)
+}
+
+/// Creates the hash of an encoding held in memory, as get_hash does for
+/// one in output.db. The grid supplies the (max x, max y) of the meaning grid.
+pub fn get_encoding_hash(
+    encoding: &Encoding,
+    grid: &MeaningGrid,
+    is_virtual: &bool,
+    agrees_to_the_creed: &bool,
+) -> Result<String, OkeuvoError> {
+    get_encoding_hash_with_spec(
+        encoding,
+        grid,
+        is_virtual,
+        &HashSpec::default(),
+        agrees_to_the_creed,
+    )
+}
+
+/// Creates the hash of an encoding held in memory with a given spec,
+/// as get_hash_with_spec does for one in output.db.
+/// The grid supplies the (max x, max y) of the meaning grid.
+pub fn get_encoding_hash_with_spec(
+    encoding: &Encoding,
+    grid: &MeaningGrid,
+    is_virtual: &bool,
+    spec: &HashSpec,
+    agrees_to_the_creed: &bool,
+) -> Result<String, OkeuvoError> {
+    // Check that the user has agreed to The Creed.
+    // Stop processing with an error if they haven't.
+    // The user interface must present a choice whose value we use here.
+    if !agrees_to_the_creed {
+        return Err(OkeuvoError::CreedNotAccepted);
+    }
+
+    if grid.is_empty() {
+        return Err(OkeuvoError::EmptyMeaningGrid);
+    }
+
+    let versions: Versions = hash_versions(&encoding.versions, grid.version())?;
+
+    format_hash(
+        encoding.hash_items(is_virtual),
+        &grid.max_xy(),
+        spec,
+        &versions,
+    )
+}
+
+/// Gets the apex angles and lean directions of hash items, the discourse
+/// hypernym first, from which apex angle and exact hashes are made
+/// (see ParsedHash::exact). The grid supplies the (max x, max y) of the meaning grid.
+pub fn get_formatted_hash_items(
+    hash_item_vec: &[HashItem],
+    grid: &MeaningGrid,
+    agrees_to_the_creed: &bool,
+) -> Result<Vec<HashItemFormatted>, OkeuvoError> {
+    // Check that the user has agreed to The Creed.
+    // Stop processing with an error if they haven't.
+    // The user interface must present a choice whose value we use here.
+    if !agrees_to_the_creed {
+        return Err(OkeuvoError::CreedNotAccepted);
+    }
+
+    if grid.is_empty() {
+        return Err(OkeuvoError::EmptyMeaningGrid);
+    }
+
+    preformat_hash_items(hash_item_vec, &grid.max_xy())
+}
+
+/// Gets the versions a hash of an encoding is tagged with: those the encoding
+/// was made with. The meaning grid that supplies the (max x, max y) of the hash
+/// must be the one the encoding was made with. Encodings that don't record
+/// their grid version (see encode) take that of the grid.
+fn hash_versions(
+    encoding_versions: &Versions,
+    grid_version: &str,
+) -> Result<Versions, OkeuvoError> {
+    let grid_version: &str = version_number(grid_version);
+    let mut versions: Versions = encoding_versions.clone();
+
+    if versions.grid.is_empty() {
+        versions.grid = grid_version.to_string();
+    } else if versions.grid != grid_version {
+        return Err(OkeuvoError::InvalidVersion {
+            message: format!(
+                "the discourse was encoded with meaning grid version {}, not {}",
+                versions.grid, grid_version
+            ),
+        });
+    }
+
+    versions.validate()?;
+
+    Ok(versions)
+}
+
+/// Formats hash items, the discourse hypernym first, into a hash
+/// with a given spec (see ParsedHash for the formats), tagged with versions.
+fn format_hash(
+    hash_item_vec: &[HashItem],
+    max_xy: &(f64, f64),
+    spec: &HashSpec,
+    versions: &Versions,
+) -> Result<String, OkeuvoError> {
+    spec.validate()?;
+
+    match spec.format {
+        HashFormat::ApexAngle => {
+            let preformatted_hash_items: Vec<HashItemFormatted> =
+                preformat_hash_items(hash_item_vec, max_xy)?;
+
+            Ok(round_pad_records(&preformatted_hash_items, spec, versions).to_string())
+        }
+        HashFormat::AreaRatio => {
+            Ok(area_ratio_records(hash_item_vec, max_xy, spec, versions).to_string())
+        }
+        HashFormat::Exact => {
+            let preformatted_hash_items: Vec<HashItemFormatted> =
+                preformat_hash_items(hash_item_vec, max_xy)?;
+
+            Ok(exact_records(&preformatted_hash_items, spec, versions).to_string())
         }
     }
+}
+
+/// Measures how similar two hashes (see get_hash) are.
+///
+/// Records at the same rank are compared by the distance between their apex
+/// angles, penalized for differing lean directions. The score is the mean of
+/// the record scores weighted by rank, the discourse hypernym counting the
+/// most, from 0 (unrelated) to 1 (the same).
+/// A record facing padding scores 0; ranks where both hashes are padding are skipped.
+/// Hashes in different formats can't be compared.
+pub fn compare_hashes(
+    hash1: &str,
+    hash2: &str,
+    agrees_to_the_creed: &bool,
+) -> Result<HashSimilarity, OkeuvoError> {
+    // Check that the user has agreed to The Creed.
+    // Stop processing with an error if they haven't.
+    // The user interface must present a choice whose value we use here.
+    if !agrees_to_the_creed {
+        return Err(OkeuvoError::CreedNotAccepted);
+    }
+
+    let parsed_hash1: ParsedHash = ParsedHash::parse(hash1)?;
+    let parsed_hash2: ParsedHash = ParsedHash::parse(hash2)?;
+
+    parsed_hash1.similarity(&parsed_hash2)
+}
+
+/// Measures how similar two sets of hash items, the discourse hypernym first,
+/// are, as compare_hashes does for their hashes but without rounding
+/// the apex angles. The grid supplies the (max x, max y) of the meaning grid.
+pub fn compare_hash_items(
+    hash_item_vec1: &[HashItem],
+    hash_item_vec2: &[HashItem],
+    grid: &MeaningGrid,
+    agrees_to_the_creed: &bool,
+) -> Result<HashSimilarity, OkeuvoError> {
+    // Check that the user has agreed to The Creed.
+    // Stop processing with an error if they haven't.
+    // The user interface must present a choice whose value we use here.
+    if !agrees_to_the_creed {
+        return Err(OkeuvoError::CreedNotAccepted);
+    }
+
+    if grid.is_empty() {
+        return Err(OkeuvoError::EmptyMeaningGrid);
+    }
+
+    let max_xy: (f64, f64) = grid.max_xy();
+    let formatted_hash_items1: Vec<HashItemFormatted> =
+        preformat_hash_items(hash_item_vec1, &max_xy)?;
+    let formatted_hash_items2: Vec<HashItemFormatted> =
+        preformat_hash_items(hash_item_vec2, &max_xy)?;
+
+    Ok(compare_records(
+        &formatted_comparable_records(&formatted_hash_items1),
+        &formatted_comparable_records(&formatted_hash_items2),
+    ))
+}
+
+/// Creates the envelope of the hash of a discourse (see HashEnvelope), binding the
+/// hash to the document_hash, author_public_hash and date_unix_epoch of the
+/// discourse, the versions and the Ed25519 public key of the author.
+/// Invoke after calling encode_discourse.
+///
+/// Works on the databases in ./storage. See Okeuvo::get_hash_envelope
+/// to work on databases elsewhere.
+#[cfg(feature = "signing")]
+pub fn get_hash_envelope(
+    discourse_id: &i32,
+    agrees_to_the_creed: &bool,
+    is_virtual: &bool,
+    spec: &HashSpec,
+    author_public_key: &[u8; 32],
+) -> Result<HashEnvelope, OkeuvoError> {
+    // Check that the user has agreed to The Creed.
+    // Stop processing with an error if they haven't.
+    // The user interface must present a choice whose value we use here.
+    if !agrees_to_the_creed {
+        return Err(OkeuvoError::CreedNotAccepted);
+    }
+
+    let okeuvo = Okeuvo::new(OkeuvoConfig::default())?;
+    let result: HashEnvelope = okeuvo.get_hash_envelope(
+        discourse_id,
+        agrees_to_the_creed,
+        is_virtual,
+        spec,
+        author_public_key,
+    )?;
+    okeuvo.close()?;
+
+    Ok(result)
+}
+
+/// Signs a hash envelope with the Ed25519 secret key of its author,
+/// adding the SHA-256 digest of the envelope and the signature of the digest.
+///
+/// Return format: the signed envelope, which displays as the record
+/// to submit to the ledger.
+#[cfg(feature = "signing")]
+pub fn sign_hash_envelope(
+    envelope: &HashEnvelope,
+    secret_key: &[u8; 32],
+    agrees_to_the_creed: &bool,
+) -> Result<SignedEnvelope, OkeuvoError> {
+    // Check that the user has agreed to The Creed.
+    // Stop processing with an error if they haven't.
+    // The user interface must present a choice whose value we use here.
+    if !agrees_to_the_creed {
+        return Err(OkeuvoError::CreedNotAccepted);
+    }
 
-    // All done!
-    if let Err(e) = conn_input.close() {
-        Err(e.1.to_string())?
-    }
-    if let Err(e) = conn_output.close() {
-        Err(e.1.to_string())?
+    envelope.sign(secret_key)
+}
+
+/// Verifies a signed hash envelope record (see SignedEnvelope): its digest must be
+/// that of the envelope and its signature that of the digest by the author.
+///
+/// Return format: the signed envelope.
+#[cfg(feature = "signing")]
+pub fn verify_hash_envelope(
+    record: &str,
+    agrees_to_the_creed: &bool,
+) -> Result<SignedEnvelope, OkeuvoError> {
+    // Check that the user has agreed to The Creed.
+    // Stop processing with an error if they haven't.
+    // The user interface must present a choice whose value we use here.
+    if !agrees_to_the_creed {
+        return Err(OkeuvoError::CreedNotAccepted);
     }
 
-    Ok(())
+    let signed_envelope: SignedEnvelope = SignedEnvelope::parse(record)?;
+    signed_envelope.verify()?;
+
+    Ok(signed_envelope)
 }
 
-/// Creates the discourse hash.
-/// Invoke after calling encode_discourse.
-pub extern "C" fn get_hash(
+/// Explains the encoding of a discourse for human inspection.
+/// The hash items stored for the discourse by encode_discourse are read back
+/// from output.db in hash order and, for each, the k meaning grid concepts
+/// nearest to its ground state and excited state coordinates are returned,
+/// along with their distances.
+///
+/// Works on the databases in ./storage. See Okeuvo::explain_hash
+/// to work on databases elsewhere.
+pub fn explain_hash(
     discourse_id: &i32,
-    agrees_to_the_creed: &bool,
     is_virtual: &bool,
-) -> Result<String, Box<dyn std::error::Error>> {
+    index: &MeaningGridIndex,
+    k: &usize,
+    agrees_to_the_creed: &bool,
+) -> Result<Vec<HashItemExplanation>, OkeuvoError> {
     // Check that the user has agreed to The Creed.
     // Stop processing with an error if they haven't.
     // The user interface must present a choice whose value we use here.
     if !agrees_to_the_creed {
-        Err("You must agree to The Creed to continue".to_string())?
-    }
-    // Allow a hash that's 199 characters long
-    // That's n * element length + n - 1
-    // => 20 * 9 + 20 - 1 = 199.
-    let max_rows: usize = 20;
-
-    let mut virtual_marker = 1;
-    if *is_virtual {
-        virtual_marker = 0;
+        return Err(OkeuvoError::CreedNotAccepted);
     }
 
-    let conn_output = Connection::open(OUTPUTPATH)?;
-    let hash_item_vec: Vec<HashItem> =
-        select_hash_item(&conn_output, &discourse_id, &virtual_marker)?;
-
-    let preformatted_hash_items: Vec<HashItemFormatted> = preformat_hash_items(&hash_item_vec)?;
+    let okeuvo = Okeuvo::new(OkeuvoConfig::default())?;
+    let result: Vec<HashItemExplanation> =
+        okeuvo.explain_hash(discourse_id, is_virtual, index, k, agrees_to_the_creed)?;
+    okeuvo.close()?;
 
-    let formatted_hash_items: Vec<String> =
-        round_pad_stringulate(&preformatted_hash_items, &max_rows)?;
+    Ok(result)
+}
 
-    if let Err(e) = conn_output.close() {
-        Err(e.1.to_string())?
+/// Explains a set of hash items (such as those returned by top_contributors)
+/// by the k meaning grid concepts nearest to the ground state and excited
+/// state coordinates of each item.
+pub fn explain_hash_items(
+    hash_item_vec: &[HashItem],
+    index: &MeaningGridIndex,
+    k: &usize,
+    agrees_to_the_creed: &bool,
+) -> Result<Vec<HashItemExplanation>, OkeuvoError> {
+    // Check that the user has agreed to The Creed.
+    // Stop processing with an error if they haven't.
+    // The user interface must present a choice whose value we use here.
+    if !agrees_to_the_creed {
+        return Err(OkeuvoError::CreedNotAccepted);
     }
 
-    // Complete the hash by joining the vector to string with a "-" separator.
-    let result: String = formatted_hash_items.join("-");
-
-    Ok(result)
+    Ok(explain_hash_items_inner(hash_item_vec, index, *k))
 }
 
 /// Gets a unique angle for each vector as well as an indicator
@@ -434,14 +1143,15 @@ pub extern "C" fn get_hash(
 /// The indicator of the direction in which the apex angle falls
 /// is a function of the length of the limbs between the apex
 /// angle and the triangle vertices on the x axis.
+/// The parameter, max_xy is the (max x, max y) of the meaning grid
+/// (see MeaningGrid::max_xy).
 fn preformat_hash_items(
-    hash_item_vec: &Vec<HashItem>,
-) -> Result<Vec<HashItemFormatted>, Box<dyn std::error::Error>> {
+    hash_item_vec: &[HashItem],
+    max_xy: &(f64, f64),
+) -> Result<Vec<HashItemFormatted>, OkeuvoError> {
     // Note that calculations are done with the direction set as "max x --> origin".
     let mut result: Vec<HashItemFormatted> = Vec::new();
 
-    let max_xy: (f64, f64) = select_meaning_grid_max_xy()?;
-
     for item in hash_item_vec {
         // Apply cosine formula to get length of last triangle side.
         let a1_squared: f64 = (item.radius.powi(2) + max_xy.0.powi(2))
@@ -454,14 +1164,14 @@ fn preformat_hash_items(
         let a2 = a2_squared.sqrt();
 
         // Apply cosine formula to get angle opposite item.angle along x (max_xy.0).
-        let angle1_opp_cos: f64 = (a1_squared + max_xy.0.powi(2) - item.radius.powi(2))
-            / (2.0 * a1_squared * max_xy.0.powi(2));
-        let angle1_opp: f64 = angle1_opp_cos.acos();
+        let angle1_opp_cos: f64 =
+            (a1_squared + max_xy.0.powi(2) - item.radius.powi(2)) / (2.0 * a1 * max_xy.0);
+        let angle1_opp: f64 = angle1_opp_cos.clamp(-1.0, 1.0).acos();
 
         // Apply cosine formula to get angle opposite excited_angle along x (max_xy.0).
-        let angle2_opp_cos: f64 = (a2_squared + max_xy.0.powi(2) - item.excited_radius.powi(2))
-            / (2.0 * a2_squared * max_xy.0.powi(2));
-        let angle2_opp: f64 = angle2_opp_cos.acos();
+        let angle2_opp_cos: f64 =
+            (a2_squared + max_xy.0.powi(2) - item.excited_radius.powi(2)) / (2.0 * a2 * max_xy.0);
+        let angle2_opp: f64 = angle2_opp_cos.clamp(-1.0, 1.0).acos();
 
         // Get upper angle.
         let upper_angle = std::f64::consts::PI - item.angle - angle1_opp;
@@ -494,61 +1204,6 @@ fn preformat_hash_items(
     Ok(result)
 }
 
-/// Does the final formatting of hashes.
-/// Rounds floats that denote polar angles and radii to integers.
-/// Truncates the hash vector to a fixed length.
-/// Pads resulting integers less than ten with a leading "0",
-/// so that each element is two characters long.
-/// Inserts a "!" between each element of the sections of the hash.
-fn round_pad_stringulate(
-    formatted_hash_items: &Vec<HashItemFormatted>,
-    max_rows: &usize,
-) -> Result<Vec<String>, Box<dyn std::error::Error>> {
-    let mut result: Vec<String> = Vec::new();
-    let length = &formatted_hash_items.len();
-
-    for i in 1..*length {
-        let item = &formatted_hash_items[i];
-        let mut hash_local = item.right_leaning.to_string();
-        hash_local.push_str(&"!".to_string());
-        // Ensure upper_angle is two digits long.
-        let upper_angle = item.upper_angle.round() as i32;
-        if upper_angle < 10 {
-            hash_local.push_str("0");
-        }
-        hash_local.push_str(&upper_angle.to_string());
-        hash_local.push_str(&"!".to_string());
-        hash_local.push_str(&item.right_leaning_excited.to_string());
-        hash_local.push_str(&"!".to_string());
-        // Ensure excited upper_angle is two digits long.
-        let upper_angle_excited = item.upper_angle.round() as i32;
-        if upper_angle_excited < 10 {
-            hash_local.push_str("0");
-        }
-        hash_local.push_str(&item.upper_angle_excited.round().to_string());
-
-        result.push(hash_local);
-
-        // Truncate vectors that are longer than max_rows,
-        if i == *max_rows {
-            break;
-        }
-    }
-
-    // Pad vectors that are shorter than max_rows,
-    // replace all values with "#" to signify that
-    // they are empty.
-    if result.len() < *max_rows {
-        let start: usize = result.len();
-        for _i in start..*max_rows {
-            let empty: String = "#!##!#!##".to_string();
-            result.push(empty);
-        }
-    }
-
-    Ok(result)
-}
-
 /// Gets a vector collection of entities sorted in descending order by aggregate excitation.
 /// This vector of entities will be the input to the hash function (get_hash).
 /// Only entities that are either coreferenced or have a degree centrality above zero are considered.
@@ -556,10 +1211,10 @@ fn get_hash_raw(
     discourse: &InputDiscourse,
     degree_centralities: &HashMap<i64, i32>,
     unit_tensors: &Vec<UnitTensor>,
-    sentences_vec: &Vec<InputSentence>,
+    sentences_vec: &[InputSentence],
     discourse_entities: &HashMap<i64, Entity>,
     hash_type: &i32,
-) -> Result<(HashItem, Vec<HashItem>), Box<dyn std::error::Error>> {
+) -> Result<(HashItem, Vec<HashItem>), OkeuvoError> {
     // Get the hash_item for the hypernym of this dicourse.
     // This item will be at the beginning of the discourses hash.
 
@@ -567,7 +1222,8 @@ fn get_hash_raw(
     // 1. areal_jaccard (shared properties).
 
     let mut hash_item_vec: Vec<HashItem> = Vec::new();
-    let mut excitation_totals: HashMap<i64, Vector2D> = HashMap::new();
+    // Ordered by entity_id, so that sums and ties in the hash order are the same on every run.
+    let mut excitation_totals: BTreeMap<i64, Vector2D> = BTreeMap::new();
 
     for item in unit_tensors {
         // Disallow questions.
@@ -656,8 +1312,8 @@ fn get_hash_raw(
 
             // Get the excitation polar coordinates of the current entity.
             let excitation_polar_coord: (f64, f64) = cartesian_to_polar(&Point2D {
-                x: entity.x,
-                y: entity.y,
+                x: item_complexity_plus_excitation.end.x,
+                y: item_complexity_plus_excitation.end.y,
             });
 
             let hash_item = HashItem {
@@ -777,34 +1433,55 @@ fn is_triplet_transitional(input_words: &HashMap<i32, InputWord>) -> bool {
 /// Excitation is the value of complexity added to
 /// the subject in a specific interaction within
 /// a broader comminication.
-///
-/// Return format: Vec<(subject word_id, excitation)>.
+/// It is the sum of the vectors of the words in the sections of the triplet
+/// and of their modifiers (see get_modifiers), less the subject itself.
+/// Each word counts once.
+/// Words are summed in word_id order, so the excitation is the same on every run.
+/// Function words count as function_words sets out (see FunctionWords::weights).
 fn get_excitation(
+    discourse: &Discourse,
     input_words: &HashMap<i32, InputWord>,
-    sections_pruned: &HashMap<i32, (i32, i32, i32)>,
-) -> (i32, ExcitationData) {
-    let mut subject_word_id: i32 = -1;
-    if let Some(pruned_row) = sections_pruned.iter().nth(0) {
-        subject_word_id = (pruned_row.1).0;
-    }
-
+    triplet_id: &i32,
+    subject_word_id: &i32,
+    function_words: &FunctionWords,
+) -> ExcitationData {
     let mut resultant = Vector2D {
         start: Point2D { x: 0.0, y: 0.0 },
         end: Point2D { x: 0.0, y: 0.0 },
     };
 
-    // Sum up resultants.
-    for word in input_words {
+    // The words of the triplet's sections and their modifiers.
+    let mut word_ids: BTreeSet<i32> = BTreeSet::new();
+    for section in discourse.sections_by_triplet(triplet_id) {
+        word_ids.insert(section.word_id);
+        word_ids.extend(get_modifiers(discourse, &section.word_id));
+    }
+    let words: Vec<&InputWord> = word_ids
+        .iter()
+        .filter_map(|word_id| input_words.get(word_id))
+        .collect();
+
+    // Format - HashMap<word_id, word_id of its head>.
+    let heads: HashMap<i32, i32> = discourse
+        .relations
+        .iter()
+        .filter(|relation| word_ids.contains(&relation.word_id))
+        .map(|relation| (relation.word_id, relation.word_id_modified))
+        .collect();
+    let weights: HashMap<i32, f64> = function_words.weights(&words, &heads);
+
+    for word in words {
         // Don't add the subject vector.
-        if (word.1).word_id == subject_word_id {
+        if word.word_id == *subject_word_id {
             continue;
         }
 
+        let weight: f64 = weights[&word.word_id];
         let vector = Vector2D {
             start: Point2D { x: 0.0, y: 0.0 },
             end: Point2D {
-                x: (word.1).x,
-                y: (word.1).y,
+                x: word.x * weight,
+                y: word.y * weight,
             },
         };
 
@@ -812,17 +1489,40 @@ fn get_excitation(
     }
 
     let magnitude: f64 = vector_magnitude_2d(&resultant);
-    let excitation = ExcitationData {
+    let result = ExcitationData {
         magnitude,
         x: resultant.end.x,
         y: resultant.end.y,
     };
 
-    let result: (i32, ExcitationData) = (subject_word_id, excitation);
-
     result
 }
 
+/// Gets the focus word of a section of a triplet from the words of its sentence.
+/// Fails with OkeuvoError::MissingTripletSection if the section has no focus
+/// (word_id is -1), or with OkeuvoError::MissingWord if the word is not in the sentence.
+fn get_section_focus<'a>(
+    input_words: &'a HashMap<i32, InputWord>,
+    sentence_id: &i32,
+    triplet_id: &i32,
+    section_type: &i32,
+    word_id: &i32,
+) -> Result<&'a InputWord, OkeuvoError> {
+    if *word_id == -1 {
+        return Err(OkeuvoError::MissingTripletSection {
+            sentence_id: *sentence_id,
+            triplet_id: *triplet_id,
+            section_type: *section_type,
+        });
+    }
+
+    input_words.get(word_id).ok_or(OkeuvoError::MissingWord {
+        sentence_id: *sentence_id,
+        triplet_id: *triplet_id,
+        word_id: *word_id,
+    })
+}
+
 /// Creates a new Entity with entity_id set to -1.
 fn create_entity(word: &InputWord, rank: &f64, triplet_id: &i32, discourse_id: &i32) -> Entity {
     let result = Entity {
@@ -853,6 +1553,7 @@ fn create_unit_tensor(
     mood: &String,
     excited_x: &f64,
     excited_y: &f64,
+    is_negated: &bool,
 ) -> UnitTensor {
     let result = UnitTensor {
         unit_tensor_id: -1,
@@ -867,26 +1568,89 @@ fn create_unit_tensor(
         mood: mood.clone(),
         excited_x: *excited_x,
         excited_y: *excited_y,
+        is_negated: *is_negated,
     };
 
     result
 }
 
-/// Swaps subject and object of passive sections.
-/// The parameter section has signature
+/// Swaps subject and object of a passive section.
+/// The parameter section has signature (word_id, word_id, word_id),
+/// which is also the signature of the value returned.
+fn swap_passive_subject_object(section: &(i32, i32, i32)) -> (i32, i32, i32) {
+    (section.2, section.1, section.0)
+}
+
+/// Adds an entity to an encoding, giving it the next entity_id.
+/// An entity that is an instance (instance_index above 0) already in the
+/// encoding replaces the existing one and keeps its entity_id.
 ///
-/// HashMap<triplet_id, (word_id, word_id, word_id)>.
+/// Return format: entity_id.
+fn add_or_update_entity(encoding: &mut Encoding, mut entity: Entity) -> i64 {
+    // 0 is the default value, so it doesn't count in the check for existing.
+    if entity.instance_index > 0 {
+        if let Some(existing) = encoding
+            .entities
+            .iter_mut()
+            .find(|existing| existing.instance_index == entity.instance_index)
+        {
+            entity.entity_id = existing.entity_id;
+            *existing = entity;
+
+            return i64::from(existing.entity_id);
+        }
+    }
+
+    entity.entity_id = encoding.entities.len() as i32 + 1;
+    let entity_id = i64::from(entity.entity_id);
+    encoding.entities.push(entity);
+
+    entity_id
+}
+
+/// Gets unit tensors filtered by real or virtual.
+fn get_unit_tensors(encoding: &Encoding, is_virtual: &bool) -> Vec<UnitTensor> {
+    encoding
+        .unit_tensors
+        .iter()
+        .filter(|unit_tensor| {
+            encoding.is_virtual_unit_tensor(&unit_tensor.unit_tensor_id) == *is_virtual
+        })
+        .cloned()
+        .collect()
+}
+
+/// Get degree centrality of entities in unit tensors filtered by real or virtual.
+/// Only coreferenced items are considered.
 ///
-/// The function returns a value with the same signature above, but wrapped in a Result.
-fn swap_passive_subject_object(
-    sections: &mut HashMap<i32, (i32, i32, i32)>,
-    triplet_id: &i32,
-) -> Result<(), Box<dyn std::error::Error>> {
-    let word_ids: (i32, i32, i32) = sections[&triplet_id];
-    // Swap subject and object word_ids and update sections.
-    sections.insert(*triplet_id, (word_ids.2, word_ids.1, word_ids.0));
+/// Return format: HashMap<entity_id, degree centrality>.
+fn get_unit_tensor_centrality(encoding: &Encoding, is_virtual: &bool) -> HashMap<i64, i32> {
+    let mut result: HashMap<i64, i32> = HashMap::new();
+
+    for unit_tensor in get_unit_tensors(encoding, is_virtual) {
+        let mut entity_ids: Vec<i64> = vec![
+            unit_tensor.object_entity_id,
+            unit_tensor.subject_entity_id,
+            unit_tensor.predicate_entity_id,
+        ];
+        // Every "where" and "when", the first of which the unit tensor holds.
+        entity_ids.extend(
+            encoding
+                .locations(&unit_tensor.unit_tensor_id)
+                .iter()
+                .map(|location| location.entity_id),
+        );
 
-    Ok(())
+        for entity_id in &entity_ids {
+            if let Some(entity) = encoding.entity(entity_id) {
+                if entity.instance_index > 0 {
+                    *result.entry(*entity_id).or_insert(0) += 1;
+                }
+            }
+        }
+    }
+
+    result
 }
 
 /*/// Swaps subject and object of passive sections.
@@ -907,7 +1671,7 @@ fn swap_passive_subject_object(
 /// Result format: Result<HashMap<(word_id, word_id, word_id), Vec<triplet_id>>.
 fn get_section_groups(
     subject_predicate_object: HashMap<i32, (i32, i32, i32)>,
-) -> Result<HashMap<(i32, i32, i32), Vec<i32>>, Box<dyn std::error::Error>> {
+) -> Result<HashMap<(i32, i32, i32), Vec<i32>>, OkeuvoError> {
     let mut result: HashMap<(i32, i32, i32), Vec<i32>> = HashMap::new();
 
     for item in &subject_predicate_object {
@@ -925,63 +1689,98 @@ fn get_section_groups(
     Ok(result)
 }
 
-/// Yields the dominant word (subject, predicate or object) in each section of the section.
-/// It returns a HashMap that has section_id's as keys and a tuple of word_id's as values.
+/// Grades of the Universal Dependency relations that mark the likely focus of a section.
+/// Relations are matched by containment, so subtypes (such as nsubj:pass)
+/// take the grade of their type.
+const SECTION_FOCUS_GRADES: &[(&str, i32)] = &[
+    ("nsubj", 40),
+    ("obj", 40),
+    ("iobj", 40),
+    ("csubj", 39),
+    ("ccomp", 39),
+    ("xcomp", 39),
+    ("nmod", 38),
+    ("appos", 38),
+    ("nummod", 38),
+    ("amod", 38),
+    ("acl", 38),
+];
+
+/// Gets the highest grade (see SECTION_FOCUS_GRADES) of the relations in which
+/// a word is the dependent, or None if none of them is graded.
+fn get_section_focus_grade(discourse: &Discourse, word_id: &i32) -> Option<i32> {
+    discourse
+        .relations_by_word(word_id)
+        .iter()
+        .flat_map(|relation| {
+            SECTION_FOCUS_GRADES
+                .iter()
+                .filter(move |(ud_relation, _)| relation.ud_relation.contains(ud_relation))
+                .map(|(_, grade)| *grade)
+        })
+        .max()
+}
+
+/// Yields the dominant word (subject, predicate or object) in each section of a triplet.
+/// A value of -1 marks a section that has no words.
 ///
-/// Return format: HashMap<triplet_id, (word_id, word_id, word_id)>.
-fn get_subject_predicate_object(
-    conn: &Connection,
-    triplet_id: &i32,
-) -> Result<HashMap<i32, (i32, i32, i32)>, rusqlite::Error> {
-    // result is a HashMap that has section_id's as keys and a tuple of word_id's as values.
-    let mut result: HashMap<i32, (i32, i32, i32)> = HashMap::new();
+/// Return format: (word_id, word_id, word_id).
+fn get_subject_predicate_object(discourse: &Discourse, triplet_id: &i32) -> (i32, i32, i32) {
+    let mut section = (-1, -1, -1);
 
-    // Fetch all sections.
-    let sections_ranked: Vec<InputSection> = select_input_section_all_ranked(&conn, &triplet_id)?;
+    // Rank all sections of the triplet, so that the first item in each section type
+    // is most likely the subject, predicate or object (depending on section type).
+    // Words with no graded relation come last.
+    let mut sections_ranked: Vec<(&InputSection, Option<i32>)> = discourse
+        .sections_by_triplet(triplet_id)
+        .into_iter()
+        .map(|item| (item, get_section_focus_grade(discourse, &item.word_id)))
+        .collect();
+    sections_ranked.sort_by(|a, b| {
+        (a.0.section_type)
+            .cmp(&b.0.section_type)
+            .then_with(|| b.1.cmp(&a.1))
+    });
 
-    // Tracker to mark if best candidate word_id has been chosen for
-    // each unique combination of section_id and section type.
-    // Format - Vec<(section_id, section_type, word_id)>
+    // Tracker to mark if best candidate word_id has been chosen for each section type.
     let mut processed_section_type: i32 = 0;
-    let mut section = (-1, -1, -1);
 
-    // sections_ranked is sorted so that the first item in each section_id-section_type group
-    // is most likely the subject, predicate or object (depending on section type).
-    // We will temporarily assign it to the sections tuple pending further best candidate checks.
-    for item in &sections_ranked {
+    // We will temporarily assign the first item of each section type to the sections
+    // tuple pending further best candidate checks.
+    for (item, _) in &sections_ranked {
         if item.section_type <= processed_section_type {
             continue;
         }
-        if item.section_type == 1 {
-            section.0 =
-                further_subject_predicate_object_checks(&conn, item.word_id, &item.section_id)?;
-            processed_section_type = 1;
-        }
-        if item.section_type == 2 {
-            section.1 =
-                further_subject_predicate_object_checks(&conn, item.word_id, &item.section_id)?;
-            processed_section_type = 1;
-        }
-        if item.section_type == 3 {
-            section.2 =
-                further_subject_predicate_object_checks(&conn, item.word_id, &item.section_id)?;
-            break;
+
+        let word_id: i32 = further_subject_predicate_object_checks(
+            discourse,
+            item.word_id,
+            triplet_id,
+            &item.section_type,
+        );
+
+        match item.section_type {
+            1 => section.0 = word_id,
+            2 => section.1 = word_id,
+            3 => section.2 = word_id,
+            _ => {}
         }
-    }
 
-    result.insert(*triplet_id, section);
+        processed_section_type = item.section_type;
+    }
 
-    Ok(result)
+    section
 }
 
-/// Returns the best candidate word_id for the focus of a triplet (subject, predicate or object).
+/// Returns the best candidate word_id for the focus of a triplet section (subject, predicate or object).
 ///
-/// Return format: Result<word_id, error>.
+/// Return format: word_id.
 fn further_subject_predicate_object_checks(
-    conn: &Connection,
+    discourse: &Discourse,
     temporary_word_id: i32,
-    section_id: &i32,
-) -> Result<i32, rusqlite::Error> {
+    triplet_id: &i32,
+    section_type: &i32,
+) -> i32 {
     let mut result: i32 = temporary_word_id;
 
     // Grade the feature groups.
@@ -992,48 +1791,47 @@ fn further_subject_predicate_object_checks(
     weights.insert("reflex".to_string(), 2);
     weights.insert("animacy".to_string(), 1);
 
-    let temporary_word_features: Vec<InputWordFeature> =
-        select_input_features_by_word_id(&conn, &temporary_word_id)?;
+    let temporary_word_features: Vec<&InputWordFeature> =
+        discourse.features_by_word(&temporary_word_id);
     let mut temporary_word_max_weight: i32 = 0;
 
     // Get the maximum weight group for temporary_word_id's features.
     for feature in &temporary_word_features {
-        if weights.contains_key(&feature.ud_feature.to_lowercase()) {
-            if let Some(weight) = weights.get(&feature.ud_feature.to_lowercase()) {
-                // Update temporary_word_max_weight if a greater weight is found.
-                if weight > &temporary_word_max_weight {
-                    temporary_word_max_weight = *weight;
-                }
+        if let Some(weight) = weights.get(&feature.ud_feature.to_lowercase()) {
+            // Update temporary_word_max_weight if a greater weight is found.
+            if weight > &temporary_word_max_weight {
+                temporary_word_max_weight = *weight;
             }
         }
     }
 
+    // Gets the value of one of temporary_word_id's features.
+    let temporary_word_feature_value = |feature_tag: &str| -> Option<String> {
+        temporary_word_features
+            .iter()
+            .find(|feature| feature.ud_feature.to_lowercase() == feature_tag)
+            .map(|feature| feature.ud_feature_value.to_lowercase())
+    };
+
     // Vector to hold candidates for focus of the section (feature.word_id, weight, feature_tag, feature_value).
     let mut candidates: Vec<(i32, i32, String, String)> = Vec::new();
-    // Fetch features for all words in the section.
-    let features_by_sentence_and_section: Vec<InputWordFeature> =
-        select_input_features_by_sentence_and_section_and_word(
-            &conn,
-            &section_id,
-            &temporary_word_id,
-        )?;
 
     // Test for more suitable candidates for the focus of the current section by
-    // comparing their feature group weights to temporary_word_max_weight.
-    for feature in &features_by_sentence_and_section {
-        let feature_tag: String = feature.ud_feature.to_lowercase();
-        let feature_value: String = feature.ud_feature_value.to_lowercase();
-        if weights.contains_key(&feature_tag) {
-            if let Some(weight) = weights.get(&feature.ud_feature.to_lowercase()) {
+    // comparing the feature group weights of the other words in the section
+    // to temporary_word_max_weight.
+    for item in discourse.sections_by_triplet(triplet_id) {
+        if item.section_type != *section_type || item.word_id == temporary_word_id {
+            continue;
+        }
+
+        for feature in discourse.features_by_word(&item.word_id) {
+            let feature_tag: String = feature.ud_feature.to_lowercase();
+            let feature_value: String = feature.ud_feature_value.to_lowercase();
+            if let Some(weight) = weights.get(&feature_tag) {
                 // Update candidates with current feature if its weight is
                 // greater than or equal to temporary_word_max_weight.
                 if weight >= &temporary_word_max_weight {
-                    candidates.push((
-                        feature.word_id,
-                        *weight,
-                        feature_tag.clone(),
-                        feature_value.clone(),
-                    ));
+                    candidates.push((feature.word_id, *weight, feature_tag, feature_value));
                 }
             }
         }
@@ -1044,86 +1842,42 @@ fn further_subject_predicate_object_checks(
     candidates.sort_by(|a, b| b.1.cmp(&a.1));
 
     for item in &candidates {
-        if item.1 > temporary_word_max_weight {
-            let mut can_proceed: bool = false;
-
-            if item.2 == "prontype".to_string() && item.3 == "prs".to_string() {
-                can_proceed = true;
-            } /*else {
-                can_proceed = true;
-            }*/
-
-            if can_proceed {
-                result = item.0;
-                break;
-            }
+        if item.1 > temporary_word_max_weight && item.2 == "prontype" && item.3 == "prs" {
+            result = item.0;
+            break;
         }
 
         // When weight = temporary_word_max_weight, we must check the individual feature values for primacy.
         if item.1 == temporary_word_max_weight {
-            if item.2 == "person".to_string() {
+            if item.2 == "person" {
                 // Vaue gradient to represent grammatical person values, from 1st to 4th.
-                let gradient: String = "12345".to_string();
-                // Create a collection from the gradients chars.
-                let mut iter = gradient.chars();
-                // Convert the current items feature value into a char.
-                if let Some(ch1) = item.3.chars().nth(0) {
-                    // Get the position of the current items feature value (ch1) in the gradient.
-                    if let Some(item_index) = iter.position(|x| x == ch1) {
-                        for feature in &temporary_word_features {
-                            // Find the feature value corresponding to "person" in temporary_word_features.
-                            if feature.ud_feature == "person" {
-                                // Get its position.
-                                if let Some(temp_index) =
-                                    feature.ud_feature_value.chars().position(|x| x == ch1)
-                                {
-                                    // set result to the word_id of the current item if
-                                    // its person is less than the current item's.
-                                    if item_index < temp_index {
-                                        result = item.0;
-                                        break;
-                                    }
-                                }
-                            }
-                        }
-                    }
-                }
-            }
+                let gradient: &str = "12345";
 
-            /* These don't matter when feature equals "person".
-            if item.2 == "poss".to_string() {
-                result = item.0;
-                break;
-            }
+                // Set result to the word_id of the current item if its
+                // person comes before temporary_word_id's in the gradient.
+                let item_index = item.3.chars().next().and_then(|ch| gradient.find(ch));
+                let temp_index = temporary_word_feature_value("person")
+                    .and_then(|value| value.chars().next())
+                    .and_then(|ch| gradient.find(ch));
 
-            if item.2 == "prontype".to_string() && item.3 == "prs".to_string() {
-                result = item.0;
-                break;
+                if let (Some(item_index), Some(temp_index)) = (item_index, temp_index) {
+                    if item_index < temp_index {
+                        result = item.0;
+                        break;
+                    }
+                }
             }
 
-            if item.2 == "reflex".to_string() {
-                result = item.0;
-                break;
-            }*/
-
-            if item.2 == "animacy".to_string() {
+            if item.2 == "animacy" {
                 // vector to be used to grade animacy types (by index).
-                let animacy_vec: Vec<String> = vec![
-                    "hum".to_string(),
-                    "anim".to_string(),
-                    "nhum".to_string(),
-                    "inan".to_string(),
-                ];
+                let animacy_vec: Vec<&str> = vec!["hum", "anim", "nhum", "inan"];
 
                 // Get indices.
-                if let Some(item_index) = animacy_vec.iter().position(|x| x.to_string() == item.3) {
-                    let mut temp_index = 6;
-                    for (i, feature) in temporary_word_features.iter().enumerate() {
-                        if feature.ud_feature_value == "poss" {
-                            temp_index = i;
-                            break;
-                        }
-                    }
+                // Words with no animacy value come after every animacy type.
+                if let Some(item_index) = animacy_vec.iter().position(|x| *x == item.3) {
+                    let temp_index: usize = temporary_word_feature_value("animacy")
+                        .and_then(|value| animacy_vec.iter().position(|x| *x == value))
+                        .unwrap_or(animacy_vec.len());
 
                     if item_index < temp_index {
                         result = item.0;
@@ -1134,93 +1888,42 @@ fn further_subject_predicate_object_checks(
         }
     }
 
-    Ok(result)
-}
-
-/// Tests the 3rd (rightmost) section of triplet. It returns an integer in a Result.
-/// The value of the integer depicts the location type.
-/// 1 = virtual location (the default),
-/// 2 = geographic location,
-/// 3 = temporal location.
-/// 4 = temporal location (terminative).
-fn get_location_type(
-    conn: &Connection,
-    object_word_id: &i32,
-    sentence_id: &i32,
-) -> Result<i32, Box<dyn std::error::Error>> {
-    let mut result: i32 = 1;
-
-    let features_by_section: Vec<InputWordFeature> =
-        select_input_features_by_section(&conn, &object_word_id)?;
-
-    let input_word_relations: Vec<InputWordRelation> =
-        select_input_word_relation_by_sentence(&conn, &sentence_id)?;
-
-    for feature in &features_by_section {
-        if feature.ud_feature.to_lowercase() == "case".to_string() {
-            // Check if this feature has a connection to object_word_id.
-            // If there is a connection, check if it satisfies location types that are of interest.
-            for item in &input_word_relations {
-                if (item.word_id == feature.word_id && item.word_id_modified == *object_word_id)
-                    || (item.word_id_modified == feature.word_id && item.word_id == *object_word_id)
-                {
-                    if feature.ud_feature_value.to_lowercase() == "loc".to_string() {
-                        result = 2;
-                    }
-                    if feature.ud_feature_value.to_lowercase() == "tem".to_string() {
-                        result = 3;
-                    }
-                    if feature.ud_feature_value.to_lowercase() == "ter".to_string() {
-                        result = 4;
-                    }
-
-                    break;
-                }
-            }
-        }
-    }
-
-    Ok(result)
+    result
 }
 
 /// Checks if a triplet is virtual or real.
 /// Returns a tuple, (is_virtual, mood), where mood is verb mood.
-/// The parameter, allowed_tags exempts any triplet being
-/// marked as virtual based on the presence of the allowed tag
-/// in its "input_features.ud_feature_value" tags.
+/// The parameter, exempt_tags exempts any triplet being
+/// marked as virtual based on the presence of the exempt tag
+/// in the "Mood" features of the words in its sections.
 fn is_virtual(
-    conn: &Connection,
+    discourse: &Discourse,
     triplet: &InputTriplet,
     // Tags that might be exempt, such as imperative (imp).
-    exempt_tags: &Vec<InputExemptFeature>,
-) -> Result<(bool, String), Box<dyn std::error::Error>> {
+    exempt_tags: &[InputExemptFeature],
+) -> (bool, String) {
     let mut found_virtual = false;
     let mut mood = "ind".to_string();
 
-    let input_features: Vec<InputWordFeature> =
-        select_input_features_by_triplet(&conn, &triplet.triplet_id)?;
+    'sections: for section in discourse.sections_by_triplet(&triplet.triplet_id) {
+        for feature in discourse.features_by_word(&section.word_id) {
+            // Return true for all items not marked "indicative" (mood).
+            let feature_tag: String = feature.ud_feature.to_lowercase();
+            let feature_value: String = feature.ud_feature_value.to_lowercase();
 
-    for feature in &input_features {
-        // Return true for all items not marked "indicative" (mood).
-        let relation_tag: String = feature.ud_relation.to_lowercase();
-        let feature_tag: String = feature.ud_feature_value.to_lowercase();
+            if feature_tag == "mood" && feature_value != "ind" {
+                found_virtual = !exempt_tags.iter().any(|exemption| {
+                    exemption.ud_feature.to_lowercase() == "mood"
+                        && exemption.ud_feature_value.to_lowercase() == feature_value
+                });
+                mood = feature_value;
 
-        if relation_tag == "mood" && feature_tag != "ind" {
-            for exemption in exempt_tags {
-                if exemption.ud_feature.to_lowercase() != "mood" {
-                    continue;
-                }
-                if exemption.ud_feature_value.to_lowercase() == feature_tag {
-                    mood = String::from(&feature_tag);
-                    break;
-                }
+                break 'sections;
             }
-
-            found_virtual = true;
-            break;
         }
     }
-    Ok((found_virtual, mood))
+
+    (found_virtual, mood)
 }
 
 /// Creates senses for batches of words that do not yet exist in the meaning grid.
@@ -1239,19 +1942,69 @@ fn is_virtual(
 /// (with the exception of the new words hypernym), the resultant is then
 /// added to the position vector of the hypernym, yielding a new resultant whose
 /// end coordinate is represents the sense coordinate of the new word.
+///
+/// The sense coordinates are stored with the definitions. What is returned,
+/// by new_word_id, are health ratios: the x and y of the hypernym over those of
+/// the new sense, to be checked against the 0.8 of the 80-20 rule.
+///
+/// The definitions are read from the input database, conn, and the hypernyms
+/// are looked up in ./storage/metadata.db. See
+/// batch_define_new_word_sense_with_metadata to use a metadata database elsewhere.
 #[no_mangle]
 pub extern "C" fn batch_define_new_word_sense(
     conn: &Connection,
     discourse_id: i32,
     agrees_to_the_creed: &bool,
-) -> Result<HashMap<i32, (f64, f64)>, Box<dyn std::error::Error>> {
+) -> Result<HashMap<i32, (f64, f64)>, OkeuvoError> {
+    // Check that the user has agreed to The Creed.
+    // Stop processing with an error if they haven't.
+    // The user interface must present a choice whose value we use here.
+    if !agrees_to_the_creed {
+        return Err(OkeuvoError::CreedNotAccepted);
+    }
+
+    let conn_meta = Connection::open(METAPATH)?;
+    let health_checks: HashMap<i32, (f64, f64)> = batch_define_new_word_sense_with_metadata(
+        conn,
+        &conn_meta,
+        discourse_id,
+        agrees_to_the_creed,
+    )?;
+
+    if let Err(e) = conn_meta.close() {
+        Err(e.1)?
+    }
+
+    Ok(health_checks)
+}
+
+/// Creates senses for batches of words that do not yet exist in the meaning grid,
+/// as batch_define_new_word_sense does, with the hypernyms looked up in the
+/// metadata database, conn_meta. See Okeuvo::batch_define_new_word_sense to use
+/// the databases of an engine.
+pub fn batch_define_new_word_sense_with_metadata(
+    conn: &Connection,
+    conn_meta: &Connection,
+    discourse_id: i32,
+    agrees_to_the_creed: &bool,
+) -> Result<HashMap<i32, (f64, f64)>, OkeuvoError> {
     // Check that the user has agreed to The Creed.
     // Stop processing with an error if they haven't.
     // The user interface must present a choice whose value we use here.
     if !agrees_to_the_creed {
-        Err("You must agree to The Creed to continue".to_string())?
+        return Err(OkeuvoError::CreedNotAccepted);
     }
 
+    batch_define_new_word_sense_inner(conn, conn_meta, discourse_id, &FunctionWords::default())
+}
+
+/// Does the work of batch_define_new_word_sense.
+fn batch_define_new_word_sense_inner(
+    conn: &Connection,
+    conn_meta: &Connection,
+    discourse_id: i32,
+    function_words: &FunctionWords,
+) -> Result<HashMap<i32, (f64, f64)>, OkeuvoError> {
     // HashMap<new_word_id, Point2D>
     let mut new_coordinates: HashMap<i32, Point2D> = HashMap::new();
 
@@ -1260,27 +2013,44 @@ pub extern "C" fn batch_define_new_word_sense(
     // or for further scrutiny.
     let mut health_checks: HashMap<i32, (f64, f64)> = HashMap::new();
 
-    let new_word_vec: Vec<InputNewWordDef> = select_input_new_word_defs(&conn, discourse_id)?;
+    let in_discourse = |e: rusqlite::Error| OkeuvoError::from(e).in_discourse(&discourse_id);
+
+    let new_word_vec: Vec<InputNewWordDef> =
+        select_input_new_word_defs(conn, discourse_id).map_err(in_discourse)?;
+
+    // Format - HashMap<word_id, word_id of its head>.
+    let heads: HashMap<i32, i32> = select_input_word_relations_by_discourse(conn, &discourse_id)
+        .map_err(in_discourse)?
+        .into_iter()
+        .map(|relation| (relation.word_id, relation.word_id_modified))
+        .collect();
 
     for new_word in &new_word_vec {
         let start_word_id = new_word.hypernym_synset_id;
-        let isa_xy: Point2D = select_new_def_isa(&start_word_id)?;
+        let isa_xy: Point2D = select_new_def_isa(conn_meta, &start_word_id)
+            .map_err(|e| e.in_discourse(&discourse_id))?;
 
         let definition: Vec<InputWord> =
-            select_input_words_new_def(&conn, &discourse_id, new_word.new_word_id)?;
+            select_input_words_new_def(conn, &discourse_id, new_word.new_word_id)
+                .map_err(in_discourse)?;
 
         let mut resultant = Vector2D {
             start: Point2D { x: 0.0, y: 0.0 },
             end: Point2D { x: 0.0, y: 0.0 },
         };
 
+        // Function words count as function_words sets out (see FunctionWords::weights).
+        let definition_words: Vec<&InputWord> = definition.iter().collect();
+        let weights: HashMap<i32, f64> = function_words.weights(&definition_words, &heads);
+
         for def_word in &definition {
             if def_word.lexeme.to_lowercase() != new_word.lexeme.to_lowercase() {
+                let weight: f64 = weights[&def_word.word_id];
                 let position_vector = Vector2D {
                     start: Point2D { x: 0.0, y: 0.0 },
                     end: Point2D {
-                        x: def_word.x,
-                        y: def_word.y,
+                        x: def_word.x * weight,
+                        y: def_word.y * weight,
                     },
                 };
 
@@ -1329,12 +2099,154 @@ pub extern "C" fn batch_define_new_word_sense(
 
     // Set the coordinates for all new definitions in this discourse.
     for item in &new_coordinates {
-        update_input_new_word_def(&conn, &item.1.x, &item.1.y, &item.0)?;
+        update_input_new_word_def(conn, &item.1.x, &item.1.y, item.0).map_err(in_discourse)?;
     }
 
     Ok(health_checks)
 }
 
+/// Imports the meaning grid from a CSV file in the format of meaningGrid.csv
+/// into the table, meaning_grid_item in metadata.db.
+/// Existing meaning grid items are replaced. The import runs in a single
+/// transaction and is refused outright if any entry fails validation
+/// (duplicate synsetId's, non-numeric coordinates or synsetId's outside
+/// the Wordnet SQL ranges), so a bad file never leaves a partial grid.
+///
+/// Works on ./storage/metadata.db. See Okeuvo::import_meaning_grid
+/// to import into a metadata database elsewhere.
+///
+/// Return format: (grid version, number of items imported).
+pub fn import_meaning_grid(
+    csv_path: &str,
+    agrees_to_the_creed: &bool,
+) -> Result<(String, usize), OkeuvoError> {
+    // Check that the user has agreed to The Creed.
+    // Stop processing with an error if they haven't.
+    // The user interface must present a choice whose value we use here.
+    if !agrees_to_the_creed {
+        return Err(OkeuvoError::CreedNotAccepted);
+    }
+
+    let mut okeuvo = Okeuvo::new(OkeuvoConfig::default())?;
+    let result: (String, usize) = okeuvo.import_meaning_grid(csv_path, agrees_to_the_creed)?;
+    okeuvo.close()?;
+
+    Ok(result)
+}
+
+/// Does the work of import_meaning_grid.
+fn import_meaning_grid_inner(
+    conn_meta: &mut Connection,
+    csv_path: &str,
+) -> Result<(String, usize), OkeuvoError> {
+    // Parse and validate the whole file before touching the database.
+    let grid: MeaningGridCsv = read_meaning_grid_csv(std::path::Path::new(csv_path))?;
+
+    let item_count: usize = insert_meaning_grid_items(conn_meta, &grid.version, &grid.items)?;
+
+    Ok((grid.version, item_count))
+}
+
+/// Imports antonym pairs into the table, antonyms in metadata.db from a local
+/// Wordnet 3.0 database file (data.noun, data.verb, data.adj or data.adv).
+/// Each antonym pointer ("!") of the file makes a pair, listed once although
+/// Wordnet lists it from both words (see read_wordnet_antonyms).
+///
+/// The pairs already held for the parts of speech of the file are replaced, so
+/// the four files can be imported one after the other. The import runs in a
+/// single transaction and is refused outright if any entry fails validation,
+/// so a bad file never leaves a partial table.
+///
+/// Works on ./storage/metadata.db. See Okeuvo::import_antonyms
+/// to import into a metadata database elsewhere.
+///
+/// Return format: number of pairs imported.
+pub fn import_antonyms(
+    wordnet_path: &str,
+    agrees_to_the_creed: &bool,
+) -> Result<usize, OkeuvoError> {
+    // Check that the user has agreed to The Creed.
+    // Stop processing with an error if they haven't.
+    // The user interface must present a choice whose value we use here.
+    if !agrees_to_the_creed {
+        return Err(OkeuvoError::CreedNotAccepted);
+    }
+
+    let mut okeuvo = Okeuvo::new(OkeuvoConfig::default())?;
+    let pair_count: usize = okeuvo.import_antonyms(wordnet_path, agrees_to_the_creed)?;
+    okeuvo.close()?;
+
+    Ok(pair_count)
+}
+
+/// Does the work of import_antonyms.
+fn import_antonyms_inner(
+    conn_meta: &mut Connection,
+    wordnet_path: &str,
+) -> Result<usize, OkeuvoError> {
+    // Parse and validate the whole file before touching the database.
+    let pairs: Vec<AntonymPair> = read_wordnet_antonyms_inner(std::path::Path::new(wordnet_path))?;
+
+    Ok(insert_antonym_pairs(conn_meta, &pairs)?)
+}
+
+/// Reads the antonym pairs of a local Wordnet 3.0 database file into memory,
+/// without touching metadata.db. Synset offsets become Wordnet SQL synset_id's
+/// (adjective satellites counting as adjectives) and lemmas lose their adjective
+/// markers and underscores. The pairs can be added to a meaning grid loaded
+/// from meaningGrid.csv with MeaningGrid::add_antonyms.
+pub fn read_wordnet_antonyms(
+    wordnet_path: &str,
+    agrees_to_the_creed: &bool,
+) -> Result<Vec<AntonymPair>, OkeuvoError> {
+    // Check that the user has agreed to The Creed.
+    // Stop processing with an error if they haven't.
+    // The user interface must present a choice whose value we use here.
+    if !agrees_to_the_creed {
+        return Err(OkeuvoError::CreedNotAccepted);
+    }
+
+    read_wordnet_antonyms_inner(std::path::Path::new(wordnet_path))
+}
+
+/// Gets the antonyms of a synset from metadata.db, in the order they were imported,
+/// with their coordinates on the meaning grid (0 for antonyms not on the grid).
+/// A synset with no antonyms gives an empty list.
+///
+/// Works on ./storage/metadata.db. See Okeuvo::antonyms, or MeaningGrid::antonyms
+/// for a grid already in memory.
+pub fn get_antonyms(
+    synset_id: &i32,
+    agrees_to_the_creed: &bool,
+) -> Result<Vec<Antonym>, OkeuvoError> {
+    // Check that the user has agreed to The Creed.
+    // Stop processing with an error if they haven't.
+    // The user interface must present a choice whose value we use here.
+    if !agrees_to_the_creed {
+        return Err(OkeuvoError::CreedNotAccepted);
+    }
+
+    let okeuvo = Okeuvo::new(OkeuvoConfig::default())?;
+    let antonyms: Vec<Antonym> = okeuvo.antonyms(synset_id, agrees_to_the_creed)?;
+    okeuvo.close()?;
+
+    Ok(antonyms)
+}
+
+/// Loads the meaning grid in metadata.db into memory.
+/// Load it once and keep it, lookups on the returned grid
+/// do not touch the database again.
+pub fn load_meaning_grid(agrees_to_the_creed: &bool) -> Result<MeaningGrid, OkeuvoError> {
+    // Check that the user has agreed to The Creed.
+    // Stop processing with an error if they haven't.
+    // The user interface must present a choice whose value we use here.
+    if !agrees_to_the_creed {
+        return Err(OkeuvoError::CreedNotAccepted);
+    }
+
+    MeaningGrid::from_metadata_path(std::path::Path::new(METAPATH))
+}
+
 /// Toy. Gets a vector collection of sets of math vectors that correspond
 /// to the concracting set of convex hulls.
 /// As with the convex_hull_sets, this is a gradient, with the first set containing
@@ -1342,12 +2254,12 @@ pub extern "C" fn batch_define_new_word_sense(
 pub extern "C" fn representative_vector_sets(
     convex_hull_sets: &Vec<Vec<Point2D>>,
     agrees_to_the_creed: &bool,
-) -> Result<Vec<Vec<Vector2D>>, Box<dyn std::error::Error>> {
+) -> Result<Vec<Vec<Vector2D>>, OkeuvoError> {
     // Check that the user has agreed to The Creed.
     // Stop processing with an error if they haven't.
     // The user interface must present a choice whose value we use here.
     if !agrees_to_the_creed {
-        Err("You must agree to The Creed to continue".to_string())?
+        return Err(OkeuvoError::CreedNotAccepted);
     }
 
     let mut result: Vec<Vec<Vector2D>> = Vec::new();
@@ -1370,12 +2282,12 @@ pub extern "C" fn convex_hull_sets(
     input_vectors: &Vec<Vector2D>,
     //translate: &Fn(&Vec<Vector2D>) -> Vec<Vector2D>,
     agrees_to_the_creed: &bool,
-) -> Result<Vec<Vec<Point2D>>, Box<dyn std::error::Error>> {
+) -> Result<Vec<Vec<Point2D>>, OkeuvoError> {
     // Check that the user has agreed to The Creed.
     // Stop processing with an error if they haven't.
     // The user interface must present a choice whose value we use here.
     if !agrees_to_the_creed {
-        Err("You must agree to The Creed to continue".to_string())?
+        return Err(OkeuvoError::CreedNotAccepted);
     }
 
     // Perform the desired translation on the vectors.
@@ -1481,8 +2393,82 @@ pub extern "C" fn top_contributors(hash_item_vec: &Vec<HashItem>) -> Vec<HashIte
     result
 }
 
+/// Gets the version of this library, with its codename.
+/// Hashes are tagged with its version number, LIBRARY_VERSION (see Versions).
 #[no_mangle]
 pub extern "C" fn version() -> &'static str {
-    let current_version = "19.07.23 (Codename Ama)";
-    current_version
+    crate::version::LIBRARY_VERSION_AND_CODENAME
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{discourse, grid};
+
+    /// "he ate cake", titled by its first word, with the coordinates of its words given.
+    fn titled_discourse(hypernym_synset_id: i32) -> Discourse {
+        let mut discourse = discourse(&[
+            "he he PRON _ 2 nsubj",
+            "ate eat VERB Tense=Past 0 root",
+            "cake cake NOUN _ 2 obj",
+        ]);
+        discourse.discourse.hypernym_synset_id = hypernym_synset_id;
+        discourse.title = vec![InputDiscourseTitle {
+            discourse_id: 0,
+            word_id: 1,
+        }];
+        for word in &mut discourse.words {
+            word.x = word.word_id as f64;
+            word.y = 1.0;
+        }
+
+        discourse
+    }
+
+    /// A grid holding only the hypernym 100001740, at (3, 4).
+    fn hypernym_grid() -> MeaningGrid {
+        grid(&[(100001740, 3.0, 4.0)])
+    }
+
+    #[test]
+    fn places_the_hypernym_on_the_grid() {
+        let encoding =
+            encode_with_grid(&titled_discourse(100001740), &hypernym_grid(), &true).unwrap();
+
+        // The hypernym portion of the hash goes first.
+        assert!((encoding.hash_items_real[0].radius - 5.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn reports_a_hypernym_missing_from_the_grid() {
+        match encode_with_grid(&titled_discourse(100002137), &hypernym_grid(), &true) {
+            Err(OkeuvoError::MissingCoordinates { words, .. }) => assert_eq!(
+                words,
+                vec![UnresolvedWord {
+                    word_id: 0,
+                    sentence_id: 0,
+                    synset_id: 100002137,
+                    new_word_id: -1,
+                    lexeme: String::new(),
+                }]
+            ),
+            other => panic!("expected MissingCoordinates, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn leaves_the_hypernym_of_an_untitled_discourse() {
+        let mut discourse = titled_discourse(100002137);
+        discourse.title.clear();
+
+        assert!(encode_with_grid(&discourse, &hypernym_grid(), &true).is_ok());
+    }
+
+    #[test]
+    fn version_is_the_library_version_with_its_codename() {
+        assert_eq!(
+            version(),
+            format!("{} (Codename {})", LIBRARY_VERSION, LIBRARY_CODENAME)
+        );
+    }
 }
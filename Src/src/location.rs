@@ -0,0 +1,338 @@
+/*
+DO NOT ALTER OR REMOVE COPYRIGHT NOTICES OR THIS HEADER.
+
+Copyright © 2019 Asame Imoni Obiomah. All rights reserved.
+
+Artificial intelligence ethics is of existential importance.
+The licensing model of OkeuvoLite enforces adherence to a strict ethical code.
+
+The contents of this file are subject to the terms of both the GNU General Public License Version 2 only (“GPL”)
+and Inverse license (collectively, the “License”). You may not use this file except in compliance with the License.
+You can obtain a copy of the License at LICENSE.txt. See the License for the specific language governing
+permissions and limitations under the License.
+
+When distributing the software, include this License Header Notice in each file and include the License file at LICENSE.txt.
+*/
+
+//! Finds where and when the event of a triplet happens, from the modifiers
+//! of its sections that Universal Dependencies marks as places or times.
+
+use crate::discourse::Discourse;
+use crate::triplets::{base_relation, get_dependents, get_feature_value};
+
+/// Relations that attach a modifier that may be a place or a time:
+/// obl to a predicate, nmod to a noun (and to a predicate in UD version 1).
+const LOCATION_RELATIONS: &[&str] = &["obl", "nmod"];
+
+/// Subtype of obl and nmod that marks a bare noun phrase of time ("she left last week").
+const TEMPORAL_SUBTYPE: &str = "tmod";
+
+/// Values of the Case feature that mark a place.
+const LOCATIVE_CASES: &[&str] = &[
+    "loc", "ine", "ill", "ela", "ade", "all", "abl", "sup", "sub", "del", "lat",
+];
+
+/// Lemmas of adpositions that mark a place, or a time when their noun is one
+/// ("in the park", "in June").
+const LOCATIVE_ADPOSITIONS: &[&str] = &[
+    "in",
+    "at",
+    "on",
+    "inside",
+    "outside",
+    "into",
+    "onto",
+    "near",
+    "under",
+    "over",
+    "above",
+    "below",
+    "beneath",
+    "behind",
+    "beside",
+    "between",
+    "among",
+    "around",
+    "across",
+    "along",
+    "through",
+    "throughout",
+    "within",
+];
+
+/// Lemmas of adpositions that mark a time.
+const TEMPORAL_ADPOSITIONS: &[&str] = &["during", "since", "before", "after"];
+
+/// Lemmas of adpositions that mark the time at which an event ends.
+const TERMINATIVE_ADPOSITIONS: &[&str] = &["until", "till"];
+
+/// Lemmas of nouns that name a time.
+const TEMPORAL_NOUNS: &[&str] = &[
+    "time",
+    "moment",
+    "second",
+    "minute",
+    "hour",
+    "day",
+    "night",
+    "morning",
+    "afternoon",
+    "evening",
+    "week",
+    "weekend",
+    "month",
+    "year",
+    "decade",
+    "century",
+    "season",
+    "spring",
+    "summer",
+    "autumn",
+    "fall",
+    "winter",
+    "today",
+    "tomorrow",
+    "yesterday",
+    "monday",
+    "tuesday",
+    "wednesday",
+    "thursday",
+    "friday",
+    "saturday",
+    "sunday",
+    "january",
+    "february",
+    "march",
+    "april",
+    "may",
+    "june",
+    "july",
+    "august",
+    "september",
+    "october",
+    "november",
+    "december",
+];
+
+/// Gets the "where"s and "when"s of a triplet.
+///
+/// They are the obl and nmod dependents of the focus of each section that are
+/// marked as places or times, so a triplet can have several of each:
+///
+/// 1. obl:tmod and nmod:tmod dependents are times,
+/// 2. a Case feature of Tem is a time, of Ter the end of a time, and of
+///    Loc (or another locative case: Ine, Ill, Ela, Ade, All, Abl, Sup, Sub,
+///    Del, Lat) a place,
+/// 3. otherwise, the adposition attached to the dependent by case decides:
+///    during, since, before and after mark a time, until and till the end of
+///    a time, and in, at, on (and the like, see LOCATIVE_ADPOSITIONS) a place,
+///    or a time when the dependent is a number or a noun of time ("in June").
+///
+/// Dependents with none of these markers are neither.
+///
+/// A marked obl dependent of the predicate is not an object of the triplet
+/// (see extract_triplets), so the where or when of a triplet is never its
+/// object: in "she met him in Paris", "him" is the object and "Paris" the where.
+///
+/// Return format: Vec<(word_id, location type)>, sorted by word_id.
+/// 2 = geographic location,
+/// 3 = temporal location,
+/// 4 = temporal location (terminative).
+pub(crate) fn get_locations(discourse: &Discourse, sections: &(i32, i32, i32)) -> Vec<(i32, i32)> {
+    let mut result: Vec<(i32, i32)> = Vec::new();
+
+    for focus_id in &[sections.0, sections.1, sections.2] {
+        if *focus_id == -1 {
+            continue;
+        }
+
+        for relation in discourse.relations_by_head(focus_id) {
+            if !LOCATION_RELATIONS.contains(&base_relation(&relation.ud_relation)) {
+                continue;
+            }
+            if result
+                .iter()
+                .any(|(word_id, _)| *word_id == relation.word_id)
+            {
+                continue;
+            }
+
+            if let Some(location_type) =
+                get_location_type(discourse, &relation.word_id, &relation.ud_relation)
+            {
+                result.push((relation.word_id, location_type));
+            }
+        }
+    }
+    result.sort_unstable();
+
+    result
+}
+
+/// Tests if an obl or nmod dependent is marked as a place or a time (see get_locations).
+pub(crate) fn is_location(discourse: &Discourse, word_id: &i32, ud_relation: &str) -> bool {
+    get_location_type(discourse, word_id, ud_relation).is_some()
+}
+
+/// Gets the location type of an obl or nmod dependent (see get_locations),
+/// or None if it is neither a place nor a time.
+fn get_location_type(discourse: &Discourse, word_id: &i32, ud_relation: &str) -> Option<i32> {
+    if ud_relation.split(':').nth(1) == Some(TEMPORAL_SUBTYPE) {
+        return Some(3);
+    }
+
+    if let Some(case) = get_feature_value(discourse, word_id, "Case") {
+        match case.to_lowercase().as_str() {
+            "tem" => return Some(3),
+            "ter" => return Some(4),
+            case if LOCATIVE_CASES.contains(&case) => return Some(2),
+            _ => {}
+        }
+    }
+
+    for adposition_id in get_dependents(discourse, word_id, &["case"]) {
+        let lemma: String = get_lemma(discourse, &adposition_id);
+
+        if TERMINATIVE_ADPOSITIONS.contains(&lemma.as_str()) {
+            return Some(4);
+        }
+        if TEMPORAL_ADPOSITIONS.contains(&lemma.as_str()) {
+            return Some(3);
+        }
+        if LOCATIVE_ADPOSITIONS.contains(&lemma.as_str()) {
+            return match is_time(discourse, word_id) {
+                true => Some(3),
+                false => Some(2),
+            };
+        }
+    }
+
+    None
+}
+
+/// Tests if a word is a number or a noun of time (see TEMPORAL_NOUNS).
+fn is_time(discourse: &Discourse, word_id: &i32) -> bool {
+    discourse.words.iter().any(|word| {
+        word.word_id == *word_id
+            && (word.pos == "NUM" || TEMPORAL_NOUNS.contains(&word.lexeme.to_lowercase().as_str()))
+    })
+}
+
+/// Gets the lemma of a word in lower case, empty if the word is not in the discourse.
+fn get_lemma(discourse: &Discourse, word_id: &i32) -> String {
+    discourse
+        .words
+        .iter()
+        .find(|word| word.word_id == *word_id)
+        .map(|word| word.lexeme.to_lowercase())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::discourse;
+
+    /// Gets the locations of the first triplet of a sentence.
+    fn locations(words: &[&str]) -> Vec<(i32, i32)> {
+        let discourse = discourse(words);
+        let sections = |section_type: i32| {
+            discourse
+                .sections
+                .iter()
+                .find(|section| section.triplet_id == 1 && section.section_type == section_type)
+                .map_or(-1, |section| section.word_id)
+        };
+
+        get_locations(&discourse, &(sections(1), sections(2), sections(3)))
+    }
+
+    #[test]
+    fn finds_places_and_times_from_their_adpositions() {
+        assert_eq!(
+            locations(&[
+                "she she PRON _ 2 nsubj",
+                "met meet VERB Tense=Past 0 root",
+                "him he PRON _ 2 obj",
+                "in in ADP _ 5 case",
+                "Paris Paris PROPN _ 2 obl",
+                "in in ADP _ 7 case",
+                "June June PROPN _ 2 obl",
+                "until until ADP _ 9 case",
+                "noon noon NOUN _ 2 obl",
+            ]),
+            vec![(5, 2), (7, 3), (9, 4)]
+        );
+    }
+
+    #[test]
+    fn finds_times_from_subtypes_and_cases() {
+        assert_eq!(
+            locations(&[
+                "she she PRON _ 2 nsubj",
+                "met meet VERB Tense=Past 0 root",
+                "him he PRON _ 2 obj",
+                "last last ADJ _ 5 amod",
+                "week week NOUN _ 2 obl:tmod",
+                "Helsinki Helsinki PROPN Case=Ine 3 nmod",
+            ]),
+            vec![(5, 3), (6, 2)]
+        );
+    }
+
+    #[test]
+    fn leaves_out_modifiers_that_are_neither() {
+        assert!(locations(&[
+            "she she PRON _ 2 nsubj",
+            "met meet VERB Tense=Past 0 root",
+            "him he PRON _ 2 obj",
+            "with with ADP _ 5 case",
+            "friends friend NOUN _ 2 obl",
+        ])
+        .is_empty());
+    }
+
+    #[test]
+    fn the_where_of_a_triplet_is_not_its_object() {
+        // "she met him in Paris": "him" is the object (see extract_triplets)
+        // and "in" makes "Paris" a place, the where of the triplet.
+        let mut discourse = discourse(&[
+            "she she PRON _ 2 nsubj",
+            "met meet VERB Tense=Past 0 root",
+            "him he PRON _ 2 obj",
+            "in in ADP _ 5 case",
+            "Paris Paris PROPN _ 2 obl",
+        ]);
+        for (word, (x, y)) in discourse.words.iter_mut().zip(vec![
+            (1.0, 1.0),
+            (2.0, 1.0),
+            (3.0, 2.0),
+            (0.0, 0.0),
+            (5.0, 3.0),
+        ]) {
+            word.x = x;
+            word.y = y;
+            word.instance_index = word.word_id;
+        }
+
+        let encoding = crate::encode(&discourse, &true).unwrap();
+
+        assert_eq!(encoding.unit_tensors.len(), 1);
+        let unit_tensor = &encoding.unit_tensors[0];
+        assert_ne!(unit_tensor.where_entity_id, unit_tensor.object_entity_id);
+        assert_eq!(unit_tensor.when_entity_id, -1);
+        assert_eq!(encoding.locations.len(), 1);
+        assert_eq!(encoding.locations[0].entity_id, unit_tensor.where_entity_id);
+        let entity = |entity_id: i64| {
+            encoding
+                .entities
+                .iter()
+                .find(|entity| i64::from(entity.entity_id) == entity_id)
+                .map(|entity| entity.word_id)
+        };
+        assert_eq!(entity(unit_tensor.where_entity_id), Some(5));
+        assert_eq!(entity(unit_tensor.object_entity_id), Some(3));
+        // "met" + "him" + "Paris" (the subject and "in" add nothing).
+        assert_eq!((unit_tensor.excited_x, unit_tensor.excited_y), (10.0, 6.0));
+    }
+}
@@ -338,10 +338,10 @@ pub fn vector_translate_backward_3d(vector: &Vector3D, max_z: &f64) -> Vector3D
     vector
 }
 
-/// Returns the magnitude of a 2D vector.
+/// Returns the magnitude of a 2D vector, the distance from its start to its end.
 pub fn vector_magnitude_2d(vector: &Vector2D) -> f64 {
-    let xi: f64 = vector.start.x * vector.start.x;
-    let yi: f64 = vector.start.y * vector.start.y;
+    let xi: f64 = (vector.end.x - vector.start.x).powi(2);
+    let yi: f64 = (vector.end.y - vector.start.y).powi(2);
 
     let magnitude_squared = xi + yi;
 
@@ -420,3 +420,99 @@ pub(crate) fn areal_jaccard(point1: &Point2D, point2: &Point2D) -> f64 {
 
     jaccard
 }
+
+/// Converts a 2D Polar coordinate (radius, angle) to Cartesian.
+/// The inverse of cartesian_to_polar for points with a positive x coordinate,
+/// which holds for every point on the meaning grid.
+pub(crate) fn polar_to_cartesian(radius: &f64, angle: &f64) -> Point2D {
+    Point2D {
+        x: radius * angle.cos(),
+        y: radius * angle.sin(),
+    }
+}
+
+/// Encodes a coordinate as the ratio of the areas of two similar triangles,
+/// as drawn in Images/areaRatios.png. The near triangle has its base on the
+/// line at -max (parallel to the other axis) and its apex at the coordinate,
+/// the far triangle is its mirror through the apex, with its base on the line
+/// at +max. As the triangles are similar, the ratio of their areas (far to near)
+/// is the square of the ratio of their heights.
+/// The ratio is negative for a coordinate beyond either line,
+/// where both triangles lie on the same side of the apex.
+pub(crate) fn area_ratio(coordinate: &f64, max: &f64) -> f64 {
+    let near_height: f64 = coordinate + max;
+    let far_height: f64 = max - coordinate;
+
+    (far_height / near_height).powi(2) * (near_height * far_height).signum()
+}
+
+/// Recovers a coordinate from its area ratio (see area_ratio).
+/// An infinite ratio is the coordinate -max.
+pub(crate) fn area_ratio_coordinate(ratio: &f64, max: &f64) -> f64 {
+    if ratio.is_infinite() {
+        return -max;
+    }
+
+    // Ratio of the heights of the triangles, far to near.
+    let height_ratio: f64 = ratio.signum() * ratio.abs().sqrt();
+
+    max * (1.0 - height_ratio) / (1.0 + height_ratio)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MAX: f64 = 7.5;
+
+    #[test]
+    fn area_ratios_at_the_origin_and_the_max() {
+        assert_eq!(area_ratio(&0.0, &MAX), 1.0);
+        assert_eq!(area_ratio(&MAX, &MAX), 0.0);
+        assert_eq!(area_ratio(&-MAX, &MAX), f64::INFINITY);
+
+        assert_eq!(area_ratio_coordinate(&1.0, &MAX), 0.0);
+        assert_eq!(area_ratio_coordinate(&0.0, &MAX), MAX);
+        assert_eq!(area_ratio_coordinate(&f64::INFINITY, &MAX), -MAX);
+    }
+
+    #[test]
+    fn area_ratios_beyond_the_max_are_negative() {
+        assert!((area_ratio(&(2.0 * MAX), &MAX) - -1.0 / 9.0).abs() < 1.0e-15);
+        assert_eq!(area_ratio(&(-2.0 * MAX), &MAX), -9.0);
+
+        for coordinate in &[1.0001 * MAX, 3.0 * MAX, -1.0001 * MAX, -3.0 * MAX] {
+            assert!(area_ratio(coordinate, &MAX) < 0.0);
+        }
+    }
+
+    #[test]
+    fn area_ratio_coordinate_inverts_area_ratio() {
+        for i in -400..=400 {
+            let coordinate: f64 = i as f64 * MAX / 100.0;
+            let decoded: f64 = area_ratio_coordinate(&area_ratio(&coordinate, &MAX), &MAX);
+
+            assert!(
+                (decoded - coordinate).abs() < 1.0e-12 * MAX,
+                "{} decoded as {}",
+                coordinate,
+                decoded
+            );
+        }
+    }
+
+    #[test]
+    fn vector_magnitude_is_the_distance_from_start_to_end() {
+        let vector = |start: (f64, f64), end: (f64, f64)| Vector2D {
+            start: Point2D {
+                x: start.0,
+                y: start.1,
+            },
+            end: Point2D { x: end.0, y: end.1 },
+        };
+
+        assert_eq!(vector_magnitude_2d(&vector((0.0, 0.0), (3.0, 4.0))), 5.0);
+        assert_eq!(vector_magnitude_2d(&vector((1.0, 1.0), (4.0, -3.0))), 5.0);
+        assert_eq!(vector_magnitude_2d(&vector((2.0, 2.0), (2.0, 2.0))), 0.0);
+    }
+}
@@ -0,0 +1,352 @@
+/*
+DO NOT ALTER OR REMOVE COPYRIGHT NOTICES OR THIS HEADER.
+
+Copyright © 2019 Asame Imoni Obiomah. All rights reserved.
+
+Artificial intelligence ethics is of existential importance.
+The licensing model of OkeuvoLite enforces adherence to a strict ethical code.
+
+The contents of this file are subject to the terms of both the GNU General Public License Version 2 only (“GPL”)
+and Inverse license (collectively, the “License”). You may not use this file except in compliance with the License.
+You can obtain a copy of the License at LICENSE.txt. See the License for the specific language governing
+permissions and limitations under the License.
+
+When distributing the software, include this License Header Notice in each file and include the License file at LICENSE.txt.
+*/
+
+//! Detects negation in the Universal Dependencies relations and features of a
+//! discourse, and negates the predicates and objects of triplets.
+
+use crate::data::InputWord;
+use crate::discourse::Discourse;
+use crate::grid::MeaningGrid;
+use crate::triplets::{get_dependents, get_feature_value};
+use std::collections::HashMap;
+
+/// Relations that attach a negating adverb to the word it negates.
+/// neg is the relation of Universal Dependencies version 1.
+const NEGATING_RELATIONS: &[&str] = &["advmod", "neg"];
+
+/// Lemmas of adverbs that negate the word they modify.
+const NEGATING_ADVERBS: &[&str] = &["not", "n't", "never"];
+
+/// Relations that attach a determiner to its noun.
+const DETERMINER_RELATIONS: &[&str] = &["det"];
+
+/// Lemmas of determiners that negate their noun.
+const NEGATIVE_DETERMINERS: &[&str] = &["no", "neither"];
+
+/// Tests which sections of a triplet are negated.
+///
+/// The predicate is negated by Polarity=Neg on the predicate itself, and by
+/// each negating adverb (not, n't, never, or any adverb with Polarity=Neg)
+/// attached to it through advmod (neg in UD version 1).
+/// The object is negated by each negative determiner (no, neither, or any
+/// determiner with PronType=Neg or Polarity=Neg) attached to it through det.
+/// Negations don't cancel: as in negative concord ("he never ate no cake",
+/// or "ate" with Polarity=Neg and "not"), a section with any of them is negated.
+///
+/// Return format: (predicate is negated, object is negated).
+pub(crate) fn get_negations(discourse: &Discourse, sections: &(i32, i32, i32)) -> (bool, bool) {
+    let is_predicate_negated: bool = is_polarity_negative(discourse, &sections.1)
+        || get_dependents(discourse, &sections.1, NEGATING_RELATIONS)
+            .iter()
+            .any(|word_id| {
+                is_polarity_negative(discourse, word_id)
+                    || has_lexeme(discourse, word_id, NEGATING_ADVERBS)
+            });
+
+    let is_object_negated: bool = get_dependents(discourse, &sections.2, DETERMINER_RELATIONS)
+        .iter()
+        .any(|word_id| {
+            is_polarity_negative(discourse, word_id)
+                || get_feature_value(discourse, word_id, "PronType") == Some("Neg")
+                || has_lexeme(discourse, word_id, NEGATIVE_DETERMINERS)
+        });
+
+    (is_predicate_negated, is_object_negated)
+}
+
+/// Gets the words of a sentence as a triplet sees them: a copy in which the
+/// predicate and object of the triplet are negated (see negate_word) where
+/// get_negations found them to be.
+///
+/// Negation belongs to the triplet, not the word, so the words of the sentence
+/// are left as they are for its other triplets: in "he did not eat the cake
+/// that she ate", only the triplet of "eat" is negated.
+///
+/// The parameter, negations is that returned by get_negations for the sections.
+pub(crate) fn negate_triplet_words(
+    input_words: &HashMap<i32, InputWord>,
+    sections: &(i32, i32, i32),
+    negations: &(bool, bool),
+    grid: Option<&MeaningGrid>,
+) -> HashMap<i32, InputWord> {
+    let mut result: HashMap<i32, InputWord> = input_words.clone();
+
+    let mut negated_word_ids: Vec<i32> = Vec::new();
+    if negations.0 {
+        negated_word_ids.push(sections.1);
+    }
+    if negations.1 && !negated_word_ids.contains(&sections.2) {
+        negated_word_ids.push(sections.2);
+    }
+
+    for word_id in &negated_word_ids {
+        if let Some(word) = result.get_mut(word_id) {
+            negate_word(word, grid);
+        }
+    }
+
+    result
+}
+
+/// Negates the coordinates of a word.
+///
+/// A word with an antonym on the meaning grid takes the coordinates and the
+/// synset_id of the antonym, so the entity made from it is the antonym.
+/// Any other word (and every word when there is no grid) is reflected
+/// across the y axis, (x, y) becoming (-x, y): it keeps its distance from the
+/// origin but leaves the grid, whose coordinates are all positive. It keeps
+/// its synset_id, as no synset stands for its negation; the unit tensor is
+/// marked is_negated instead.
+/// Negating a reflected word again restores it.
+pub(crate) fn negate_word(word: &mut InputWord, grid: Option<&MeaningGrid>) {
+    if let Some(antonym) = grid.and_then(|grid| grid.antonym(&word.synset_id)) {
+        word.x = antonym.x;
+        word.y = antonym.y;
+        word.synset_id = antonym.synset_id;
+        return;
+    }
+
+    word.x = -word.x;
+}
+
+/// Tests if a word has Polarity=Neg.
+fn is_polarity_negative(discourse: &Discourse, word_id: &i32) -> bool {
+    get_feature_value(discourse, word_id, "Polarity") == Some("Neg")
+}
+
+/// Tests if the lemma of a word is one of lexemes, ignoring case.
+fn has_lexeme(discourse: &Discourse, word_id: &i32, lexemes: &[&str]) -> bool {
+    discourse.words.iter().any(|word| {
+        word.word_id == *word_id && lexemes.contains(&word.lexeme.to_lowercase().as_str())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::AntonymPair;
+    use crate::test_support::{discourse, grid, word};
+
+    fn words() -> HashMap<i32, InputWord> {
+        vec![
+            word(1, 100, 1.0, 2.0),
+            word(2, 200, 3.0, 4.0),
+            word(3, 300, 5.0, 6.0),
+        ]
+        .into_iter()
+        .map(|word| (word.word_id, word))
+        .collect()
+    }
+
+    /// A grid on which 200 and 201 are antonyms.
+    fn antonym_grid() -> MeaningGrid {
+        let mut grid = grid(&[(100, 1.0, 2.0), (200, 3.0, 4.0), (201, 7.0, 1.0)]);
+        grid.add_antonyms(&[AntonymPair {
+            coarse_class: 2,
+            synset_id1: 200,
+            lemma1: "love".to_string(),
+            synset_id2: 201,
+            lemma2: "hate".to_string(),
+        }]);
+
+        grid
+    }
+
+    fn coordinates(words: &HashMap<i32, InputWord>, word_id: &i32) -> (i32, f64, f64) {
+        let word: &InputWord = &words[word_id];
+
+        (word.synset_id, word.x, word.y)
+    }
+
+    #[test]
+    fn negates_a_copy_of_the_words_for_the_triplet() {
+        let input_words = words();
+
+        let negated = negate_triplet_words(&input_words, &(1, 2, 3), &(true, false), None);
+
+        assert_eq!(coordinates(&negated, &2), (200, -3.0, 4.0));
+        assert_eq!(coordinates(&negated, &3), (300, 5.0, 6.0));
+        assert_eq!(coordinates(&negated, &1), (100, 1.0, 2.0));
+        // The words of the sentence are left as they are for other triplets.
+        assert_eq!(coordinates(&input_words, &2), (200, 3.0, 4.0));
+    }
+
+    #[test]
+    fn negates_a_word_that_is_predicate_and_object_once() {
+        let negated = negate_triplet_words(&words(), &(1, 2, 2), &(true, true), None);
+
+        assert_eq!(coordinates(&negated, &2), (200, -3.0, 4.0));
+    }
+
+    #[test]
+    fn takes_the_synset_and_coordinates_of_the_antonym() {
+        let grid = antonym_grid();
+
+        let negated = negate_triplet_words(&words(), &(1, 2, 3), &(true, true), Some(&grid));
+
+        assert_eq!(coordinates(&negated, &2), (201, 7.0, 1.0));
+        // No antonym on the grid: reflected, keeping its synset.
+        assert_eq!(coordinates(&negated, &3), (300, -5.0, 6.0));
+    }
+
+    #[test]
+    fn reads_negations_from_relations_and_features() {
+        let negations = |words: &[&str]| get_negations(&discourse(words), &(1, 2, 3));
+
+        assert_eq!(
+            negations(&[
+                "he he PRON _ 2 nsubj",
+                "ate eat VERB Tense=Past 0 root",
+                "cake cake NOUN _ 2 obj",
+                "not not PART _ 2 advmod",
+            ]),
+            (true, false)
+        );
+        assert_eq!(
+            negations(&[
+                "he he PRON _ 2 nsubj",
+                "ate eat VERB Tense=Past 0 root",
+                "cake cake NOUN _ 2 obj",
+                "no no DET _ 3 det",
+            ]),
+            (false, true)
+        );
+        // Negative concord: more negations still negate once.
+        assert_eq!(
+            negations(&[
+                "he he PRON _ 2 nsubj",
+                "ate eat VERB Polarity=Neg 0 root",
+                "cake cake NOUN _ 2 obj",
+                "never never ADV _ 2 advmod",
+                "no no DET PronType=Neg 3 det",
+            ]),
+            (true, true)
+        );
+        // Adverbs that don't negate.
+        assert_eq!(
+            negations(&[
+                "he he PRON _ 2 nsubj",
+                "ate eat VERB Tense=Past 0 root",
+                "cake cake NOUN _ 2 obj",
+                "just just ADV _ 2 advmod",
+            ]),
+            (false, false)
+        );
+    }
+
+    #[test]
+    fn negation_does_not_reach_the_other_triplets_of_the_sentence() {
+        // "they asked him not to read the book": "read" is negated as the
+        // predicate of its own triplet, but not as an object of "asked".
+        // It is compared with "they asked him just to read the book", whose
+        // adverb is at the origin like "not", so only the negation differs.
+        let encode = |adverb: &str| {
+            let adverb: String = format!("{} {} ADV _ 6 advmod", adverb, adverb);
+            let mut discourse = discourse(&[
+                "they they PRON _ 2 nsubj",
+                "asked ask VERB Tense=Past 0 root",
+                "him he PRON _ 2 obj",
+                &adverb,
+                "to to PART _ 6 mark",
+                "read read VERB VerbForm=Inf 2 xcomp",
+                "the the DET _ 8 det",
+                "book book NOUN _ 6 obj",
+            ]);
+            for word in &mut discourse.words {
+                if word.word_id != 4 {
+                    word.x = word.word_id as f64;
+                    word.y = 1.0;
+                }
+            }
+
+            crate::encode(&discourse, &true).unwrap()
+        };
+
+        let negated = encode("not");
+        let not_negated = encode("just");
+
+        let asked = (&negated.unit_tensors[0], &not_negated.unit_tensors[0]);
+        assert!(!asked.0.is_negated && !asked.1.is_negated);
+        assert_eq!(
+            (asked.0.excited_x, asked.0.excited_y),
+            (asked.1.excited_x, asked.1.excited_y)
+        );
+
+        let read = (&negated.unit_tensors[1], &not_negated.unit_tensors[1]);
+        assert!(read.0.is_negated && !read.1.is_negated);
+        assert_ne!(read.0.excited_x, read.1.excited_x);
+        let predicate_x = |encoding: &crate::Encoding, entity_id: i64| {
+            encoding
+                .entities
+                .iter()
+                .find(|entity| i64::from(entity.entity_id) == entity_id)
+                .map(|entity| entity.x)
+        };
+        assert_eq!(
+            predicate_x(&negated, read.0.predicate_entity_id),
+            Some(-6.0)
+        );
+        assert_eq!(
+            predicate_x(&not_negated, read.1.predicate_entity_id),
+            Some(6.0)
+        );
+    }
+
+    #[test]
+    fn takes_the_antonyms_of_metadata_when_encoding_from_the_database() {
+        // "he does not love her", with "love" (200) and "hate" (201) antonyms in
+        // metadata.db, encoded with the coordinates of input.db as they are.
+        let okeuvo = crate::Okeuvo::open_in_memory().unwrap();
+        okeuvo
+            .metadata_connection()
+            .execute_batch(
+                "insert into meaning_grid_version (version) values ('19.07.23');
+insert into meaning_grid_item (synset_id, x, y) values (200, 3, 4), (201, 7, 1);
+insert into antonyms (coarse_class, synset_id1, lemma1, synset_id2, lemma2)
+values (2, 200, 'love', 201, 'hate');",
+            )
+            .unwrap();
+        let mut discourse = discourse(&[
+            "he he PRON _ 4 nsubj",
+            "does do AUX _ 4 aux",
+            "not not PART _ 4 advmod",
+            "love love VERB _ 0 root",
+            "her she PRON _ 4 obj",
+        ]);
+        discourse.discourse.discourse_id = 7;
+        for word in &mut discourse.words {
+            word.x = word.word_id as f64;
+            word.y = 1.0;
+        }
+        discourse.words[3].synset_id = 200;
+        okeuvo.save_discourse(&discourse, &true).unwrap();
+
+        let encoding = okeuvo.encode_discourse(&7, &true).unwrap();
+
+        let unit_tensor = &encoding.unit_tensors[0];
+        assert!(unit_tensor.is_negated);
+        let predicate = encoding
+            .entities
+            .iter()
+            .find(|entity| i64::from(entity.entity_id) == unit_tensor.predicate_entity_id)
+            .unwrap();
+        assert_eq!(
+            (predicate.synset_id, predicate.x, predicate.y),
+            (201, 7.0, 1.0)
+        );
+        assert_eq!(encoding.versions.grid, "19.07.23");
+    }
+}
@@ -0,0 +1,637 @@
+/*
+DO NOT ALTER OR REMOVE COPYRIGHT NOTICES OR THIS HEADER.
+
+Copyright © 2019 Asame Imoni Obiomah. All rights reserved.
+
+Artificial intelligence ethics is of existential importance.
+The licensing model of OkeuvoLite enforces adherence to a strict ethical code.
+
+The contents of this file are subject to the terms of both the GNU General Public License Version 2 only (“GPL”)
+and Inverse license (collectively, the “License”). You may not use this file except in compliance with the License.
+You can obtain a copy of the License at LICENSE.txt. See the License for the specific language governing
+permissions and limitations under the License.
+
+When distributing the software, include this License Header Notice in each file and include the License file at LICENSE.txt.
+*/
+
+use crate::conllu::{conllu_to_discourse, read_conllu, ConlluSentence, ConlluToken};
+#[cfg(feature = "signing")]
+use crate::data::select_input_discourse;
+use crate::data::{
+    insert_encoding, insert_input_discourse, insert_input_triplets, select_antonyms,
+    select_discourse, select_encoding, select_hash_item, Antonym, HashItem, InputDiscourse,
+};
+use crate::discourse::{Discourse, Encoding};
+#[cfg(feature = "signing")]
+use crate::envelope::HashEnvelope;
+use crate::error::OkeuvoError;
+use crate::explain::{explain_hash_items, HashItemExplanation};
+use crate::function_word::FunctionWords;
+use crate::grid::MeaningGrid;
+use crate::grid_index::MeaningGridIndex;
+use crate::hash::HashSpec;
+#[cfg(feature = "serde")]
+use crate::json::DiscourseDocument;
+use crate::schema::{migrate, select_schema_version, DatabaseKind};
+use crate::triplets::extract_triplets;
+use crate::version::Versions;
+use crate::{
+    batch_define_new_word_sense_inner, encode_inner, get_hash_inner, import_antonyms_inner,
+    import_meaning_grid_inner, INPUTPATH, METAPATH, OUTPUTPATH,
+};
+use rusqlite::Connection;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// File name of the input database inside a storage directory.
+const INPUT_FILE_NAME: &str = "input.db";
+/// File name of the output database inside a storage directory.
+const OUTPUT_FILE_NAME: &str = "output.db";
+/// File name of the metadata database inside a storage directory.
+const METADATA_FILE_NAME: &str = "metadata.db";
+
+/// Where one of the Okeuvo databases (input, output or metadata) lives.
+#[derive(Debug)]
+pub enum DatabaseLocation {
+    /// A database file, created if it does not exist.
+    Path(PathBuf),
+    /// An already open connection, handed over to the engine.
+    Connection(Connection),
+    /// A private in-memory database that lasts as long as the engine.
+    InMemory,
+}
+
+impl DatabaseLocation {
+    /// Opens the database.
+    fn open(self) -> rusqlite::Result<Connection> {
+        match self {
+            DatabaseLocation::Path(path) => Connection::open(path),
+            DatabaseLocation::Connection(conn) => Ok(conn),
+            DatabaseLocation::InMemory => Connection::open_in_memory(),
+        }
+    }
+}
+
+/// Locations of the three databases an Okeuvo engine works with.
+#[derive(Debug)]
+pub struct OkeuvoConfig {
+    /// The input database, which holds raw triplets and triplet-word relations.
+    pub input: DatabaseLocation,
+    /// The output database, which holds the encoding of the input as "hash items".
+    pub output: DatabaseLocation,
+    /// The metadata database, whose contents include the meaning grid.
+    pub metadata: DatabaseLocation,
+    /// How function words take part in encoding and new-sense definition.
+    pub function_words: FunctionWords,
+}
+
+impl OkeuvoConfig {
+    /// Uses input.db, output.db and metadata.db in directory.
+    pub fn from_directory(directory: &Path) -> OkeuvoConfig {
+        OkeuvoConfig {
+            input: DatabaseLocation::Path(directory.join(INPUT_FILE_NAME)),
+            output: DatabaseLocation::Path(directory.join(OUTPUT_FILE_NAME)),
+            metadata: DatabaseLocation::Path(directory.join(METADATA_FILE_NAME)),
+            function_words: FunctionWords::default(),
+        }
+    }
+
+    /// Uses a separate in-memory database for each of input, output and metadata.
+    /// Nothing is written to disk, so each engine built this way is isolated.
+    pub fn in_memory() -> OkeuvoConfig {
+        OkeuvoConfig {
+            input: DatabaseLocation::InMemory,
+            output: DatabaseLocation::InMemory,
+            metadata: DatabaseLocation::InMemory,
+            function_words: FunctionWords::default(),
+        }
+    }
+}
+
+impl Default for OkeuvoConfig {
+    /// Uses ./storage/input.db, ./storage/output.db and ./storage/metadata.db,
+    /// relative to the working directory.
+    /// The free functions of the library that take no engine work on these,
+    /// so calling them creates the databases, or upgrades their schemas in place
+    /// (see Okeuvo::new).
+    fn default() -> OkeuvoConfig {
+        OkeuvoConfig {
+            input: DatabaseLocation::Path(PathBuf::from(INPUTPATH)),
+            output: DatabaseLocation::Path(PathBuf::from(OUTPUTPATH)),
+            metadata: DatabaseLocation::Path(PathBuf::from(METAPATH)),
+            function_words: FunctionWords::default(),
+        }
+    }
+}
+
+/// An Okeuvo engine.
+/// Holds open connections to one set of input, output and metadata databases,
+/// so that several isolated corpora can be worked on in one process.
+#[derive(Debug)]
+pub struct Okeuvo {
+    conn_input: Connection,
+    conn_output: Connection,
+    conn_meta: Connection,
+    function_words: FunctionWords,
+}
+
+impl Okeuvo {
+    /// Opens the databases named in config.
+    /// Their schemas are created, or upgraded to the latest schema version,
+    /// as they are opened (connections handed over in config included).
+    pub fn new(config: OkeuvoConfig) -> Result<Okeuvo, OkeuvoError> {
+        let mut conn_input: Connection = config.input.open()?;
+        migrate(&mut conn_input, DatabaseKind::Input)?;
+
+        let mut conn_output: Connection = config.output.open()?;
+        migrate(&mut conn_output, DatabaseKind::Output)?;
+
+        let mut conn_meta: Connection = config.metadata.open()?;
+        migrate(&mut conn_meta, DatabaseKind::Metadata)?;
+
+        Ok(Okeuvo {
+            conn_input,
+            conn_output,
+            conn_meta,
+            function_words: config.function_words,
+        })
+    }
+
+    /// Opens the databases in directory (see OkeuvoConfig::from_directory).
+    pub fn open_directory(directory: &Path) -> Result<Okeuvo, OkeuvoError> {
+        Okeuvo::new(OkeuvoConfig::from_directory(directory))
+    }
+
+    /// Opens a fresh set of in-memory databases (see OkeuvoConfig::in_memory).
+    pub fn open_in_memory() -> Result<Okeuvo, OkeuvoError> {
+        Okeuvo::new(OkeuvoConfig::in_memory())
+    }
+
+    /// Connection to the input database.
+    pub fn input_connection(&self) -> &Connection {
+        &self.conn_input
+    }
+
+    /// Connection to the output database.
+    pub fn output_connection(&self) -> &Connection {
+        &self.conn_output
+    }
+
+    /// Connection to the metadata database.
+    pub fn metadata_connection(&self) -> &Connection {
+        &self.conn_meta
+    }
+
+    /// The function-word lexicon used to encode and define new word senses.
+    pub fn function_words(&self) -> &FunctionWords {
+        &self.function_words
+    }
+
+    /// Gets the schema version stamped in one of the databases.
+    pub fn schema_version(&self, kind: DatabaseKind) -> Result<u32, OkeuvoError> {
+        let conn: &Connection = match kind {
+            DatabaseKind::Input => &self.conn_input,
+            DatabaseKind::Output => &self.conn_output,
+            DatabaseKind::Metadata => &self.conn_meta,
+        };
+
+        Ok(select_schema_version(conn)?)
+    }
+
+    /// Loads a discourse and all of its rows in the input database into memory.
+    pub fn load_discourse(
+        &self,
+        discourse_id: &i32,
+        agrees_to_the_creed: &bool,
+    ) -> Result<Discourse, OkeuvoError> {
+        // Check that the user has agreed to The Creed.
+        // Stop processing with an error if they haven't.
+        // The user interface must present a choice whose value we use here.
+        if !agrees_to_the_creed {
+            return Err(OkeuvoError::CreedNotAccepted);
+        }
+
+        select_discourse(&self.conn_input, discourse_id)
+    }
+
+    /// Writes a discourse to the input database, replacing any earlier rows
+    /// of the same discourse_id. Sentence, word, triplet, section and new word
+    /// ids are replaced by the database keys.
+    ///
+    /// Return format: the discourse as stored.
+    pub fn save_discourse(
+        &self,
+        discourse: &Discourse,
+        agrees_to_the_creed: &bool,
+    ) -> Result<Discourse, OkeuvoError> {
+        // Check that the user has agreed to The Creed.
+        // Stop processing with an error if they haven't.
+        // The user interface must present a choice whose value we use here.
+        if !agrees_to_the_creed {
+            return Err(OkeuvoError::CreedNotAccepted);
+        }
+
+        insert_input_discourse(&self.conn_input, discourse)
+            .map_err(|e| OkeuvoError::from(e).in_discourse(&discourse.discourse_id()))
+    }
+
+    /// Imports a CoNLL-U file into the input database as one discourse
+    /// (see import_conllu).
+    ///
+    /// Return format: the discourse as stored.
+    pub fn import_conllu(
+        &self,
+        conllu_path: &str,
+        discourse: InputDiscourse,
+        synset_id: &mut dyn FnMut(&ConlluSentence, &ConlluToken) -> Option<i32>,
+        agrees_to_the_creed: &bool,
+    ) -> Result<Discourse, OkeuvoError> {
+        // Check that the user has agreed to The Creed.
+        // Stop processing with an error if they haven't.
+        // The user interface must present a choice whose value we use here.
+        if !agrees_to_the_creed {
+            return Err(OkeuvoError::CreedNotAccepted);
+        }
+
+        let sentences: Vec<ConlluSentence> = read_conllu(Path::new(conllu_path))?;
+        let discourse: Discourse = conllu_to_discourse(discourse, &sentences, synset_id);
+
+        insert_input_discourse(&self.conn_input, &discourse)
+            .map_err(|e| OkeuvoError::from(e).in_discourse(&discourse.discourse_id()))
+    }
+
+    /// Derives the triplets and sections of a discourse in the input database from
+    /// its relations and features (see extract_triplets), replacing those it has.
+    ///
+    /// Return format: the discourse with the triplets and sections as stored.
+    pub fn extract_triplets(
+        &self,
+        discourse_id: &i32,
+        agrees_to_the_creed: &bool,
+    ) -> Result<Discourse, OkeuvoError> {
+        // Check that the user has agreed to The Creed.
+        // Stop processing with an error if they haven't.
+        // The user interface must present a choice whose value we use here.
+        if !agrees_to_the_creed {
+            return Err(OkeuvoError::CreedNotAccepted);
+        }
+
+        let mut discourse: Discourse = select_discourse(&self.conn_input, discourse_id)?;
+        let (triplets, sections) = extract_triplets(&discourse);
+        let (triplets, sections) =
+            insert_input_triplets(&self.conn_input, discourse_id, &triplets, &sections)
+                .map_err(|e| OkeuvoError::from(e).in_discourse(discourse_id))?;
+        discourse.triplets = triplets;
+        discourse.sections = sections;
+
+        Ok(discourse)
+    }
+
+    /// Loads the encoding of a discourse in the output database into memory.
+    /// entity_id and unit_tensor_id are the database keys.
+    /// A discourse that has not been encoded gives an empty encoding.
+    pub fn load_encoding(
+        &self,
+        discourse_id: &i32,
+        agrees_to_the_creed: &bool,
+    ) -> Result<Encoding, OkeuvoError> {
+        // Check that the user has agreed to The Creed.
+        // Stop processing with an error if they haven't.
+        // The user interface must present a choice whose value we use here.
+        if !agrees_to_the_creed {
+            return Err(OkeuvoError::CreedNotAccepted);
+        }
+
+        select_encoding(&self.conn_output, discourse_id)
+            .map_err(|e| OkeuvoError::from(e).in_discourse(discourse_id))
+    }
+
+    /// Imports the discourse of a JSON document (see DiscourseDocument) into
+    /// the input database (see import_json).
+    ///
+    /// Return format: the discourse as stored.
+    #[cfg(feature = "serde")]
+    pub fn import_json(
+        &self,
+        json: &str,
+        agrees_to_the_creed: &bool,
+    ) -> Result<Discourse, OkeuvoError> {
+        // Check that the user has agreed to The Creed.
+        // Stop processing with an error if they haven't.
+        // The user interface must present a choice whose value we use here.
+        if !agrees_to_the_creed {
+            return Err(OkeuvoError::CreedNotAccepted);
+        }
+
+        let discourse: Discourse = DiscourseDocument::from_json(json)?.discourse;
+
+        insert_input_discourse(&self.conn_input, &discourse)
+            .map_err(|e| OkeuvoError::from(e).in_discourse(&discourse.discourse_id()))
+    }
+
+    /// Exports a discourse in the input database, with its encoding in the
+    /// output database, as a JSON document (see export_json).
+    #[cfg(feature = "serde")]
+    pub fn export_json(
+        &self,
+        discourse_id: &i32,
+        agrees_to_the_creed: &bool,
+    ) -> Result<String, OkeuvoError> {
+        // Check that the user has agreed to The Creed.
+        // Stop processing with an error if they haven't.
+        // The user interface must present a choice whose value we use here.
+        if !agrees_to_the_creed {
+            return Err(OkeuvoError::CreedNotAccepted);
+        }
+
+        let discourse: Discourse = select_discourse(&self.conn_input, discourse_id)?;
+        let encoding: Encoding = select_encoding(&self.conn_output, discourse_id)
+            .map_err(|e| OkeuvoError::from(e).in_discourse(discourse_id))?;
+        let is_encoded: bool = !encoding.unit_tensors.is_empty()
+            || !encoding.hash_items_real.is_empty()
+            || !encoding.hash_items_virtual.is_empty();
+
+        DiscourseDocument::new(discourse, if is_encoded { Some(encoding) } else { None }).to_json()
+    }
+
+    /// Writes an encoding (see encode) to the output database,
+    /// replacing any earlier encoding of the same discourse.
+    pub fn save_encoding(
+        &self,
+        encoding: &Encoding,
+        agrees_to_the_creed: &bool,
+    ) -> Result<(), OkeuvoError> {
+        // Check that the user has agreed to The Creed.
+        // Stop processing with an error if they haven't.
+        // The user interface must present a choice whose value we use here.
+        if !agrees_to_the_creed {
+            return Err(OkeuvoError::CreedNotAccepted);
+        }
+
+        insert_encoding(&self.conn_output, encoding)
+            .map_err(|e| OkeuvoError::from(e).in_discourse(&encoding.discourse_id))
+    }
+
+    /// Encodes a discourse (see encode_discourse).
+    /// The encoding written to the output database is also returned.
+    pub fn encode_discourse(
+        &self,
+        discourse_id: &i32,
+        agrees_to_the_creed: &bool,
+    ) -> Result<Encoding, OkeuvoError> {
+        // Check that the user has agreed to The Creed.
+        // Stop processing with an error if they haven't.
+        // The user interface must present a choice whose value we use here.
+        if !agrees_to_the_creed {
+            return Err(OkeuvoError::CreedNotAccepted);
+        }
+
+        let discourse: Discourse = select_discourse(&self.conn_input, discourse_id)?;
+
+        // The coordinates in the input database are taken to be those
+        // of the meaning grid in the metadata database, whose antonyms
+        // negated words take.
+        let antonym_grid: MeaningGrid = MeaningGrid::antonyms_from_metadata(&self.conn_meta)
+            .map_err(|e| e.in_discourse(discourse_id))?;
+        let mut encoding: Encoding =
+            encode_inner(&discourse, None, Some(&antonym_grid), &self.function_words)?;
+        encoding.versions = Versions::new(antonym_grid.version());
+
+        insert_encoding(&self.conn_output, &encoding)
+            .map_err(|e| OkeuvoError::from(e).in_discourse(discourse_id))?;
+
+        Ok(encoding)
+    }
+
+    /// Encodes a discourse, resolving missing word coordinates from
+    /// the meaning grid first (see encode_discourse_with_grid).
+    /// The encoding written to the output database is also returned.
+    pub fn encode_discourse_with_grid(
+        &self,
+        discourse_id: &i32,
+        grid: &MeaningGrid,
+        agrees_to_the_creed: &bool,
+    ) -> Result<Encoding, OkeuvoError> {
+        // Check that the user has agreed to The Creed.
+        // Stop processing with an error if they haven't.
+        // The user interface must present a choice whose value we use here.
+        if !agrees_to_the_creed {
+            return Err(OkeuvoError::CreedNotAccepted);
+        }
+
+        let discourse: Discourse = select_discourse(&self.conn_input, discourse_id)?;
+        let encoding: Encoding =
+            encode_inner(&discourse, Some(grid), Some(grid), &self.function_words)?;
+        insert_encoding(&self.conn_output, &encoding)
+            .map_err(|e| OkeuvoError::from(e).in_discourse(discourse_id))?;
+
+        Ok(encoding)
+    }
+
+    /// Creates the discourse hash (see get_hash).
+    pub fn get_hash(
+        &self,
+        discourse_id: &i32,
+        agrees_to_the_creed: &bool,
+        is_virtual: &bool,
+    ) -> Result<String, OkeuvoError> {
+        // Check that the user has agreed to The Creed.
+        // Stop processing with an error if they haven't.
+        // The user interface must present a choice whose value we use here.
+        if !agrees_to_the_creed {
+            return Err(OkeuvoError::CreedNotAccepted);
+        }
+
+        get_hash_inner(
+            &self.conn_output,
+            &self.conn_meta,
+            discourse_id,
+            is_virtual,
+            &HashSpec::default(),
+        )
+    }
+
+    /// Creates the discourse hash with a given spec (see get_hash_with_spec).
+    pub fn get_hash_with_spec(
+        &self,
+        discourse_id: &i32,
+        agrees_to_the_creed: &bool,
+        is_virtual: &bool,
+        spec: &HashSpec,
+    ) -> Result<String, OkeuvoError> {
+        // Check that the user has agreed to The Creed.
+        // Stop processing with an error if they haven't.
+        // The user interface must present a choice whose value we use here.
+        if !agrees_to_the_creed {
+            return Err(OkeuvoError::CreedNotAccepted);
+        }
+
+        get_hash_inner(
+            &self.conn_output,
+            &self.conn_meta,
+            discourse_id,
+            is_virtual,
+            spec,
+        )
+    }
+
+    /// Creates the envelope of the hash of a discourse (see get_hash_envelope).
+    #[cfg(feature = "signing")]
+    pub fn get_hash_envelope(
+        &self,
+        discourse_id: &i32,
+        agrees_to_the_creed: &bool,
+        is_virtual: &bool,
+        spec: &HashSpec,
+        author_public_key: &[u8; 32],
+    ) -> Result<HashEnvelope, OkeuvoError> {
+        // Check that the user has agreed to The Creed.
+        // Stop processing with an error if they haven't.
+        // The user interface must present a choice whose value we use here.
+        if !agrees_to_the_creed {
+            return Err(OkeuvoError::CreedNotAccepted);
+        }
+
+        let discourse: InputDiscourse = select_input_discourse(&self.conn_input, discourse_id)?;
+        let hash: String = get_hash_inner(
+            &self.conn_output,
+            &self.conn_meta,
+            discourse_id,
+            is_virtual,
+            spec,
+        )?;
+
+        HashEnvelope::new(&discourse, &hash, author_public_key)
+    }
+
+    /// Explains the encoding of a discourse (see explain_hash).
+    pub fn explain_hash(
+        &self,
+        discourse_id: &i32,
+        is_virtual: &bool,
+        index: &MeaningGridIndex,
+        k: &usize,
+        agrees_to_the_creed: &bool,
+    ) -> Result<Vec<HashItemExplanation>, OkeuvoError> {
+        // Check that the user has agreed to The Creed.
+        // Stop processing with an error if they haven't.
+        // The user interface must present a choice whose value we use here.
+        if !agrees_to_the_creed {
+            return Err(OkeuvoError::CreedNotAccepted);
+        }
+
+        let hash_item_vec: Vec<HashItem> =
+            select_hash_item(&self.conn_output, discourse_id, &(*is_virtual as i32))
+                .map_err(|e| OkeuvoError::from(e).in_discourse(discourse_id))?;
+
+        Ok(explain_hash_items(&hash_item_vec, index, *k))
+    }
+
+    /// Creates senses for words of a discourse that do not yet exist
+    /// in the meaning grid (see batch_define_new_word_sense).
+    pub fn batch_define_new_word_sense(
+        &self,
+        discourse_id: i32,
+        agrees_to_the_creed: &bool,
+    ) -> Result<HashMap<i32, (f64, f64)>, OkeuvoError> {
+        // Check that the user has agreed to The Creed.
+        // Stop processing with an error if they haven't.
+        // The user interface must present a choice whose value we use here.
+        if !agrees_to_the_creed {
+            return Err(OkeuvoError::CreedNotAccepted);
+        }
+
+        batch_define_new_word_sense_inner(
+            &self.conn_input,
+            &self.conn_meta,
+            discourse_id,
+            &self.function_words,
+        )
+    }
+
+    /// Imports the meaning grid from a CSV file into the metadata database
+    /// (see import_meaning_grid).
+    ///
+    /// Return format: (grid version, number of items imported).
+    pub fn import_meaning_grid(
+        &mut self,
+        csv_path: &str,
+        agrees_to_the_creed: &bool,
+    ) -> Result<(String, usize), OkeuvoError> {
+        // Check that the user has agreed to The Creed.
+        // Stop processing with an error if they haven't.
+        // The user interface must present a choice whose value we use here.
+        if !agrees_to_the_creed {
+            return Err(OkeuvoError::CreedNotAccepted);
+        }
+
+        import_meaning_grid_inner(&mut self.conn_meta, csv_path)
+    }
+
+    /// Loads the meaning grid in the metadata database into memory.
+    pub fn load_meaning_grid(
+        &self,
+        agrees_to_the_creed: &bool,
+    ) -> Result<MeaningGrid, OkeuvoError> {
+        // Check that the user has agreed to The Creed.
+        // Stop processing with an error if they haven't.
+        // The user interface must present a choice whose value we use here.
+        if !agrees_to_the_creed {
+            return Err(OkeuvoError::CreedNotAccepted);
+        }
+
+        MeaningGrid::from_metadata(&self.conn_meta)
+    }
+
+    /// Imports the antonym pairs of a Wordnet 3.0 database file into the
+    /// metadata database (see import_antonyms).
+    ///
+    /// Return format: number of pairs imported.
+    pub fn import_antonyms(
+        &mut self,
+        wordnet_path: &str,
+        agrees_to_the_creed: &bool,
+    ) -> Result<usize, OkeuvoError> {
+        // Check that the user has agreed to The Creed.
+        // Stop processing with an error if they haven't.
+        // The user interface must present a choice whose value we use here.
+        if !agrees_to_the_creed {
+            return Err(OkeuvoError::CreedNotAccepted);
+        }
+
+        import_antonyms_inner(&mut self.conn_meta, wordnet_path)
+    }
+
+    /// Gets the antonyms of a synset in the metadata database (see get_antonyms).
+    pub fn antonyms(
+        &self,
+        synset_id: &i32,
+        agrees_to_the_creed: &bool,
+    ) -> Result<Vec<Antonym>, OkeuvoError> {
+        // Check that the user has agreed to The Creed.
+        // Stop processing with an error if they haven't.
+        // The user interface must present a choice whose value we use here.
+        if !agrees_to_the_creed {
+            return Err(OkeuvoError::CreedNotAccepted);
+        }
+
+        Ok(select_antonyms(&self.conn_meta, synset_id)?)
+    }
+
+    /// Closes all three databases, reporting the first failure.
+    pub fn close(self) -> Result<(), OkeuvoError> {
+        let results = vec![
+            self.conn_input.close(),
+            self.conn_output.close(),
+            self.conn_meta.close(),
+        ];
+
+        for result in results {
+            if let Err(e) = result {
+                Err(e.1)?
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,713 @@
+/*
+DO NOT ALTER OR REMOVE COPYRIGHT NOTICES OR THIS HEADER.
+
+Copyright © 2019 Asame Imoni Obiomah. All rights reserved.
+
+Artificial intelligence ethics is of existential importance.
+The licensing model of OkeuvoLite enforces adherence to a strict ethical code.
+
+The contents of this file are subject to the terms of both the GNU General Public License Version 2 only (“GPL”)
+and Inverse license (collectively, the “License”). You may not use this file except in compliance with the License.
+You can obtain a copy of the License at LICENSE.txt. See the License for the specific language governing
+permissions and limitations under the License.
+
+When distributing the software, include this License Header Notice in each file and include the License file at LICENSE.txt.
+*/
+
+//! Schemas of input.db, output.db and metadata.db.
+//!
+//! Each database records its schema version in "pragma user_version".
+//! The schema is built up by an ordered list of migrations; the migration at
+//! position i of a list takes a database from schema version i to i + 1.
+//! A database with no version stamp (version 0) is either new or a store
+//! created by hand from the SQL folder, and both are brought up to date by
+//! running every migration in order.
+//!
+//! Migrations are never edited once released. To change a schema, append
+//! a migration to the end of the list.
+
+use crate::error::OkeuvoError;
+use rusqlite::Connection;
+
+/// Migrations for input.db.
+const INPUT_MIGRATIONS: &[&str] = &[
+    // 1. The schema as first shipped in storage/input.db.
+    "create table if not exists input_discourse (
+        discourse_id       integer primary key,
+        hypernym_synset_id integer,
+        document_hash      string,
+        author_public_hash string,
+        author_title       string,
+        author_first_name  string,
+        author_middle_name string,
+        author_surname     string,
+        author_zone        string,
+        date_unix_epoch    integer,
+        x                  double,
+        y                  double
+    );
+    create table if not exists input_discourse_title (
+        discourse_id integer primary key,
+        word_id      integer
+    );
+    create table if not exists input_exempt_feature (
+        discourse_id     integer,
+        ud_feature       string,
+        ud_feature_value string
+    );
+    create table if not exists input_new_word_def (
+        new_word_id  integer primary key,
+        is_a         integer,
+        discourse_id integer,
+        lexeme       string,
+        x            double,
+        y            double
+    );
+    create table if not exists input_section (
+        section_id   integer primary key,
+        triplet_id   integer,
+        word_id      integer,
+        section_type integer
+    );
+    create table if not exists input_sentence (
+        sentence_id  integer primary key,
+        discourse_id integer,
+        is_question  bool
+    );
+    create table if not exists input_triplet (
+        triplet_id  integer primary key,
+        sentence_id integer,
+        tense       integer,
+        is_virtual  boolean,
+        is_passive  boolean
+    );
+    create table if not exists input_word (
+        word_id        integer primary key,
+        sentence_id    integer,
+        synset_id      integer default (0),
+        index_of_word  integer,
+        lexeme         string,
+        instance_name  string,
+        instance_index string  default (0),
+        pos            string,
+        x              double  default (0),
+        y              double  default (0),
+        is_transition  boolean,
+        new_word_id    integer default (-1)
+    );
+    create table if not exists input_word_feature (
+        word_id          integer,
+        ud_relation      string,
+        ud_feature       string,
+        ud_feature_value string
+    );
+    create table if not exists input_word_relation (
+        word_id          integer,
+        word_id_modified integer,
+        ud_relation      string
+    );",
+    // 2. Bring the shipped schema in line with SQL/input.sql.
+    // Columns declared "string" have numeric affinity in SQLite, so they
+    // become text. instance_index is read as an integer, so it becomes one.
+    // A discourse title can have more than one word, so input_discourse_title
+    // is keyed on (discourse_id, word_id) rather than on discourse_id alone.
+    // The foreign keys of SQL/input.sql are declared, deferred to the end of
+    // each transaction. A word that is not new has a null new_word_id instead
+    // of -1, and the relation of a root word a null word_id_modified instead of -1.
+    "create table input_discourse_new (
+        discourse_id       integer primary key,
+        hypernym_synset_id integer,
+        document_hash      text,
+        author_public_hash text,
+        author_title       text,
+        author_first_name  text,
+        author_middle_name text,
+        author_surname     text,
+        author_zone        text,
+        date_unix_epoch    integer,
+        x                  double,
+        y                  double
+    );
+    insert into input_discourse_new
+        select discourse_id, hypernym_synset_id, document_hash, author_public_hash, author_title,
+        author_first_name, author_middle_name, author_surname, author_zone, date_unix_epoch, x, y
+        from input_discourse;
+    drop table input_discourse;
+    alter table input_discourse_new rename to input_discourse;
+
+    create table input_discourse_title_new (
+        discourse_id integer references input_discourse (discourse_id) deferrable initially deferred,
+        word_id      integer references input_word (word_id) deferrable initially deferred,
+        primary key (discourse_id, word_id)
+    );
+    insert or ignore into input_discourse_title_new
+        select discourse_id, word_id from input_discourse_title;
+    drop table input_discourse_title;
+    alter table input_discourse_title_new rename to input_discourse_title;
+
+    create table input_exempt_feature_new (
+        discourse_id     integer references input_discourse (discourse_id) deferrable initially deferred,
+        ud_feature       text,
+        ud_feature_value text
+    );
+    insert into input_exempt_feature_new
+        select discourse_id, ud_feature, ud_feature_value from input_exempt_feature;
+    drop table input_exempt_feature;
+    alter table input_exempt_feature_new rename to input_exempt_feature;
+
+    create table input_new_word_def_new (
+        new_word_id  integer primary key,
+        is_a         integer,
+        discourse_id integer references input_discourse (discourse_id) deferrable initially deferred,
+        lexeme       text,
+        x            double,
+        y            double
+    );
+    insert into input_new_word_def_new
+        select new_word_id, is_a, discourse_id, lexeme, x, y from input_new_word_def;
+    drop table input_new_word_def;
+    alter table input_new_word_def_new rename to input_new_word_def;
+
+    create table input_section_new (
+        section_id   integer primary key,
+        triplet_id   integer references input_triplet (triplet_id) deferrable initially deferred,
+        word_id      integer references input_word (word_id) deferrable initially deferred,
+        section_type integer
+    );
+    insert into input_section_new
+        select section_id, triplet_id, word_id, section_type from input_section;
+    drop table input_section;
+    alter table input_section_new rename to input_section;
+
+    create table input_sentence_new (
+        sentence_id  integer primary key,
+        discourse_id integer references input_discourse (discourse_id) deferrable initially deferred,
+        is_question  bool
+    );
+    insert into input_sentence_new
+        select sentence_id, discourse_id, is_question from input_sentence;
+    drop table input_sentence;
+    alter table input_sentence_new rename to input_sentence;
+
+    create table input_triplet_new (
+        triplet_id  integer primary key,
+        sentence_id integer references input_sentence (sentence_id) deferrable initially deferred,
+        tense       integer,
+        is_virtual  boolean,
+        is_passive  boolean
+    );
+    insert into input_triplet_new
+        select triplet_id, sentence_id, tense, is_virtual, is_passive from input_triplet;
+    drop table input_triplet;
+    alter table input_triplet_new rename to input_triplet;
+
+    create table input_word_new (
+        word_id        integer primary key,
+        sentence_id    integer references input_sentence (sentence_id) deferrable initially deferred,
+        synset_id      integer default (0),
+        index_of_word  integer,
+        lexeme         text,
+        instance_name  text,
+        instance_index integer default (0),
+        pos            text,
+        x              double  default (0),
+        y              double  default (0),
+        is_transition  boolean,
+        new_word_id    integer references input_new_word_def (new_word_id) deferrable initially deferred
+    );
+    insert into input_word_new
+        select word_id, sentence_id, synset_id, index_of_word, lexeme, instance_name,
+        cast(instance_index as integer), pos, x, y, is_transition, nullif(new_word_id, -1)
+        from input_word;
+    drop table input_word;
+    alter table input_word_new rename to input_word;
+
+    create table input_word_feature_new (
+        word_id          integer references input_word (word_id) deferrable initially deferred,
+        ud_relation      text,
+        ud_feature       text,
+        ud_feature_value text
+    );
+    insert into input_word_feature_new
+        select word_id, ud_relation, ud_feature, ud_feature_value from input_word_feature;
+    drop table input_word_feature;
+    alter table input_word_feature_new rename to input_word_feature;
+
+    create table input_word_relation_new (
+        word_id          integer references input_word (word_id) deferrable initially deferred,
+        word_id_modified integer references input_word (word_id) deferrable initially deferred,
+        ud_relation      text
+    );
+    insert into input_word_relation_new
+        select word_id, nullif(word_id_modified, -1), ud_relation from input_word_relation;
+    drop table input_word_relation;
+    alter table input_word_relation_new rename to input_word_relation;",
+];
+
+/// Migrations for output.db.
+const OUTPUT_MIGRATIONS: &[&str] = &[
+    // 1. The schema as first shipped in storage/output.db.
+    "create table if not exists entity (
+        entity_id      integer primary key,
+        instance_index integer,
+        discourse_id   integer,
+        synset_id      integer,
+        word_id        integer,
+        rank           double,
+        x              double,
+        y              double,
+        triplet_id     integer
+    );
+    create table if not exists hash_item (
+        radius          integer,
+        radius_original double,
+        angle           integer,
+        angle_original  double,
+        hash_type       integer,
+        discourse_id    integer,
+        excited_radius  double,
+        excited_angle   double,
+        order_by        integer,
+        is_virtual      bool
+    );
+    create table if not exists unit_tensor (
+        unit_tensor_id      integer primary key,
+        sentence_id         integer,
+        discourse_id        integer,
+        object_entity_id    integer,
+        subject_entity_id   integer,
+        where_entity_id     integer,
+        when_entity_id      integer,
+        predicate_entity_id integer,
+        tense               integer,
+        mood                string,
+        excited_x           double,
+        excited_y           double
+    );
+    create table if not exists unit_tensor_ethereal_def (
+        id        integer primary key,
+        branch_id integer,
+        rejoin_id integer
+    );",
+    // 2. mood is text ("string" has numeric affinity in SQLite).
+    "create table unit_tensor_new (
+        unit_tensor_id      integer primary key,
+        sentence_id         integer,
+        discourse_id        integer,
+        object_entity_id    integer,
+        subject_entity_id   integer,
+        where_entity_id     integer,
+        when_entity_id      integer,
+        predicate_entity_id integer,
+        tense               integer,
+        mood                text,
+        excited_x           double,
+        excited_y           double
+    );
+    insert into unit_tensor_new
+        select unit_tensor_id, sentence_id, discourse_id, object_entity_id, subject_entity_id,
+        where_entity_id, when_entity_id, predicate_entity_id, tense, mood, excited_x, excited_y
+        from unit_tensor;
+    drop table unit_tensor;
+    alter table unit_tensor_new rename to unit_tensor;
+    create index if not exists hash_item_discourse_id on hash_item (discourse_id, is_virtual);",
+    // 3. Record the library and meaning grid versions each discourse was encoded with.
+    "create table if not exists encoding_version (
+        discourse_id    integer primary key,
+        library_version text,
+        grid_version    text
+    );",
+    // 4. Record which unit tensors have a negated predicate or object.
+    "alter table unit_tensor add column is_negated bool not null default 0;",
+    // 5. Every "where" and "when" of a unit tensor, of which there can be several.
+    "create table if not exists unit_tensor_location (
+        id             integer primary key,
+        unit_tensor_id integer,
+        entity_id      integer,
+        location_type  integer
+    );
+    create index if not exists unit_tensor_location_unit_tensor_id
+        on unit_tensor_location (unit_tensor_id);",
+];
+
+/// Migrations for metadata.db.
+const METADATA_MIGRATIONS: &[&str] = &[
+    // 1. The schema as first shipped in storage/metadata.db.
+    "create table if not exists meaning_grid_item (
+        x         double,
+        y         double,
+        synset_id integer
+    );
+    create table if not exists antonyms (
+        coarse_class integer,
+        synset_id1   integer,
+        lemma1       string,
+        synset_id2   integer,
+        lemma2       string
+    );",
+    // 2. Record the version of the imported meaning grid, index synset lookups
+    // and make lemmas text ("string" has numeric affinity in SQLite).
+    "create table if not exists meaning_grid_version (
+        version text
+    );
+    create index if not exists meaning_grid_item_synset_id on meaning_grid_item (synset_id);
+    create table antonyms_new (
+        coarse_class integer,
+        synset_id1   integer,
+        lemma1       text,
+        synset_id2   integer,
+        lemma2       text
+    );
+    insert into antonyms_new
+        select coarse_class, synset_id1, lemma1, synset_id2, lemma2 from antonyms;
+    drop table antonyms;
+    alter table antonyms_new rename to antonyms;",
+    // 3. Index antonym lookups, which go by either word of a pair.
+    "create index if not exists antonyms_synset_id1 on antonyms (synset_id1);
+    create index if not exists antonyms_synset_id2 on antonyms (synset_id2);",
+];
+
+/// The databases an Okeuvo engine works with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DatabaseKind {
+    /// input.db, which holds raw triplets and triplet-word relations.
+    Input,
+    /// output.db, which holds the encoding of the input as "hash items".
+    Output,
+    /// metadata.db, whose contents include the meaning grid.
+    Metadata,
+}
+
+impl DatabaseKind {
+    /// Name of the database, for messages.
+    pub fn name(&self) -> &'static str {
+        match self {
+            DatabaseKind::Input => "input.db",
+            DatabaseKind::Output => "output.db",
+            DatabaseKind::Metadata => "metadata.db",
+        }
+    }
+
+    /// The schema version this library brings the database up to.
+    pub fn schema_version(&self) -> u32 {
+        self.migrations().len() as u32
+    }
+
+    fn migrations(&self) -> &'static [&'static str] {
+        match self {
+            DatabaseKind::Input => INPUT_MIGRATIONS,
+            DatabaseKind::Output => OUTPUT_MIGRATIONS,
+            DatabaseKind::Metadata => METADATA_MIGRATIONS,
+        }
+    }
+}
+
+/// Gets the schema version stamped in a database (0 if it has none).
+pub(crate) fn select_schema_version(conn: &Connection) -> rusqlite::Result<u32> {
+    conn.query_row("pragma user_version", [], |row| row.get(0))
+}
+
+/// Creates the schema of a database, or upgrades it, by applying every migration
+/// past the version stamped in it. Each migration runs in its own transaction
+/// together with the new version stamp, so a failed migration leaves the
+/// database at the last version that was applied in full.
+///
+/// Databases stamped with a version newer than this library knows are refused.
+///
+/// Return format: the schema version of the database after migrating.
+pub(crate) fn migrate(conn: &mut Connection, kind: DatabaseKind) -> Result<u32, OkeuvoError> {
+    let current_version: u32 = select_schema_version(conn)?;
+    let migrations: &[&str] = kind.migrations();
+
+    if current_version as usize > migrations.len() {
+        return Err(OkeuvoError::UnsupportedSchemaVersion {
+            database: kind,
+            version: current_version,
+            supported: migrations.len() as u32,
+        });
+    }
+
+    for (i, migration) in migrations.iter().enumerate().skip(current_version as usize) {
+        let tx = conn.transaction()?;
+        tx.execute_batch(migration)?;
+        tx.pragma_update(None, "user_version", (i + 1) as u32)?;
+        tx.commit()?;
+    }
+
+    Ok(migrations.len() as u32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KINDS: &[DatabaseKind] = &[
+        DatabaseKind::Input,
+        DatabaseKind::Output,
+        DatabaseKind::Metadata,
+    ];
+
+    /// Gets the names of the tables and indexes of a database.
+    fn schema_names(conn: &Connection) -> Vec<String> {
+        let mut stmt = conn
+            .prepare("select name from sqlite_master where name not like 'sqlite_%' order by name")
+            .unwrap();
+        let names = stmt
+            .query_map([], |row| row.get(0))
+            .unwrap()
+            .collect::<rusqlite::Result<Vec<String>>>()
+            .unwrap();
+
+        names
+    }
+
+    #[test]
+    fn migrates_a_fresh_database_to_the_latest_version() {
+        for kind in KINDS {
+            let mut conn = Connection::open_in_memory().unwrap();
+
+            assert_eq!(migrate(&mut conn, *kind).unwrap(), kind.schema_version());
+            assert_eq!(select_schema_version(&conn).unwrap(), kind.schema_version());
+
+            // Migrating again changes nothing.
+            let names: Vec<String> = schema_names(&conn);
+            assert_eq!(migrate(&mut conn, *kind).unwrap(), kind.schema_version());
+            assert_eq!(schema_names(&conn), names);
+        }
+    }
+
+    #[test]
+    fn migrates_a_version_2_database_to_the_latest_version() {
+        for kind in KINDS {
+            let mut conn = Connection::open_in_memory().unwrap();
+            for migration in &kind.migrations()[..2] {
+                conn.execute_batch(migration).unwrap();
+            }
+            conn.pragma_update(None, "user_version", 2).unwrap();
+
+            assert_eq!(migrate(&mut conn, *kind).unwrap(), kind.schema_version());
+            assert_eq!(select_schema_version(&conn).unwrap(), kind.schema_version());
+
+            let mut fresh = Connection::open_in_memory().unwrap();
+            migrate(&mut fresh, *kind).unwrap();
+            assert_eq!(schema_names(&conn), schema_names(&fresh));
+        }
+    }
+
+    #[test]
+    fn keeps_rows_when_migrating_output_from_version_2() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        for migration in &OUTPUT_MIGRATIONS[..2] {
+            conn.execute_batch(migration).unwrap();
+        }
+        conn.pragma_update(None, "user_version", 2).unwrap();
+        conn.execute(
+            "insert into unit_tensor (unit_tensor_id, discourse_id, mood) values (1, 7, 'Ind')",
+            [],
+        )
+        .unwrap();
+
+        migrate(&mut conn, DatabaseKind::Output).unwrap();
+
+        let (discourse_id, mood, is_negated): (i32, String, bool) = conn
+            .query_row(
+                "select discourse_id, mood, is_negated from unit_tensor where unit_tensor_id = 1",
+                [],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+            )
+            .unwrap();
+        assert_eq!((discourse_id, mood.as_str(), is_negated), (7, "Ind", false));
+    }
+
+    /// Gets the columns of every table of a database, sorted by name (the reference
+    /// scripts list some in another order than the migrations), and its schema version.
+    fn schema_columns(conn: &Connection) -> (Vec<(String, String)>, u32) {
+        let mut stmt = conn
+            .prepare(
+                "select m.name, c.name from sqlite_master m, pragma_table_info(m.name) c
+                 where m.type = 'table' order by m.name, c.name",
+            )
+            .unwrap();
+        let columns = stmt
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+            .unwrap()
+            .collect::<rusqlite::Result<Vec<(String, String)>>>()
+            .unwrap();
+
+        (columns, select_schema_version(conn).unwrap())
+    }
+
+    /// Gets the foreign keys of every table of a database, with when they are checked.
+    ///
+    /// Return format: Vec<(table, column, parent table, parent column, deferred)>.
+    fn foreign_keys(conn: &Connection) -> Vec<(String, String, String, String, bool)> {
+        let mut stmt = conn
+            .prepare(
+                "select m.name, k.\"from\", k.\"table\", k.\"to\", m.sql
+                 from sqlite_master m, pragma_foreign_key_list(m.name) k
+                 where m.type = 'table' order by m.name, k.\"from\"",
+            )
+            .unwrap();
+        let keys = stmt
+            .query_map([], |row| {
+                let sql: String = row.get(4)?;
+                Ok((
+                    row.get(0)?,
+                    row.get(1)?,
+                    row.get(2)?,
+                    row.get(3)?,
+                    sql.to_lowercase().contains("deferrable initially deferred"),
+                ))
+            })
+            .unwrap()
+            .collect::<rusqlite::Result<Vec<(String, String, String, String, bool)>>>()
+            .unwrap();
+
+        keys
+    }
+
+    /// Counts the rows of a database that break a foreign key.
+    fn foreign_key_violations(conn: &Connection) -> i32 {
+        conn.query_row("select count(*) from pragma_foreign_key_check", [], |row| {
+            row.get(0)
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn declares_the_foreign_keys_of_input_deferred() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        migrate(&mut conn, DatabaseKind::Input).unwrap();
+
+        let keys = foreign_keys(&conn);
+        assert_eq!(keys.len(), 13);
+        assert!(keys.iter().all(|key| key.4));
+        assert!(keys.contains(&(
+            "input_word".to_string(),
+            "new_word_id".to_string(),
+            "input_new_word_def".to_string(),
+            "new_word_id".to_string(),
+            true
+        )));
+    }
+
+    #[test]
+    fn keeps_rows_when_migrating_input_from_version_1() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(INPUT_MIGRATIONS[0]).unwrap();
+        conn.pragma_update(None, "user_version", 1).unwrap();
+        conn.execute_batch(
+            "insert into input_discourse (discourse_id) values (7);
+             insert into input_sentence (sentence_id, discourse_id) values (1, 7);
+             insert into input_word (word_id, sentence_id, instance_index) values (1, 1, '2');
+             insert into input_new_word_def (new_word_id, discourse_id) values (3, 7);
+             insert into input_word (word_id, sentence_id, new_word_id) values (2, 1, 3);
+             insert into input_word_relation values (1, -1, 'root'), (2, 1, 'obj');",
+        )
+        .unwrap();
+
+        migrate(&mut conn, DatabaseKind::Input).unwrap();
+
+        let mut stmt = conn
+            .prepare(
+                "select w.word_id, w.instance_index, w.new_word_id, r.word_id_modified
+                 from input_word w inner join input_word_relation r on r.word_id = w.word_id
+                 order by w.word_id",
+            )
+            .unwrap();
+        let words = stmt
+            .query_map([], |row| {
+                Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?))
+            })
+            .unwrap()
+            .collect::<rusqlite::Result<Vec<(i32, i32, Option<i32>, Option<i32>)>>>()
+            .unwrap();
+        // -1 (not a new word, or the root, which has no head) becomes null.
+        assert_eq!(words, vec![(1, 2, None, None), (2, 0, Some(3), Some(1))]);
+        assert_eq!(foreign_key_violations(&conn), 0);
+    }
+
+    #[test]
+    fn stores_a_discourse_with_foreign_keys_enforced() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        migrate(&mut conn, DatabaseKind::Input).unwrap();
+        conn.pragma_update(None, "foreign_keys", true).unwrap();
+        let mut discourse = crate::test_support::discourse(&[
+            "he he PRON _ 2 nsubj",
+            "ate eat VERB Tense=Past 0 root",
+            "cake cake NOUN _ 2 obj",
+        ]);
+        discourse.discourse.discourse_id = 7;
+        discourse.new_word_defs.push(crate::data::InputNewWordDef {
+            new_word_id: 1,
+            lexeme: "cake".to_string(),
+            ..Default::default()
+        });
+        discourse.words[2].new_word_id = 1;
+
+        // Written in full, then again in place of the first.
+        crate::data::insert_input_discourse(&conn, &discourse).unwrap();
+        let stored = crate::data::insert_input_discourse(&conn, &discourse).unwrap();
+
+        assert_eq!(foreign_key_violations(&conn), 0);
+        let mut stmt = conn
+            .prepare("select new_word_id from input_word order by word_id")
+            .unwrap();
+        let new_word_ids = stmt
+            .query_map([], |row| row.get(0))
+            .unwrap()
+            .collect::<rusqlite::Result<Vec<Option<i32>>>>()
+            .unwrap();
+        assert_eq!(
+            new_word_ids,
+            vec![None, None, Some(stored.new_word_defs[0].new_word_id)]
+        );
+    }
+
+    #[test]
+    fn reference_scripts_match_the_migrations() {
+        for (kind, script) in KINDS.iter().zip(&[
+            include_str!("../SQL/input.sql"),
+            include_str!("../SQL/output.sql"),
+            include_str!("../SQL/metadata.sql"),
+        ]) {
+            let mut conn = Connection::open_in_memory().unwrap();
+            migrate(&mut conn, *kind).unwrap();
+
+            let scripted = Connection::open_in_memory().unwrap();
+            scripted.execute_batch(script).unwrap();
+
+            assert_eq!(schema_names(&scripted), schema_names(&conn), "{:?}", kind);
+            assert_eq!(
+                schema_columns(&scripted),
+                schema_columns(&conn),
+                "{:?}",
+                kind
+            );
+            assert_eq!(foreign_keys(&scripted), foreign_keys(&conn), "{:?}", kind);
+        }
+    }
+
+    #[test]
+    fn refuses_a_database_newer_than_the_library() {
+        for kind in KINDS {
+            let mut conn = Connection::open_in_memory().unwrap();
+            let newer: u32 = kind.schema_version() + 1;
+            conn.pragma_update(None, "user_version", newer).unwrap();
+
+            match migrate(&mut conn, *kind) {
+                Err(OkeuvoError::UnsupportedSchemaVersion {
+                    database,
+                    version,
+                    supported,
+                }) => {
+                    assert_eq!(database, *kind);
+                    assert_eq!(version, newer);
+                    assert_eq!(supported, kind.schema_version());
+                }
+                result => panic!("expected UnsupportedSchemaVersion, got {:?}", result),
+            }
+            assert_eq!(select_schema_version(&conn).unwrap(), newer);
+        }
+    }
+}
@@ -0,0 +1,355 @@
+/*
+DO NOT ALTER OR REMOVE COPYRIGHT NOTICES OR THIS HEADER.
+
+Copyright © 2019 Asame Imoni Obiomah. All rights reserved.
+
+Artificial intelligence ethics is of existential importance.
+The licensing model of OkeuvoLite enforces adherence to a strict ethical code.
+
+The contents of this file are subject to the terms of both the GNU General Public License Version 2 only (“GPL”)
+and Inverse license (collectively, the “License”). You may not use this file except in compliance with the License.
+You can obtain a copy of the License at LICENSE.txt. See the License for the specific language governing
+permissions and limitations under the License.
+
+When distributing the software, include this License Header Notice in each file and include the License file at LICENSE.txt.
+*/
+
+//! Measures how similar two hashes are, record by record.
+
+use crate::data::HashItemFormatted;
+use crate::hash::{HashRecord, Lean, ParsedHash, HASH_RECORDS};
+use crate::math::{area_ratio_coordinate, Point2D};
+use crate::utils::distance;
+
+/// Largest apex angle, in degrees.
+const MAX_ANGLE: f64 = 180.0;
+
+/// Distance, relative to the (max x, max y) of the meaning grid,
+/// at which two coordinates of area ratio hashes share nothing:
+/// that between the origin and (max x, max y).
+const MAX_RELATIVE_DISTANCE: f64 = std::f64::consts::SQRT_2;
+
+/// Share of the similarity of an apex angle lost for each step
+/// between two lean directions (left to upright, upright to right).
+const LEAN_STEP_PENALTY: f64 = 0.25;
+
+/// How similar the records of two hashes at the same rank are.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecordSimilarity {
+    /// Position of the records in their hashes, 0 being the discourse hypernym.
+    pub rank: usize,
+    /// Weight of the records in the score of the hashes: 1 / (rank + 1).
+    pub weight: f64,
+    /// Similarity of the unmodified coordinates, from 0 to 1.
+    pub similarity: f64,
+    /// Similarity of the excited coordinates, from 0 to 1.
+    pub excited_similarity: f64,
+    /// True where only one of the records is padding.
+    /// Such records have no similarity.
+    pub is_padded: bool,
+}
+
+impl RecordSimilarity {
+    /// Gets the similarity of the records,
+    /// the mean of those of their unmodified and excited coordinates.
+    pub fn score(&self) -> f64 {
+        (self.similarity + self.excited_similarity) / 2.0
+    }
+}
+
+/// How similar two hashes are.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HashSimilarity {
+    /// Weighted mean of the record scores, from 0 (unrelated) to 1 (the same).
+    pub score: f64,
+    /// The records compared, in rank order.
+    /// Ranks at which both hashes are padding are left out.
+    pub records: Vec<RecordSimilarity>,
+}
+
+/// A record reduced to what is compared.
+#[derive(Debug, Clone)]
+pub(crate) enum ComparableRecord {
+    /// The lean directions and apex angles (in degrees).
+    ApexAngle {
+        lean: Lean,
+        apex_angle: f64,
+        excited_lean: Lean,
+        excited_apex_angle: f64,
+    },
+    /// The coordinates, relative to the (max x, max y) of the meaning grid.
+    Point {
+        point: Point2D,
+        excited_point: Point2D,
+    },
+}
+
+/// Gets the comparable records of a hash. Padding is None.
+pub(crate) fn hash_comparable_records(hash: &ParsedHash) -> Vec<Option<ComparableRecord>> {
+    hash.records
+        .iter()
+        .map(|record| match record {
+            HashRecord::ApexAngle {
+                lean,
+                apex_angle,
+                excited_lean,
+                excited_apex_angle,
+            } => Some(ComparableRecord::ApexAngle {
+                lean: *lean,
+                apex_angle: *apex_angle as f64,
+                excited_lean: *excited_lean,
+                excited_apex_angle: *excited_apex_angle as f64,
+            }),
+            HashRecord::Exact {
+                lean,
+                apex_angle,
+                excited_lean,
+                excited_apex_angle,
+            } => Some(ComparableRecord::ApexAngle {
+                lean: *lean,
+                apex_angle: apex_angle.to_degrees(),
+                excited_lean: *excited_lean,
+                excited_apex_angle: excited_apex_angle.to_degrees(),
+            }),
+            HashRecord::AreaRatio {
+                x_ratio,
+                y_ratio,
+                excited_x_ratio,
+                excited_y_ratio,
+            } => Some(ComparableRecord::Point {
+                point: Point2D {
+                    x: area_ratio_coordinate(x_ratio, &1.0),
+                    y: area_ratio_coordinate(y_ratio, &1.0),
+                },
+                excited_point: Point2D {
+                    x: area_ratio_coordinate(excited_x_ratio, &1.0),
+                    y: area_ratio_coordinate(excited_y_ratio, &1.0),
+                },
+            }),
+            HashRecord::Padding => None,
+        })
+        .collect()
+}
+
+/// Gets the comparable records of formatted hash items, without rounding
+/// their apex angles. As in a hash, only the first HASH_RECORDS are kept.
+pub(crate) fn formatted_comparable_records(
+    formatted_hash_items: &[HashItemFormatted],
+) -> Vec<Option<ComparableRecord>> {
+    formatted_hash_items
+        .iter()
+        .take(HASH_RECORDS)
+        .map(|item| {
+            Some(ComparableRecord::ApexAngle {
+                lean: Lean::from_digit(&item.right_leaning).unwrap_or(Lean::Upright),
+                apex_angle: item.upper_angle.to_degrees(),
+                excited_lean: Lean::from_digit(&item.right_leaning_excited)
+                    .unwrap_or(Lean::Upright),
+                excited_apex_angle: item.upper_angle_excited.to_degrees(),
+            })
+        })
+        .collect()
+}
+
+/// Compares two hashes record by record.
+///
+/// Records at the same rank are compared by the distance between their apex
+/// angles: 1 - |angle1 - angle2| / 180, less LEAN_STEP_PENALTY of that for
+/// each step between their lean directions. Records of area ratio hashes are
+/// compared by the distance between their coordinates relative to the grid:
+/// 1 - distance / MAX_RELATIVE_DISTANCE.
+/// A record facing padding scores 0 and ranks at which both hashes are
+/// padding are skipped. The score of the hashes is the mean of the record
+/// scores weighted by 1 / (rank + 1), so the discourse hypernym and the
+/// most excited items count the most. Two hashes of padding alone score 1.
+pub(crate) fn compare_records(
+    records1: &[Option<ComparableRecord>],
+    records2: &[Option<ComparableRecord>],
+) -> HashSimilarity {
+    let mut records: Vec<RecordSimilarity> = Vec::new();
+    let mut weighted_sum: f64 = 0.0;
+    let mut weight_total: f64 = 0.0;
+
+    for rank in 0..records1.len().max(records2.len()) {
+        let record1 = records1.get(rank).and_then(Option::as_ref);
+        let record2 = records2.get(rank).and_then(Option::as_ref);
+        let weight: f64 = 1.0 / (rank + 1) as f64;
+
+        let (similarity, excited_similarity) = match (record1, record2) {
+            (None, None) => continue,
+            (
+                Some(ComparableRecord::ApexAngle {
+                    lean: lean1,
+                    apex_angle: apex_angle1,
+                    excited_lean: excited_lean1,
+                    excited_apex_angle: excited_apex_angle1,
+                }),
+                Some(ComparableRecord::ApexAngle {
+                    lean: lean2,
+                    apex_angle: apex_angle2,
+                    excited_lean: excited_lean2,
+                    excited_apex_angle: excited_apex_angle2,
+                }),
+            ) => (
+                angle_similarity(lean1, apex_angle1, lean2, apex_angle2),
+                angle_similarity(
+                    excited_lean1,
+                    excited_apex_angle1,
+                    excited_lean2,
+                    excited_apex_angle2,
+                ),
+            ),
+            (
+                Some(ComparableRecord::Point {
+                    point: point1,
+                    excited_point: excited_point1,
+                }),
+                Some(ComparableRecord::Point {
+                    point: point2,
+                    excited_point: excited_point2,
+                }),
+            ) => (
+                point_similarity(point1, point2),
+                point_similarity(excited_point1, excited_point2),
+            ),
+            _ => (0.0, 0.0),
+        };
+
+        let record_similarity = RecordSimilarity {
+            rank,
+            weight,
+            similarity,
+            excited_similarity,
+            is_padded: record1.is_none() || record2.is_none(),
+        };
+
+        weighted_sum += weight * record_similarity.score();
+        weight_total += weight;
+        records.push(record_similarity);
+    }
+
+    HashSimilarity {
+        score: match weight_total {
+            total if total > 0.0 => weighted_sum / total,
+            _ => 1.0,
+        },
+        records,
+    }
+}
+
+/// Gets the similarity of two apex angles (in degrees) and their lean directions.
+fn angle_similarity(lean1: &Lean, angle1: &f64, lean2: &Lean, angle2: &f64) -> f64 {
+    let closeness: f64 = (1.0 - (angle1 - angle2).abs() / MAX_ANGLE).clamp(0.0, 1.0);
+    let lean_steps: i32 = (lean1.digit() - lean2.digit()).abs();
+
+    closeness * (1.0 - LEAN_STEP_PENALTY * lean_steps as f64)
+}
+
+/// Gets the similarity of two coordinates relative to the grid.
+fn point_similarity(point1: &Point2D, point2: &Point2D) -> f64 {
+    let distance: f64 = distance(point1, point2);
+    if distance.is_nan() {
+        return 0.0;
+    }
+
+    (1.0 - distance / MAX_RELATIVE_DISTANCE).clamp(0.0, 1.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::OkeuvoError;
+
+    fn parse(hash: &str) -> ParsedHash {
+        ParsedHash::parse(hash).unwrap()
+    }
+
+    fn point(x: f64, y: f64) -> Option<ComparableRecord> {
+        Some(ComparableRecord::Point {
+            point: Point2D { x, y },
+            excited_point: Point2D { x, y },
+        })
+    }
+
+    const HASH: &str = "v19.07.23/19.07.23:aa1r3d2#-!:1!09!0!36-2!90!1!45-#!##!#!##";
+
+    #[test]
+    fn a_hash_is_the_same_as_itself() {
+        let similarity: HashSimilarity = parse(HASH).similarity(&parse(HASH)).unwrap();
+
+        assert_eq!(similarity.score, 1.0);
+        // The padding at rank 2 is left out.
+        assert_eq!(similarity.records.len(), 2);
+        for record in &similarity.records {
+            assert_eq!((record.similarity, record.excited_similarity), (1.0, 1.0));
+            assert!(!record.is_padded);
+        }
+    }
+
+    #[test]
+    fn opposite_records_share_nothing() {
+        let similarity: HashSimilarity = parse("v19.07.23/19.07.23:aa1r2d2#-!:0!00!0!00-0!00!0!00")
+            .similarity(&parse(
+                "v19.07.23/19.07.23:aa1r2d2#-!:2!180!2!180-2!180!2!180",
+            ))
+            .unwrap();
+
+        assert_eq!(similarity.score, 0.0);
+    }
+
+    #[test]
+    fn a_record_facing_padding_scores_nothing() {
+        let similarity: HashSimilarity = parse(HASH)
+            .similarity(&parse(
+                "v19.07.23/19.07.23:aa1r3d2#-!:1!09!0!36-#!##!#!##-#!##!#!##",
+            ))
+            .unwrap();
+
+        assert_eq!(similarity.records.len(), 2);
+        assert_eq!(similarity.records[1].score(), 0.0);
+        assert!(similarity.records[1].is_padded);
+        // Weighted by 1 / (rank + 1): (1 * 1 + 0 * 1/2) / (1 + 1/2).
+        assert!((similarity.score - 2.0 / 3.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn similarity_is_symmetric() {
+        let hash1: ParsedHash = parse(HASH);
+        let hash2: ParsedHash =
+            parse("v19.07.23/19.07.23:aa1r3d2#-!:0!30!1!36-2!80!2!95-1!10!1!12");
+
+        assert_eq!(
+            hash1.similarity(&hash2).unwrap(),
+            hash2.similarity(&hash1).unwrap()
+        );
+        let score: f64 = hash1.similarity(&hash2).unwrap().score;
+        assert!(score > 0.0 && score < 1.0);
+    }
+
+    #[test]
+    fn points_are_compared_by_their_distance() {
+        let same: HashSimilarity = compare_records(&[point(0.5, -0.5)], &[point(0.5, -0.5)]);
+        let far: HashSimilarity = compare_records(&[point(0.0, 0.0)], &[point(1.0, 1.0)]);
+        let near: HashSimilarity = compare_records(&[point(0.0, 0.0)], &[point(0.1, 0.0)]);
+
+        assert_eq!(same.score, 1.0);
+        assert!(far.score.abs() < 1e-12);
+        assert!((near.score - (1.0 - 0.1 / MAX_RELATIVE_DISTANCE)).abs() < 1e-12);
+    }
+
+    #[test]
+    fn hashes_of_padding_alone_are_the_same() {
+        assert_eq!(compare_records(&[None, None], &[None]).score, 1.0);
+    }
+
+    #[test]
+    fn hashes_of_different_formats_are_incomparable() {
+        let area_ratio: ParsedHash =
+            parse("v19.07.23/19.07.23:ar1r2d3#;,:2.500e-1,-1.111e0,0.000e0,1.000e0;#,#,#,#");
+
+        assert!(matches!(
+            parse(HASH).similarity(&area_ratio),
+            Err(OkeuvoError::IncomparableHashes { .. })
+        ));
+    }
+}
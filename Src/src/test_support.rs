@@ -0,0 +1,94 @@
+/*
+DO NOT ALTER OR REMOVE COPYRIGHT NOTICES OR THIS HEADER.
+
+Copyright © 2019 Asame Imoni Obiomah. All rights reserved.
+
+Artificial intelligence ethics is of existential importance.
+The licensing model of OkeuvoLite enforces adherence to a strict ethical code.
+
+The contents of this file are subject to the terms of both the GNU General Public License Version 2 only (“GPL”)
+and Inverse license (collectively, the “License”). You may not use this file except in compliance with the License.
+You can obtain a copy of the License at LICENSE.txt. See the License for the specific language governing
+permissions and limitations under the License.
+
+When distributing the software, include this License Header Notice in each file and include the License file at LICENSE.txt.
+*/
+
+//! Fixtures shared by the unit tests of the crate.
+
+use crate::conllu::{conllu_to_discourse, parse_conllu};
+use crate::data::{HashItem, InputDiscourse, InputWord, MeaningGridItem};
+use crate::discourse::Discourse;
+use crate::grid::MeaningGrid;
+use crate::version::Versions;
+
+/// Builds a discourse of one sentence from its words, each given as
+/// FORM LEMMA UPOS FEATS HEAD DEPREL (CoNLL-U, less ID, XPOS, DEPS and MISC).
+pub(crate) fn discourse(words: &[&str]) -> Discourse {
+    let mut text = String::new();
+    for (i, word) in words.iter().enumerate() {
+        let fields: Vec<&str> = word.split_whitespace().collect();
+        text += &format!(
+            "{}\t{}\t{}\t{}\t_\t{}\t{}\t{}\t_\t_\n",
+            i + 1,
+            fields[0],
+            fields[1],
+            fields[2],
+            fields[3],
+            fields[4],
+            fields[5]
+        );
+    }
+    let sentences = parse_conllu(text.as_bytes()).unwrap();
+
+    conllu_to_discourse(InputDiscourse::default(), &sentences, &mut |_, _| None)
+}
+
+/// A word of sentence 1 with a synset and coordinates.
+pub(crate) fn word(word_id: i32, synset_id: i32, x: f64, y: f64) -> InputWord {
+    InputWord {
+        word_id,
+        sentence_id: 1,
+        synset_id,
+        x,
+        y,
+        new_word_id: -1,
+        ..Default::default()
+    }
+}
+
+pub(crate) fn item(synset_id: i32, x: f64, y: f64) -> MeaningGridItem {
+    MeaningGridItem { x, y, synset_id }
+}
+
+/// A meaning grid of version 19.07.23 holding items, given as (synset_id, x, y).
+pub(crate) fn grid(items: &[(i32, f64, f64)]) -> MeaningGrid {
+    MeaningGrid::new(
+        "19.07.23",
+        items
+            .iter()
+            .map(|(synset_id, x, y)| item(*synset_id, *x, *y))
+            .collect(),
+    )
+}
+
+/// A real hash item of discourse 1 with the ground state and excited state
+/// coordinates, point and excited_point.
+pub(crate) fn hash_item(point: (f64, f64), excited_point: (f64, f64)) -> HashItem {
+    HashItem {
+        radius: point.0.hypot(point.1),
+        angle: point.1.atan2(point.0),
+        hash_type: 1,
+        discourse_id: 1,
+        excited_radius: excited_point.0.hypot(excited_point.1),
+        excited_angle: excited_point.1.atan2(excited_point.0),
+    }
+}
+
+/// Versions 19.07.23 of both the library and the meaning grid.
+pub(crate) fn versions() -> Versions {
+    Versions {
+        library: "19.07.23".to_string(),
+        grid: "19.07.23".to_string(),
+    }
+}
@@ -0,0 +1,578 @@
+/*
+DO NOT ALTER OR REMOVE COPYRIGHT NOTICES OR THIS HEADER.
+
+Copyright © 2019 Asame Imoni Obiomah. All rights reserved.
+
+Artificial intelligence ethics is of existential importance.
+The licensing model of OkeuvoLite enforces adherence to a strict ethical code.
+
+The contents of this file are subject to the terms of both the GNU General Public License Version 2 only (“GPL”)
+and Inverse license (collectively, the “License”). You may not use this file except in compliance with the License.
+You can obtain a copy of the License at LICENSE.txt. See the License for the specific language governing
+permissions and limitations under the License.
+
+When distributing the software, include this License Header Notice in each file and include the License file at LICENSE.txt.
+*/
+
+//! Derives triplets (subject, predicate, object) and their sections from the
+//! Universal Dependencies relations and features of a discourse, so that a
+//! plain UD parse is enough input.
+
+use crate::data::{InputSection, InputTriplet, InputWord, InputWordRelation};
+use crate::discourse::Discourse;
+use crate::location::is_location;
+
+/// Relations that attach the subject of a clause to its predicate.
+const SUBJECT_RELATIONS: &[&str] = &["nsubj", "csubj"];
+
+/// Relations that attach the object of a clause to its predicate.
+/// Core objects (obj, iobj) outrank the others when a section focus is chosen
+/// (see SECTION_FOCUS_GRADES in lib.rs).
+/// An obl marked as a place or a time is not an object but a modifier of the
+/// predicate, the where or when of its triplet (see get_locations).
+const OBJECT_RELATIONS: &[&str] = &["obj", "iobj", "obl", "ccomp", "xcomp"];
+
+/// Relations through which a predicate with no subject of its own
+/// shares the subject of the predicate it depends on.
+const SHARED_SUBJECT_RELATIONS: &[&str] = &["xcomp", "conj"];
+
+/// Relations that attach a modifier (or function word) to the word it modifies,
+/// rather than an argument or a clause of its own.
+/// obl attaches a modifier only when it is marked as a place or a time, any
+/// other obl attaches an object (see OBJECT_RELATIONS).
+const MODIFIER_RELATIONS: &[&str] = &[
+    "amod", "advmod", "nmod", "obl", "nummod", "appos", "compound", "flat", "fixed", "det", "case",
+    "aux", "cop", "neg",
+];
+
+/// Lemmas of auxiliaries that mark the future tense.
+const FUTURE_AUXILIARIES: &[&str] = &["will", "shall"];
+
+/// Tense of a triplet whose words carry no tense.
+const DEFAULT_TENSE: i32 = 2;
+
+/// Gets the relation type of a Universal Dependency relation,
+/// without its subtype (nsubj:pass is nsubj).
+pub(crate) fn base_relation(ud_relation: &str) -> &str {
+    ud_relation.split(':').next().unwrap_or_default()
+}
+
+/// Derives the triplets of every sentence of a discourse from its relations
+/// (input_word_relation) and features (input_word_feature).
+///
+/// A predicate is a verb, or any word with a subject or core object
+/// (nsubj, nsubj:pass, csubj, obj or iobj) of its own. For each predicate:
+///
+/// 1. the subject section holds its nsubj and csubj dependents (of any subtype).
+///    A predicate joined to another by xcomp or conj shares the subject of that
+///    predicate; under xcomp the object of the controlling predicate is taken
+///    instead when it has one ("they asked him to stay"),
+/// 2. the predicate section holds the predicate,
+/// 3. the object section holds its obj, iobj, obl, ccomp and xcomp dependents,
+///    leaving out obl dependents marked as places or times: those are modifiers,
+///    the where and when of the triplet ("she met him in Paris").
+///    A conj predicate with none shares those objects of the predicate it depends on
+///    that follow it ("she bought and read the book").
+///
+/// Subjects and objects are joined by the words conj'ed to them. Predicates
+/// lacking a subject or an object make no triplet, as each section of a
+/// triplet must have a focus.
+///
+/// is_passive is set by Voice=Pass on the predicate or an auxiliary, or by a
+/// :pass relation to the predicate; subject and object keep their grammatical
+/// roles and are swapped when the discourse is encoded. tense comes from the
+/// auxiliaries (see get_tense).
+///
+/// Triplets and sections are numbered from 1 within the discourse,
+/// in the order of sentences and words.
+///
+/// Return format: (triplets, sections).
+pub(crate) fn extract_triplets(discourse: &Discourse) -> (Vec<InputTriplet>, Vec<InputSection>) {
+    let mut triplets: Vec<InputTriplet> = Vec::new();
+    let mut sections: Vec<InputSection> = Vec::new();
+
+    for sentence in &discourse.sentences {
+        let mut words: Vec<&InputWord> = discourse
+            .words
+            .iter()
+            .filter(|word| word.sentence_id == sentence.sentence_id)
+            .collect();
+        words.sort_by_key(|word| word.word_id);
+
+        for word in words {
+            if !is_predicate(discourse, word) {
+                continue;
+            }
+
+            let subjects: Vec<i32> = get_subjects(discourse, &word.word_id, discourse.words.len());
+            let objects: Vec<i32> = get_objects(discourse, &word.word_id);
+            if subjects.is_empty() || objects.is_empty() {
+                continue;
+            }
+
+            let triplet_id: i32 = triplets.len() as i32 + 1;
+            triplets.push(InputTriplet {
+                triplet_id,
+                sentence_id: sentence.sentence_id,
+                tense: get_tense(discourse, &word.word_id, discourse.words.len()),
+                is_virtual: false,
+                is_passive: is_passive(discourse, &word.word_id),
+            });
+
+            let section_words = subjects
+                .iter()
+                .map(|word_id| (*word_id, 1))
+                .chain(std::iter::once((word.word_id, 2)))
+                .chain(objects.iter().map(|word_id| (*word_id, 3)));
+
+            for (word_id, section_type) in section_words {
+                sections.push(InputSection {
+                    section_id: sections.len() as i32 + 1,
+                    triplet_id,
+                    word_id,
+                    section_type,
+                });
+            }
+        }
+    }
+
+    (triplets, sections)
+}
+
+/// Tests if a word heads a clause: a verb, or a word with a subject or core object.
+fn is_predicate(discourse: &Discourse, word: &InputWord) -> bool {
+    word.pos == "VERB"
+        || discourse
+            .relations_by_head(&word.word_id)
+            .iter()
+            .any(|relation| {
+                matches!(
+                    base_relation(&relation.ud_relation),
+                    "nsubj" | "csubj" | "obj" | "iobj"
+                )
+            })
+}
+
+/// Gets the word_id's of the dependents of a word whose relation type is one of relations.
+pub(crate) fn get_dependents(discourse: &Discourse, word_id: &i32, relations: &[&str]) -> Vec<i32> {
+    discourse
+        .relations_by_head(word_id)
+        .iter()
+        .filter(|relation| relations.contains(&base_relation(&relation.ud_relation)))
+        .map(|relation| relation.word_id)
+        .collect()
+}
+
+/// Adds the words conj'ed to each word (and to those, in turn).
+fn with_conjuncts(discourse: &Discourse, word_ids: Vec<i32>) -> Vec<i32> {
+    let mut result: Vec<i32> = Vec::new();
+    let mut pending: Vec<i32> = word_ids;
+
+    while let Some(word_id) = pending.pop() {
+        if result.contains(&word_id) {
+            continue;
+        }
+        result.push(word_id);
+        pending.extend(get_dependents(discourse, &word_id, &["conj"]));
+    }
+    result.sort_unstable();
+
+    result
+}
+
+/// Tests if a relation is an obl marked as a place or a time (see is_location),
+/// which makes its word a modifier rather than an object.
+fn is_location_obl(discourse: &Discourse, relation: &InputWordRelation) -> bool {
+    base_relation(&relation.ud_relation) == "obl"
+        && is_location(discourse, &relation.word_id, &relation.ud_relation)
+}
+
+/// Gets the word_id's of the dependents of a word that are its objects
+/// (see OBJECT_RELATIONS).
+fn get_object_dependents(discourse: &Discourse, word_id: &i32) -> Vec<i32> {
+    discourse
+        .relations_by_head(word_id)
+        .iter()
+        .filter(|relation| {
+            OBJECT_RELATIONS.contains(&base_relation(&relation.ud_relation))
+                && !is_location_obl(discourse, relation)
+        })
+        .map(|relation| relation.word_id)
+        .collect()
+}
+
+/// Gets the word_id's of the dependents of a word that are its modifiers
+/// (see MODIFIER_RELATIONS).
+fn get_modifier_dependents(discourse: &Discourse, word_id: &i32) -> Vec<i32> {
+    discourse
+        .relations_by_head(word_id)
+        .iter()
+        .filter(|relation| {
+            let relation_type: &str = base_relation(&relation.ud_relation);
+
+            MODIFIER_RELATIONS.contains(&relation_type)
+                && (relation_type != "obl" || is_location_obl(discourse, relation))
+        })
+        .map(|relation| relation.word_id)
+        .collect()
+}
+
+/// Gets the modifiers of a word (see MODIFIER_RELATIONS),
+/// and those of the modifiers in turn ("the very big dog"), sorted by word_id.
+pub(crate) fn get_modifiers(discourse: &Discourse, word_id: &i32) -> Vec<i32> {
+    let mut result: Vec<i32> = Vec::new();
+    let mut pending: Vec<i32> = get_modifier_dependents(discourse, word_id);
+
+    while let Some(modifier_id) = pending.pop() {
+        if modifier_id == *word_id || result.contains(&modifier_id) {
+            continue;
+        }
+        result.push(modifier_id);
+        pending.extend(get_modifier_dependents(discourse, &modifier_id));
+    }
+    result.sort_unstable();
+
+    result
+}
+
+/// Gets the subjects of a predicate (see extract_triplets).
+/// depth bounds the predicates followed, so malformed (cyclic) relations end.
+fn get_subjects(discourse: &Discourse, predicate_id: &i32, depth: usize) -> Vec<i32> {
+    let subjects: Vec<i32> = get_dependents(discourse, predicate_id, SUBJECT_RELATIONS);
+    if !subjects.is_empty() {
+        return with_conjuncts(discourse, subjects);
+    }
+    if depth == 0 {
+        return Vec::new();
+    }
+
+    for relation in discourse.relations_by_word(predicate_id) {
+        let relation_type: &str = base_relation(&relation.ud_relation);
+        if !SHARED_SUBJECT_RELATIONS.contains(&relation_type) {
+            continue;
+        }
+
+        if relation_type == "xcomp" {
+            let controllers: Vec<i32> =
+                get_dependents(discourse, &relation.word_id_modified, &["obj"]);
+            if !controllers.is_empty() {
+                return with_conjuncts(discourse, controllers);
+            }
+        }
+
+        return get_subjects(discourse, &relation.word_id_modified, depth - 1);
+    }
+
+    Vec::new()
+}
+
+/// Gets the objects of a predicate (see extract_triplets).
+fn get_objects(discourse: &Discourse, predicate_id: &i32) -> Vec<i32> {
+    let objects: Vec<i32> = get_object_dependents(discourse, predicate_id);
+    if !objects.is_empty() {
+        return with_conjuncts(discourse, objects);
+    }
+
+    for relation in discourse.relations_by_word(predicate_id) {
+        if base_relation(&relation.ud_relation) != "conj" {
+            continue;
+        }
+
+        let shared: Vec<i32> = get_object_dependents(discourse, &relation.word_id_modified)
+            .into_iter()
+            .filter(|word_id| word_id > predicate_id)
+            .collect();
+
+        return with_conjuncts(discourse, shared);
+    }
+
+    Vec::new()
+}
+
+/// Gets the value of a universal feature of a word.
+pub(crate) fn get_feature_value<'a>(
+    discourse: &'a Discourse,
+    word_id: &i32,
+    feature: &str,
+) -> Option<&'a str> {
+    discourse
+        .features_by_word(word_id)
+        .into_iter()
+        .find(|item| item.ud_feature == feature)
+        .map(|item| item.ud_feature_value.as_str())
+}
+
+/// Tests if the clause headed by a predicate is in passive voice.
+fn is_passive(discourse: &Discourse, predicate_id: &i32) -> bool {
+    let relations = discourse.relations_by_head(predicate_id);
+
+    get_feature_value(discourse, predicate_id, "Voice") == Some("Pass")
+        || relations.iter().any(|relation| {
+            relation.ud_relation.ends_with(":pass")
+                || (base_relation(&relation.ud_relation) == "aux"
+                    && get_feature_value(discourse, &relation.word_id, "Voice") == Some("Pass"))
+        })
+}
+
+/// Gets the tense of the clause headed by a predicate:
+/// 1 = past, 2 = present and 3 = future.
+///
+/// A future auxiliary (will, shall) makes the future. Otherwise the Tense of the
+/// first auxiliary or copula that has one is taken, as the auxiliaries of a
+/// compound tense carry it ("has been taken"), then the Tense of the predicate.
+/// A predicate with no tense takes that of the predicate it is xcomp'ed or
+/// conj'ed to, or else is present.
+fn get_tense(discourse: &Discourse, predicate_id: &i32, depth: usize) -> i32 {
+    let auxiliaries: Vec<i32> = get_dependents(discourse, predicate_id, &["aux", "cop"]);
+
+    let is_future: bool = auxiliaries.iter().any(|word_id| {
+        discourse.words.iter().any(|word| {
+            word.word_id == *word_id
+                && FUTURE_AUXILIARIES.contains(&word.lexeme.to_lowercase().as_str())
+        })
+    });
+    if is_future {
+        return 3;
+    }
+
+    for word_id in auxiliaries.iter().chain(std::iter::once(predicate_id)) {
+        let tense = match get_feature_value(discourse, word_id, "Tense") {
+            Some("Past") | Some("Pqp") | Some("Imp") => Some(1),
+            Some("Pres") => Some(2),
+            Some("Fut") => Some(3),
+            _ => None,
+        };
+
+        if let Some(tense) = tense {
+            return tense;
+        }
+    }
+
+    if depth > 0 {
+        for relation in discourse.relations_by_word(predicate_id) {
+            if SHARED_SUBJECT_RELATIONS.contains(&base_relation(&relation.ud_relation)) {
+                return get_tense(discourse, &relation.word_id_modified, depth - 1);
+            }
+        }
+    }
+
+    DEFAULT_TENSE
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::discourse;
+
+    /// Gets the words of each section type (subject, predicate, object) of a triplet.
+    fn sections(discourse: &Discourse, triplet_id: i32) -> [Vec<i32>; 3] {
+        let mut result: [Vec<i32>; 3] = Default::default();
+        for section in &discourse.sections {
+            if section.triplet_id == triplet_id {
+                result[section.section_type as usize - 1].push(section.word_id);
+            }
+        }
+
+        result
+    }
+
+    #[test]
+    fn xcomp_takes_the_object_of_its_controller_as_subject() {
+        let discourse = discourse(&[
+            "they they PRON _ 2 nsubj",
+            "asked ask VERB Tense=Past 0 root",
+            "him he PRON _ 2 obj",
+            "to to PART _ 5 mark",
+            "read read VERB VerbForm=Inf 2 xcomp",
+            "the the DET _ 7 det",
+            "book book NOUN _ 5 obj",
+        ]);
+
+        assert_eq!(discourse.triplets.len(), 2);
+        assert_eq!(sections(&discourse, 1), [vec![1], vec![2], vec![3, 5]]);
+        assert_eq!(sections(&discourse, 2), [vec![3], vec![5], vec![7]]);
+        // "to read" has no tense of its own and takes that of "asked".
+        assert_eq!(discourse.triplets[1].tense, 1);
+    }
+
+    #[test]
+    fn xcomp_without_a_controlling_object_shares_the_subject() {
+        let discourse = discourse(&[
+            "she she PRON _ 2 nsubj",
+            "wants want VERB Tense=Pres 0 root",
+            "to to PART _ 4 mark",
+            "read read VERB VerbForm=Inf 2 xcomp",
+            "books book NOUN _ 4 obj",
+        ]);
+
+        assert_eq!(discourse.triplets.len(), 2);
+        assert_eq!(sections(&discourse, 2), [vec![1], vec![4], vec![5]]);
+    }
+
+    #[test]
+    fn conj_shares_the_subject_and_a_following_object() {
+        let discourse = discourse(&[
+            "she she PRON _ 2 nsubj",
+            "bought buy VERB Tense=Past 0 root",
+            "and and CCONJ _ 4 cc",
+            "read read VERB Tense=Past 2 conj",
+            "the the DET _ 6 det",
+            "book book NOUN _ 2 obj",
+        ]);
+
+        assert_eq!(discourse.triplets.len(), 2);
+        assert_eq!(sections(&discourse, 1), [vec![1], vec![2], vec![6]]);
+        assert_eq!(sections(&discourse, 2), [vec![1], vec![4], vec![6]]);
+    }
+
+    #[test]
+    fn conjuncts_join_the_subject_and_object_sections() {
+        let discourse = discourse(&[
+            "Ann Ann PROPN _ 4 nsubj",
+            "and and CCONJ _ 3 cc",
+            "Bob Bob PROPN _ 1 conj",
+            "like like VERB Tense=Pres 0 root",
+            "tea tea NOUN _ 4 obj",
+            "and and CCONJ _ 7 cc",
+            "cake cake NOUN _ 5 conj",
+        ]);
+
+        assert_eq!(discourse.triplets.len(), 1);
+        assert_eq!(sections(&discourse, 1), [vec![1, 3], vec![4], vec![5, 7]]);
+    }
+
+    #[test]
+    fn detects_the_passive_voice() {
+        let discourse = discourse(&[
+            "the the DET _ 2 det",
+            "cake cake NOUN _ 4 nsubj:pass",
+            "was be AUX Tense=Past 4 aux:pass",
+            "eaten eat VERB VerbForm=Part|Voice=Pass 0 root",
+            "by by ADP _ 6 case",
+            "him he PRON _ 4 obl:agent",
+        ]);
+
+        assert_eq!(discourse.triplets.len(), 1);
+        assert!(discourse.triplets[0].is_passive);
+        // Subject and object keep their grammatical roles.
+        assert_eq!(sections(&discourse, 1), [vec![2], vec![4], vec![6]]);
+        assert_eq!(discourse.triplets[0].tense, 1);
+    }
+
+    #[test]
+    fn active_clauses_are_not_passive() {
+        let discourse = discourse(&[
+            "he he PRON _ 2 nsubj",
+            "ate eat VERB Tense=Past 0 root",
+            "cake cake NOUN _ 2 obj",
+        ]);
+
+        assert!(!discourse.triplets[0].is_passive);
+    }
+
+    #[test]
+    fn takes_the_tense_from_the_auxiliaries() {
+        let tense = |auxiliary: &str, predicate: &str| {
+            discourse(&[
+                "she she PRON _ 3 nsubj",
+                auxiliary,
+                predicate,
+                "books book NOUN _ 3 obj",
+            ])
+            .triplets[0]
+                .tense
+        };
+
+        // A future auxiliary has no Tense feature.
+        assert_eq!(
+            tense(
+                "will will AUX _ 3 aux",
+                "read read VERB VerbForm=Inf 0 root"
+            ),
+            3
+        );
+        assert_eq!(
+            tense(
+                "shall shall AUX _ 3 aux",
+                "read read VERB VerbForm=Inf 0 root"
+            ),
+            3
+        );
+        // The auxiliary of a compound tense outranks the participle.
+        assert_eq!(
+            tense(
+                "had have AUX Tense=Past 3 aux",
+                "read read VERB Tense=Past|VerbForm=Part 0 root"
+            ),
+            1
+        );
+        assert_eq!(
+            tense(
+                "has have AUX Tense=Pres 3 aux",
+                "read read VERB Tense=Past|VerbForm=Part 0 root"
+            ),
+            2
+        );
+        assert_eq!(
+            tense(
+                "does do AUX Tense=Pres 3 aux",
+                "read read VERB VerbForm=Inf 0 root"
+            ),
+            2
+        );
+    }
+
+    #[test]
+    fn a_clause_without_tense_is_present() {
+        let discourse = discourse(&[
+            "they they PRON _ 2 nsubj",
+            "read read VERB _ 0 root",
+            "books book NOUN _ 2 obj",
+        ]);
+
+        assert_eq!(discourse.triplets[0].tense, DEFAULT_TENSE);
+    }
+
+    #[test]
+    fn predicates_without_an_object_make_no_triplet() {
+        let discourse = discourse(&[
+            "she she PRON _ 2 nsubj",
+            "sleeps sleep VERB Tense=Pres 0 root",
+        ]);
+
+        assert!(discourse.triplets.is_empty());
+        assert!(discourse.sections.is_empty());
+    }
+
+    #[test]
+    fn places_and_times_are_modifiers_rather_than_objects() {
+        // "she met him with friends in Paris": "with friends" is an object,
+        // "in Paris" a modifier of "met", its where.
+        let discourse = discourse(&[
+            "she she PRON _ 2 nsubj",
+            "met meet VERB Tense=Past 0 root",
+            "him he PRON _ 2 obj",
+            "with with ADP _ 5 case",
+            "friends friend NOUN _ 2 obl",
+            "in in ADP _ 7 case",
+            "Paris Paris PROPN _ 2 obl",
+        ]);
+
+        assert_eq!(sections(&discourse, 1), [vec![1], vec![2], vec![3, 5]]);
+        assert_eq!(get_modifiers(&discourse, &2), vec![6, 7]);
+        assert_eq!(get_modifiers(&discourse, &5), vec![4]);
+    }
+
+    #[test]
+    fn a_place_alone_makes_no_object() {
+        // "she lives in Paris": "Paris" is where she lives, not what.
+        let discourse = discourse(&[
+            "she she PRON _ 2 nsubj",
+            "lives live VERB Tense=Pres 0 root",
+            "in in ADP _ 4 case",
+            "Paris Paris PROPN _ 2 obl",
+        ]);
+
+        assert!(discourse.triplets.is_empty());
+    }
+}
@@ -231,3 +231,16 @@ pub(crate) fn pos_letter(synset_id: &i32) -> Option<String> {
 
     Some(pos)
 }
+
+/// Checks that a synset_id falls inside one of the Wordnet SQL format
+/// ranges recognised by pos_letter (noun, verb, adjective or adverb).
+pub(crate) fn is_wordnet_sql_synset_id(synset_id: &i32) -> bool {
+    if synset_id <= &100000000 || synset_id >= &500000000 {
+        return false;
+    }
+
+    match pos_letter(synset_id) {
+        Some(pos) => !pos.is_empty(),
+        None => false,
+    }
+}
@@ -0,0 +1,243 @@
+/*
+DO NOT ALTER OR REMOVE COPYRIGHT NOTICES OR THIS HEADER.
+
+Copyright © 2019 Asame Imoni Obiomah. All rights reserved.
+
+Artificial intelligence ethics is of existential importance.
+The licensing model of OkeuvoLite enforces adherence to a strict ethical code.
+
+The contents of this file are subject to the terms of both the GNU General Public License Version 2 only (“GPL”)
+and Inverse license (collectively, the “License”). You may not use this file except in compliance with the License.
+You can obtain a copy of the License at LICENSE.txt. See the License for the specific language governing
+permissions and limitations under the License.
+
+When distributing the software, include this License Header Notice in each file and include the License file at LICENSE.txt.
+*/
+
+//! The versions of OkeuvoLite and of the meaning grid an encoding was made with.
+//!
+//! Every encoding and hash carries both, as hashes are only comparable when
+//! they come from the same library and the same meaning grid.
+
+use crate::error::OkeuvoError;
+
+// The version number and codename of this library, as literals so that
+// concat! can join them for version.
+macro_rules! library_version {
+    () => {
+        "26.10.18"
+    };
+}
+macro_rules! library_codename {
+    () => {
+        "Ama"
+    };
+}
+
+/// Version number of this library (see version).
+/// It changes whenever the hash of an encoding changes, so that hashes made
+/// the old way are never compared with new ones.
+pub const LIBRARY_VERSION: &str = library_version!();
+
+/// Codename of this library version.
+pub const LIBRARY_CODENAME: &str = library_codename!();
+
+/// The version number of this library with its codename, as version gives it.
+pub(crate) const LIBRARY_VERSION_AND_CODENAME: &str =
+    concat!(library_version!(), " (Codename ", library_codename!(), ")");
+
+/// Starts the versions tag of a hash.
+pub const VERSIONS_TAG: char = 'v';
+/// Separates the library version from the grid version in the versions tag of a hash.
+pub const VERSIONS_SEPARATOR: char = '/';
+
+/// The versions of OkeuvoLite and of the meaning grid an encoding was made with.
+/// Versions are version numbers alone, without codenames: for a meaning grid with
+/// the header line "#Alpha version 19.07.23 (codename, Ama)", the grid version is "19.07.23".
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct Versions {
+    /// Version of OkeuvoLite.
+    pub library: String,
+    /// Version of the meaning grid. Empty when it is not known.
+    pub grid: String,
+}
+
+impl Versions {
+    /// Gets the versions of this library and a meaning grid
+    /// (see MeaningGrid::version).
+    pub fn new(grid_version: &str) -> Versions {
+        Versions {
+            library: LIBRARY_VERSION.to_string(),
+            grid: version_number(grid_version).to_string(),
+        }
+    }
+
+    /// Checks that both versions are known and can be written in a hash:
+    /// they may hold only letters, digits, ".", "-", "_" and "+".
+    pub fn validate(&self) -> Result<(), OkeuvoError> {
+        for (name, version) in &[("library", &self.library), ("meaning grid", &self.grid)] {
+            if version.is_empty() {
+                return Err(OkeuvoError::InvalidVersion {
+                    message: format!("the {} version is not known", name),
+                });
+            }
+
+            if !version
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '_' | '+'))
+            {
+                return Err(OkeuvoError::InvalidVersion {
+                    message: format!(
+                        "the {} version '{}' can't be written in a hash",
+                        name, version
+                    ),
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Gets the versions tag of hashes: VERSIONS_TAG, the library version,
+    /// VERSIONS_SEPARATOR and the grid version, e.g. "v26.10.18/19.07.23".
+    pub fn tag(&self) -> String {
+        format!(
+            "{}{}{}{}",
+            VERSIONS_TAG, self.library, VERSIONS_SEPARATOR, self.grid
+        )
+    }
+
+    /// Parses the versions tag of a hash (see tag).
+    pub fn from_tag(tag: &str) -> Result<Versions, OkeuvoError> {
+        let (library, grid) = tag
+            .strip_prefix(VERSIONS_TAG)
+            .and_then(|versions| versions.split_once(VERSIONS_SEPARATOR))
+            .ok_or_else(|| OkeuvoError::InvalidVersion {
+                message: format!("'{}' is not a versions tag", tag),
+            })?;
+
+        let versions = Versions {
+            library: library.to_string(),
+            grid: grid.to_string(),
+        };
+        versions.validate()?;
+
+        Ok(versions)
+    }
+
+    /// Checks that what was made with these versions can be compared
+    /// with what was made with others: both versions must be the same.
+    pub fn check_comparable(&self, other: &Versions) -> Result<(), OkeuvoError> {
+        if self != other {
+            return Err(OkeuvoError::IncomparableHashes {
+                message: format!(
+                    "one was made with library version {} and meaning grid version {}, the other with {} and {}",
+                    self.library, self.grid, other.library, other.grid
+                ),
+            });
+        }
+
+        Ok(())
+    }
+}
+
+/// Gets the version number of a version, the part before any codename
+/// (e.g. "19.07.23" of "19.07.23 (codename, Ama)").
+pub fn version_number(version: &str) -> &str {
+    version.split_whitespace().next().unwrap_or("")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn versions(library: &str, grid: &str) -> Versions {
+        Versions {
+            library: library.to_string(),
+            grid: grid.to_string(),
+        }
+    }
+
+    fn invalid_version<T: std::fmt::Debug>(result: Result<T, OkeuvoError>) -> String {
+        match result {
+            Err(OkeuvoError::InvalidVersion { message }) => message,
+            other => panic!("expected InvalidVersion, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn takes_the_version_number_of_a_version() {
+        assert_eq!(version_number("19.07.23 (codename, Ama)"), "19.07.23");
+        assert_eq!(version_number("  19.07.23"), "19.07.23");
+        assert_eq!(version_number(""), "");
+    }
+
+    #[test]
+    fn new_versions_are_of_this_library() {
+        assert_eq!(
+            Versions::new("19.07.23 (codename, Ama)"),
+            versions(LIBRARY_VERSION, "19.07.23")
+        );
+    }
+
+    #[test]
+    fn tags_round_trip() {
+        let versions = versions("26.10.18", "19.07.23");
+
+        assert_eq!(versions.tag(), "v26.10.18/19.07.23");
+        assert_eq!(Versions::from_tag(&versions.tag()).unwrap(), versions);
+    }
+
+    #[test]
+    fn refuses_malformed_tags() {
+        assert_eq!(
+            invalid_version(Versions::from_tag("26.10.18/19.07.23")),
+            "'26.10.18/19.07.23' is not a versions tag"
+        );
+        assert_eq!(
+            invalid_version(Versions::from_tag("v26.10.18")),
+            "'v26.10.18' is not a versions tag"
+        );
+        assert_eq!(
+            invalid_version(Versions::from_tag("v26.10.18/")),
+            "the meaning grid version is not known"
+        );
+    }
+
+    #[test]
+    fn validates_the_characters_of_versions() {
+        assert!(versions("1.0.0-beta_2+build", "19.07.23")
+            .validate()
+            .is_ok());
+        assert_eq!(
+            invalid_version(versions("", "19.07.23").validate()),
+            "the library version is not known"
+        );
+        assert_eq!(
+            invalid_version(versions("26.10.18", "19.07.23 (Ama)").validate()),
+            "the meaning grid version '19.07.23 (Ama)' can't be written in a hash"
+        );
+        assert_eq!(
+            invalid_version(Versions::from_tag("v26/10/18/19.07.23")),
+            "the meaning grid version '10/18/19.07.23' can't be written in a hash"
+        );
+    }
+
+    #[test]
+    fn only_the_same_versions_are_comparable() {
+        let versions1 = versions("26.10.18", "19.07.23");
+
+        assert!(versions1.check_comparable(&versions1.clone()).is_ok());
+        for versions2 in &[
+            versions("19.07.23", "19.07.23"),
+            versions("26.10.18", "26.10.18"),
+        ] {
+            assert!(matches!(
+                versions1.check_comparable(versions2),
+                Err(OkeuvoError::IncomparableHashes { .. })
+            ));
+        }
+    }
+}
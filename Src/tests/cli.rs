@@ -0,0 +1,131 @@
+/*
+DO NOT ALTER OR REMOVE COPYRIGHT NOTICES OR THIS HEADER.
+
+Copyright © 2019 Asame Imoni Obiomah. All rights reserved.
+
+Artificial intelligence ethics is of existential importance.
+The licensing model of OkeuvoLite enforces adherence to a strict ethical code.
+
+The contents of this file are subject to the terms of both the GNU General Public License Version 2 only (“GPL”)
+and Inverse license (collectively, the “License”). You may not use this file except in compliance with the License.
+You can obtain a copy of the License at LICENSE.txt. See the License for the specific language governing
+permissions and limitations under the License.
+
+When distributing the software, include this License Header Notice in each file and include the License file at LICENSE.txt.
+*/
+
+//! Smoke test of the okeuvo command line tool: init, import, encode and hash.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+
+/// A meaning grid holding every synset of SENTENCES.
+const GRID: &str = "#Alpha version 19.07.23 (codename, Ama)
+100001740\t3\t4
+100002137\t1\t2
+201168468\t5\t1
+107628870\t2\t6
+";
+
+/// "cats eat fish. fish eat cats." with the synset of each word in its MISC field.
+const SENTENCES: &str = "1\tcats\tcat\tNOUN\t_\tNumber=Plur\t2\tnsubj\t_\tSynsetId=100002137
+2\teat\teat\tVERB\t_\t_\t0\troot\t_\tSynsetId=201168468
+3\tfish\tfish\tNOUN\t_\t_\t2\tobj\t_\tSynsetId=107628870
+
+1\tfish\tfish\tNOUN\t_\t_\t2\tnsubj\t_\tSynsetId=107628870
+2\teat\teat\tVERB\t_\t_\t0\troot\t_\tSynsetId=201168468
+3\tcats\tcat\tNOUN\t_\tNumber=Plur\t2\tobj\t_\tSynsetId=100002137
+
+";
+
+/// A fresh folder for the databases and input files of a test.
+fn storage(name: &str) -> PathBuf {
+    let storage: PathBuf =
+        std::env::temp_dir().join(format!("okeuvo-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&storage);
+    fs::create_dir_all(&storage).unwrap();
+    storage
+}
+
+/// Runs okeuvo on the databases in storage and returns its standard output.
+/// Panics with its standard error if it fails.
+fn okeuvo(storage: &Path, arguments: &[&str]) -> String {
+    let output: Output = Command::new(env!("CARGO_BIN_EXE_okeuvo"))
+        .arg("--storage")
+        .arg(storage)
+        .arg("--agree-to-the-creed")
+        .args(arguments)
+        .output()
+        .unwrap();
+
+    assert!(
+        output.status.success(),
+        "okeuvo {:?} failed: {}",
+        arguments,
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+fn imports_encodes_and_hashes_a_discourse() {
+    let storage: PathBuf = storage("hash");
+    let grid_path: PathBuf = storage.join("meaningGrid.csv");
+    let conllu_path: PathBuf = storage.join("discourse.conllu");
+    fs::write(&grid_path, GRID).unwrap();
+    fs::write(&conllu_path, SENTENCES).unwrap();
+
+    let init: String = okeuvo(&storage, &["init"]);
+    assert!(init.contains("input.db: schema version"), "{}", init);
+
+    let import_grid: String = okeuvo(&storage, &["import-grid", grid_path.to_str().unwrap()]);
+    assert!(
+        import_grid.starts_with("Imported 4 meaning grid items"),
+        "{}",
+        import_grid
+    );
+
+    let import: String = okeuvo(
+        &storage,
+        &[
+            "import",
+            conllu_path.to_str().unwrap(),
+            "--discourse-id",
+            "1",
+            "--hypernym",
+            "100001740",
+            "--title-sentence",
+            "1",
+        ],
+    );
+    assert_eq!(
+        import.trim(),
+        "Imported discourse 1: 2 sentences, 6 words, 2 triplets."
+    );
+
+    let encode: String = okeuvo(&storage, &["encode", "1"]);
+    assert!(encode.starts_with("Encoded discourse 1:"), "{}", encode);
+
+    let hash: String = okeuvo(&storage, &["hash", "1"]);
+    assert!(!hash.trim().is_empty());
+    // The hash of an encoded discourse does not change.
+    assert_eq!(okeuvo(&storage, &["hash", "1"]), hash);
+
+    fs::remove_dir_all(&storage).unwrap();
+}
+
+#[test]
+fn refuses_a_discourse_command_without_the_creed() {
+    let storage: PathBuf = storage("creed");
+
+    let output: Output = Command::new(env!("CARGO_BIN_EXE_okeuvo"))
+        .arg("--storage")
+        .arg(&storage)
+        .args(["hash", "1"])
+        .output()
+        .unwrap();
+
+    assert!(!output.status.success());
+    fs::remove_dir_all(&storage).unwrap();
+}